@@ -13,6 +13,7 @@ pub use parser::ParseError;
 use parser::ParseError::*;
 use parser::UpdateFileChunk;
 pub use parser::parse_patch;
+use similar::DiffTag;
 use similar::TextDiff;
 use thiserror::Error;
 use tree_sitter::LanguageError;
@@ -22,6 +23,11 @@ use tree_sitter_bash::LANGUAGE as BASH;
 /// Detailed instructions for gpt-4.1 on how to use the `apply_patch` tool.
 pub const APPLY_PATCH_TOOL_INSTRUCTIONS: &str = include_str!("../apply_patch_tool_instructions.md");
 
+/// Lines of context around each hunk of [`ApplyPatchFileChange::Update`]'s
+/// `unified_diff`. Hunk indices passed to
+/// [`ApplyPatchAction::without_rejected`] refer to hunks computed with it.
+const UNIFIED_DIFF_CONTEXT: usize = 1;
+
 #[derive(Debug, Error, PartialEq)]
 pub enum ApplyPatchError {
     #[error(transparent)]
@@ -87,7 +93,7 @@ pub fn maybe_parse_apply_patch(argv: &[String]) -> MaybeApplyPatch {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ApplyPatchFileChange {
     Add {
         content: String,
@@ -116,7 +122,7 @@ pub enum MaybeApplyPatchVerified {
     NotApplyPatch,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 /// ApplyPatchAction is the result of parsing an `apply_patch` command. By
 /// construction, all paths should be absolute paths.
 pub struct ApplyPatchAction {
@@ -133,6 +139,63 @@ impl ApplyPatchAction {
         &self.changes
     }
 
+    /// Returns a copy of this action that leaves out the rejected parts.
+    ///
+    /// Paths missing from `rejected` are kept as they are. For updates,
+    /// [`Rejection::Hunks`] holds zero-based indices of the `@@` hunks in the
+    /// change's `unified_diff`; the remaining hunks are applied to the current
+    /// contents of the file. Changes that end up with nothing left to do are
+    /// dropped, so the result may be empty.
+    pub fn without_rejected(
+        &self,
+        rejected: &HashMap<PathBuf, Rejection>,
+    ) -> std::result::Result<Self, ApplyPatchError> {
+        let mut changes = HashMap::with_capacity(self.changes.len());
+        for (path, change) in &self.changes {
+            let change = match (rejected.get(path), change) {
+                (None, change) => change.clone(),
+                (Some(Rejection::WholeFile), _) => continue,
+                (
+                    Some(Rejection::Hunks(hunks)),
+                    ApplyPatchFileChange::Update {
+                        move_path,
+                        new_content,
+                        ..
+                    },
+                ) => {
+                    let original = std::fs::read_to_string(path).map_err(|source| {
+                        ApplyPatchError::IoError(IoError {
+                            context: format!("Failed to read file to update {}", path.display()),
+                            source,
+                        })
+                    })?;
+                    let new_content = drop_hunks(&original, new_content, hunks);
+                    if new_content == original && move_path.is_none() {
+                        continue;
+                    }
+                    let unified_diff = TextDiff::from_lines(&original, &new_content)
+                        .unified_diff()
+                        .context_radius(UNIFIED_DIFF_CONTEXT)
+                        .to_string();
+                    ApplyPatchFileChange::Update {
+                        unified_diff,
+                        move_path: move_path.clone(),
+                        new_content,
+                    }
+                }
+                // Adds and deletes consist of a single hunk.
+                (Some(Rejection::Hunks(hunks)), change) => {
+                    if hunks.contains(&0) {
+                        continue;
+                    }
+                    change.clone()
+                }
+            };
+            changes.insert(path.clone(), change);
+        }
+        Ok(Self { changes })
+    }
+
     /// Should be used exclusively for testing. (Not worth the overhead of
     /// creating a feature flag for this.)
     pub fn new_add_for_test(path: &Path, content: String) -> Self {
@@ -145,52 +208,99 @@ impl ApplyPatchAction {
     }
 }
 
+/// Parts of a single file change to leave out, see
+/// [`ApplyPatchAction::without_rejected`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rejection {
+    WholeFile,
+    Hunks(Vec<usize>),
+}
+
+/// Rebuilds `new` from `original`, keeping every hunk of their diff except
+/// those whose index is listed in `rejected`.
+fn drop_hunks(original: &str, new: &str, rejected: &[usize]) -> String {
+    let diff = TextDiff::from_lines(original, new);
+    let old_lines = diff.old_slices();
+    let new_lines = diff.new_slices();
+    let mut out = String::with_capacity(new.len());
+    let mut old_pos = 0;
+    for (idx, group) in diff.grouped_ops(UNIFIED_DIFF_CONTEXT).iter().enumerate() {
+        let keep = !rejected.contains(&idx);
+        for op in group.iter().filter(|op| op.tag() != DiffTag::Equal) {
+            let old_range = op.old_range();
+            out.extend(old_lines[old_pos..old_range.start].iter().copied());
+            if keep {
+                out.extend(new_lines[op.new_range()].iter().copied());
+            } else {
+                out.extend(old_lines[old_range.clone()].iter().copied());
+            }
+            old_pos = old_range.end;
+        }
+    }
+    out.extend(old_lines[old_pos..].iter().copied());
+    out
+}
+
 /// cwd must be an absolute path so that we can resolve relative paths in the
 /// patch.
 pub fn maybe_parse_apply_patch_verified(argv: &[String], cwd: &Path) -> MaybeApplyPatchVerified {
     match maybe_parse_apply_patch(argv) {
-        MaybeApplyPatch::Body(hunks) => {
-            let mut changes = HashMap::new();
-            for hunk in hunks {
-                let path = hunk.resolve_path(cwd);
-                match hunk {
-                    Hunk::AddFile { contents, .. } => {
-                        changes.insert(path, ApplyPatchFileChange::Add { content: contents });
-                    }
-                    Hunk::DeleteFile { .. } => {
-                        changes.insert(path, ApplyPatchFileChange::Delete);
-                    }
-                    Hunk::UpdateFile {
-                        move_path, chunks, ..
-                    } => {
-                        let ApplyPatchFileUpdate {
-                            unified_diff,
-                            content: contents,
-                        } = match unified_diff_from_chunks(&path, &chunks) {
-                            Ok(diff) => diff,
-                            Err(e) => {
-                                return MaybeApplyPatchVerified::CorrectnessError(e);
-                            }
-                        };
-                        changes.insert(
-                            path,
-                            ApplyPatchFileChange::Update {
-                                unified_diff,
-                                move_path: move_path.map(|p| cwd.join(p)),
-                                new_content: contents,
-                            },
-                        );
-                    }
-                }
-            }
-            MaybeApplyPatchVerified::Body(ApplyPatchAction { changes })
-        }
+        MaybeApplyPatch::Body(hunks) => match apply_patch_action_from_hunks(hunks, cwd) {
+            Ok(action) => MaybeApplyPatchVerified::Body(action),
+            Err(e) => MaybeApplyPatchVerified::CorrectnessError(e),
+        },
         MaybeApplyPatch::ShellParseError(e) => MaybeApplyPatchVerified::ShellParseError(e),
         MaybeApplyPatch::PatchParseError(e) => MaybeApplyPatchVerified::CorrectnessError(e.into()),
         MaybeApplyPatch::NotApplyPatch => MaybeApplyPatchVerified::NotApplyPatch,
     }
 }
 
+/// Parses the raw text of a patch (as passed to the `apply_patch` function
+/// tool, i.e., without any shell or heredoc wrapping) and verifies it against
+/// the filesystem. Relative paths in the patch are resolved against `cwd`.
+pub fn parse_apply_patch_verified(
+    patch: &str,
+    cwd: &Path,
+) -> std::result::Result<ApplyPatchAction, ApplyPatchError> {
+    let hunks = parse_patch(patch)?;
+    apply_patch_action_from_hunks(hunks, cwd)
+}
+
+fn apply_patch_action_from_hunks(
+    hunks: Vec<Hunk>,
+    cwd: &Path,
+) -> std::result::Result<ApplyPatchAction, ApplyPatchError> {
+    let mut changes = HashMap::new();
+    for hunk in hunks {
+        let path = hunk.resolve_path(cwd);
+        match hunk {
+            Hunk::AddFile { contents, .. } => {
+                changes.insert(path, ApplyPatchFileChange::Add { content: contents });
+            }
+            Hunk::DeleteFile { .. } => {
+                changes.insert(path, ApplyPatchFileChange::Delete);
+            }
+            Hunk::UpdateFile {
+                move_path, chunks, ..
+            } => {
+                let ApplyPatchFileUpdate {
+                    unified_diff,
+                    content: contents,
+                } = unified_diff_from_chunks(&path, &chunks)?;
+                changes.insert(
+                    path,
+                    ApplyPatchFileChange::Update {
+                        unified_diff,
+                        move_path: move_path.map(|p| cwd.join(p)),
+                        new_content: contents,
+                    },
+                );
+            }
+        }
+    }
+    Ok(ApplyPatchAction { changes })
+}
+
 /// Attempts to extract a heredoc_body object from a string bash command like:
 /// Optimistically
 ///
@@ -583,7 +693,7 @@ pub fn unified_diff_from_chunks(
     path: &Path,
     chunks: &[UpdateFileChunk],
 ) -> std::result::Result<ApplyPatchFileUpdate, ApplyPatchError> {
-    unified_diff_from_chunks_with_context(path, chunks, 1)
+    unified_diff_from_chunks_with_context(path, chunks, UNIFIED_DIFF_CONTEXT)
 }
 
 pub fn unified_diff_from_chunks_with_context(
@@ -1153,6 +1263,32 @@ g
         );
     }
 
+    #[test]
+    fn test_parse_apply_patch_verified_reports_line_number() {
+        let dir = tempdir().unwrap();
+        let patch = "*** Begin Patch\n*** Add File: a.txt\n+hello\n*** Frobnicate File: b.txt\n*** End Patch";
+
+        assert_eq!(
+            parse_apply_patch_verified(patch, dir.path()),
+            Err(ApplyPatchError::ParseError(InvalidHunkError {
+                message: "'*** Frobnicate File: b.txt' is not a valid hunk header. \
+                Valid hunk headers: '*** Add File: {path}', '*** Delete File: {path}', '*** Update File: {path}'"
+                    .to_string(),
+                line_number: 4,
+            }))
+        );
+
+        let action = parse_apply_patch_verified(
+            "*** Begin Patch\n*** Add File: a.txt\n+hello\n*** End Patch",
+            dir.path(),
+        )
+        .unwrap();
+        assert_eq!(
+            action,
+            ApplyPatchAction::new_add_for_test(&dir.path().join("a.txt"), "hello\n".to_string())
+        );
+    }
+
     #[test]
     fn test_apply_patch_should_resolve_absolute_paths_in_cwd() {
         let session_dir = tempdir().unwrap();
@@ -1196,4 +1332,55 @@ g
             })
         );
     }
+
+    #[test]
+    fn test_without_rejected_drops_files_and_hunks() {
+        let dir = tempdir().unwrap();
+        let update_path = dir.path().join("update.txt");
+        let add_path = dir.path().join("add.txt");
+        fs::write(&update_path, "a\nb\nc\nd\ne\nf\ng\n").unwrap();
+
+        let patch = wrap_patch(
+            r#"*** Add File: add.txt
++added
+*** Update File: update.txt
+@@
+-a
++A
+@@
+ f
+-g
++G"#,
+        );
+        let action = parse_apply_patch_verified(&patch, dir.path()).unwrap();
+
+        let narrowed = action
+            .without_rejected(&HashMap::from([
+                (add_path.clone(), Rejection::WholeFile),
+                (update_path.clone(), Rejection::Hunks(vec![0])),
+            ]))
+            .unwrap();
+        assert_eq!(
+            narrowed,
+            ApplyPatchAction {
+                changes: HashMap::from([(
+                    update_path.clone(),
+                    ApplyPatchFileChange::Update {
+                        unified_diff: "@@ -6,2 +6,2 @@\n f\n-g\n+G\n".to_string(),
+                        move_path: None,
+                        new_content: "a\nb\nc\nd\ne\nf\nG\n".to_string(),
+                    },
+                )]),
+            }
+        );
+
+        // Rejecting every hunk leaves nothing to apply.
+        let narrowed = action
+            .without_rejected(&HashMap::from([
+                (add_path, Rejection::Hunks(vec![0])),
+                (update_path, Rejection::Hunks(vec![0, 1])),
+            ]))
+            .unwrap();
+        assert!(narrowed.is_empty());
+    }
 }
@@ -2,6 +2,8 @@ pub mod debug_sandbox;
 mod exit_status;
 pub mod login;
 pub mod proto;
+pub mod rules;
+pub mod sessions;
 
 use clap::Parser;
 use codex_common::CliConfigOverrides;
@@ -3,8 +3,11 @@ use codex_cli::LandlockCommand;
 use codex_cli::SeatbeltCommand;
 use codex_cli::login::run_login_with_chatgpt;
 use codex_cli::proto;
+use codex_cli::rules::RulesCli;
+use codex_cli::sessions::SessionsCli;
 use codex_common::CliConfigOverrides;
 use codex_exec::Cli as ExecCli;
+use codex_mcp_server::Cli as McpCli;
 use codex_tui::Cli as TuiCli;
 use std::path::PathBuf;
 
@@ -41,12 +44,18 @@ enum Subcommand {
     Login(LoginCommand),
 
     /// Experimental: run Codex as an MCP server.
-    Mcp,
+    Mcp(McpCli),
 
     /// Run the Protocol stream via stdin/stdout
     #[clap(visible_alias = "p")]
     Proto(ProtoCli),
 
+    /// Manage the approval rules saved for a project.
+    Rules(RulesCli),
+
+    /// List, inspect, export and prune saved sessions.
+    Sessions(SessionsCli),
+
     /// Internal debugging commands.
     Debug(DebugArgs),
 }
@@ -92,8 +101,8 @@ async fn cli_main(codex_linux_sandbox_exe: Option<PathBuf>) -> anyhow::Result<()
             prepend_config_flags(&mut exec_cli.config_overrides, cli.config_overrides);
             codex_exec::run_main(exec_cli, codex_linux_sandbox_exe).await?;
         }
-        Some(Subcommand::Mcp) => {
-            codex_mcp_server::run_main(codex_linux_sandbox_exe).await?;
+        Some(Subcommand::Mcp(mcp_cli)) => {
+            codex_mcp_server::run_main(mcp_cli, codex_linux_sandbox_exe).await?;
         }
         Some(Subcommand::Login(mut login_cli)) => {
             prepend_config_flags(&mut login_cli.config_overrides, cli.config_overrides);
@@ -103,6 +112,13 @@ async fn cli_main(codex_linux_sandbox_exe: Option<PathBuf>) -> anyhow::Result<()
             prepend_config_flags(&mut proto_cli.config_overrides, cli.config_overrides);
             proto::run_main(proto_cli).await?;
         }
+        Some(Subcommand::Rules(mut rules_cli)) => {
+            prepend_config_flags(&mut rules_cli.config_overrides, cli.config_overrides);
+            codex_cli::rules::run_main(rules_cli)?;
+        }
+        Some(Subcommand::Sessions(sessions_cli)) => {
+            codex_cli::sessions::run_main(sessions_cli)?;
+        }
         Some(Subcommand::Debug(debug_args)) => match debug_args.cmd {
             DebugCommand::Seatbelt(mut seatbelt_cli) => {
                 prepend_config_flags(&mut seatbelt_cli.config_overrides, cli.config_overrides);
@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use codex_common::CliConfigOverrides;
+use codex_core::approval_rules::ApprovalRulesStore;
+use codex_core::config::Config;
+use codex_core::config::ConfigOverrides;
+
+#[derive(Debug, Parser)]
+pub struct RulesCli {
+    #[clap(skip)]
+    pub config_overrides: CliConfigOverrides,
+
+    /// Directory of the project whose rules to manage. Defaults to the current
+    /// directory.
+    #[arg(long = "cd", short = 'C', value_name = "DIR")]
+    pub cwd: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub cmd: RulesCommand,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum RulesCommand {
+    /// List the approval rules saved for the project.
+    List,
+
+    /// Revoke a rule, identified by its number in `codex rules list`.
+    Revoke { number: usize },
+}
+
+pub fn run_main(cli: RulesCli) -> anyhow::Result<()> {
+    let cli_overrides = cli
+        .config_overrides
+        .parse_overrides()
+        .map_err(anyhow::Error::msg)?;
+    let config_overrides = ConfigOverrides {
+        cwd: cli.cwd,
+        ..Default::default()
+    };
+    let config = Config::load_with_cli_overrides(cli_overrides, config_overrides)?;
+    let store = ApprovalRulesStore::new(&config.codex_home, &config.cwd);
+    let rules = store.load()?;
+
+    match cli.cmd {
+        RulesCommand::List => {
+            println!("Approval rules for {}:", store.project().display());
+            if rules.is_empty() {
+                println!("  (none)");
+            }
+            for (idx, rule) in rules.iter().enumerate() {
+                println!("  {}. {rule}", idx + 1);
+            }
+        }
+        RulesCommand::Revoke { number } => {
+            let Some(rule) = number.checked_sub(1).and_then(|idx| rules.get(idx)) else {
+                anyhow::bail!(
+                    "no rule #{number} for {}; see `codex rules list`",
+                    store.project().display()
+                );
+            };
+            store.revoke(rule)?;
+            println!("Revoked: {rule}");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,395 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use clap::Parser;
+use clap::ValueEnum;
+use codex_core::config::find_codex_home;
+use codex_core::rollout::RetentionPolicy;
+use codex_core::rollout::SessionInfo;
+use codex_core::rollout::TranscriptEntry;
+use codex_core::rollout::find_session;
+use codex_core::rollout::list_sessions;
+use codex_core::rollout::read_transcript;
+
+/// Width at which `codex sessions list` cuts off the first prompt.
+const PROMPT_PREVIEW_CHARS: usize = 60;
+
+#[derive(Debug, Parser)]
+pub struct SessionsCli {
+    #[command(subcommand)]
+    pub cmd: SessionsCommand,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum SessionsCommand {
+    /// List saved sessions, most recently active first.
+    List {
+        /// Only show the N most recent sessions.
+        #[arg(long, short = 'n', value_name = "N")]
+        limit: Option<usize>,
+    },
+
+    /// Print the transcript of a session.
+    Show {
+        /// Session id, or a unique prefix of it.
+        id: String,
+    },
+
+    /// Export the transcript of a session as Markdown or HTML.
+    Export {
+        /// Session id, or a unique prefix of it.
+        id: String,
+
+        #[arg(long, value_enum, default_value_t = ExportFormat::Markdown)]
+        format: ExportFormat,
+
+        /// Write to FILE instead of stdout.
+        #[arg(long, short = 'o', value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Delete old sessions. A session is removed if it falls outside either
+    /// limit. Sessions written to in the last hour are kept, since they may
+    /// still be running.
+    #[command(group(
+        clap::ArgGroup::new("retention")
+            .required(true)
+            .multiple(true)
+            .args(["keep", "older_than"])
+    ))]
+    Prune {
+        /// Keep only the N most recently active sessions.
+        #[arg(long, value_name = "N")]
+        keep: Option<usize>,
+
+        /// Remove sessions not written to in the last DAYS days.
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u64>,
+
+        /// Print what would be removed without deleting anything.
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+}
+
+pub fn run_main(cli: SessionsCli) -> anyhow::Result<()> {
+    // Only the sessions directory is needed, so skip loading (and validating)
+    // the full config.
+    let codex_home = find_codex_home()?;
+
+    match cli.cmd {
+        SessionsCommand::List { limit } => {
+            let mut sessions = list_sessions(&codex_home)?;
+            if let Some(limit) = limit {
+                sessions.truncate(limit);
+            }
+            if sessions.is_empty() {
+                println!("No saved sessions.");
+                return Ok(());
+            }
+            println!(
+                "{:<8}  {:<16}  {:<12}  {:<32}  PROMPT",
+                "ID", "STARTED", "MODEL", "CWD"
+            );
+            for session in &sessions {
+                println!(
+                    "{:<8}  {:<16}  {:<12}  {:<32}  {}",
+                    short_id(&session.id),
+                    started(session),
+                    session.model.as_deref().unwrap_or("-"),
+                    session
+                        .cwd
+                        .as_ref()
+                        .map_or_else(|| "-".to_string(), |cwd| cwd.display().to_string()),
+                    session
+                        .first_prompt
+                        .as_deref()
+                        .map_or_else(|| "-".to_string(), prompt_preview),
+                );
+            }
+        }
+        SessionsCommand::Show { id } => {
+            let session = find_session(&codex_home, &id)?;
+            let transcript = read_transcript(&session.path)?;
+            print!("{}", render_text(&session, &transcript));
+        }
+        SessionsCommand::Export { id, format, output } => {
+            let session = find_session(&codex_home, &id)?;
+            let transcript = read_transcript(&session.path)?;
+            let rendered = match format {
+                ExportFormat::Markdown => render_markdown(&session, &transcript),
+                ExportFormat::Html => render_html(&session, &transcript),
+            };
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, rendered)?;
+                    eprintln!("Exported session {} to {}", session.id, path.display());
+                }
+                None => print!("{rendered}"),
+            }
+        }
+        SessionsCommand::Prune {
+            keep,
+            older_than,
+            dry_run,
+        } => {
+            let policy = RetentionPolicy {
+                keep_last: keep,
+                max_age: older_than.map(|days| Duration::from_secs(days * 24 * 60 * 60)),
+            };
+            let sessions = list_sessions(&codex_home)?;
+            let expired = policy.expired(&sessions, SystemTime::now());
+            for session in &expired {
+                if dry_run {
+                    println!("Would remove {}", session.path.display());
+                } else {
+                    std::fs::remove_file(&session.path)?;
+                    println!("Removed {}", session.path.display());
+                }
+            }
+            let verb = if dry_run { "Would remove" } else { "Removed" };
+            println!("{verb} {} of {} sessions.", expired.len(), sessions.len());
+        }
+    }
+    Ok(())
+}
+
+fn short_id(id: &str) -> &str {
+    id.get(..8).unwrap_or(id)
+}
+
+/// `2025-05-07T17:24:21.123Z` -> `2025-05-07 17:24`.
+fn started(session: &SessionInfo) -> String {
+    session
+        .timestamp
+        .get(..16)
+        .unwrap_or(&session.timestamp)
+        .replacen('T', " ", 1)
+}
+
+fn prompt_preview(prompt: &str) -> String {
+    let line = prompt.lines().next().unwrap_or_default();
+    if line.chars().count() > PROMPT_PREVIEW_CHARS || prompt.lines().nth(1).is_some() {
+        let truncated = line.chars().take(PROMPT_PREVIEW_CHARS).collect::<String>();
+        format!("{truncated}…")
+    } else {
+        line.to_string()
+    }
+}
+
+/// Header fields shared by every rendering of a session.
+fn metadata(session: &SessionInfo) -> Vec<(&'static str, String)> {
+    let mut fields = vec![("Started", session.timestamp.clone())];
+    if let Some(model) = &session.model {
+        fields.push(("Model", model.clone()));
+    }
+    if let Some(cwd) = &session.cwd {
+        fields.push(("Directory", cwd.display().to_string()));
+    }
+    if let Some(git) = &session.git {
+        let branch = git.branch.as_deref().unwrap_or("(detached)");
+        let git = match &git.commit {
+            Some(commit) => format!("{branch} @ {}", commit.get(..12).unwrap_or(commit)),
+            None => branch.to_string(),
+        };
+        fields.push(("Git", git));
+    }
+    fields
+}
+
+fn render_text(session: &SessionInfo, transcript: &[TranscriptEntry]) -> String {
+    let mut out = format!("Session {}\n", session.id);
+    for (name, value) in metadata(session) {
+        out.push_str(&format!("{name}: {value}\n"));
+    }
+    for entry in transcript {
+        let (heading, body) = match entry {
+            TranscriptEntry::User(text) => ("user".to_string(), text.as_str()),
+            TranscriptEntry::Assistant(text) => ("codex".to_string(), text.as_str()),
+            TranscriptEntry::ToolCall { name, arguments } => {
+                (format!("tool call: {name}"), arguments.as_str())
+            }
+            TranscriptEntry::ToolOutput(output) => ("tool output".to_string(), output.as_str()),
+        };
+        out.push_str(&format!("\n{heading}\n"));
+        for line in body.lines() {
+            out.push_str(&format!("  {line}\n"));
+        }
+    }
+    out
+}
+
+fn render_markdown(session: &SessionInfo, transcript: &[TranscriptEntry]) -> String {
+    let mut out = format!("# Codex session {}\n\n", session.id);
+    for (name, value) in metadata(session) {
+        out.push_str(&format!("- **{name}:** {value}\n"));
+    }
+    for entry in transcript {
+        match entry {
+            TranscriptEntry::User(text) => out.push_str(&format!("\n## User\n\n{text}\n")),
+            TranscriptEntry::Assistant(text) => out.push_str(&format!("\n## Codex\n\n{text}\n")),
+            TranscriptEntry::ToolCall { name, arguments } => {
+                out.push_str(&format!("\n**Tool call:** `{name}`\n\n"));
+                out.push_str(&code_block(arguments));
+            }
+            TranscriptEntry::ToolOutput(output) => {
+                out.push_str("\n**Output:**\n\n");
+                out.push_str(&code_block(output));
+            }
+        }
+    }
+    out
+}
+
+/// Fences `text` with enough backticks that any fences inside it survive.
+fn code_block(text: &str) -> String {
+    let mut longest_run = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest_run = longest_run.max(run);
+    }
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{fence}\n{}\n{fence}\n", text.trim_end_matches('\n'))
+}
+
+fn render_html(session: &SessionInfo, transcript: &[TranscriptEntry]) -> String {
+    let title = format!("Codex session {}", escape_html(&session.id));
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>\n\
+         body {{ font-family: sans-serif; max-width: 60rem; margin: 2rem auto; }}\n\
+         pre {{ white-space: pre-wrap; background: #f5f5f5; padding: 0.75rem; }}\n\
+         .user h2 {{ color: #0b5cad; }}\n\
+         .assistant h2 {{ color: #8a3ab9; }}\n\
+         .tool h3 {{ color: #555; }}\n\
+         </style>\n</head>\n<body>\n<h1>{title}</h1>\n<dl>\n"
+    );
+    for (name, value) in metadata(session) {
+        out.push_str(&format!(
+            "<dt>{name}</dt><dd>{}</dd>\n",
+            escape_html(&value)
+        ));
+    }
+    out.push_str("</dl>\n");
+    for entry in transcript {
+        let (class, heading, body) = match entry {
+            TranscriptEntry::User(text) => ("user", "<h2>User</h2>".to_string(), text),
+            TranscriptEntry::Assistant(text) => ("assistant", "<h2>Codex</h2>".to_string(), text),
+            TranscriptEntry::ToolCall { name, arguments } => (
+                "tool",
+                format!("<h3>Tool call: <code>{}</code></h3>", escape_html(name)),
+                arguments,
+            ),
+            TranscriptEntry::ToolOutput(output) => ("tool", "<h3>Output</h3>".to_string(), output),
+        };
+        out.push_str(&format!(
+            "<section class=\"{class}\">\n{heading}\n<pre>{}</pre>\n</section>\n",
+            escape_html(body)
+        ));
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codex_core::rollout::GitInfo;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn code_block_fence_outlasts_inner_fences() {
+        assert_eq!(code_block("ls -a\n"), "```\nls -a\n```\n");
+        assert_eq!(
+            code_block("```rust\nfn main() {}\n```"),
+            "````\n```rust\nfn main() {}\n```\n````\n"
+        );
+    }
+
+    #[test]
+    fn escape_html_escapes_markup_and_quotes() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn renders_markdown_transcript() {
+        let session = SessionInfo {
+            path: PathBuf::from("rollout.jsonl"),
+            id: "abcd".to_string(),
+            timestamp: "2025-05-07T17:24:21.123Z".to_string(),
+            modified: SystemTime::UNIX_EPOCH,
+            cwd: Some(PathBuf::from("/repo")),
+            model: Some("o3".to_string()),
+            git: Some(GitInfo {
+                branch: Some("main".to_string()),
+                commit: Some("0123456789abcdef".to_string()),
+            }),
+            first_prompt: None,
+        };
+        let transcript = vec![
+            TranscriptEntry::User("list files".to_string()),
+            TranscriptEntry::ToolCall {
+                name: "shell".to_string(),
+                arguments: "ls".to_string(),
+            },
+            TranscriptEntry::ToolOutput("README.md".to_string()),
+            TranscriptEntry::Assistant("Done.".to_string()),
+        ];
+        assert_eq!(
+            render_markdown(&session, &transcript),
+            "# Codex session abcd
+
+- **Started:** 2025-05-07T17:24:21.123Z
+- **Model:** o3
+- **Directory:** /repo
+- **Git:** main @ 0123456789ab
+
+## User
+
+list files
+
+**Tool call:** `shell`
+
+```
+ls
+```
+
+**Output:**
+
+```
+README.md
+```
+
+## Codex
+
+Done.
+"
+        );
+    }
+}
@@ -9,6 +9,12 @@ pub use approval_mode_cli_arg::ApprovalModeCliArg;
 #[cfg(feature = "cli")]
 pub use approval_mode_cli_arg::SandboxPermissionOption;
 
+#[cfg(feature = "cli")]
+mod sandbox_mode_cli_arg;
+
+#[cfg(feature = "cli")]
+pub use sandbox_mode_cli_arg::SandboxModeCliArg;
+
 #[cfg(any(feature = "cli", test))]
 mod config_override;
 
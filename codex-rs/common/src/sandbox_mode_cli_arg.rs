@@ -0,0 +1,30 @@
+//! Standard type to use with the `--sandbox` CLI option.
+//! Available when the `cli` feature is enabled for the crate.
+
+use clap::ValueEnum;
+
+use codex_core::config_types::SandboxMode;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+pub enum SandboxModeCliArg {
+    /// Commands may read any file but write nothing and have no network.
+    ReadOnly,
+
+    /// Commands may also write to the working directory and the temp dir
+    /// (plus `sandbox_workspace_write` settings from config.toml).
+    WorkspaceWrite,
+
+    /// Commands run without any sandbox.
+    DangerFullAccess,
+}
+
+impl From<SandboxModeCliArg> for SandboxMode {
+    fn from(value: SandboxModeCliArg) -> Self {
+        match value {
+            SandboxModeCliArg::ReadOnly => SandboxMode::ReadOnly,
+            SandboxModeCliArg::WorkspaceWrite => SandboxMode::WorkspaceWrite,
+            SandboxModeCliArg::DangerFullAccess => SandboxMode::DangerFullAccess,
+        }
+    }
+}
@@ -0,0 +1,317 @@
+//! Approval rules that outlive a session.
+//!
+//! When the user approves a request and asks Codex to remember it, the
+//! resulting [`ApprovalRule`] is saved to `~/.codex/approval_rules.json`,
+//! keyed by project so that a rule granted in one repository never applies to
+//! another:
+//!
+//! ````text
+//! {"projects":{"/home/me/src/app":[{"type":"command_prefix","prefix":["cargo","test"]}]}}
+//! ````
+//!
+//! The project is the root of the git repository containing the session's
+//! `cwd`, or `cwd` itself outside of a repository.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Result;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::is_safe_command::parse_bash_lc_plain_command;
+use crate::protocol::ApprovalRule;
+
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+
+/// Filename that stores the approval rules inside `~/.codex`.
+const APPROVAL_RULES_FILENAME: &str = "approval_rules.json";
+
+/// Sidecar file locked around every read-modify-write of the rules file. The
+/// rules file itself is replaced on each write, so it cannot carry the lock.
+const APPROVAL_RULES_LOCK_FILENAME: &str = "approval_rules.json.lock";
+
+/// Maximum number of words in a suggested command prefix.
+const MAX_SUGGESTED_PREFIX_WORDS: usize = 3;
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct ApprovalRulesFile {
+    #[serde(default)]
+    projects: BTreeMap<PathBuf, Vec<ApprovalRule>>,
+}
+
+/// The approval rules file, viewed through the rules of a single project.
+#[derive(Debug, Clone)]
+pub struct ApprovalRulesStore {
+    path: PathBuf,
+    lock_path: PathBuf,
+    project: PathBuf,
+}
+
+impl ApprovalRulesStore {
+    pub fn new(codex_home: &Path, cwd: &Path) -> Self {
+        Self {
+            path: codex_home.join(APPROVAL_RULES_FILENAME),
+            lock_path: codex_home.join(APPROVAL_RULES_LOCK_FILENAME),
+            project: project_root(cwd),
+        }
+    }
+
+    /// The directory the rules are scoped to.
+    pub fn project(&self) -> &Path {
+        &self.project
+    }
+
+    /// Rules saved for this project. A missing file means there are none.
+    pub fn load(&self) -> Result<Vec<ApprovalRule>> {
+        Ok(self
+            .read_file()?
+            .projects
+            .remove(&self.project)
+            .unwrap_or_default())
+    }
+
+    /// Saves `rule` unless it is already present. Returns the project's rules.
+    pub fn add(&self, rule: ApprovalRule) -> Result<Vec<ApprovalRule>> {
+        let _lock = self.lock()?;
+        let mut file = self.read_file()?;
+        let rules = file.projects.entry(self.project.clone()).or_default();
+        if !rules.contains(&rule) {
+            rules.push(rule);
+        }
+        let rules = rules.clone();
+        self.write_file(&file)?;
+        Ok(rules)
+    }
+
+    /// Removes `rule`. Returns the remaining rules, or `None` if the project
+    /// had no such rule.
+    pub fn revoke(&self, rule: &ApprovalRule) -> Result<Option<Vec<ApprovalRule>>> {
+        let _lock = self.lock()?;
+        let mut file = self.read_file()?;
+        let Some(rules) = file.projects.get_mut(&self.project) else {
+            return Ok(None);
+        };
+        let len_before = rules.len();
+        rules.retain(|r| r != rule);
+        if rules.len() == len_before {
+            return Ok(None);
+        }
+        let rules = rules.clone();
+        if rules.is_empty() {
+            file.projects.remove(&self.project);
+        }
+        self.write_file(&file)?;
+        Ok(Some(rules))
+    }
+
+    /// Takes the exclusive advisory lock that serialises writers across
+    /// processes, so that concurrent sessions do not drop each other's rules.
+    /// The lock is released when the returned file is dropped.
+    fn lock(&self) -> Result<File> {
+        if let Some(parent) = self.lock_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let lock = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.lock_path)?;
+        fs2::FileExt::lock_exclusive(&lock)?;
+        Ok(lock)
+    }
+
+    fn read_file(&self) -> Result<ApprovalRulesFile> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|e| {
+                std::io::Error::other(format!("failed to parse {}: {e}", self.path.display()))
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(ApprovalRulesFile::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Replaces the file via a rename so that a concurrent reader never sees a
+    /// partially written file.
+    fn write_file(&self, file: &ApprovalRulesFile) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(file)
+            .map_err(|e| std::io::Error::other(format!("failed to serialise rules: {e}")))?;
+
+        let tmp_path = self
+            .path
+            .with_extension(format!("json.{}", std::process::id()));
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            options.mode(0o600);
+        }
+        let mut tmp = options.open(&tmp_path)?;
+        tmp.write_all(json.as_bytes())?;
+        tmp.flush()?;
+        drop(tmp);
+        std::fs::rename(&tmp_path, &self.path)
+    }
+}
+
+/// Whether one of `rules` approves running `command`.
+pub(crate) fn rules_allow_command(rules: &[ApprovalRule], command: &[String]) -> bool {
+    let words = command_words(command);
+    rules.iter().any(|rule| match rule {
+        ApprovalRule::CommandPrefix { prefix } => !prefix.is_empty() && words.starts_with(prefix),
+        ApprovalRule::WritableRoot { .. } => false,
+    })
+}
+
+/// The prefix offered when the user asks to always allow commands like
+/// `command`: the program followed by its leading subcommand-like words, e.g.
+/// `cargo test` for `cargo test -p core`. Returns `None` for scripts that are
+/// not a single plain command, since no prefix would describe them.
+pub fn suggested_command_prefix(command: &[String]) -> Option<Vec<String>> {
+    if is_bash_lc(command) && parse_bash_lc_plain_command(command).is_none() {
+        return None;
+    }
+    let words = command_words(command);
+    let (program, rest) = words.split_first()?;
+    let mut prefix = vec![program.clone()];
+    prefix.extend(
+        rest.iter()
+            .take_while(|word| !word.starts_with('-') && !word.contains(['/', '=']))
+            .take(MAX_SUGGESTED_PREFIX_WORDS - 1)
+            .cloned(),
+    );
+    Some(prefix)
+}
+
+/// The words rules are matched against: the script's words for a plain
+/// `bash -lc` command, the argv otherwise.
+fn command_words(command: &[String]) -> Vec<String> {
+    parse_bash_lc_plain_command(command).unwrap_or_else(|| command.to_vec())
+}
+
+fn is_bash_lc(command: &[String]) -> bool {
+    matches!(command, [bash, flag, _] if bash == "bash" && flag == "-lc")
+}
+
+/// The closest ancestor of `cwd` (inclusive) that contains `.git`, or `cwd`.
+fn project_root(cwd: &Path) -> PathBuf {
+    cwd.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(cwd)
+        .to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    fn vec_str(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn prefix_rules_match_argv_and_plain_bash_scripts() {
+        let rules = vec![ApprovalRule::CommandPrefix {
+            prefix: vec_str(&["cargo", "test"]),
+        }];
+        assert!(rules_allow_command(&rules, &vec_str(&["cargo", "test"])));
+        assert!(rules_allow_command(
+            &rules,
+            &vec_str(&["bash", "-lc", "cargo test -p core"])
+        ));
+        assert!(!rules_allow_command(&rules, &vec_str(&["cargo", "build"])));
+        assert!(!rules_allow_command(
+            &rules,
+            &vec_str(&["bash", "-lc", "cargo test && rm -rf /"])
+        ));
+    }
+
+    #[test]
+    fn suggested_prefixes() {
+        assert_eq!(
+            suggested_command_prefix(&vec_str(&["cargo", "test", "-p", "core"])),
+            Some(vec_str(&["cargo", "test"]))
+        );
+        assert_eq!(
+            suggested_command_prefix(&vec_str(&["bash", "-lc", "npm run lint"])),
+            Some(vec_str(&["npm", "run", "lint"]))
+        );
+        assert_eq!(
+            suggested_command_prefix(&vec_str(&["python", "./build.py", "all"])),
+            Some(vec_str(&["python"]))
+        );
+        assert_eq!(
+            suggested_command_prefix(&vec_str(&["bash", "-lc", "make | tee log"])),
+            None
+        );
+    }
+
+    #[test]
+    fn store_is_scoped_per_project() {
+        let home = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+        std::fs::create_dir(repo.path().join(".git")).unwrap();
+        std::fs::create_dir(repo.path().join("sub")).unwrap();
+        let other = TempDir::new().unwrap();
+
+        let store = ApprovalRulesStore::new(home.path(), &repo.path().join("sub"));
+        assert_eq!(store.project(), repo.path());
+        assert_eq!(store.load().unwrap(), Vec::new());
+
+        let prefix = ApprovalRule::CommandPrefix {
+            prefix: vec_str(&["cargo", "test"]),
+        };
+        let root = ApprovalRule::WritableRoot {
+            path: PathBuf::from("/tmp/out"),
+        };
+        store.add(prefix.clone()).unwrap();
+        store.add(root.clone()).unwrap();
+        assert_eq!(store.add(prefix.clone()).unwrap().len(), 2);
+
+        let reloaded = ApprovalRulesStore::new(home.path(), repo.path());
+        assert_eq!(reloaded.load().unwrap(), vec![prefix.clone(), root.clone()]);
+        let other_store = ApprovalRulesStore::new(home.path(), other.path());
+        assert_eq!(other_store.load().unwrap(), Vec::new());
+
+        assert_eq!(reloaded.revoke(&prefix).unwrap(), Some(vec![root]));
+        assert_eq!(reloaded.revoke(&prefix).unwrap(), None);
+    }
+
+    #[test]
+    fn concurrent_adds_keep_every_rule() {
+        let home = TempDir::new().unwrap();
+        let project = TempDir::new().unwrap();
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                // Separate stores, as separate sessions would have.
+                let store = ApprovalRulesStore::new(home.path(), project.path());
+                std::thread::spawn(move || {
+                    store
+                        .add(ApprovalRule::CommandPrefix {
+                            prefix: vec![format!("tool{i}")],
+                        })
+                        .unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let store = ApprovalRulesStore::new(home.path(), project.path());
+        assert_eq!(store.load().unwrap().len(), 8);
+    }
+}
@@ -1,11 +1,13 @@
 use std::io::BufRead;
 use std::path::Path;
 use std::time::Duration;
+use std::time::SystemTime;
 
 use bytes::Bytes;
 use eventsource_stream::Eventsource;
 use futures::prelude::*;
 use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json::Value;
@@ -22,15 +24,18 @@ use crate::client_common::Prompt;
 use crate::client_common::ResponseEvent;
 use crate::client_common::ResponseStream;
 use crate::client_common::ResponsesApiRequest;
+use crate::client_common::RetryNotifier;
+use crate::client_common::TokenUsage;
 use crate::client_common::create_reasoning_param_for_request;
+use crate::client_common::create_text_param_for_request;
 use crate::config_types::ReasoningEffort as ReasoningEffortConfig;
 use crate::config_types::ReasoningSummary as ReasoningSummaryConfig;
 use crate::error::CodexErr;
-use crate::error::EnvVarError;
 use crate::error::Result;
 use crate::flags::CODEX_RS_SSE_FIXTURE;
-use crate::flags::OPENAI_REQUEST_MAX_RETRIES;
-use crate::flags::OPENAI_STREAM_IDLE_TIMEOUT_MS;
+use crate::flags::OPENAI_MAX_RETRY_DELAY_MS;
+use crate::messages_api::stream_messages;
+use crate::model_family::ModelFamily;
 use crate::model_provider_info::ModelProviderInfo;
 use crate::model_provider_info::WireApi;
 use crate::models::ResponseItem;
@@ -40,6 +45,7 @@ use crate::util::backoff;
 #[derive(Clone)]
 pub struct ModelClient {
     model: String,
+    model_family: ModelFamily,
     client: reqwest::Client,
     provider: ModelProviderInfo,
     effort: ReasoningEffortConfig,
@@ -49,12 +55,14 @@ pub struct ModelClient {
 impl ModelClient {
     pub fn new(
         model: impl ToString,
+        model_family: ModelFamily,
         provider: ModelProviderInfo,
         effort: ReasoningEffortConfig,
         summary: ReasoningSummaryConfig,
     ) -> Self {
         Self {
             model: model.to_string(),
+            model_family,
             client: reqwest::Client::new(),
             provider,
             effort,
@@ -62,20 +70,75 @@ impl ModelClient {
         }
     }
 
-    /// Dispatches to either the Responses or Chat implementation depending on
+    /// A client for `model` served by `provider`, with the same reasoning
+    /// settings.
+    pub fn with_model(
+        &self,
+        model: impl ToString,
+        model_family: ModelFamily,
+        provider: ModelProviderInfo,
+    ) -> Self {
+        Self {
+            model: model.to_string(),
+            model_family,
+            provider,
+            ..self.clone()
+        }
+    }
+
+    /// The same client with different reasoning settings.
+    pub fn with_reasoning(
+        &self,
+        effort: ReasoningEffortConfig,
+        summary: ReasoningSummaryConfig,
+    ) -> Self {
+        Self {
+            effort,
+            summary,
+            ..self.clone()
+        }
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn model_family(&self) -> &ModelFamily {
+        &self.model_family
+    }
+
+    pub fn reasoning_effort(&self) -> ReasoningEffortConfig {
+        self.effort
+    }
+
+    pub fn reasoning_summary(&self) -> ReasoningSummaryConfig {
+        self.summary
+    }
+
+    pub fn provider(&self) -> &ModelProviderInfo {
+        &self.provider
+    }
+
+    /// Dispatches to the Responses, Chat or Messages implementation depending on
     /// the provider config.  Public callers always invoke `stream()` – the
     /// specialised helpers are private to avoid accidental misuse.
-    pub async fn stream(&self, prompt: &Prompt) -> Result<ResponseStream> {
+    pub async fn stream(
+        &self,
+        prompt: &Prompt,
+        on_retry: &RetryNotifier,
+    ) -> Result<ResponseStream> {
         match self.provider.wire_api {
-            WireApi::Responses => self.stream_responses(prompt).await,
+            WireApi::Responses => self.stream_responses(prompt, on_retry).await,
             WireApi::Chat => {
                 // Create the raw streaming connection first.
                 let response_stream = stream_chat_completions(
                     prompt,
                     &self.model,
+                    &self.model_family,
                     &self.client,
                     &self.provider,
                     prompt.stream,
+                    on_retry,
                 )
                 .await?;
 
@@ -100,20 +163,36 @@ impl ModelClient {
 
                 Ok(ResponseStream { rx_event: rx })
             }
+            WireApi::Messages => {
+                stream_messages(
+                    prompt,
+                    &self.model,
+                    &self.model_family,
+                    &self.client,
+                    &self.provider,
+                    on_retry,
+                )
+                .await
+            }
         }
     }
 
     /// Implementation for the OpenAI *Responses* experimental API.
-    async fn stream_responses(&self, prompt: &Prompt) -> Result<ResponseStream> {
+    async fn stream_responses(
+        &self,
+        prompt: &Prompt,
+        on_retry: &RetryNotifier,
+    ) -> Result<ResponseStream> {
         if let Some(path) = &*CODEX_RS_SSE_FIXTURE {
             // short circuit for tests
             warn!(path, "Streaming from fixture");
-            return stream_from_fixture(path).await;
+            return stream_from_fixture(path, self.provider.stream_idle_timeout()).await;
         }
 
-        let full_instructions = prompt.get_full_instructions(&self.model);
-        let tools_json = create_tools_json_for_responses_api(prompt, &self.model)?;
-        let reasoning = create_reasoning_param_for_request(&self.model, self.effort, self.summary);
+        let full_instructions = prompt.get_full_instructions(&self.model_family);
+        let tools_json = create_tools_json_for_responses_api(prompt, &self.model_family)?;
+        let reasoning =
+            create_reasoning_param_for_request(&self.model_family, self.effort, self.summary);
         let payload = ResponsesApiRequest {
             model: &self.model,
             instructions: &full_instructions,
@@ -123,85 +202,165 @@ impl ModelClient {
             parallel_tool_calls: false,
             reasoning,
             previous_response_id: prompt.prev_id.clone(),
+            text: create_text_param_for_request(prompt.output_schema.as_ref()),
             store: prompt.store,
             stream: prompt.stream,
         };
 
-        let base_url = self.provider.base_url.clone();
-        let base_url = base_url.trim_end_matches('/');
-        let url = format!("{}/responses", base_url);
-        trace!("POST to {url}: {}", serde_json::to_string(&payload)?);
-
-        let mut attempt = 0;
-        loop {
-            attempt += 1;
-
-            let api_key = self.provider.api_key()?.ok_or_else(|| {
-                CodexErr::EnvVar(EnvVarError {
-                    var: self.provider.env_key.clone().unwrap_or_default(),
-                    instructions: None,
-                })
-            })?;
-            let res = self
-                .client
-                .post(&url)
-                .bearer_auth(api_key)
+        trace!(
+            "POST to {}/responses: {}",
+            self.provider.base_url,
+            serde_json::to_string(&payload)?
+        );
+
+        let resp = send_with_retries(&self.provider, on_retry, || {
+            Ok(self
+                .provider
+                .create_request_builder(&self.client, "responses")?
                 .header("OpenAI-Beta", "responses=experimental")
                 .header(reqwest::header::ACCEPT, "text/event-stream")
-                .json(&payload)
-                .send()
-                .await;
-            match res {
-                Ok(resp) if resp.status().is_success() => {
-                    let (tx_event, rx_event) = mpsc::channel::<Result<ResponseEvent>>(16);
-
-                    // spawn task to process SSE
-                    let stream = resp.bytes_stream().map_err(CodexErr::Reqwest);
-                    tokio::spawn(process_sse(stream, tx_event));
-
-                    return Ok(ResponseStream { rx_event });
-                }
-                Ok(res) => {
-                    let status = res.status();
-                    // The OpenAI Responses endpoint returns structured JSON bodies even for 4xx/5xx
-                    // errors. When we bubble early with only the HTTP status the caller sees an opaque
-                    // "unexpected status 400 Bad Request" which makes debugging nearly impossible.
-                    // Instead, read (and include) the response text so higher layers and users see the
-                    // exact error message (e.g. "Unknown parameter: 'input[0].metadata'"). The body is
-                    // small and this branch only runs on error paths so the extra allocation is
-                    // negligible.
-                    if !(status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()) {
-                        // Surface the error body to callers. Use `unwrap_or_default` per Clippy.
-                        let body = (res.text().await).unwrap_or_default();
-                        return Err(CodexErr::UnexpectedStatus(status, body));
-                    }
+                .json(&payload))
+        })
+        .await?;
 
-                    if attempt > *OPENAI_REQUEST_MAX_RETRIES {
-                        return Err(CodexErr::RetryLimit(status));
-                    }
+        let (tx_event, rx_event) = mpsc::channel::<Result<ResponseEvent>>(16);
+
+        // spawn task to process SSE
+        let stream = resp.bytes_stream().map_err(CodexErr::Reqwest);
+        tokio::spawn(process_sse(
+            stream,
+            tx_event,
+            self.provider.stream_idle_timeout(),
+        ));
+
+        Ok(ResponseStream { rx_event })
+    }
+}
 
-                    // Pull out Retry‑After header if present.
-                    let retry_after_secs = res
-                        .headers()
-                        .get(reqwest::header::RETRY_AFTER)
-                        .and_then(|v| v.to_str().ok())
-                        .and_then(|s| s.parse::<u64>().ok());
-
-                    let delay = retry_after_secs
-                        .map(|s| Duration::from_millis(s * 1_000))
-                        .unwrap_or_else(|| backoff(attempt));
-                    tokio::time::sleep(delay).await;
+/// Sends the request produced by `build_request`, retrying network errors,
+/// `429 Too Many Requests` and 5xx responses up to the provider's
+/// `request_max_retries`. Every wait is reported through `on_retry` so that
+/// the user can tell a rate-limited turn from a stalled one.
+pub(crate) async fn send_with_retries(
+    provider: &ModelProviderInfo,
+    on_retry: &RetryNotifier,
+    build_request: impl Fn() -> Result<reqwest::RequestBuilder>,
+) -> Result<reqwest::Response> {
+    let max_retries = provider.request_max_retries();
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+
+        let (delay, reason) = match build_request()?.send().await {
+            Ok(resp) if resp.status().is_success() => return Ok(resp),
+            Ok(res) => {
+                let status = res.status();
+                // The OpenAI Responses endpoint returns structured JSON bodies even for 4xx/5xx
+                // errors. When we bubble early with only the HTTP status the caller sees an opaque
+                // "unexpected status 400 Bad Request" which makes debugging nearly impossible.
+                // Instead, read (and include) the response text so higher layers and users see the
+                // exact error message (e.g. "Unknown parameter: 'input[0].metadata'"). The body is
+                // small and this branch only runs on error paths so the extra allocation is
+                // negligible.
+                if !(status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()) {
+                    // Surface the error body to callers. Use `unwrap_or_default` per Clippy.
+                    let body = (res.text().await).unwrap_or_default();
+                    return Err(CodexErr::UnexpectedStatus(status, body));
                 }
-                Err(e) => {
-                    if attempt > *OPENAI_REQUEST_MAX_RETRIES {
-                        return Err(e.into());
-                    }
-                    let delay = backoff(attempt);
-                    tokio::time::sleep(delay).await;
+
+                if attempt > max_retries {
+                    return Err(CodexErr::RetryLimit(status));
                 }
+
+                let delay = retry_delay_from_headers(res.headers(), SystemTime::now()).map_or_else(
+                    || backoff(attempt),
+                    |delay| delay.min(*OPENAI_MAX_RETRY_DELAY_MS),
+                );
+                let reason = if status == StatusCode::TOO_MANY_REQUESTS {
+                    format!("rate limited by {}", provider.name)
+                } else {
+                    format!("{} responded with {status}", provider.name)
+                };
+                (delay, reason)
             }
+            Err(e) => {
+                if attempt > max_retries {
+                    return Err(e.into());
+                }
+                (
+                    backoff(attempt),
+                    format!("request to {} failed: {e}", provider.name),
+                )
+            }
+        };
+
+        on_retry(format!(
+            "{reason}; retrying {attempt}/{max_retries} in {delay:?}…"
+        ))
+        .await;
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// How long the server asked us to wait before retrying: `Retry-After` (in
+/// seconds or as an HTTP date, or OpenAI's more precise `retry-after-ms`) or,
+/// failing that, the time until an exhausted `x-ratelimit-*` budget resets.
+fn retry_delay_from_headers(headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.trim().parse::<f64>().ok()) {
+        return Duration::try_from_secs_f64(ms / 1_000.0).ok();
+    }
+    if let Some(retry_after) = header(reqwest::header::RETRY_AFTER.as_str()).map(str::trim) {
+        if let Ok(secs) = retry_after.parse::<f64>() {
+            return Duration::try_from_secs_f64(secs).ok();
+        }
+        if let Ok(date) = httpdate::parse_http_date(retry_after) {
+            // A date in the past means "now".
+            return Some(date.duration_since(now).unwrap_or_default());
         }
     }
+
+    ["requests", "tokens"]
+        .iter()
+        .filter_map(|budget| {
+            let remaining = header(&format!("x-ratelimit-remaining-{budget}"))?;
+            if remaining.trim() != "0" {
+                return None;
+            }
+            parse_rate_limit_reset(header(&format!("x-ratelimit-reset-{budget}"))?)
+        })
+        .max()
+}
+
+/// Parses an `x-ratelimit-reset-*` value: either a number of seconds or a Go
+/// style duration such as `1m30s`, `6.5s` or `250ms`.
+fn parse_rate_limit_reset(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<f64>() {
+        return Duration::try_from_secs_f64(secs).ok();
+    }
+
+    let mut secs = 0.0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+        let (number, tail) = rest.split_at(number_len);
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let scale = match unit {
+            "h" => 3_600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            _ => return None,
+        };
+        secs += number.parse::<f64>().ok()? * scale;
+        rest = tail;
+    }
+    Duration::try_from_secs_f64(secs).ok()
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -215,18 +374,38 @@ struct SseEvent {
 #[derive(Debug, Deserialize)]
 struct ResponseCompleted {
     id: String,
+    usage: Option<ResponseCompletedUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseCompletedUsage {
+    input_tokens: u64,
+    output_tokens: u64,
 }
 
-async fn process_sse<S>(stream: S, tx_event: mpsc::Sender<Result<ResponseEvent>>)
-where
+impl From<ResponseCompletedUsage> for TokenUsage {
+    fn from(usage: ResponseCompletedUsage) -> Self {
+        Self {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+        }
+    }
+}
+
+/// If the stream stays completely silent for `idle_timeout` it is treated as
+/// disconnected.
+async fn process_sse<S>(
+    stream: S,
+    tx_event: mpsc::Sender<Result<ResponseEvent>>,
+    idle_timeout: Duration,
+) where
     S: Stream<Item = Result<Bytes>> + Unpin,
 {
     let mut stream = stream.eventsource();
 
-    // If the stream stays completely silent for an extended period treat it as disconnected.
-    let idle_timeout = *OPENAI_STREAM_IDLE_TIMEOUT_MS;
-    // The response id returned from the "complete" message.
+    // The response id and usage returned from the "complete" message.
     let mut response_id = None;
+    let mut token_usage = None;
 
     loop {
         let sse = match timeout(idle_timeout, stream.next()).await {
@@ -240,7 +419,10 @@ where
             Ok(None) => {
                 match response_id {
                     Some(response_id) => {
-                        let event = ResponseEvent::Completed { response_id };
+                        let event = ResponseEvent::Completed {
+                            response_id,
+                            token_usage,
+                        };
                         let _ = tx_event.send(Ok(event)).await;
                     }
                     None => {
@@ -307,6 +489,7 @@ where
                     match serde_json::from_value::<ResponseCompleted>(resp_val) {
                         Ok(r) => {
                             response_id = Some(r.id);
+                            token_usage = r.usage.map(TokenUsage::from);
                         }
                         Err(e) => {
                             debug!("failed to parse ResponseCompleted: {e}");
@@ -334,9 +517,24 @@ where
 }
 
 /// used in tests to stream from a text SSE file
-async fn stream_from_fixture(path: impl AsRef<Path>) -> Result<ResponseStream> {
+async fn stream_from_fixture(
+    path: impl AsRef<Path>,
+    idle_timeout: Duration,
+) -> Result<ResponseStream> {
     let (tx_event, rx_event) = mpsc::channel::<Result<ResponseEvent>>(16);
-    let f = std::fs::File::open(path.as_ref())?;
+    tokio::spawn(process_sse(
+        read_sse_fixture(path.as_ref())?,
+        tx_event,
+        idle_timeout,
+    ));
+    Ok(ResponseStream { rx_event })
+}
+
+/// Reads a text SSE file into a byte stream, treating each line as an event.
+pub(crate) fn read_sse_fixture(
+    path: &Path,
+) -> Result<impl Stream<Item = Result<Bytes>> + Unpin + use<>> {
+    let f = std::fs::File::open(path)?;
     let lines = std::io::BufReader::new(f).lines();
 
     // insert \n\n after each line for proper SSE parsing
@@ -347,7 +545,102 @@ async fn stream_from_fixture(path: impl AsRef<Path>) -> Result<ResponseStream> {
     }
 
     let rdr = std::io::Cursor::new(content);
-    let stream = ReaderStream::new(rdr).map_err(CodexErr::Io);
-    tokio::spawn(process_sse(stream, tx_event));
-    Ok(ResponseStream { rx_event })
+    Ok(ReaderStream::new(rdr).map_err(CodexErr::Io))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| {
+                (
+                    reqwest::header::HeaderName::from_static(name),
+                    HeaderValue::from_static(value),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn retry_delay_prefers_retry_after() {
+        assert_eq!(
+            retry_delay_from_headers(
+                &headers(&[("retry-after-ms", "1500"), ("retry-after", "2")]),
+                SystemTime::now()
+            ),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(
+            retry_delay_from_headers(&headers(&[("retry-after", "2")]), SystemTime::now()),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            retry_delay_from_headers(&headers(&[]), SystemTime::now()),
+            None
+        );
+    }
+
+    #[test]
+    fn retry_after_accepts_http_dates() {
+        let now = httpdate::parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+        assert_eq!(
+            retry_delay_from_headers(
+                &headers(&[("retry-after", "Wed, 21 Oct 2015 07:28:30 GMT")]),
+                now
+            ),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            retry_delay_from_headers(
+                &headers(&[("retry-after", "Wed, 21 Oct 2015 07:27:00 GMT")]),
+                now
+            ),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn retry_delay_waits_for_exhausted_rate_limits() {
+        let exhausted = headers(&[
+            ("x-ratelimit-remaining-requests", "0"),
+            ("x-ratelimit-reset-requests", "1m30s"),
+            ("x-ratelimit-remaining-tokens", "0"),
+            ("x-ratelimit-reset-tokens", "250ms"),
+        ]);
+        assert_eq!(
+            retry_delay_from_headers(&exhausted, SystemTime::now()),
+            Some(Duration::from_secs(90))
+        );
+
+        let not_exhausted = headers(&[
+            ("x-ratelimit-remaining-requests", "12"),
+            ("x-ratelimit-reset-requests", "6s"),
+        ]);
+        assert_eq!(
+            retry_delay_from_headers(&not_exhausted, SystemTime::now()),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_rate_limit_resets() {
+        assert_eq!(
+            parse_rate_limit_reset("6.5s"),
+            Some(Duration::from_millis(6500))
+        );
+        assert_eq!(
+            parse_rate_limit_reset("1h2m"),
+            Some(Duration::from_secs(3720))
+        );
+        assert_eq!(parse_rate_limit_reset("20"), Some(Duration::from_secs(20)));
+        assert_eq!(parse_rate_limit_reset("soon"), None);
+        assert_eq!(parse_rate_limit_reset("5 days"), None);
+    }
 }
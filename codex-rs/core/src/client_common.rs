@@ -1,10 +1,13 @@
 use crate::config_types::ReasoningEffort as ReasoningEffortConfig;
 use crate::config_types::ReasoningSummary as ReasoningSummaryConfig;
 use crate::error::Result;
+use crate::model_family::ModelFamily;
 use crate::models::ResponseItem;
 use codex_apply_patch::APPLY_PATCH_TOOL_INSTRUCTIONS;
 use futures::Stream;
+use futures::future::BoxFuture;
 use serde::Serialize;
+use serde_json::json;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::pin::Pin;
@@ -36,15 +39,25 @@ pub struct Prompt {
     /// the "fully qualified" tool name (i.e., prefixed with the server name),
     /// which should be reported to the model in place of Tool::name.
     pub extra_tools: HashMap<String, mcp_types::Tool>,
+
+    /// Whether to offer the model a dedicated `apply_patch` function tool
+    /// rather than having it invoke `apply_patch` through the shell.
+    pub include_apply_patch_tool: bool,
+
+    /// Whether to offer the model the read-only file tools.
+    pub include_file_tools: bool,
+
+    /// JSON Schema the model's final message must conform to, if any.
+    pub output_schema: Option<serde_json::Value>,
 }
 
 impl Prompt {
-    pub(crate) fn get_full_instructions(&self, model: &str) -> Cow<str> {
+    pub(crate) fn get_full_instructions(&self, model_family: &ModelFamily) -> Cow<'_, str> {
         let mut sections: Vec<&str> = vec![BASE_INSTRUCTIONS];
         if let Some(ref user) = self.user_instructions {
             sections.push(user);
         }
-        if model.starts_with("gpt-4.1") {
+        if model_family.needs_apply_patch_instructions && !self.include_apply_patch_tool {
             sections.push(APPLY_PATCH_TOOL_INSTRUCTIONS);
         }
         Cow::Owned(sections.join("\n"))
@@ -54,7 +67,24 @@ impl Prompt {
 #[derive(Debug)]
 pub enum ResponseEvent {
     OutputItemDone(ResponseItem),
-    Completed { response_id: String },
+    Completed {
+        response_id: String,
+        /// `None` if the provider did not report usage.
+        token_usage: Option<TokenUsage>,
+    },
+}
+
+/// Tokens consumed by one model response, as reported by the provider.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -124,20 +154,22 @@ pub(crate) struct ResponsesApiRequest<'a> {
     pub(crate) reasoning: Option<Reasoning>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) previous_response_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) text: Option<TextControls>,
     /// true when using the Responses API.
     pub(crate) store: bool,
     pub(crate) stream: bool,
 }
 
 pub(crate) fn create_reasoning_param_for_request(
-    model: &str,
+    model_family: &ModelFamily,
     effort: ReasoningEffortConfig,
     summary: ReasoningSummaryConfig,
 ) -> Option<Reasoning> {
     let effort: Option<OpenAiReasoningEffort> = effort.into();
     let effort = effort?;
 
-    if model_supports_reasoning_summaries(model) {
+    if model_family.supports_reasoning {
         Some(Reasoning {
             effort,
             summary: summary.into(),
@@ -147,22 +179,75 @@ pub(crate) fn create_reasoning_param_for_request(
     }
 }
 
-pub fn model_supports_reasoning_summaries(model: &str) -> bool {
-    // Currently, we hardcode this rule to decide whether enable reasoning.
-    // We expect reasoning to apply only to OpenAI models, but we do not want
-    // users to have to mess with their config to disable reasoning for models
-    // that do not support it, such as `gpt-4.1`.
-    //
-    // Though if a user is using Codex with non-OpenAI models that, say, happen
-    // to start with "o", then they can set `model_reasoning_effort = "none` in
-    // config.toml to disable reasoning.
-    //
-    // Ultimately, this should also be configurable in config.toml, but we
-    // need to have defaults that "just work." Perhaps we could have a
-    // "reasoning models pattern" as part of ModelProviderInfo?
-    model.starts_with("o") || model.starts_with("codex")
+/// Name under which an output schema is sent to the model.
+pub(crate) const OUTPUT_SCHEMA_NAME: &str = "codex_output_schema";
+
+/// The `text` parameter of a Responses API request.
+#[derive(Debug, Serialize)]
+pub(crate) struct TextControls {
+    pub(crate) format: TextFormat,
+}
+
+/// See https://platform.openai.com/docs/guides/structured-outputs?api-mode=responses
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum TextFormat {
+    JsonSchema {
+        name: &'static str,
+        schema: serde_json::Value,
+        strict: bool,
+    },
+}
+
+pub(crate) fn create_text_param_for_request(
+    output_schema: Option<&serde_json::Value>,
+) -> Option<TextControls> {
+    output_schema.map(|schema| TextControls {
+        format: TextFormat::JsonSchema {
+            name: OUTPUT_SCHEMA_NAME,
+            schema: schema.clone(),
+            strict: supports_strict_mode(schema),
+        },
+    })
 }
 
+/// Whether `schema` meets the requirements of the API's strict mode: every
+/// object lists all of its properties as required and sets
+/// `additionalProperties: false`. Strict mode rejects any other schema with a
+/// 400, so those are sent non-strict and only validated by the caller.
+pub(crate) fn supports_strict_mode(schema: &serde_json::Value) -> bool {
+    match schema {
+        serde_json::Value::Object(map) => {
+            let is_object_schema =
+                map.contains_key("properties") || map.get("type") == Some(&json!("object"));
+            if is_object_schema {
+                let required = map
+                    .get("required")
+                    .and_then(|required| required.as_array())
+                    .cloned()
+                    .unwrap_or_default();
+                let all_required = map
+                    .get("properties")
+                    .and_then(|properties| properties.as_object())
+                    .is_none_or(|properties| {
+                        properties.keys().all(|key| required.contains(&json!(key)))
+                    });
+                if !all_required || map.get("additionalProperties") != Some(&json!(false)) {
+                    return false;
+                }
+            }
+            map.values().all(supports_strict_mode)
+        }
+        serde_json::Value::Array(items) => items.iter().all(supports_strict_mode),
+        _ => true,
+    }
+}
+
+/// Receives a message each time a model request is about to be retried. The
+/// retry waits for the returned future, so the message is not dropped when
+/// the event queue is full.
+pub(crate) type RetryNotifier = dyn Fn(String) -> BoxFuture<'static, ()> + Send + Sync;
+
 pub(crate) struct ResponseStream {
     pub(crate) rx_event: mpsc::Receiver<Result<ResponseEvent>>,
 }
@@ -174,3 +259,44 @@ impl Stream for ResponseStream {
         self.rx_event.poll_recv(cx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn strict_mode_needs_closed_objects_with_every_property_required() {
+        let strict = json!({
+            "type": "object",
+            "properties": {
+                "summary": { "type": "string" },
+                "files": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": { "path": { "type": "string" } },
+                        "required": ["path"],
+                        "additionalProperties": false,
+                    },
+                },
+            },
+            "required": ["summary", "files"],
+            "additionalProperties": false,
+        });
+        assert!(supports_strict_mode(&strict));
+
+        let mut optional_property = strict.clone();
+        optional_property["required"] = json!(["summary"]);
+        assert!(!supports_strict_mode(&optional_property));
+
+        let mut open_nested_object = strict.clone();
+        open_nested_object["properties"]["files"]["items"]
+            .as_object_mut()
+            .unwrap()
+            .remove("additionalProperties");
+        assert!(!supports_strict_mode(&open_nested_object));
+
+        assert!(supports_strict_mode(&json!({ "type": "string" })));
+    }
+}
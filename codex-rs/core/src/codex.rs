@@ -3,6 +3,7 @@
 
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -17,8 +18,12 @@ use codex_apply_patch::AffectedPaths;
 use codex_apply_patch::ApplyPatchAction;
 use codex_apply_patch::ApplyPatchFileChange;
 use codex_apply_patch::MaybeApplyPatchVerified;
+use codex_apply_patch::Rejection;
 use codex_apply_patch::maybe_parse_apply_patch_verified;
+use codex_apply_patch::parse_apply_patch_verified;
 use codex_apply_patch::print_summary;
+use futures::future::BoxFuture;
+use futures::future::Shared;
 use futures::prelude::*;
 use mcp_types::CallToolResult;
 use serde::Serialize;
@@ -33,11 +38,20 @@ use tracing::trace;
 use tracing::warn;
 use uuid::Uuid;
 
+use crate::ModelProviderInfo;
 use crate::WireApi;
+use crate::approval_rules::ApprovalRulesStore;
 use crate::client::ModelClient;
 use crate::client_common::Prompt;
 use crate::client_common::ResponseEvent;
+use crate::client_common::TokenUsage;
 use crate::config::Config;
+use crate::config_types::HookConfig;
+use crate::config_types::Hooks;
+use crate::config_types::ModelFamilyOverride;
+use crate::config_types::ReasoningEffort as ReasoningEffortConfig;
+use crate::config_types::ReasoningSummary as ReasoningSummaryConfig;
+use crate::config_types::RunBudget;
 use crate::config_types::ShellEnvironmentPolicy;
 use crate::conversation_history::ConversationHistory;
 use crate::error::CodexErr;
@@ -48,10 +62,16 @@ use crate::exec::ExecToolCallOutput;
 use crate::exec::SandboxType;
 use crate::exec::process_exec_tool_call;
 use crate::exec_env::create_env;
-use crate::flags::OPENAI_STREAM_MAX_RETRIES;
+use crate::file_tools::handle_file_tool_call;
+use crate::file_tools::is_file_tool;
+use crate::git_snapshot::GitSnapshot;
+use crate::hooks::HookEvent;
 use crate::mcp_connection_manager::McpConnectionManager;
 use crate::mcp_connection_manager::try_parse_fully_qualified_tool_name;
 use crate::mcp_tool_call::handle_mcp_tool_call;
+use crate::model_family::ModelFamily;
+use crate::model_family::find_family_for_model;
+use crate::models::ApplyPatchToolCallParams;
 use crate::models::ContentItem;
 use crate::models::FunctionCallOutputPayload;
 use crate::models::LocalShellAction;
@@ -63,8 +83,11 @@ use crate::project_doc::get_user_instructions;
 use crate::protocol::AgentMessageEvent;
 use crate::protocol::AgentReasoningEvent;
 use crate::protocol::ApplyPatchApprovalRequestEvent;
+use crate::protocol::ApprovalRule;
+use crate::protocol::ApprovalRulesEvent;
 use crate::protocol::AskForApproval;
 use crate::protocol::BackgroundEventEvent;
+use crate::protocol::BudgetExceededEvent;
 use crate::protocol::ErrorEvent;
 use crate::protocol::Event;
 use crate::protocol::EventMsg;
@@ -73,20 +96,27 @@ use crate::protocol::ExecCommandBeginEvent;
 use crate::protocol::ExecCommandEndEvent;
 use crate::protocol::FileChange;
 use crate::protocol::InputItem;
+use crate::protocol::ModelFallbackEvent;
 use crate::protocol::Op;
 use crate::protocol::PatchApplyBeginEvent;
 use crate::protocol::PatchApplyEndEvent;
+use crate::protocol::RejectedFileChange;
 use crate::protocol::ReviewDecision;
 use crate::protocol::SandboxPolicy;
 use crate::protocol::SessionConfiguredEvent;
+use crate::protocol::SessionDiffEvent;
 use crate::protocol::Submission;
 use crate::protocol::TaskCompleteEvent;
+use crate::protocol::TurnDiffEvent;
 use crate::rollout::RolloutRecorder;
+use crate::run_budget::BudgetTracker;
 use crate::safety::SafetyCheck;
 use crate::safety::assess_command_safety;
 use crate::safety::assess_patch_safety;
+use crate::turn_diff::DiffBaseline;
 use crate::user_notification::UserNotification;
 use crate::util::backoff;
+use crate::webhook_notifier::WebhookNotifier;
 
 /// The high-level interface to the Codex system.
 /// It operates as a queue pair where you send submissions and receive events.
@@ -94,6 +124,9 @@ pub struct Codex {
     next_id: AtomicU64,
     tx_sub: Sender<Submission>,
     rx_event: Receiver<Event>,
+    /// Shared with the session so that queued notifications can be flushed
+    /// before the process exits.
+    webhook: Option<Arc<WebhookNotifier>>,
 }
 
 impl Codex {
@@ -118,12 +151,23 @@ impl Codex {
             cwd: config.cwd.clone(),
         };
 
+        let webhook = config
+            .notify_webhook
+            .clone()
+            .map(|webhook| Arc::new(WebhookNotifier::spawn(webhook)));
         let config = Arc::new(config);
-        tokio::spawn(submission_loop(config, rx_sub, tx_event, ctrl_c));
+        tokio::spawn(submission_loop(
+            config,
+            rx_sub,
+            tx_event,
+            ctrl_c,
+            webhook.clone(),
+        ));
         let codex = Codex {
             next_id: AtomicU64::new(0),
             tx_sub,
             rx_event,
+            webhook,
         };
         let init_id = codex.submit(configure_session).await?;
 
@@ -159,27 +203,50 @@ impl Codex {
             .map_err(|_| CodexErr::InternalAgentDied)?;
         Ok(event)
     }
+
+    /// Waits up to `max_wait` for notifications queued for the webhook to be
+    /// delivered. Front-ends that exit right after a task completes call this
+    /// first, since exiting drops whatever is still queued.
+    pub async fn flush_notifications(&self, max_wait: Duration) {
+        let Some(webhook) = &self.webhook else {
+            return;
+        };
+        if !webhook.flush(max_wait).await {
+            warn!("gave up waiting for webhook notifications after {max_wait:?}");
+        }
+    }
 }
 
 /// Context for an initialized model agent
 ///
 /// A session has at most 1 running task at a time, and can be interrupted by user input.
 pub(crate) struct Session {
-    client: ModelClient,
+    /// Replaced when the session falls back to another model.
+    client: Mutex<ModelClient>,
     tx_event: Sender<Event>,
     ctrl_c: Arc<Notify>,
+    session_id: Uuid,
     stream: bool,
+    include_apply_patch_tool: bool,
+    include_file_tools: bool,
+    /// JSON Schema the final message of each turn must conform to.
+    output_schema: Option<serde_json::Value>,
+    /// Limits on each task.
+    budget: RunBudget,
 
     /// The session's current working directory. All relative paths provided by
     /// the model as well as sandbox policies are resolved against this path
     /// instead of `std::env::current_dir()`.
     cwd: PathBuf,
     instructions: Option<String>,
-    approval_policy: AskForApproval,
-    sandbox_policy: SandboxPolicy,
+    approval_policy: Mutex<AskForApproval>,
+    sandbox_policy: Mutex<SandboxPolicy>,
     shell_environment_policy: ShellEnvironmentPolicy,
     writable_roots: Mutex<Vec<PathBuf>>,
 
+    /// Persisted approval rules for the project containing `cwd`.
+    approval_rules: ApprovalRulesStore,
+
     /// Manager for external MCP servers/tools.
     mcp_connection_manager: McpConnectionManager,
 
@@ -187,6 +254,12 @@ pub(crate) struct Session {
     /// `None` this feature is disabled.
     notify: Option<Vec<String>>,
 
+    /// Delivers the same notifications as `notify` over HTTP, if configured.
+    webhook: Option<Arc<WebhookNotifier>>,
+
+    /// User scripts run around commands, patches and turns.
+    hooks: Hooks,
+
     /// Optional rollout recorder for persisting the conversation transcript so
     /// sessions can be replayed or inspected later.
     rollout: Mutex<Option<crate::rollout::RolloutRecorder>>,
@@ -200,28 +273,107 @@ impl Session {
             .map(PathBuf::from)
             .map_or_else(|| self.cwd.clone(), |p| self.cwd.join(p))
     }
+
+    pub(crate) fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    pub(crate) fn sandbox_policy(&self) -> SandboxPolicy {
+        self.sandbox_policy.lock().unwrap().clone()
+    }
+
+    fn approval_policy(&self) -> AskForApproval {
+        *self.approval_policy.lock().unwrap()
+    }
+
+    fn client(&self) -> ModelClient {
+        self.client.lock().unwrap().clone()
+    }
+}
+
+/// A model to switch to when the current one fails.
+#[derive(Debug, Clone)]
+struct FallbackModel {
+    model: String,
+    model_family: ModelFamily,
+    provider_id: String,
+    provider: ModelProviderInfo,
+}
+
+/// The user's answer to an approval request.
+#[derive(Debug, Default)]
+pub(crate) struct ApprovalResponse {
+    pub(crate) decision: ReviewDecision,
+    /// Parts of the patch the user declined. Always empty for commands.
+    pub(crate) rejected_changes: Vec<RejectedFileChange>,
 }
 
 /// Mutable state of the agent
 #[derive(Default)]
 struct State {
     approved_commands: HashSet<Vec<String>>,
+    /// Rules loaded from (and kept in sync with) `Session::approval_rules`.
+    approval_rules: Vec<ApprovalRule>,
     current_task: Option<AgentTask>,
     previous_response_id: Option<String>,
-    pending_approvals: HashMap<String, oneshot::Sender<ReviewDecision>>,
+    pending_approvals: HashMap<String, oneshot::Sender<ApprovalResponse>>,
     pending_input: Vec<ResponseInputItem>,
-    zdr_transcript: Option<ConversationHistory>,
+    /// Every item of the conversation so far.
+    transcript: ConversationHistory,
+    /// Send `transcript` with every request instead of relying on
+    /// `previous_response_id`. See [`record_conversation_history`].
+    full_history: bool,
+    /// Working tree snapshots taken at the start of each task, most recent
+    /// last. Popped by `Op::Undo`.
+    snapshots: Vec<GitSnapshot>,
+    /// The first snapshot taken in this session, used by `Op::GetSessionDiff`.
+    session_snapshot: Option<GitSnapshot>,
+    /// The snapshot of the current (or last) task while it is being taken.
+    task_snapshot: Option<Shared<BoxFuture<'static, Option<GitSnapshot>>>>,
+    /// Contents of files touched by `apply_patch`, before the first change in
+    /// the current task and in the whole session, respectively. These are only
+    /// used to compute diffs when there are no git snapshots.
+    task_baseline: DiffBaseline,
+    session_baseline: DiffBaseline,
+    /// Tokens used so far in this session, summed over the responses whose
+    /// provider reported usage.
+    token_usage: TokenUsage,
+    /// Models to switch to, in order, when the current one keeps failing.
+    model_fallbacks: VecDeque<FallbackModel>,
+    /// The configured model and provider, once the session has fallen back
+    /// from them. A `ConfigureSession` for the same model keeps the fallback
+    /// rather than returning to the failing model.
+    fell_back_from: Option<(String, ModelProviderInfo)>,
 }
 
 impl Session {
     pub fn set_task(&self, task: AgentTask) {
         let mut state = self.state.lock().unwrap();
         if let Some(current_task) = state.current_task.take() {
-            current_task.abort();
+            current_task.abort(false);
         }
         state.current_task = Some(task);
     }
 
+    pub(crate) fn token_usage(&self) -> TokenUsage {
+        self.state.lock().unwrap().token_usage
+    }
+
+    /// Ends the task `sub_id` because it used up part of its budget.
+    async fn stop_task_over_budget(&self, sub_id: &str, exceeded: BudgetExceededEvent) {
+        info!("Task {sub_id} over budget: {}", exceeded.message);
+        self.maybe_notify(UserNotification::TaskAborted {
+            turn_id: sub_id.to_string(),
+            reason: exceeded.message.clone(),
+        });
+        self.remove_task(sub_id);
+        self.send_event(Event {
+            id: sub_id.to_string(),
+            msg: EventMsg::BudgetExceeded(exceeded),
+        })
+        .await;
+    }
+
     pub fn remove_task(&self, sub_id: &str) {
         let mut state = self.state.lock().unwrap();
         if let Some(task) = &state.current_task {
@@ -245,8 +397,13 @@ impl Session {
         command: Vec<String>,
         cwd: PathBuf,
         reason: Option<String>,
-    ) -> oneshot::Receiver<ReviewDecision> {
-        let (tx_approve, rx_approve) = oneshot::channel();
+    ) -> oneshot::Receiver<ApprovalResponse> {
+        self.maybe_notify(UserNotification::ExecApprovalRequested {
+            turn_id: sub_id.clone(),
+            command: command.clone(),
+            cwd: cwd.clone(),
+            reason: reason.clone(),
+        });
         let event = Event {
             id: sub_id.clone(),
             msg: EventMsg::ExecApprovalRequest(ExecApprovalRequestEvent {
@@ -255,12 +412,7 @@ impl Session {
                 reason,
             }),
         };
-        let _ = self.tx_event.send(event).await;
-        {
-            let mut state = self.state.lock().unwrap();
-            state.pending_approvals.insert(sub_id, tx_approve);
-        }
-        rx_approve
+        self.send_approval_request(event).await
     }
 
     pub async fn request_patch_approval(
@@ -269,8 +421,15 @@ impl Session {
         action: &ApplyPatchAction,
         reason: Option<String>,
         grant_root: Option<PathBuf>,
-    ) -> oneshot::Receiver<ReviewDecision> {
-        let (tx_approve, rx_approve) = oneshot::channel();
+    ) -> oneshot::Receiver<ApprovalResponse> {
+        let mut paths: Vec<PathBuf> = action.changes().keys().cloned().collect();
+        paths.sort();
+        self.maybe_notify(UserNotification::PatchApprovalRequested {
+            turn_id: sub_id.clone(),
+            paths,
+            reason: reason.clone(),
+            grant_root: grant_root.clone(),
+        });
         let event = Event {
             id: sub_id.clone(),
             msg: EventMsg::ApplyPatchApprovalRequest(ApplyPatchApprovalRequestEvent {
@@ -279,18 +438,28 @@ impl Session {
                 grant_root,
             }),
         };
-        let _ = self.tx_event.send(event).await;
+        self.send_approval_request(event).await
+    }
+
+    /// Emits an approval request and returns where its answer will arrive.
+    /// The request is registered in `pending_approvals` before it is sent: a
+    /// client may answer as soon as it sees the event, and `notify_approval`
+    /// drops answers nobody is waiting for, which would leave the task
+    /// waiting forever.
+    async fn send_approval_request(&self, event: Event) -> oneshot::Receiver<ApprovalResponse> {
+        let (tx_approve, rx_approve) = oneshot::channel();
         {
             let mut state = self.state.lock().unwrap();
-            state.pending_approvals.insert(sub_id, tx_approve);
+            state.pending_approvals.insert(event.id.clone(), tx_approve);
         }
+        let _ = self.tx_event.send(event).await;
         rx_approve
     }
 
-    pub fn notify_approval(&self, sub_id: &str, decision: ReviewDecision) {
+    pub fn notify_approval(&self, sub_id: &str, response: ApprovalResponse) {
         let mut state = self.state.lock().unwrap();
         if let Some(tx_approve) = state.pending_approvals.remove(sub_id) {
-            tx_approve.send(decision).ok();
+            tx_approve.send(response).ok();
         }
     }
 
@@ -299,15 +468,70 @@ impl Session {
         state.approved_commands.insert(cmd);
     }
 
-    /// Records items to both the rollout and the chat completions/ZDR
-    /// transcript, if enabled.
+    /// Saves `rule` for the project and applies it for the rest of the
+    /// session. The rule still applies to this session if saving fails.
+    async fn remember_approval_rule(&self, sub_id: &str, rule: ApprovalRule) {
+        if let ApprovalRule::WritableRoot { path } = &rule {
+            self.writable_roots.lock().unwrap().push(path.clone());
+        }
+        let store = self.approval_rules.clone();
+        let to_save = rule.clone();
+        let saved = tokio::task::spawn_blocking(move || store.add(to_save))
+            .await
+            .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+        let rules = match saved {
+            Ok(rules) => rules,
+            Err(e) => {
+                self.send_event(Event {
+                    id: sub_id.to_string(),
+                    msg: EventMsg::BackgroundEvent(BackgroundEventEvent {
+                        message: format!(
+                            "Failed to save approval rule; it applies to this session only: {e}"
+                        ),
+                    }),
+                })
+                .await;
+                let mut rules = self.state.lock().unwrap().approval_rules.clone();
+                rules.push(rule);
+                rules
+            }
+        };
+        self.state.lock().unwrap().approval_rules = rules;
+    }
+
+    fn approval_rules_event(&self) -> EventMsg {
+        EventMsg::ApprovalRules(ApprovalRulesEvent {
+            project: self.approval_rules.project().to_path_buf(),
+            rules: self.state.lock().unwrap().approval_rules.clone(),
+        })
+    }
+
+    async fn revoke_approval_rule(&self, rule: &ApprovalRule) -> EventMsg {
+        let store = self.approval_rules.clone();
+        let to_revoke = rule.clone();
+        let revoked = tokio::task::spawn_blocking(move || store.revoke(&to_revoke))
+            .await
+            .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+        match revoked {
+            Ok(Some(rules)) => {
+                self.state.lock().unwrap().approval_rules = rules;
+                self.approval_rules_event()
+            }
+            Ok(None) => EventMsg::Error(ErrorEvent {
+                message: format!("No approval rule to {rule} in this project."),
+            }),
+            Err(e) => EventMsg::Error(ErrorEvent {
+                message: format!("Failed to revoke approval rule: {e}"),
+            }),
+        }
+    }
+
+    /// Records items to both the rollout and the transcript.
     async fn record_conversation_items(&self, items: &[ResponseItem]) {
         debug!("Recording items for conversation: {items:?}");
         self.record_rollout_items(items).await;
 
-        if let Some(transcript) = self.state.lock().unwrap().zdr_transcript.as_mut() {
-            transcript.record_items(items);
-        }
+        self.state.lock().unwrap().transcript.record_items(items);
     }
 
     /// Append the given items to the session's rollout transcript (if enabled)
@@ -375,6 +599,108 @@ impl Session {
         let _ = self.tx_event.send(event).await;
     }
 
+    /// Removes images from `items` when the current model cannot accept
+    /// them, telling the user what was left out.
+    async fn drop_unsupported_input(&self, sub_id: &str, items: Vec<InputItem>) -> Vec<InputItem> {
+        if self.client().model_family().supports_image_input {
+            return items;
+        }
+        let (images, items): (Vec<_>, Vec<_>) = items.into_iter().partition(|item| {
+            matches!(item, InputItem::Image { .. } | InputItem::LocalImage { .. })
+        });
+        if !images.is_empty() {
+            self.notify_background_event(
+                sub_id,
+                format!(
+                    "{} does not accept image input; {} image(s) left out",
+                    self.client().model(),
+                    images.len()
+                ),
+            )
+            .await;
+        }
+        items
+    }
+
+    /// Applies the model-related parts of `Op::OverrideTurnContext` and
+    /// returns a description of what changed.
+    fn switch_model(
+        &self,
+        provider: Option<ModelProviderInfo>,
+        model: Option<String>,
+        effort: Option<ReasoningEffortConfig>,
+        summary: Option<ReasoningSummaryConfig>,
+        model_families: &HashMap<String, ModelFamilyOverride>,
+    ) -> Vec<String> {
+        let mut changes = Vec::new();
+        let mut client = self.client.lock().unwrap();
+        let provider_changed = provider
+            .as_ref()
+            .is_some_and(|provider| provider != client.provider());
+        if model.is_some() || provider_changed {
+            let model = model.unwrap_or_else(|| client.model().to_string());
+            let provider = provider.unwrap_or_else(|| client.provider().clone());
+            let model_family = find_family_for_model(&model, model_families);
+            *client = client.with_model(&model, model_family, provider);
+            changes.push(format!("model {model} ({})", client.provider().name));
+        }
+        if effort.is_some() || summary.is_some() {
+            let effort = effort.unwrap_or(client.reasoning_effort());
+            let summary = summary.unwrap_or(client.reasoning_summary());
+            *client = client.with_reasoning(effort, summary);
+            changes.push(format!(
+                "reasoning effort {effort}, reasoning summaries {summary}"
+            ));
+        }
+        if provider_changed {
+            // `previous_response_id` is only meaningful to the provider that
+            // issued it, so the new one is sent the whole conversation.
+            let mut state = self.state.lock().unwrap();
+            state.full_history = true;
+            state.previous_response_id = None;
+        }
+        changes
+    }
+
+    /// Switches to the next fallback model, if any, after the current one
+    /// failed with `err`. Returns whether there was one to switch to.
+    async fn fall_back_to_next_model(&self, sub_id: &str, err: &CodexErr) -> bool {
+        let fallback = {
+            let mut state = self.state.lock().unwrap();
+            let Some(fallback) = state.model_fallbacks.pop_front() else {
+                return false;
+            };
+            let mut client = self.client.lock().unwrap();
+            if state.fell_back_from.is_none() {
+                state.fell_back_from =
+                    Some((client.model().to_string(), client.provider().clone()));
+            }
+            *client = client.with_model(
+                &fallback.model,
+                fallback.model_family.clone(),
+                fallback.provider.clone(),
+            );
+            // `previous_response_id` belongs to the previous model.
+            state.full_history = true;
+            state.previous_response_id = None;
+            fallback
+        };
+        info!(
+            "falling back to model {} ({}) after: {err}",
+            fallback.model, fallback.provider_id
+        );
+        self.send_event(Event {
+            id: sub_id.to_string(),
+            msg: EventMsg::ModelFallback(ModelFallbackEvent {
+                model: fallback.model,
+                provider: fallback.provider_id,
+                reason: err.to_string(),
+            }),
+        })
+        .await;
+        true
+    }
+
     /// Returns the input if there was no task running to inject into
     pub fn inject_input(&self, input: Vec<InputItem>) -> Result<(), Vec<InputItem>> {
         let mut state = self.state.lock().unwrap();
@@ -397,6 +723,202 @@ impl Session {
         }
     }
 
+    /// Starts snapshotting the working tree so that the task about to run
+    /// can be reverted with `Op::Undo` and its changes reported via
+    /// `TurnDiff`. The snapshot is taken in the background so the first model
+    /// request does not wait on `git`; see [`Session::task_snapshot`].
+    fn begin_task_tracking(self: &Arc<Self>, sub_id: &str) {
+        let sess = Arc::clone(self);
+        let sub_id = sub_id.to_string();
+        let mut state = self.state.lock().unwrap();
+        state.task_baseline = DiffBaseline::default();
+        // Snapshots are chained, so the previous one has to finish first.
+        let previous = state.task_snapshot.take();
+        let capture = tokio::spawn(async move {
+            if let Some(previous) = previous {
+                previous.await;
+            }
+            sess.capture_task_snapshot(&sub_id).await
+        })
+        .map(|joined| {
+            joined.unwrap_or_else(|e| {
+                warn!("snapshot task failed: {e}");
+                None
+            })
+        })
+        .boxed()
+        .shared();
+        state.task_snapshot = Some(capture);
+    }
+
+    /// Waits for the snapshot started by [`Session::begin_task_tracking`].
+    /// Anything that may modify files must call this first so the snapshot
+    /// reflects the state before the task.
+    async fn task_snapshot(&self) -> Option<GitSnapshot> {
+        let pending = self.state.lock().unwrap().task_snapshot.clone();
+        match pending {
+            Some(pending) => pending.await,
+            None => None,
+        }
+    }
+
+    async fn capture_task_snapshot(&self, sub_id: &str) -> Option<GitSnapshot> {
+        let previous = self.state.lock().unwrap().snapshots.last().cloned();
+        let ref_name = self.session_id.to_string();
+        match GitSnapshot::capture(&self.cwd, &ref_name, previous.as_ref()).await {
+            Ok(Some(snapshot)) => {
+                let mut state = self.state.lock().unwrap();
+                state.snapshots.push(snapshot.clone());
+                state
+                    .session_snapshot
+                    .get_or_insert_with(|| snapshot.clone());
+                Some(snapshot)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                warn!("failed to snapshot working tree: {e:#}");
+                // Older snapshots no longer describe "the state before the
+                // last task", so undoing to them would be surprising.
+                self.state.lock().unwrap().snapshots.clear();
+                self.notify_background_event(
+                    sub_id,
+                    format!("Failed to snapshot the working tree; undo is unavailable for this task: {e:#}"),
+                )
+                .await;
+                None
+            }
+        }
+    }
+
+    /// Emits a `TurnDiff` event describing everything that changed since
+    /// `task_snapshot` was taken (or, without git, since `apply_patch` first
+    /// touched each file during this task).
+    async fn notify_turn_diff(&self, sub_id: &str, task_snapshot: Option<&GitSnapshot>) {
+        let unified_diff = match task_snapshot {
+            Some(snapshot) => match snapshot.diff_to_working_tree().await {
+                Ok(diff) => diff,
+                Err(e) => {
+                    warn!("failed to diff working tree: {e:#}");
+                    return;
+                }
+            },
+            None => {
+                let baseline = self.state.lock().unwrap().task_baseline.clone();
+                baseline.unified_diff(&self.cwd)
+            }
+        };
+        if unified_diff.is_empty() {
+            return;
+        }
+        self.send_event(Event {
+            id: sub_id.to_string(),
+            msg: EventMsg::TurnDiff(TurnDiffEvent { unified_diff }),
+        })
+        .await;
+    }
+
+    async fn session_diff(&self) -> Result<String, String> {
+        self.task_snapshot().await;
+        let (session_snapshot, baseline) = {
+            let state = self.state.lock().unwrap();
+            (
+                state.session_snapshot.clone(),
+                state.session_baseline.clone(),
+            )
+        };
+        match session_snapshot {
+            Some(snapshot) => snapshot
+                .diff_to_working_tree()
+                .await
+                .map_err(|e| format!("Failed to compute diff: {e:#}")),
+            None => Ok(baseline.unified_diff(&self.cwd)),
+        }
+    }
+
+    /// Remembers the current contents of the files `action` is about to
+    /// modify, for diff reporting.
+    fn record_diff_baseline(&self, action: &ApplyPatchAction) {
+        let mut state = self.state.lock().unwrap();
+        for (path, change) in action.changes() {
+            let mut paths = vec![path.as_path()];
+            if let ApplyPatchFileChange::Update {
+                move_path: Some(dest),
+                ..
+            } = change
+            {
+                paths.push(dest.as_path());
+            }
+            for p in paths {
+                state.task_baseline.record(p);
+                state.session_baseline.record(p);
+            }
+        }
+    }
+
+    /// Restores the snapshot taken before the most recent task and queues a
+    /// note so the model learns about the revert with its next input.
+    async fn undo_last_task(&self, sub_id: &str) {
+        self.task_snapshot().await;
+        let snapshot = self.state.lock().unwrap().snapshots.pop();
+        let Some(snapshot) = snapshot else {
+            self.send_event(Event {
+                id: sub_id.to_string(),
+                msg: EventMsg::Error(ErrorEvent {
+                    message: "Nothing to undo.".to_string(),
+                }),
+            })
+            .await;
+            return;
+        };
+
+        let paths = match snapshot.restore().await {
+            Ok(paths) => paths,
+            Err(e) => {
+                self.state.lock().unwrap().snapshots.push(snapshot);
+                self.send_event(Event {
+                    id: sub_id.to_string(),
+                    msg: EventMsg::Error(ErrorEvent {
+                        message: format!("Failed to undo: {e:#}"),
+                    }),
+                })
+                .await;
+                return;
+            }
+        };
+
+        let path_list = paths
+            .iter()
+            .map(|p| format!("- {}", p.display()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let note = if paths.is_empty() {
+            "The user undid your previous turn. It had not changed any files.".to_string()
+        } else {
+            format!(
+                "The user undid your previous turn. The working tree was restored to its state before that turn, reverting these paths:\n{path_list}"
+            )
+        };
+        self.state
+            .lock()
+            .unwrap()
+            .pending_input
+            .push(ResponseInputItem::Message {
+                role: "user".to_string(),
+                content: vec![ContentItem::InputText { text: note }],
+            });
+
+        let message = match paths.len() {
+            0 => "Undo: the last task did not change any files.".to_string(),
+            1 => "Undo: restored 1 file to its state before the last task.".to_string(),
+            n => format!("Undo: restored {n} files to their state before the last task."),
+        };
+        self.notify_background_event(sub_id, message).await;
+    }
+
+    fn has_current_task(&self) -> bool {
+        self.state.lock().unwrap().current_task.is_some()
+    }
+
     pub async fn call_tool(
         &self,
         server: &str,
@@ -409,20 +931,91 @@ impl Session {
             .await
     }
 
+    /// Stops the running task, e.g. to reconfigure the session. Unlike
+    /// [`Session::interrupt`], this does not notify the user.
     pub fn abort(&self) {
+        self.abort_task(false);
+    }
+
+    /// Stops the running task at the user's request (Ctrl-C, `Op::Interrupt`
+    /// or an approval answered with `Abort`) and notifies them.
+    pub fn interrupt(&self) {
+        self.abort_task(true);
+    }
+
+    fn abort_task(&self, notify_user: bool) {
         info!("Aborting existing session");
         let mut state = self.state.lock().unwrap();
         state.pending_approvals.clear();
         state.pending_input.clear();
         if let Some(task) = state.current_task.take() {
-            task.abort();
+            task.abort(notify_user);
+        }
+    }
+
+    /// Runs pre-hooks for an action. On a veto, tells the UI and returns the
+    /// reason for the model. `event` is only built if there are hooks to run.
+    async fn run_pre_hooks(
+        &self,
+        sub_id: &str,
+        hooks: &[HookConfig],
+        event: impl FnOnce() -> HookEvent,
+    ) -> Result<(), String> {
+        if hooks.is_empty() {
+            return Ok(());
+        }
+        let result = crate::hooks::run_pre_hooks(hooks, &event(), &self.cwd).await;
+        if let Err(reason) = &result {
+            self.notify_background_event(sub_id, reason.clone()).await;
+        }
+        result
+    }
+
+    /// Runs post-hooks for an action and returns their output for the model.
+    async fn run_post_hooks(
+        &self,
+        hooks: &[HookConfig],
+        event: impl FnOnce() -> HookEvent,
+    ) -> Option<String> {
+        if hooks.is_empty() {
+            return None;
+        }
+        crate::hooks::run_post_hooks(hooks, &event(), &self.cwd).await
+    }
+
+    /// Runs the post-exec hooks and appends their output to `content`.
+    async fn append_post_exec_output(
+        &self,
+        content: &mut String,
+        call_id: &str,
+        params: &ExecParams,
+        output: &ExecToolCallOutput,
+    ) {
+        let extra = self
+            .run_post_hooks(&self.hooks.post_exec, || HookEvent::PostExec {
+                call_id: call_id.to_string(),
+                command: params.command.clone(),
+                cwd: params.cwd.clone(),
+                exit_code: output.exit_code,
+                stdout: output.stdout.clone(),
+                stderr: output.stderr.clone(),
+            })
+            .await;
+        if let Some(extra) = extra {
+            content.push('\n');
+            content.push_str(&extra);
         }
     }
 
     /// Spawn the configured notifier (if any) with the given JSON payload as
-    /// the last argument. Failures are logged but otherwise ignored so that
-    /// notification issues do not interfere with the main workflow.
+    /// the last argument, and queue it for the webhook (if any). Failures are
+    /// logged but otherwise ignored so that notification issues do not
+    /// interfere with the main workflow.
     fn maybe_notify(&self, notification: UserNotification) {
+        if let Some(webhook) = &self.webhook {
+            webhook.notify(&notification);
+        }
+
         let Some(notify_command) = &self.notify else {
             return;
         };
@@ -456,15 +1049,18 @@ impl Drop for Session {
 }
 
 impl State {
-    pub fn partial_clone(&self, retain_zdr_transcript: bool) -> Self {
+    pub fn partial_clone(&self, full_history: bool) -> Self {
         Self {
             approved_commands: self.approved_commands.clone(),
             previous_response_id: self.previous_response_id.clone(),
-            zdr_transcript: if retain_zdr_transcript {
-                self.zdr_transcript.clone()
-            } else {
-                None
-            },
+            snapshots: self.snapshots.clone(),
+            session_snapshot: self.session_snapshot.clone(),
+            session_baseline: self.session_baseline.clone(),
+            transcript: self.transcript.clone(),
+            full_history,
+            token_usage: self.token_usage,
+            model_fallbacks: self.model_fallbacks.clone(),
+            fell_back_from: self.fell_back_from.clone(),
             ..Default::default()
         }
     }
@@ -488,9 +1084,17 @@ impl AgentTask {
         }
     }
 
-    fn abort(self) {
+    /// Stops the task. `notify_user` sends a `TaskAborted` notification, which
+    /// only makes sense when the user asked for the abort.
+    fn abort(self, notify_user: bool) {
         if !self.handle.is_finished() {
             self.handle.abort();
+            if notify_user {
+                self.sess.maybe_notify(UserNotification::TaskAborted {
+                    turn_id: self.sub_id.clone(),
+                    reason: "Turn interrupted".to_string(),
+                });
+            }
             let event = Event {
                 id: self.sub_id,
                 msg: EventMsg::Error(ErrorEvent {
@@ -510,6 +1114,7 @@ async fn submission_loop(
     rx_sub: Receiver<Submission>,
     tx_event: Sender<Event>,
     ctrl_c: Arc<Notify>,
+    webhook: Option<Arc<WebhookNotifier>>,
 ) {
     // Generate a unique ID for the lifetime of this Codex session.
     let session_id = Uuid::new_v4();
@@ -536,7 +1141,7 @@ async fn submission_loop(
             },
             _ = interrupted => {
                 if let Some(sess) = sess.as_ref(){
-                    sess.abort();
+                    sess.interrupt();
                 }
                 continue;
             },
@@ -552,7 +1157,7 @@ async fn submission_loop(
                         continue;
                     }
                 };
-                sess.abort();
+                sess.interrupt();
             }
             Op::ConfigureSession {
                 provider,
@@ -580,38 +1185,80 @@ async fn submission_loop(
                     return;
                 }
 
-                let client = ModelClient::new(
+                let mut client = ModelClient::new(
                     model.clone(),
+                    find_family_for_model(&model, &config.model_families),
                     provider.clone(),
                     model_reasoning_effort,
                     model_reasoning_summary,
                 );
 
                 // abort any current running session and clone its state
-                let retain_zdr_transcript =
+                let full_history =
                     record_conversation_history(disable_response_storage, provider.wire_api);
-                let state = match sess.take() {
+                let (mut state, previous_client) = match sess.take() {
                     Some(sess) => {
                         sess.abort();
-                        sess.state
-                            .lock()
-                            .unwrap()
-                            .partial_clone(retain_zdr_transcript)
+                        let state = sess.state.lock().unwrap().partial_clone(full_history);
+                        (state, Some(sess.client()))
                     }
-                    None => State {
-                        zdr_transcript: if retain_zdr_transcript {
-                            Some(ConversationHistory::new())
-                        } else {
-                            None
+                    None => (
+                        State {
+                            full_history,
+                            ..Default::default()
                         },
-                        ..Default::default()
-                    },
+                        None,
+                    ),
                 };
 
-                let writable_roots = Mutex::new(get_writable_roots(&cwd));
+                // Reconfiguring with the model the session fell back from
+                // keeps the fallback model, the rest of the chain and the
+                // full-history mode the fallback needs.
+                let keep_fallback =
+                    state
+                        .fell_back_from
+                        .as_ref()
+                        .is_some_and(|(from_model, from_provider)| {
+                            *from_model == model && *from_provider == provider
+                        });
+                match previous_client {
+                    Some(previous) if keep_fallback => {
+                        client = client.with_model(
+                            previous.model(),
+                            previous.model_family().clone(),
+                            previous.provider().clone(),
+                        );
+                        state.full_history = true;
+                    }
+                    _ => {
+                        state.fell_back_from = None;
+                        state.model_fallbacks = fallback_chain(&config);
+                    }
+                }
+                let model = client.model().to_string();
 
                 // Error messages to dispatch after SessionConfigured is sent.
                 let mut mcp_connection_errors = Vec::<Event>::new();
+
+                let approval_rules = ApprovalRulesStore::new(&config.codex_home, &cwd);
+                state.approval_rules = match approval_rules.load() {
+                    Ok(rules) => rules,
+                    Err(e) => {
+                        let message = format!("Failed to load approval rules: {e}");
+                        error!("{message}");
+                        mcp_connection_errors.push(Event {
+                            id: sub.id.clone(),
+                            msg: EventMsg::Error(ErrorEvent { message }),
+                        });
+                        Vec::new()
+                    }
+                };
+                let mut writable_roots = get_writable_roots(&cwd);
+                writable_roots.extend(state.approval_rules.iter().filter_map(|rule| match rule {
+                    ApprovalRule::WritableRoot { path } => Some(path.clone()),
+                    ApprovalRule::CommandPrefix { .. } => None,
+                }));
+                let writable_roots = Mutex::new(writable_roots);
                 let (mcp_connection_manager, failed_clients) =
                     match McpConnectionManager::new(config.mcp_servers.clone()).await {
                         Ok((mgr, failures)) => (mgr, failures),
@@ -644,28 +1291,43 @@ async fn submission_loop(
                 // TODO: if ConfigureSession is sent twice, we will create an
                 // overlapping rollout file. Consider passing RolloutRecorder
                 // from above.
-                let rollout_recorder =
-                    match RolloutRecorder::new(&config, session_id, instructions.clone()).await {
-                        Ok(r) => Some(r),
-                        Err(e) => {
-                            tracing::warn!("failed to initialise rollout recorder: {e}");
-                            None
-                        }
-                    };
+                let rollout_recorder = match RolloutRecorder::new(
+                    &config,
+                    session_id,
+                    instructions.clone(),
+                    &cwd,
+                    &model,
+                )
+                .await
+                {
+                    Ok(r) => Some(r),
+                    Err(e) => {
+                        tracing::warn!("failed to initialise rollout recorder: {e}");
+                        None
+                    }
+                };
 
                 sess = Some(Arc::new(Session {
-                    client,
+                    client: Mutex::new(client),
                     tx_event: tx_event.clone(),
                     ctrl_c: Arc::clone(&ctrl_c),
+                    session_id,
                     stream: config.stream,
+                    include_apply_patch_tool: config.include_apply_patch_tool,
+                    include_file_tools: config.include_file_tools,
+                    output_schema: config.output_schema.clone(),
+                    budget: config.budget,
                     instructions,
-                    approval_policy,
-                    sandbox_policy,
+                    approval_policy: Mutex::new(approval_policy),
+                    sandbox_policy: Mutex::new(sandbox_policy),
                     shell_environment_policy: config.shell_environment_policy.clone(),
                     cwd,
                     writable_roots,
+                    approval_rules,
                     mcp_connection_manager,
                     notify,
+                    webhook: webhook.clone(),
+                    hooks: config.hooks.clone(),
                     state: Mutex::new(state),
                     rollout: Mutex::new(rollout_recorder),
                     codex_linux_sandbox_exe: config.codex_linux_sandbox_exe.clone(),
@@ -680,7 +1342,7 @@ async fn submission_loop(
                     id: sub.id.clone(),
                     msg: EventMsg::SessionConfigured(SessionConfiguredEvent {
                         session_id,
-                        model,
+                        model: model.clone(),
                         history_log_id,
                         history_entry_count,
                     }),
@@ -691,6 +1353,18 @@ async fn submission_loop(
                         error!("failed to send event: {e:?}");
                     }
                 }
+
+                if let Some(sess) = sess.as_ref() {
+                    crate::hooks::spawn_hooks(
+                        &sess.hooks.session_start,
+                        HookEvent::SessionStart {
+                            session_id,
+                            cwd: sess.cwd.clone(),
+                            model,
+                        },
+                        sess.cwd.clone(),
+                    );
+                }
             }
             Op::UserInput { items } => {
                 let sess = match sess.as_ref() {
@@ -700,15 +1374,119 @@ async fn submission_loop(
                         continue;
                     }
                 };
-
-                // attempt to inject input into current task
-                if let Err(items) = sess.inject_input(items) {
-                    // no current task, spawn a new one
-                    let task = AgentTask::spawn(Arc::clone(sess), sub.id, items);
-                    sess.set_task(task);
-                }
+
+                let items = sess.drop_unsupported_input(&sub.id, items).await;
+
+                // attempt to inject input into current task
+                if let Err(items) = sess.inject_input(items) {
+                    // no current task, spawn a new one
+                    let task = AgentTask::spawn(Arc::clone(sess), sub.id, items);
+                    sess.set_task(task);
+                }
+            }
+            Op::ExecApproval { id, decision } => {
+                let sess = match sess.as_ref() {
+                    Some(sess) => sess,
+                    None => {
+                        send_no_session_event(sub.id).await;
+                        continue;
+                    }
+                };
+                match decision {
+                    ReviewDecision::Abort => {
+                        sess.interrupt();
+                    }
+                    other => {
+                        if let ReviewDecision::ApprovedAndRemember { rule } = &other {
+                            sess.remember_approval_rule(&sub.id, rule.clone()).await;
+                        }
+                        sess.notify_approval(
+                            &id,
+                            ApprovalResponse {
+                                decision: other,
+                                rejected_changes: Vec::new(),
+                            },
+                        )
+                    }
+                }
+            }
+            Op::PatchApproval {
+                id,
+                decision,
+                rejected_changes,
+            } => {
+                let sess = match sess.as_ref() {
+                    Some(sess) => sess,
+                    None => {
+                        send_no_session_event(sub.id).await;
+                        continue;
+                    }
+                };
+                match decision {
+                    ReviewDecision::Abort => {
+                        sess.interrupt();
+                    }
+                    other => {
+                        if let ReviewDecision::ApprovedAndRemember { rule } = &other {
+                            sess.remember_approval_rule(&sub.id, rule.clone()).await;
+                        }
+                        sess.notify_approval(
+                            &id,
+                            ApprovalResponse {
+                                decision: other,
+                                rejected_changes,
+                            },
+                        )
+                    }
+                }
+            }
+            Op::Undo => {
+                let sess = match sess.as_ref() {
+                    Some(sess) => sess,
+                    None => {
+                        send_no_session_event(sub.id).await;
+                        continue;
+                    }
+                };
+                if sess.has_current_task() {
+                    let event = Event {
+                        id: sub.id,
+                        msg: EventMsg::Error(ErrorEvent {
+                            message: "Cannot undo while a task is running; interrupt it first."
+                                .to_string(),
+                        }),
+                    };
+                    tx_event.send(event).await.ok();
+                    continue;
+                }
+                sess.undo_last_task(&sub.id).await;
+            }
+            Op::GetSessionDiff => {
+                let sess = match sess.as_ref() {
+                    Some(sess) => sess,
+                    None => {
+                        send_no_session_event(sub.id).await;
+                        continue;
+                    }
+                };
+                let msg = match sess.session_diff().await {
+                    Ok(unified_diff) => EventMsg::SessionDiff(SessionDiffEvent { unified_diff }),
+                    Err(message) => EventMsg::Error(ErrorEvent { message }),
+                };
+                sess.send_event(Event { id: sub.id, msg }).await;
+            }
+            Op::ListApprovalRules => {
+                let sess = match sess.as_ref() {
+                    Some(sess) => sess,
+                    None => {
+                        send_no_session_event(sub.id).await;
+                        continue;
+                    }
+                };
+                let msg = sess.approval_rules_event();
+                sess.send_event(Event { id: sub.id, msg }).await;
             }
-            Op::ExecApproval { id, decision } => {
+            Op::RevokeApprovalRule { rule } => {
                 let sess = match sess.as_ref() {
                     Some(sess) => sess,
                     None => {
@@ -716,14 +1494,17 @@ async fn submission_loop(
                         continue;
                     }
                 };
-                match decision {
-                    ReviewDecision::Abort => {
-                        sess.abort();
-                    }
-                    other => sess.notify_approval(&id, other),
-                }
+                let msg = sess.revoke_approval_rule(&rule).await;
+                sess.send_event(Event { id: sub.id, msg }).await;
             }
-            Op::PatchApproval { id, decision } => {
+            Op::OverrideTurnContext {
+                provider,
+                model,
+                model_reasoning_effort,
+                model_reasoning_summary,
+                approval_policy,
+                sandbox_policy,
+            } => {
                 let sess = match sess.as_ref() {
                     Some(sess) => sess,
                     None => {
@@ -731,11 +1512,34 @@ async fn submission_loop(
                         continue;
                     }
                 };
-                match decision {
-                    ReviewDecision::Abort => {
-                        sess.abort();
-                    }
-                    other => sess.notify_approval(&id, other),
+                let model_changes = sess.switch_model(
+                    provider,
+                    model,
+                    model_reasoning_effort,
+                    model_reasoning_summary,
+                    &config.model_families,
+                );
+                // Tool calls read the policies when they run, so these take
+                // effect right away.
+                let mut policy_changes = Vec::new();
+                if let Some(approval_policy) = approval_policy {
+                    *sess.approval_policy.lock().unwrap() = approval_policy;
+                    policy_changes.push(format!("approval policy {approval_policy}"));
+                }
+                if let Some(sandbox_policy) = sandbox_policy {
+                    policy_changes.push(format!("sandbox {sandbox_policy}"));
+                    *sess.sandbox_policy.lock().unwrap() = sandbox_policy;
+                }
+                let mut summary = Vec::new();
+                if !model_changes.is_empty() {
+                    summary.push(format!("From the next turn: {}", model_changes.join(", ")));
+                }
+                if !policy_changes.is_empty() {
+                    summary.push(format!("Now: {}", policy_changes.join(", ")));
+                }
+                if !summary.is_empty() {
+                    sess.notify_background_event(&sub.id, summary.join("; "))
+                        .await;
                 }
             }
             Op::AddToHistory { text } => {
@@ -780,6 +1584,11 @@ async fn submission_loop(
             }
         }
     }
+    // Every `Codex` handle is gone, so nobody can observe or interrupt the
+    // running task any more; stop it rather than let it run unattended.
+    if let Some(sess) = sess {
+        sess.abort();
+    }
     debug!("Agent loop exited");
 }
 
@@ -808,13 +1617,27 @@ async fn run_task(sess: Arc<Session>, sub_id: String, input: Vec<InputItem>) {
         return;
     }
 
-    let initial_input_for_turn = ResponseInputItem::from(input);
-    sess.record_conversation_items(&[initial_input_for_turn.clone().into()])
-        .await;
+    sess.begin_task_tracking(&sub_id);
+    let mut budget = BudgetTracker::new(sess.budget);
+
+    // Anything queued while no task was running (e.g., the note left by
+    // `Op::Undo`) should precede the new user input.
+    let mut input_for_next_turn = sess.get_pending_input();
+    input_for_next_turn.push(ResponseInputItem::from(input));
+    let initial_items = input_for_next_turn
+        .iter()
+        .cloned()
+        .map(ResponseItem::from)
+        .collect::<Vec<_>>();
+    sess.record_conversation_items(&initial_items).await;
 
-    let mut input_for_next_turn: Vec<ResponseInputItem> = vec![initial_input_for_turn];
     let last_agent_message: Option<String>;
     loop {
+        if let Some(exceeded) = budget.exceeded() {
+            sess.stop_task_over_budget(&sub_id, exceeded).await;
+            return;
+        }
+
         let mut net_new_turn_input = input_for_next_turn
             .drain(..)
             .map(ResponseItem::from)
@@ -835,30 +1658,33 @@ async fn run_task(sess: Arc<Session>, sub_id: String, input: Vec<InputItem>) {
         // conversation history on each turn. The rollout file, however, should
         // only record the new items that originated in this turn so that it
         // represents an append-only log without duplicates.
-        let turn_input: Vec<ResponseItem> =
-            if let Some(transcript) = sess.state.lock().unwrap().zdr_transcript.as_mut() {
-                // If we are using Chat/ZDR, we need to send the transcript with
-                // every turn. By induction, `transcript` already contains:
-                // - The `input` that kicked off this task.
-                // - Each `ResponseItem` that was recorded in the previous turn.
-                // - Each response to a `ResponseItem` (in practice, the only
-                //   response type we seem to have is `FunctionCallOutput`).
-                //
-                // The only thing the `transcript` does not contain is the
-                // `pending_input` that was injected while the model was
-                // running. We need to add that to the conversation history
-                // so that the model can see it in the next turn.
-                [transcript.contents(), pending_input].concat()
-            } else {
-                // In practice, net_new_turn_input should contain only:
-                // - User messages
-                // - Outputs for function calls requested by the model
-                net_new_turn_input.extend(pending_input);
-
-                // Responses API path – we can just send the new items and
-                // record the same.
-                net_new_turn_input
-            };
+        let transcript = {
+            let state = sess.state.lock().unwrap();
+            state.full_history.then(|| state.transcript.contents())
+        };
+        let turn_input: Vec<ResponseItem> = if let Some(transcript) = transcript {
+            // If we are using Chat/ZDR, we need to send the transcript with
+            // every turn. By induction, `transcript` already contains:
+            // - The `input` that kicked off this task.
+            // - Each `ResponseItem` that was recorded in the previous turn.
+            // - Each response to a `ResponseItem` (in practice, the only
+            //   response type we seem to have is `FunctionCallOutput`).
+            //
+            // The only thing the `transcript` does not contain is the
+            // `pending_input` that was injected while the model was
+            // running. We need to add that to the conversation history
+            // so that the model can see it in the next turn.
+            [transcript, pending_input].concat()
+        } else {
+            // In practice, net_new_turn_input should contain only:
+            // - User messages
+            // - Outputs for function calls requested by the model
+            net_new_turn_input.extend(pending_input);
+
+            // Responses API path – we can just send the new items and
+            // record the same.
+            net_new_turn_input
+        };
 
         let turn_input_messages: Vec<String> = turn_input
             .iter()
@@ -873,7 +1699,8 @@ async fn run_task(sess: Arc<Session>, sub_id: String, input: Vec<InputItem>) {
                 })
             })
             .collect();
-        match run_turn(&sess, sub_id.clone(), turn_input).await {
+        let tokens_before_turn = sess.token_usage().total_tokens();
+        match run_turn(&sess, sub_id.clone(), turn_input, &budget).await {
             Ok(turn_output) => {
                 let mut items_to_record_in_conversation_history = Vec::<ResponseItem>::new();
                 let mut responses = Vec::<ResponseInputItem>::new();
@@ -914,15 +1741,15 @@ async fn run_task(sess: Arc<Session>, sub_id: String, input: Vec<InputItem>) {
                         ) => {
                             items_to_record_in_conversation_history.push(item);
                             let (content, success): (String, Option<bool>) = match result {
-                                Ok(CallToolResult { content, is_error }) => {
-                                    match serde_json::to_string(content) {
-                                        Ok(content) => (content, *is_error),
-                                        Err(e) => {
-                                            warn!("Failed to serialize MCP tool call output: {e}");
-                                            (e.to_string(), Some(true))
-                                        }
+                                Ok(CallToolResult {
+                                    content, is_error, ..
+                                }) => match serde_json::to_string(content) {
+                                    Ok(content) => (content, *is_error),
+                                    Err(e) => {
+                                        warn!("Failed to serialize MCP tool call output: {e}");
+                                        (e.to_string(), Some(true))
                                     }
-                                }
+                                },
                                 Err(e) => (e.clone(), Some(true)),
                             };
                             items_to_record_in_conversation_history.push(
@@ -955,6 +1782,15 @@ async fn run_task(sess: Arc<Session>, sub_id: String, input: Vec<InputItem>) {
                     last_agent_message = get_last_assistant_message_from_turn(
                         &items_to_record_in_conversation_history,
                     );
+                    crate::hooks::spawn_hooks(
+                        &sess.hooks.turn_complete,
+                        HookEvent::TurnComplete {
+                            turn_id: sub_id.clone(),
+                            input_messages: turn_input_messages.clone(),
+                            last_assistant_message: last_agent_message.clone(),
+                        },
+                        sess.cwd.clone(),
+                    );
                     sess.maybe_notify(UserNotification::AgentTurnComplete {
                         turn_id: sub_id.clone(),
                         input_messages: turn_input_messages,
@@ -963,10 +1799,29 @@ async fn run_task(sess: Arc<Session>, sub_id: String, input: Vec<InputItem>) {
                     break;
                 }
 
+                budget.record_turn(
+                    responses.len(),
+                    sess.token_usage().total_tokens() - tokens_before_turn,
+                );
                 input_for_next_turn = responses;
             }
+            Err(CodexErr::DurationBudgetExceeded) => {
+                sess.stop_task_over_budget(&sub_id, budget.duration_exceeded())
+                    .await;
+                return;
+            }
             Err(e) => {
                 info!("Turn error: {e:#}");
+                sess.maybe_notify(match e {
+                    CodexErr::Interrupted => UserNotification::TaskAborted {
+                        turn_id: sub_id.clone(),
+                        reason: e.to_string(),
+                    },
+                    _ => UserNotification::Error {
+                        turn_id: sub_id.clone(),
+                        message: e.to_string(),
+                    },
+                });
                 let event = Event {
                     id: sub_id.clone(),
                     msg: EventMsg::Error(ErrorEvent {
@@ -978,6 +1833,8 @@ async fn run_task(sess: Arc<Session>, sub_id: String, input: Vec<InputItem>) {
             }
         }
     }
+    let task_snapshot = sess.task_snapshot().await;
+    sess.notify_turn_diff(&sub_id, task_snapshot.as_ref()).await;
     sess.remove_task(&sub_id);
     let event = Event {
         id: sub_id,
@@ -990,11 +1847,33 @@ async fn run_turn(
     sess: &Session,
     sub_id: String,
     input: Vec<ResponseItem>,
+    budget: &BudgetTracker,
+) -> CodexResult<Vec<ProcessedResponseItem>> {
+    let mut input = input;
+    loop {
+        let err = match run_turn_with_retries(sess, &sub_id, input, budget).await {
+            Err(e) if should_fall_back(&e) => e,
+            result => return result,
+        };
+        if !sess.fall_back_to_next_model(&sub_id, &err).await {
+            return Err(err);
+        }
+        // The fallback model has no server-side state for this conversation,
+        // so it is sent the whole transcript.
+        input = sess.state.lock().unwrap().transcript.contents();
+    }
+}
+
+async fn run_turn_with_retries(
+    sess: &Session,
+    sub_id: &str,
+    input: Vec<ResponseItem>,
+    budget: &BudgetTracker,
 ) -> CodexResult<Vec<ProcessedResponseItem>> {
     // Decide whether to use server-side storage (previous_response_id) or disable it
     let (prev_id, store) = {
         let state = sess.state.lock().unwrap();
-        let store = state.zdr_transcript.is_none();
+        let store = !state.full_history;
         let prev_id = if store {
             state.previous_response_id.clone()
         } else {
@@ -1013,31 +1892,38 @@ async fn run_turn(
         store,
         stream: sess.stream,
         extra_tools,
+        include_apply_patch_tool: sess.include_apply_patch_tool,
+        include_file_tools: sess.include_file_tools,
+        output_schema: sess.output_schema.clone(),
     };
 
+    let max_retries = sess.client().provider().stream_max_retries();
     let mut retries = 0;
     loop {
-        match try_run_turn(sess, &sub_id, &prompt).await {
+        match try_run_turn(sess, sub_id, &prompt, budget).await {
             Ok(output) => return Ok(output),
             Err(CodexErr::Interrupted) => return Err(CodexErr::Interrupted),
+            Err(CodexErr::DurationBudgetExceeded) => {
+                return Err(CodexErr::DurationBudgetExceeded);
+            }
             Err(CodexErr::EnvVar(var)) => return Err(CodexErr::EnvVar(var)),
+            // Retrying cannot make the conversation fit.
+            Err(e) if is_context_length_error(&e) => return Err(e),
             Err(e) => {
-                if retries < *OPENAI_STREAM_MAX_RETRIES {
+                if retries < max_retries {
                     retries += 1;
                     let delay = backoff(retries);
                     warn!(
-                        "stream disconnected - retrying turn ({retries}/{} in {delay:?})...",
-                        *OPENAI_STREAM_MAX_RETRIES
+                        "stream disconnected - retrying turn ({retries}/{max_retries} in {delay:?})..."
                     );
 
                     // Surface retry information to any UI/front‑end so the
                     // user understands what is happening instead of staring
                     // at a seemingly frozen screen.
                     sess.notify_background_event(
-                        &sub_id,
+                        sub_id,
                         format!(
-                            "stream error: {e}; retrying {retries}/{} in {:?}…",
-                            *OPENAI_STREAM_MAX_RETRIES, delay
+                            "stream error: {e}; retrying {retries}/{max_retries} in {delay:?}…"
                         ),
                     )
                     .await;
@@ -1064,26 +1950,81 @@ async fn try_run_turn(
     sess: &Session,
     sub_id: &str,
     prompt: &Prompt,
+    budget: &BudgetTracker,
 ) -> CodexResult<Vec<ProcessedResponseItem>> {
-    let mut stream = sess.client.clone().stream(prompt).await?;
-
+    // Requests are retried inside the client; tell the user why the turn is
+    // waiting.
+    let tx_event = sess.tx_event.clone();
+    let event_id = sub_id.to_string();
+    let on_retry = move |message: String| -> BoxFuture<'static, ()> {
+        warn!("{message}");
+        let event = Event {
+            id: event_id.clone(),
+            msg: EventMsg::BackgroundEvent(BackgroundEventEvent { message }),
+        };
+        let tx_event = tx_event.clone();
+        Box::pin(async move {
+            if let Err(e) = tx_event.send(event).await {
+                debug!("failed to report retry: {e}");
+            }
+        })
+    };
     // Buffer all the incoming messages from the stream first, then execute them.
     // If we execute a function call in the middle of handling the stream, it can time out.
-    let mut input = Vec::new();
-    while let Some(event) = stream.next().await {
-        input.push(event?);
-    }
+    let receive = async {
+        let mut stream = sess.client().stream(prompt, &on_retry).await?;
+        let mut input = Vec::new();
+        while let Some(event) = stream.next().await {
+            input.push(event?);
+        }
+        Ok::<_, CodexErr>(input)
+    };
+    // Only waiting for the model is cut short by the time budget; a tool
+    // call that has started is allowed to finish.
+    let input = match budget.deadline() {
+        Some(deadline) => tokio::time::timeout_at(deadline.into(), receive)
+            .await
+            .map_err(|_| CodexErr::DurationBudgetExceeded)??,
+        None => receive.await?,
+    };
 
     let mut output = Vec::new();
+    let mut tool_calls = 0;
     for event in input {
         match event {
             ResponseEvent::OutputItemDone(item) => {
-                let response = handle_response_item(sess, sub_id, item.clone()).await?;
+                let refused = match tool_call_id(&item) {
+                    Some(call_id) => {
+                        let refused = budget.refuses_tool_call(tool_calls).map(|exceeded| {
+                            ResponseInputItem::FunctionCallOutput {
+                                call_id: call_id.to_string(),
+                                output: FunctionCallOutputPayload {
+                                    content: format!("not run: {}", exceeded.message),
+                                    success: Some(false),
+                                },
+                            }
+                        });
+                        tool_calls += 1;
+                        refused
+                    }
+                    None => None,
+                };
+                let response = match refused {
+                    Some(refused) => Some(refused),
+                    None => handle_response_item(sess, sub_id, item.clone()).await?,
+                };
                 output.push(ProcessedResponseItem { item, response });
             }
-            ResponseEvent::Completed { response_id } => {
+            ResponseEvent::Completed {
+                response_id,
+                token_usage,
+            } => {
                 let mut state = sess.state.lock().unwrap();
                 state.previous_response_id = Some(response_id);
+                if let Some(token_usage) = token_usage {
+                    state.token_usage.input_tokens += token_usage.input_tokens;
+                    state.token_usage.output_tokens += token_usage.output_tokens;
+                }
                 break;
             }
         }
@@ -1091,6 +2032,15 @@ async fn try_run_turn(
     Ok(output)
 }
 
+/// The call id of `item` if it asks for a tool to be run.
+fn tool_call_id(item: &ResponseItem) -> Option<&str> {
+    match item {
+        ResponseItem::FunctionCall { call_id, .. } => Some(call_id),
+        ResponseItem::LocalShellCall { call_id, id, .. } => call_id.as_deref().or(id.as_deref()),
+        _ => None,
+    }
+}
+
 async fn handle_response_item(
     sess: &Session,
     sub_id: &str,
@@ -1196,9 +2146,40 @@ async fn handle_function_call(
             };
             handle_container_exec_with_params(params, sess, sub_id, call_id).await
         }
+        "apply_patch" => {
+            let params = match serde_json::from_str::<ApplyPatchToolCallParams>(&arguments) {
+                Ok(params) => params,
+                Err(e) => {
+                    return ResponseInputItem::FunctionCallOutput {
+                        call_id,
+                        output: FunctionCallOutputPayload {
+                            content: format!("failed to parse function arguments: {e}"),
+                            success: None,
+                        },
+                    };
+                }
+            };
+            match parse_apply_patch_verified(&params.input, &sess.cwd) {
+                Ok(action) => apply_patch(sess, sub_id, call_id, action).await,
+                // Return the parse error (which includes the offending line
+                // number where available) so the model can fix the patch.
+                Err(e) => ResponseInputItem::FunctionCallOutput {
+                    call_id,
+                    output: FunctionCallOutputPayload {
+                        content: format!("error: {e:#}"),
+                        success: Some(false),
+                    },
+                },
+            }
+        }
+        name if is_file_tool(name) => {
+            handle_file_tool_call(sess, &sub_id, call_id, name, arguments).await
+        }
         _ => {
             match try_parse_fully_qualified_tool_name(&name) {
                 Some((server, tool_name)) => {
+                    // MCP tools may change files too.
+                    sess.task_snapshot().await;
                     // TODO(mbolin): Determine appropriate timeout for tool call.
                     let timeout = None;
                     handle_mcp_tool_call(
@@ -1230,6 +2211,7 @@ fn to_exec_params(params: ShellToolCallParams, sess: &Session) -> ExecParams {
     }
 }
 
+#[allow(clippy::result_large_err)]
 fn parse_container_exec_arguments(
     arguments: String,
     sess: &Session,
@@ -1253,11 +2235,12 @@ fn parse_container_exec_arguments(
 }
 
 async fn handle_container_exec_with_params(
-    params: ExecParams,
+    mut params: ExecParams,
     sess: &Session,
     sub_id: String,
     call_id: String,
 ) -> ResponseInputItem {
+    sess.task_snapshot().await;
     // check if this was a patch, and apply it if so
     match maybe_parse_apply_patch_verified(&params.command, &params.cwd) {
         MaybeApplyPatchVerified::Body(changes) => {
@@ -1281,16 +2264,36 @@ async fn handle_container_exec_with_params(
         MaybeApplyPatchVerified::NotApplyPatch => (),
     }
 
+    if let Err(reason) = sess
+        .run_pre_hooks(&sub_id, &sess.hooks.pre_exec, || HookEvent::PreExec {
+            call_id: call_id.clone(),
+            command: params.command.clone(),
+            cwd: params.cwd.clone(),
+        })
+        .await
+    {
+        return ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: format!("exec command rejected: {reason}"),
+                success: None,
+            },
+        };
+    }
+
     // safety checks
     let safety = {
         let state = sess.state.lock().unwrap();
         assess_command_safety(
             &params.command,
-            sess.approval_policy,
-            &sess.sandbox_policy,
+            sess.approval_policy(),
+            &sess.sandbox_policy(),
             &state.approved_commands,
+            &state.approval_rules,
         )
     };
+    // Set when the user edited the command before approving it.
+    let mut edit_note: Option<String> = None;
     let sandbox_type = match safety {
         SafetyCheck::AutoApprove { sandbox_type } => sandbox_type,
         SafetyCheck::AskUser => {
@@ -1302,16 +2305,73 @@ async fn handle_container_exec_with_params(
                     None,
                 )
                 .await;
-            match rx_approve.await.unwrap_or_default() {
-                ReviewDecision::Approved => (),
+            match rx_approve.await.unwrap_or_default().decision {
+                ReviewDecision::Approved | ReviewDecision::ApprovedAndRemember { .. } => (),
                 ReviewDecision::ApprovedForSession => {
                     sess.add_approved_command(params.command.clone());
                 }
-                ReviewDecision::Denied | ReviewDecision::Abort => {
+                ReviewDecision::ApprovedModifiedCommand { command } => {
+                    let note = edited_command_note(&command);
+                    params.command = command;
+                    if let Err(reason) = sess
+                        .run_pre_hooks(&sub_id, &sess.hooks.pre_exec, || HookEvent::PreExec {
+                            call_id: call_id.clone(),
+                            command: params.command.clone(),
+                            cwd: params.cwd.clone(),
+                        })
+                        .await
+                    {
+                        return ResponseInputItem::FunctionCallOutput {
+                            call_id,
+                            output: FunctionCallOutputPayload {
+                                content: format!("{note}\nexec command rejected: {reason}"),
+                                success: None,
+                            },
+                        };
+                    }
+                    edit_note = Some(note);
+                    // The edited command may be safe enough to run in the
+                    // sandbox, or not allowed at all.
+                    let safety = {
+                        let state = sess.state.lock().unwrap();
+                        assess_command_safety(
+                            &params.command,
+                            sess.approval_policy(),
+                            &sess.sandbox_policy(),
+                            &state.approved_commands,
+                            &state.approval_rules,
+                        )
+                    };
+                    match safety {
+                        SafetyCheck::AutoApprove { sandbox_type } => {
+                            return with_note(
+                                run_exec_with_params(params, sandbox_type, sess, sub_id, call_id)
+                                    .await,
+                                edit_note,
+                            );
+                        }
+                        SafetyCheck::AskUser => (),
+                        SafetyCheck::Reject { reason } => {
+                            return ResponseInputItem::FunctionCallOutput {
+                                call_id,
+                                output: crate::models::FunctionCallOutputPayload {
+                                    content: format!(
+                                        "{}\nexec command rejected: {reason}",
+                                        edit_note.unwrap_or_default()
+                                    ),
+                                    success: None,
+                                },
+                            };
+                        }
+                    }
+                }
+                decision @ (ReviewDecision::Denied
+                | ReviewDecision::DeniedWithFeedback { .. }
+                | ReviewDecision::Abort) => {
                     return ResponseInputItem::FunctionCallOutput {
                         call_id,
                         output: crate::models::FunctionCallOutputPayload {
-                            content: "exec command rejected by user".to_string(),
+                            content: rejection_message("exec command", &decision),
                             success: None,
                         },
                     };
@@ -1334,6 +2394,19 @@ async fn handle_container_exec_with_params(
         }
     };
 
+    with_note(
+        run_exec_with_params(params, sandbox_type, sess, sub_id, call_id).await,
+        edit_note,
+    )
+}
+
+async fn run_exec_with_params(
+    params: ExecParams,
+    sandbox_type: SandboxType,
+    sess: &Session,
+    sub_id: String,
+    call_id: String,
+) -> ResponseInputItem {
     sess.notify_exec_command_begin(&sub_id, &call_id, &params)
         .await;
 
@@ -1341,29 +2414,34 @@ async fn handle_container_exec_with_params(
         params.clone(),
         sandbox_type,
         sess.ctrl_c.clone(),
-        &sess.sandbox_policy,
+        &sess.sandbox_policy(),
         &sess.codex_linux_sandbox_exe,
     )
     .await;
 
     match output_result {
         Ok(output) => {
-            let ExecToolCallOutput {
-                exit_code,
-                stdout,
-                stderr,
-                duration,
-            } = output;
-
-            sess.notify_exec_command_end(&sub_id, &call_id, &stdout, &stderr, exit_code)
-                .await;
+            sess.notify_exec_command_end(
+                &sub_id,
+                &call_id,
+                &output.stdout,
+                &output.stderr,
+                output.exit_code,
+            )
+            .await;
 
-            let is_success = exit_code == 0;
-            let content = format_exec_output(
-                if is_success { &stdout } else { &stderr },
-                exit_code,
-                duration,
+            let is_success = output.exit_code == 0;
+            let mut content = format_exec_output(
+                if is_success {
+                    &output.stdout
+                } else {
+                    &output.stderr
+                },
+                output.exit_code,
+                output.duration,
             );
+            sess.append_post_exec_output(&mut content, &call_id, &params, &output)
+                .await;
 
             ResponseInputItem::FunctionCallOutput {
                 call_id,
@@ -1389,16 +2467,39 @@ async fn handle_container_exec_with_params(
     }
 }
 
+/// Tells the model that the command it proposed is not the one that ran.
+fn edited_command_note(command: &[String]) -> String {
+    format!(
+        "Note: the user edited your command before approving it. The command that ran was {command:?}."
+    )
+}
+
+/// Prepends `note`, if any, to the output of a function call.
+fn with_note(item: ResponseInputItem, note: Option<String>) -> ResponseInputItem {
+    match (item, note) {
+        (ResponseInputItem::FunctionCallOutput { call_id, output }, Some(note)) => {
+            ResponseInputItem::FunctionCallOutput {
+                call_id,
+                output: FunctionCallOutputPayload {
+                    content: format!("{note}\n{}", output.content),
+                    success: output.success,
+                },
+            }
+        }
+        (item, _) => item,
+    }
+}
+
 async fn handle_sanbox_error(
     error: SandboxErr,
     sandbox_type: SandboxType,
-    params: ExecParams,
+    mut params: ExecParams,
     sess: &Session,
     sub_id: String,
     call_id: String,
 ) -> ResponseInputItem {
     // Early out if the user never wants to be asked for approval; just return to the model immediately
-    if sess.approval_policy == AskForApproval::Never {
+    if sess.approval_policy() == AskForApproval::Never {
         return ResponseInputItem::FunctionCallOutput {
             call_id,
             output: FunctionCallOutputPayload {
@@ -1424,8 +2525,37 @@ async fn handle_sanbox_error(
         )
         .await;
 
-    match rx_approve.await.unwrap_or_default() {
-        ReviewDecision::Approved | ReviewDecision::ApprovedForSession => {
+    match rx_approve.await.unwrap_or_default().decision {
+        decision @ (ReviewDecision::Approved
+        | ReviewDecision::ApprovedForSession
+        | ReviewDecision::ApprovedModifiedCommand { .. }
+        | ReviewDecision::ApprovedAndRemember { .. }) => {
+            // The user is approving a run outside the sandbox, which is as
+            // permissive as it gets, so an edited command needs no further
+            // safety assessment.
+            let mut edit_note = None;
+            if let ReviewDecision::ApprovedModifiedCommand { command } = decision {
+                let note = edited_command_note(&command);
+                params.command = command;
+                if let Err(reason) = sess
+                    .run_pre_hooks(&sub_id, &sess.hooks.pre_exec, || HookEvent::PreExec {
+                        call_id: call_id.clone(),
+                        command: params.command.clone(),
+                        cwd: params.cwd.clone(),
+                    })
+                    .await
+                {
+                    return ResponseInputItem::FunctionCallOutput {
+                        call_id,
+                        output: FunctionCallOutputPayload {
+                            content: format!("{note}\nexec command rejected: {reason}"),
+                            success: None,
+                        },
+                    };
+                }
+                edit_note = Some(note);
+            }
+
             // Persist this command as pre‑approved for the
             // remainder of the session so future
             // executions skip the sandbox directly.
@@ -1443,38 +2573,37 @@ async fn handle_sanbox_error(
             // This is an escalated retry; the policy will not be
             // examined and the sandbox has been set to `None`.
             let retry_output_result = process_exec_tool_call(
-                params,
+                params.clone(),
                 SandboxType::None,
                 sess.ctrl_c.clone(),
-                &sess.sandbox_policy,
+                &sess.sandbox_policy(),
                 &sess.codex_linux_sandbox_exe,
             )
             .await;
 
-            match retry_output_result {
+            let response = match retry_output_result {
                 Ok(retry_output) => {
-                    let ExecToolCallOutput {
-                        exit_code,
-                        stdout,
-                        stderr,
-                        duration,
-                    } = retry_output;
-
                     sess.notify_exec_command_end(
                         &sub_id,
                         &retry_call_id,
-                        &stdout,
-                        &stderr,
-                        exit_code,
+                        &retry_output.stdout,
+                        &retry_output.stderr,
+                        retry_output.exit_code,
                     )
                     .await;
 
-                    let is_success = exit_code == 0;
-                    let content = format_exec_output(
-                        if is_success { &stdout } else { &stderr },
-                        exit_code,
-                        duration,
+                    let is_success = retry_output.exit_code == 0;
+                    let mut content = format_exec_output(
+                        if is_success {
+                            &retry_output.stdout
+                        } else {
+                            &retry_output.stderr
+                        },
+                        retry_output.exit_code,
+                        retry_output.duration,
                     );
+                    sess.append_post_exec_output(&mut content, &call_id, &params, &retry_output)
+                        .await;
 
                     ResponseInputItem::FunctionCallOutput {
                         call_id,
@@ -1494,14 +2623,17 @@ async fn handle_sanbox_error(
                         },
                     }
                 }
-            }
+            };
+            with_note(response, edit_note)
         }
-        ReviewDecision::Denied | ReviewDecision::Abort => {
+        decision @ (ReviewDecision::Denied
+        | ReviewDecision::DeniedWithFeedback { .. }
+        | ReviewDecision::Abort) => {
             // Fall through to original failure handling.
             ResponseInputItem::FunctionCallOutput {
                 call_id,
                 output: FunctionCallOutputPayload {
-                    content: "exec command rejected by user".to_string(),
+                    content: rejection_message("exec command", &decision),
                     success: None,
                 },
             }
@@ -1513,8 +2645,28 @@ async fn apply_patch(
     sess: &Session,
     sub_id: String,
     call_id: String,
-    action: ApplyPatchAction,
+    mut action: ApplyPatchAction,
 ) -> ResponseInputItem {
+    sess.task_snapshot().await;
+    if let Err(reason) = sess
+        .run_pre_hooks(&sub_id, &sess.hooks.pre_patch, || HookEvent::PrePatch {
+            call_id: call_id.clone(),
+            changes: convert_apply_patch_to_protocol(&action),
+        })
+        .await
+    {
+        return ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: format!("patch rejected: {reason}"),
+                success: Some(false),
+            },
+        };
+    }
+
+    // Descriptions of the parts of the patch the user declined, for the model.
+    let mut rejected_notes: Vec<String> = Vec::new();
+
     let writable_roots_snapshot = {
         let guard = sess.writable_roots.lock().unwrap();
         guard.clone()
@@ -1522,7 +2674,7 @@ async fn apply_patch(
 
     let auto_approved = match assess_patch_safety(
         &action,
-        sess.approval_policy,
+        sess.approval_policy(),
         &writable_roots_snapshot,
         &sess.cwd,
     ) {
@@ -1533,13 +2685,29 @@ async fn apply_patch(
             let rx_approve = sess
                 .request_patch_approval(sub_id.clone(), &action, None, None)
                 .await;
-            match rx_approve.await.unwrap_or_default() {
-                ReviewDecision::Approved | ReviewDecision::ApprovedForSession => false,
-                ReviewDecision::Denied | ReviewDecision::Abort => {
+            let response = rx_approve.await.unwrap_or_default();
+            match response.decision {
+                ReviewDecision::Approved
+                | ReviewDecision::ApprovedForSession
+                | ReviewDecision::ApprovedAndRemember { .. } => {
+                    if let Err(output) = narrow_patch_to_approved(
+                        &mut action,
+                        &response,
+                        &sess.cwd,
+                        &mut rejected_notes,
+                    ) {
+                        return ResponseInputItem::FunctionCallOutput { call_id, output };
+                    }
+                    false
+                }
+                decision @ (ReviewDecision::Denied
+                | ReviewDecision::DeniedWithFeedback { .. }
+                | ReviewDecision::ApprovedModifiedCommand { .. }
+                | ReviewDecision::Abort) => {
                     return ResponseInputItem::FunctionCallOutput {
                         call_id,
                         output: FunctionCallOutputPayload {
-                            content: "patch rejected by user".to_string(),
+                            content: rejection_message("patch", &decision),
                             success: Some(false),
                         },
                     };
@@ -1572,18 +2740,26 @@ async fn apply_patch(
             .request_patch_approval(sub_id.clone(), &action, reason.clone(), Some(root.clone()))
             .await;
 
+        let response = rx.await.unwrap_or_default();
         if !matches!(
-            rx.await.unwrap_or_default(),
-            ReviewDecision::Approved | ReviewDecision::ApprovedForSession
+            response.decision,
+            ReviewDecision::Approved
+                | ReviewDecision::ApprovedForSession
+                | ReviewDecision::ApprovedAndRemember { .. }
         ) {
             return ResponseInputItem::FunctionCallOutput {
                 call_id,
                 output: FunctionCallOutputPayload {
-                    content: "patch rejected by user".to_string(),
+                    content: rejection_message("patch", &response.decision),
                     success: Some(false),
                 },
             };
         }
+        if let Err(output) =
+            narrow_patch_to_approved(&mut action, &response, &sess.cwd, &mut rejected_notes)
+        {
+            return ResponseInputItem::FunctionCallOutput { call_id, output };
+        }
 
         // user approved, extend writable roots for this session
         sess.writable_roots.lock().unwrap().push(root);
@@ -1601,6 +2777,8 @@ async fn apply_patch(
         })
         .await;
 
+    sess.record_diff_baseline(&action);
+
     let mut stdout = Vec::new();
     let mut stderr = Vec::new();
     // Enforce writable roots. If a write is blocked, collect offending root
@@ -1659,8 +2837,10 @@ async fn apply_patch(
                     )
                     .await;
                 if matches!(
-                    rx.await.unwrap_or_default(),
-                    ReviewDecision::Approved | ReviewDecision::ApprovedForSession
+                    rx.await.unwrap_or_default().decision,
+                    ReviewDecision::Approved
+                        | ReviewDecision::ApprovedForSession
+                        | ReviewDecision::ApprovedAndRemember { .. }
                 ) {
                     // Extend writable roots.
                     sess.writable_roots.lock().unwrap().push(root);
@@ -1691,21 +2871,124 @@ async fn apply_patch(
         })
         .await;
 
+    let hook_output = sess
+        .run_post_hooks(&sess.hooks.post_patch, || HookEvent::PostPatch {
+            call_id: call_id.clone(),
+            changes: convert_apply_patch_to_protocol(&action),
+            success: success_flag,
+        })
+        .await;
+
     match result {
-        Ok(_) => ResponseInputItem::FunctionCallOutput {
-            call_id,
-            output: FunctionCallOutputPayload {
-                content: String::from_utf8_lossy(&stdout).to_string(),
-                success: None,
-            },
-        },
-        Err(e) => ResponseInputItem::FunctionCallOutput {
-            call_id,
-            output: FunctionCallOutputPayload {
-                content: format!("error: {e:#}, stderr: {}", String::from_utf8_lossy(&stderr)),
-                success: Some(false),
-            },
-        },
+        Ok(_) => {
+            let mut content = String::from_utf8_lossy(&stdout).to_string();
+            if !rejected_notes.is_empty() {
+                content.push_str(
+                    "\nThe user rejected the following parts of the patch, which were NOT applied:\n",
+                );
+                content.push_str(&rejected_notes.join("\n"));
+            }
+            if let Some(hook_output) = hook_output {
+                content.push('\n');
+                content.push_str(&hook_output);
+            }
+            ResponseInputItem::FunctionCallOutput {
+                call_id,
+                output: FunctionCallOutputPayload {
+                    content,
+                    success: None,
+                },
+            }
+        }
+        Err(e) => {
+            let mut content = format!("error: {e:#}, stderr: {}", String::from_utf8_lossy(&stderr));
+            if let Some(hook_output) = hook_output {
+                content.push('\n');
+                content.push_str(&hook_output);
+            }
+            ResponseInputItem::FunctionCallOutput {
+                call_id,
+                output: FunctionCallOutputPayload {
+                    content,
+                    success: Some(false),
+                },
+            }
+        }
+    }
+}
+
+/// Tool output telling the model that the user turned down `what`, including
+/// their explanation if they gave one. An edited command is not a meaningful
+/// answer to a patch approval, so it is reported as a rejection too.
+fn rejection_message(what: &str, decision: &ReviewDecision) -> String {
+    match decision {
+        ReviewDecision::DeniedWithFeedback { feedback } => {
+            format!("{what} rejected by user with the following feedback:\n{feedback}")
+        }
+        ReviewDecision::ApprovedModifiedCommand { .. } => {
+            warn!("ignoring ApprovedModifiedCommand for a {what} approval");
+            format!(
+                "{what} rejected: the approval named a modified command, which does not apply to a {what}"
+            )
+        }
+        _ => format!("{what} rejected by user"),
+    }
+}
+
+/// Removes the parts of `action` that `response` rejected and appends a
+/// description of each to `notes`. Returns the tool output to send instead of
+/// applying anything if nothing is left or the patch cannot be narrowed.
+fn narrow_patch_to_approved(
+    action: &mut ApplyPatchAction,
+    response: &ApprovalResponse,
+    cwd: &Path,
+    notes: &mut Vec<String>,
+) -> Result<(), FunctionCallOutputPayload> {
+    let rejected_changes = &response.rejected_changes;
+    if rejected_changes.is_empty() {
+        return Ok(());
+    }
+
+    let protocol_changes = convert_apply_patch_to_protocol(action);
+    let mut rejections = HashMap::new();
+    for RejectedFileChange { path, hunks } in rejected_changes {
+        let Some(change) = protocol_changes.get(path) else {
+            continue;
+        };
+        let display = path.strip_prefix(cwd).unwrap_or(path).display();
+        let all_hunks = change.hunks();
+        // Adds and deletes have no hunks, so any rejection covers the whole
+        // file.
+        let whole_file = match hunks {
+            None => true,
+            Some(hunks) => all_hunks.is_empty() && !hunks.is_empty(),
+        };
+        if whole_file {
+            notes.push(format!("- {display} (entire change)"));
+            rejections.insert(path.clone(), Rejection::WholeFile);
+        } else if let Some(hunks) = hunks {
+            for &idx in hunks {
+                if let Some(hunk) = all_hunks.get(idx) {
+                    notes.push(format!("- {display}, hunk:\n{}", hunk.trim_end()));
+                }
+            }
+            rejections.insert(path.clone(), Rejection::Hunks(hunks.clone()));
+        }
+    }
+
+    match action.without_rejected(&rejections) {
+        Ok(narrowed) if narrowed.is_empty() => Err(FunctionCallOutputPayload {
+            content: rejection_message("patch", &response.decision),
+            success: Some(false),
+        }),
+        Ok(narrowed) => {
+            *action = narrowed;
+            Ok(())
+        }
+        Err(e) => Err(FunctionCallOutputPayload {
+            content: format!("error: failed to apply the approved parts of the patch: {e:#}"),
+            success: Some(false),
+        }),
     }
 }
 
@@ -1929,6 +3212,59 @@ fn get_last_assistant_message_from_turn(responses: &[ResponseItem]) -> Option<St
     })
 }
 
+/// The configured `model_fallbacks`, skipping those whose provider is unknown.
+fn fallback_chain(config: &Config) -> VecDeque<FallbackModel> {
+    config
+        .model_fallbacks
+        .iter()
+        .filter_map(|fallback| {
+            let provider = config.model_providers.get(&fallback.model_provider);
+            if provider.is_none() {
+                warn!(
+                    "ignoring fallback model `{}`: provider `{}` not found",
+                    fallback.model, fallback.model_provider
+                );
+            }
+            Some(FallbackModel {
+                model: fallback.model.clone(),
+                model_family: find_family_for_model(&fallback.model, &config.model_families),
+                provider_id: fallback.model_provider.clone(),
+                provider: provider?.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Whether `err` means the current model cannot answer, so that the next
+/// fallback model (if any) should take over.
+fn should_fall_back(err: &CodexErr) -> bool {
+    match err {
+        CodexErr::RetryLimit(_) | CodexErr::Stream(_) | CodexErr::Reqwest(_) => true,
+        CodexErr::UnexpectedStatus(status, _) => {
+            status.is_server_error() || is_context_length_error(err)
+        }
+        _ => false,
+    }
+}
+
+/// Whether the provider rejected the request because the conversation no
+/// longer fits in the model's context window.
+fn is_context_length_error(err: &CodexErr) -> bool {
+    let CodexErr::UnexpectedStatus(status, body) = err else {
+        return false;
+    };
+    let body = body.to_lowercase();
+    status.is_client_error()
+        && [
+            "context_length_exceeded",
+            "maximum context length",
+            "prompt is too long",
+            "context window",
+        ]
+        .iter()
+        .any(|needle| body.contains(needle))
+}
+
 /// See [`ConversationHistory`] for details.
 fn record_conversation_history(disable_response_storage: bool, wire_api: WireApi) -> bool {
     if disable_response_storage {
@@ -1937,6 +3273,6 @@ fn record_conversation_history(disable_response_storage: bool, wire_api: WireApi
 
     match wire_api {
         WireApi::Responses => false,
-        WireApi::Chat => true,
+        WireApi::Chat | WireApi::Messages => true,
     }
 }
@@ -14,7 +14,18 @@ use tokio::sync::Notify;
 /// that callers can surface the information to the UI.
 pub async fn init_codex(config: Config) -> anyhow::Result<(Codex, Event, Arc<Notify>)> {
     let ctrl_c = notify_on_sigint();
-    let (codex, init_id) = Codex::spawn(config, ctrl_c.clone()).await?;
+    let (codex, event) = init_codex_with_ctrl_c(config, ctrl_c.clone()).await?;
+    Ok((codex, event, ctrl_c))
+}
+
+/// Like [`init_codex`], but the session is interrupted through `ctrl_c`
+/// instead of its own SIGINT listener. For callers that run several sessions
+/// and handle Ctrl-C once for all of them.
+pub async fn init_codex_with_ctrl_c(
+    config: Config,
+    ctrl_c: Arc<Notify>,
+) -> anyhow::Result<(Codex, Event)> {
+    let (codex, init_id) = Codex::spawn(config, ctrl_c).await?;
 
     // The first event must be `SessionInitialized`. Validate and forward it to
     // the caller so that they can display it in the conversation history.
@@ -33,5 +44,5 @@ pub async fn init_codex(config: Config) -> anyhow::Result<(Codex, Event, Arc<Not
         ));
     }
 
-    Ok((codex, event, ctrl_c))
+    Ok((codex, event))
 }
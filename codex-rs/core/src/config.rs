@@ -1,13 +1,22 @@
 use crate::config_profile::ConfigProfile;
 use crate::config_types::History;
+use crate::config_types::Hooks;
 use crate::config_types::McpServerConfig;
+use crate::config_types::ModelFallback;
+use crate::config_types::ModelFamilyOverride;
 use crate::config_types::ReasoningEffort;
 use crate::config_types::ReasoningSummary;
+use crate::config_types::RunBudget;
+use crate::config_types::SandboxMode;
+use crate::config_types::SandboxWorkspaceWrite;
 use crate::config_types::ShellEnvironmentPolicy;
 use crate::config_types::ShellEnvironmentPolicyToml;
 use crate::config_types::Tui;
 use crate::config_types::UriBasedFileOpener;
+use crate::config_types::WebhookNotifierConfig;
 use crate::flags::OPENAI_DEFAULT_MODEL;
+use crate::model_family::ModelFamily;
+use crate::model_family::find_family_for_model;
 use crate::model_provider_info::ModelProviderInfo;
 use crate::model_provider_info::built_in_model_providers;
 use crate::protocol::AskForApproval;
@@ -50,12 +59,25 @@ pub struct Config {
     /// Optional override of model selection.
     pub model: String,
 
+    /// What is known about `model`: context window, supported request
+    /// parameters and tools.
+    pub model_family: ModelFamily,
+
+    /// User overrides of the built-in model families, keyed by model name
+    /// prefix. Kept so that the family of a model switched to later in the
+    /// session can be looked up.
+    pub model_families: HashMap<String, ModelFamilyOverride>,
+
     /// Key into the model_providers map that specifies which provider to use.
     pub model_provider_id: String,
 
     /// Info needed to make an API request to the model.
     pub model_provider: ModelProviderInfo,
 
+    /// Models to switch to, in order, when the current one keeps failing.
+    /// Every `model_provider` is a key of `model_providers`.
+    pub model_fallbacks: Vec<ModelFallback>,
+
     /// Approval policy for executing commands.
     pub approval_policy: AskForApproval,
 
@@ -101,6 +123,16 @@ pub struct Config {
     /// If unset the feature is disabled.
     pub notify: Option<Vec<String>>,
 
+    /// Optional HTTP endpoint that receives the same notifications as
+    /// `notify`.
+    pub notify_webhook: Option<WebhookNotifierConfig>,
+
+    /// User scripts run before and after commands, patches and turns.
+    pub hooks: Hooks,
+
+    /// Limits on each task; unlimited by default.
+    pub budget: RunBudget,
+
     /// The directory that should be treated as the current working directory
     /// for the session. All relative paths inside the business-logic layer are
     /// resolved against this path.
@@ -144,6 +176,20 @@ pub struct Config {
     /// If not "none", the value to use for `reasoning.summary` when making a
     /// request using the Responses API.
     pub model_reasoning_summary: ReasoningSummary,
+
+    /// When `true`, the model is offered a dedicated `apply_patch` function
+    /// tool for editing files instead of being told to run `apply_patch`
+    /// through the shell.
+    pub include_apply_patch_tool: bool,
+
+    /// When `true`, the model is also offered the in-process `read_file`,
+    /// `list_dir` and `grep` tools.
+    pub include_file_tools: bool,
+
+    /// JSON Schema that the agent's final message must conform to. Like
+    /// `codex_linux_sandbox_exe`, this cannot be set in the config file: it is
+    /// set via [`ConfigOverrides`] (e.g. by `codex exec --output-schema`).
+    pub output_schema: Option<serde_json::Value>,
 }
 
 impl Config {
@@ -257,6 +303,14 @@ pub struct ConfigToml {
     /// Provider to use from the model_providers map.
     pub model_provider: Option<String>,
 
+    /// Models to switch to, in order, when the current one keeps failing.
+    pub model_fallbacks: Option<Vec<ModelFallback>>,
+
+    /// Adjustments to what Codex assumes about models, keyed by model name
+    /// prefix.
+    #[serde(default)]
+    pub model_families: HashMap<String, ModelFamilyOverride>,
+
     /// Default approval policy for executing commands.
     pub approval_policy: Option<AskForApproval>,
 
@@ -269,6 +323,12 @@ pub struct ConfigToml {
     #[serde(default, deserialize_with = "deserialize_sandbox_permissions")]
     pub sandbox_permissions: Option<Vec<SandboxPermission>>,
 
+    /// Named sandbox preset. Takes precedence over `sandbox_permissions`.
+    pub sandbox_mode: Option<SandboxMode>,
+
+    /// Options for `sandbox_mode = "workspace-write"`.
+    pub sandbox_workspace_write: Option<SandboxWorkspaceWrite>,
+
     /// Disable server-side response storage (sends the full conversation
     /// context with every request). Currently necessary for OpenAI customers
     /// who have opted into Zero Data Retention (ZDR).
@@ -278,6 +338,12 @@ pub struct ConfigToml {
     #[serde(default)]
     pub notify: Option<Vec<String>>,
 
+    /// Optional HTTP endpoint for end-user notifications.
+    pub notify_webhook: Option<WebhookNotifierConfig>,
+
+    /// Lifecycle hooks. See [`Hooks`].
+    pub hooks: Option<Hooks>,
+
     /// System instructions.
     pub instructions: Option<String>,
 
@@ -310,6 +376,9 @@ pub struct ConfigToml {
     /// Collection of settings that are specific to the TUI.
     pub tui: Option<Tui>,
 
+    /// Limits on each task.
+    pub budget: Option<RunBudget>,
+
     /// When set to `true`, `AgentReasoning` events will be hidden from the
     /// UI/output. Defaults to `false`.
     pub hide_agent_reasoning: Option<bool>,
@@ -318,6 +387,14 @@ pub struct ConfigToml {
 
     pub model_reasoning_effort: Option<ReasoningEffort>,
     pub model_reasoning_summary: Option<ReasoningSummary>,
+
+    /// When set to `true`, expose `apply_patch` to the model as a function
+    /// tool. Defaults to `false`.
+    pub include_apply_patch_tool: Option<bool>,
+
+    /// When set to `true`, offer the model the read-only `read_file`,
+    /// `list_dir` and `grep` tools. Defaults to `false`.
+    pub include_file_tools: Option<bool>,
 }
 
 fn deserialize_sandbox_permissions<'de, D>(
@@ -353,10 +430,13 @@ pub struct ConfigOverrides {
     pub cwd: Option<PathBuf>,
     pub approval_policy: Option<AskForApproval>,
     pub sandbox_policy: Option<SandboxPolicy>,
+    /// Used when `sandbox_policy` is not set.
+    pub sandbox_mode: Option<SandboxMode>,
     pub model_provider: Option<String>,
     pub config_profile: Option<String>,
     pub codex_linux_sandbox_exe: Option<PathBuf>,
     pub stream: Option<bool>,
+    pub output_schema: Option<serde_json::Value>,
 }
 
 impl Config {
@@ -375,10 +455,12 @@ impl Config {
             cwd,
             approval_policy,
             sandbox_policy,
+            sandbox_mode,
             model_provider,
             config_profile: config_profile_key,
             codex_linux_sandbox_exe,
             stream,
+            output_schema,
         } = overrides;
 
         let config_profile = match config_profile_key.or(cfg.profile) {
@@ -395,21 +477,6 @@ impl Config {
             None => ConfigProfile::default(),
         };
 
-        let sandbox_policy = match sandbox_policy {
-            Some(sandbox_policy) => sandbox_policy,
-            None => {
-                // Derive a SandboxPolicy from the permissions in the config.
-                match cfg.sandbox_permissions {
-                    // Note this means the user can explicitly set permissions
-                    // to the empty list in the config file, granting it no
-                    // permissions whatsoever.
-                    Some(permissions) => SandboxPolicy::from(permissions),
-                    // Default to read only rather than completely locked down.
-                    None => SandboxPolicy::new_read_only_policy(),
-                }
-            }
-        };
-
         let mut model_providers = built_in_model_providers();
         // Merge user-defined providers into the built-in list.
         for (key, provider) in cfg.model_providers.into_iter() {
@@ -446,6 +513,23 @@ impl Config {
             })?
             .clone();
 
+        let model_fallbacks = config_profile
+            .model_fallbacks
+            .or(cfg.model_fallbacks)
+            .unwrap_or_default();
+        if let Some(fallback) = model_fallbacks
+            .iter()
+            .find(|fallback| !model_providers.contains_key(&fallback.model_provider))
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "Model provider `{}` for fallback model `{}` not found",
+                    fallback.model_provider, fallback.model
+                ),
+            ));
+        }
+
         let shell_environment_policy = cfg.shell_environment_policy.into();
 
         let resolved_cwd = {
@@ -467,15 +551,54 @@ impl Config {
             }
         };
 
+        let sandbox_mode = sandbox_mode
+            .or(config_profile.sandbox_mode)
+            .or(cfg.sandbox_mode);
+        let sandbox_policy = match (sandbox_policy, sandbox_mode) {
+            (Some(sandbox_policy), _) => sandbox_policy,
+            (None, Some(SandboxMode::ReadOnly)) => SandboxPolicy::new_read_only_policy(),
+            (None, Some(SandboxMode::WorkspaceWrite)) => {
+                let SandboxWorkspaceWrite {
+                    writable_roots,
+                    network_access,
+                } = cfg.sandbox_workspace_write.unwrap_or_default();
+                let writable_roots: Vec<PathBuf> = writable_roots
+                    .into_iter()
+                    .map(|root| resolved_cwd.join(root))
+                    .collect();
+                SandboxPolicy::new_workspace_write_policy(&writable_roots, network_access)
+            }
+            (None, Some(SandboxMode::DangerFullAccess)) => {
+                SandboxPolicy::new_danger_full_access_policy()
+            }
+            (None, None) => {
+                // Derive a SandboxPolicy from the permissions in the config.
+                match cfg.sandbox_permissions {
+                    // Note this means the user can explicitly set permissions
+                    // to the empty list in the config file, granting it no
+                    // permissions whatsoever.
+                    Some(permissions) => SandboxPolicy::from(permissions),
+                    // Default to read only rather than completely locked down.
+                    None => SandboxPolicy::new_read_only_policy(),
+                }
+            }
+        };
+
         let history = cfg.history.unwrap_or_default();
 
+        let model = model
+            .or(config_profile.model)
+            .or(cfg.model)
+            .unwrap_or_else(default_model);
+        let model_family = find_family_for_model(&model, &cfg.model_families);
+
         let config = Self {
-            model: model
-                .or(config_profile.model)
-                .or(cfg.model)
-                .unwrap_or_else(default_model),
+            model,
+            model_family,
+            model_families: cfg.model_families,
             model_provider_id,
             model_provider,
+            model_fallbacks,
             cwd: resolved_cwd,
             approval_policy: approval_policy
                 .or(config_profile.approval_policy)
@@ -488,6 +611,9 @@ impl Config {
                 .or(cfg.disable_response_storage)
                 .unwrap_or(false),
             notify: cfg.notify,
+            notify_webhook: cfg.notify_webhook,
+            hooks: cfg.hooks.unwrap_or_default(),
+            budget: config_profile.budget.or(cfg.budget).unwrap_or_default(),
             instructions,
             mcp_servers: cfg.mcp_servers,
             model_providers,
@@ -505,6 +631,15 @@ impl Config {
                 .unwrap_or(true),
             model_reasoning_effort: cfg.model_reasoning_effort.unwrap_or_default(),
             model_reasoning_summary: cfg.model_reasoning_summary.unwrap_or_default(),
+            include_apply_patch_tool: config_profile
+                .include_apply_patch_tool
+                .or(cfg.include_apply_patch_tool)
+                .unwrap_or(false),
+            include_file_tools: config_profile
+                .include_file_tools
+                .or(cfg.include_file_tools)
+                .unwrap_or(false),
+            output_schema,
         };
         Ok(config)
     }
@@ -539,7 +674,7 @@ fn default_model() -> String {
 ///   function will Err if the path does not exist.
 /// - If `CODEX_HOME` is not set, this function does not verify that the
 ///   directory exists.
-fn find_codex_home() -> std::io::Result<PathBuf> {
+pub fn find_codex_home() -> std::io::Result<PathBuf> {
     // Honor the `CODEX_HOME` environment variable when it is set to allow users
     // (and tests) to override the default location.
     if let Ok(val) = std::env::var("CODEX_HOME") {
@@ -707,6 +842,62 @@ persistence = "none"
         assert!(msg.contains("not-a-real-permission"));
     }
 
+    #[test]
+    fn sandbox_mode_takes_precedence_over_permissions() -> std::io::Result<()> {
+        let cfg = toml::from_str::<ConfigToml>(
+            r#"
+sandbox_permissions = ["disk-full-read-access", "network-full-access"]
+sandbox_mode = "workspace-write"
+
+[sandbox_workspace_write]
+writable_roots = ["/opt/cache", "build"]
+network_access = true
+
+[profiles.locked]
+sandbox_mode = "read-only"
+"#,
+        )
+        .expect("TOML deserialization should succeed");
+        let codex_home = TempDir::new()?;
+        let cwd = TempDir::new()?;
+        let load = |overrides: ConfigOverrides| {
+            Config::load_from_base_config_with_overrides(
+                cfg.clone(),
+                ConfigOverrides {
+                    cwd: Some(cwd.path().to_path_buf()),
+                    model_provider: Some("openai".to_string()),
+                    ..overrides
+                },
+                codex_home.path().to_path_buf(),
+            )
+        };
+
+        assert_eq!(
+            load(ConfigOverrides::default())?.sandbox_policy,
+            SandboxPolicy::new_workspace_write_policy(
+                &[PathBuf::from("/opt/cache"), cwd.path().join("build")],
+                true
+            )
+        );
+        assert_eq!(
+            load(ConfigOverrides {
+                config_profile: Some("locked".to_string()),
+                ..Default::default()
+            })?
+            .sandbox_policy,
+            SandboxPolicy::new_read_only_policy()
+        );
+        assert_eq!(
+            load(ConfigOverrides {
+                sandbox_mode: Some(SandboxMode::DangerFullAccess),
+                ..Default::default()
+            })?
+            .sandbox_policy,
+            SandboxPolicy::new_danger_full_access_policy()
+        );
+        Ok(())
+    }
+
     struct PrecedenceTestFixture {
         cwd: TempDir,
         codex_home: TempDir,
@@ -776,6 +967,13 @@ disable_response_storage = true
             base_url: "https://api.openai.com/v1".to_string(),
             env_key: Some("OPENAI_API_KEY".to_string()),
             wire_api: crate::WireApi::Chat,
+            api_key_header: None,
+            query_params: None,
+            http_headers: None,
+            env_http_headers: None,
+            request_max_retries: None,
+            stream_max_retries: None,
+            stream_idle_timeout_ms: None,
             env_key_instructions: None,
         };
         let model_provider_map = {
@@ -831,14 +1029,19 @@ disable_response_storage = true
         assert_eq!(
             Config {
                 model: "o3".to_string(),
+                model_family: find_family_for_model("o3", &HashMap::new()),
+                model_families: HashMap::new(),
                 model_provider_id: "openai".to_string(),
                 model_provider: fixture.openai_provider.clone(),
+                model_fallbacks: Vec::new(),
                 approval_policy: AskForApproval::Never,
                 sandbox_policy: SandboxPolicy::new_read_only_policy(),
                 shell_environment_policy: ShellEnvironmentPolicy::default(),
                 disable_response_storage: false,
                 instructions: None,
                 notify: None,
+                notify_webhook: None,
+                hooks: Hooks::default(),
                 cwd: fixture.cwd(),
                 mcp_servers: HashMap::new(),
                 model_providers: fixture.model_provider_map.clone(),
@@ -852,6 +1055,10 @@ disable_response_storage = true
                 stream: true,
                 model_reasoning_effort: ReasoningEffort::default(),
                 model_reasoning_summary: ReasoningSummary::default(),
+                include_apply_patch_tool: false,
+                include_file_tools: false,
+                output_schema: None,
+                budget: RunBudget::default(),
             },
             o3_profile_config
         );
@@ -874,14 +1081,19 @@ disable_response_storage = true
         )?;
         let expected_gpt3_profile_config = Config {
             model: "gpt-3.5-turbo".to_string(),
+            model_family: find_family_for_model("gpt-3.5-turbo", &HashMap::new()),
+            model_families: HashMap::new(),
             model_provider_id: "openai-chat-completions".to_string(),
             model_provider: fixture.openai_chat_completions_provider.clone(),
+            model_fallbacks: Vec::new(),
             approval_policy: AskForApproval::UnlessAllowListed,
             sandbox_policy: SandboxPolicy::new_read_only_policy(),
             shell_environment_policy: ShellEnvironmentPolicy::default(),
             disable_response_storage: false,
             instructions: None,
             notify: None,
+            notify_webhook: None,
+            hooks: Hooks::default(),
             cwd: fixture.cwd(),
             mcp_servers: HashMap::new(),
             model_providers: fixture.model_provider_map.clone(),
@@ -895,6 +1107,10 @@ disable_response_storage = true
             stream: true,
             model_reasoning_effort: ReasoningEffort::default(),
             model_reasoning_summary: ReasoningSummary::default(),
+            include_apply_patch_tool: false,
+            include_file_tools: false,
+            output_schema: None,
+            budget: RunBudget::default(),
         };
 
         assert_eq!(expected_gpt3_profile_config, gpt3_profile_config);
@@ -932,14 +1148,19 @@ disable_response_storage = true
         )?;
         let expected_zdr_profile_config = Config {
             model: "o3".to_string(),
+            model_family: find_family_for_model("o3", &HashMap::new()),
+            model_families: HashMap::new(),
             model_provider_id: "openai".to_string(),
             model_provider: fixture.openai_provider.clone(),
+            model_fallbacks: Vec::new(),
             approval_policy: AskForApproval::OnFailure,
             sandbox_policy: SandboxPolicy::new_read_only_policy(),
             shell_environment_policy: ShellEnvironmentPolicy::default(),
             disable_response_storage: true,
             instructions: None,
             notify: None,
+            notify_webhook: None,
+            hooks: Hooks::default(),
             cwd: fixture.cwd(),
             mcp_servers: HashMap::new(),
             model_providers: fixture.model_provider_map.clone(),
@@ -953,6 +1174,10 @@ disable_response_storage = true
             stream: true,
             model_reasoning_effort: ReasoningEffort::default(),
             model_reasoning_summary: ReasoningSummary::default(),
+            include_apply_patch_tool: false,
+            include_file_tools: false,
+            output_schema: None,
+            budget: RunBudget::default(),
         };
 
         assert_eq!(expected_zdr_profile_config, zdr_profile_config);
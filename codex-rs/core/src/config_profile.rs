@@ -1,5 +1,8 @@
 use serde::Deserialize;
 
+use crate::config_types::ModelFallback;
+use crate::config_types::RunBudget;
+use crate::config_types::SandboxMode;
 use crate::protocol::AskForApproval;
 
 /// Collection of common configuration options that a user can define as a unit
@@ -10,7 +13,14 @@ pub struct ConfigProfile {
     /// The key in the `model_providers` map identifying the
     /// [`ModelProviderInfo`] to use.
     pub model_provider: Option<String>,
+    /// Replaces the top-level `model_fallbacks`.
+    pub model_fallbacks: Option<Vec<ModelFallback>>,
     pub approval_policy: Option<AskForApproval>,
+    pub sandbox_mode: Option<SandboxMode>,
     pub disable_response_storage: Option<bool>,
     pub stream: Option<bool>,
+    pub include_apply_patch_tool: Option<bool>,
+    pub include_file_tools: Option<bool>,
+    /// Replaces the top-level `budget`.
+    pub budget: Option<RunBudget>,
 }
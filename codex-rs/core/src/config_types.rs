@@ -4,6 +4,7 @@
 // definitions that do not contain business logic.
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 use strum_macros::Display;
 use wildmatch::WildMatchPattern;
 
@@ -77,6 +78,136 @@ pub enum HistoryPersistence {
     None,
 }
 
+/// A model to switch to when the configured one keeps failing.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ModelFallback {
+    pub model: String,
+    /// The key in the `model_providers` map identifying the provider that
+    /// serves `model`.
+    pub model_provider: String,
+}
+
+/// Named sandbox presets for commands run by the model. Each maps onto a
+/// [`crate::protocol::SandboxPolicy`].
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq, Default, Display)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum SandboxMode {
+    /// Read any file; write nothing; no network.
+    #[default]
+    ReadOnly,
+    /// Read any file; write to the working directory, the user's temp dir
+    /// and any extra `writable_roots`; network only if enabled.
+    WorkspaceWrite,
+    /// No sandbox at all.
+    DangerFullAccess,
+}
+
+/// Options for `sandbox_mode = "workspace-write"`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SandboxWorkspaceWrite {
+    /// Folders writable in addition to the working directory. Relative paths
+    /// are resolved against the working directory.
+    #[serde(default)]
+    pub writable_roots: Vec<PathBuf>,
+
+    /// Allow commands to access the network.
+    #[serde(default)]
+    pub network_access: bool,
+}
+
+/// How the model is offered a way to run shell commands.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShellToolType {
+    /// A `shell` function tool, which any model with function calling can use.
+    Function,
+    /// The Responses API's built-in `local_shell` tool, which only some
+    /// models are trained on.
+    LocalShell,
+}
+
+/// Overrides for what Codex assumes about models whose name starts with a
+/// given prefix. Unset fields keep the built-in value.
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ModelFamilyOverride {
+    pub context_window: Option<u64>,
+    pub max_output_tokens: Option<u64>,
+    /// Whether the model accepts the `reasoning` request parameter.
+    pub supports_reasoning: Option<bool>,
+    pub shell_tool: Option<ShellToolType>,
+    pub supports_image_input: Option<bool>,
+}
+
+/// An HTTP endpoint that receives the same JSON notifications as `notify`,
+/// one `POST` per notification.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct WebhookNotifierConfig {
+    pub url: String,
+
+    /// Extra headers sent with every request, e.g. `Authorization`.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+
+    /// If set, every request carries an `X-Codex-Signature: sha256=<hex>`
+    /// header with the HMAC-SHA256 of the body under this key.
+    pub secret: Option<String>,
+
+    /// Retries after a failed delivery (network error, 429 or 5xx). Defaults
+    /// to 3.
+    pub max_retries: Option<u32>,
+
+    /// Notifications waiting to be delivered beyond this many are dropped.
+    /// Defaults to 100.
+    pub queue_size: Option<usize>,
+}
+
+/// A user script run at a point in the agent's lifecycle. It receives a JSON
+/// description of the event on stdin; see [`Hooks`].
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct HookConfig {
+    /// Program followed by its arguments. Run from the session's `cwd`.
+    pub command: Vec<String>,
+
+    /// Seconds after which the hook is killed. Defaults to 60.
+    pub timeout_secs: Option<u64>,
+}
+
+/// Scripts to run before and after the agent acts, configured per event:
+///
+/// ```toml
+/// [[hooks.pre_exec]]
+/// command = ["./scripts/no-git-push.sh"]
+///
+/// [[hooks.post_patch]]
+/// command = ["cargo", "fmt"]
+/// ```
+///
+/// A pre-hook that exits non-zero vetoes the action; whatever it printed is
+/// passed to the model as the reason. Whatever a post-hook prints is appended
+/// to the tool output the model sees. `session_start` and `turn_complete`
+/// hooks run in the background and their output is ignored.
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Hooks {
+    #[serde(default)]
+    pub pre_exec: Vec<HookConfig>,
+
+    #[serde(default)]
+    pub post_exec: Vec<HookConfig>,
+
+    #[serde(default)]
+    pub pre_patch: Vec<HookConfig>,
+
+    #[serde(default)]
+    pub post_patch: Vec<HookConfig>,
+
+    #[serde(default)]
+    pub session_start: Vec<HookConfig>,
+
+    #[serde(default)]
+    pub turn_complete: Vec<HookConfig>,
+}
+
 /// Collection of settings that are specific to the TUI.
 #[derive(Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct Tui {
@@ -92,6 +223,31 @@ pub struct Tui {
     /// `space` still work. This allows the user to select text in the TUI
     /// using the mouse without needing to hold down a modifier key.
     pub disable_mouse_capture: bool,
+
+    /// How to alert the user when an approval is needed while the terminal is
+    /// not focused. Off by default.
+    #[serde(default)]
+    pub notifications: TerminalNotificationMethod,
+}
+
+/// Escape sequence the TUI writes to get the terminal's attention.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum TerminalNotificationMethod {
+    /// `ESC ] 9 ; message BEL`, a desktop notification in iTerm2, WezTerm,
+    /// Windows Terminal and others.
+    Osc9,
+
+    /// `ESC ] 777 ; notify ; title ; message BEL`, understood by rxvt-unicode,
+    /// foot, Ghostty and others.
+    Osc777,
+
+    /// A plain terminal bell. tmux and most terminals flag the window.
+    Bell,
+
+    /// Do not notify.
+    #[default]
+    None,
 }
 
 #[derive(Deserialize, Debug, Clone, PartialEq, Default)]
@@ -209,3 +365,19 @@ pub enum ReasoningSummary {
     /// Option to disable reasoning summaries.
     None,
 }
+
+/// Limits on a single task, i.e. one user prompt and every turn the agent
+/// takes to answer it. A task that reaches a limit is stopped with an
+/// `EventMsg::BudgetExceeded`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RunBudget {
+    /// Maximum number of requests made to the model.
+    pub max_turns: Option<u32>,
+    /// Maximum number of tool calls (shell commands, patches, MCP tools).
+    pub max_tool_calls: Option<u32>,
+    /// Wall-clock limit, in seconds.
+    pub max_duration_secs: Option<u64>,
+    /// Maximum number of input plus output tokens. Only counted for
+    /// providers that report usage (the Responses and Messages APIs).
+    pub max_tokens: Option<u64>,
+}
@@ -1,22 +1,21 @@
 use crate::models::ResponseItem;
 
-/// Transcript of conversation history that is needed:
-/// - for ZDR clients for which previous_response_id is not available, so we
-///   must include the transcript with every API call. This must include each
-///   `function_call` and its corresponding `function_call_output`.
-/// - for clients using the "chat completions" API as opposed to the
-///   "responses" API.
-#[derive(Debug, Clone)]
+/// Transcript of conversation history. It is always recorded, and is sent
+/// with every API call (instead of relying on `previous_response_id`):
+/// - for ZDR clients for which previous_response_id is not available. This
+///   must include each `function_call` and its corresponding
+///   `function_call_output`.
+/// - for clients using the "chat completions" or "messages" API as opposed to
+///   the "responses" API.
+/// - after falling back to another model, which has no server-side state for
+///   the conversation.
+#[derive(Debug, Clone, Default)]
 pub(crate) struct ConversationHistory {
     /// The oldest items are at the beginning of the vector.
     items: Vec<ResponseItem>,
 }
 
 impl ConversationHistory {
-    pub(crate) fn new() -> Self {
-        Self { items: Vec::new() }
-    }
-
     /// Returns a clone of the contents in the transcript.
     pub(crate) fn contents(&self) -> Vec<ResponseItem> {
         self.items.clone()
@@ -58,6 +58,10 @@ pub enum CodexErr {
     #[error("interrupted (Ctrl-C)")]
     Interrupted,
 
+    /// The task reached `budget.max_duration_secs` while waiting for the model.
+    #[error("reached the task's time budget")]
+    DurationBudgetExceeded,
+
     /// Unexpected HTTP status code.
     #[error("unexpected status {0}: {1}")]
     UnexpectedStatus(StatusCode, String),
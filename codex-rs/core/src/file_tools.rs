@@ -0,0 +1,646 @@
+//! Built-in, read-only file tools (`read_file`, `list_dir`, `grep`).
+//!
+//! Unlike the `shell` tool, these run in-process: they do not spawn a
+//! sandboxed child, their output is not subject to `MAX_STREAM_OUTPUT`, and
+//! they never prompt for approval. Instead, every path is checked against the
+//! directories the sandbox policy allows reading before it is touched.
+
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::path::Path;
+use std::path::PathBuf;
+
+use ignore::WalkBuilder;
+use ignore::overrides::OverrideBuilder;
+use regex_lite::RegexBuilder;
+use serde::Deserialize;
+
+use crate::codex::Session;
+use crate::models::FunctionCallOutputPayload;
+use crate::models::ResponseInputItem;
+use crate::protocol::Event;
+use crate::protocol::EventMsg;
+use crate::protocol::FileToolCallEvent;
+use crate::protocol::FileToolInvocation;
+use crate::protocol::SandboxPolicy;
+
+pub(crate) const READ_FILE_TOOL_NAME: &str = "read_file";
+pub(crate) const LIST_DIR_TOOL_NAME: &str = "list_dir";
+pub(crate) const GREP_TOOL_NAME: &str = "grep";
+
+/// Maximum number of lines returned by a single `read_file` call.
+const READ_FILE_MAX_LINES: usize = 2000;
+
+/// Lines longer than this (in characters) are truncated in tool output.
+const MAX_LINE_CHARS: usize = 500;
+
+/// Bytes of a line kept in memory by `read_file`; enough for
+/// `MAX_LINE_CHARS` characters of any encoding.
+const MAX_LINE_BYTES: usize = MAX_LINE_CHARS * 4;
+
+/// `grep` skips files larger than this, and `read_file` stops reading them
+/// after the requested lines instead of counting the rest.
+const MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+const LIST_DIR_DEFAULT_DEPTH: usize = 2;
+const LIST_DIR_MAX_DEPTH: usize = 10;
+const LIST_DIR_MAX_ENTRIES: usize = 1000;
+
+const GREP_MAX_MATCHES: usize = 200;
+
+/// Number of leading bytes inspected when deciding whether a file is binary.
+const BINARY_SNIFF_LEN: usize = 8192;
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct ReadFileParams {
+    pub path: String,
+    /// 1-based, inclusive.
+    pub start_line: Option<usize>,
+    /// 1-based, inclusive.
+    pub end_line: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct ListDirParams {
+    pub path: Option<String>,
+    pub depth: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct GrepParams {
+    pub pattern: String,
+    pub path: Option<String>,
+    #[serde(default)]
+    pub globs: Vec<String>,
+    #[serde(default)]
+    pub case_insensitive: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum FileToolRequest {
+    ReadFile(ReadFileParams),
+    ListDir(ListDirParams),
+    Grep(GrepParams),
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct FileToolOutput {
+    /// Text returned to the model.
+    pub content: String,
+    /// One-line summary shown to the user.
+    pub summary: String,
+}
+
+pub(crate) fn is_file_tool(name: &str) -> bool {
+    matches!(
+        name,
+        READ_FILE_TOOL_NAME | LIST_DIR_TOOL_NAME | GREP_TOOL_NAME
+    )
+}
+
+impl FileToolRequest {
+    fn parse(tool_name: &str, arguments: &str) -> Result<Self, serde_json::Error> {
+        Ok(match tool_name {
+            READ_FILE_TOOL_NAME => Self::ReadFile(serde_json::from_str(arguments)?),
+            LIST_DIR_TOOL_NAME => Self::ListDir(serde_json::from_str(arguments)?),
+            _ => Self::Grep(serde_json::from_str(arguments)?),
+        })
+    }
+
+    fn invocation(&self) -> FileToolInvocation {
+        let path_or_cwd = |path: &Option<String>| PathBuf::from(path.as_deref().unwrap_or("."));
+        match self {
+            Self::ReadFile(ReadFileParams {
+                path,
+                start_line,
+                end_line,
+            }) => FileToolInvocation::ReadFile {
+                path: PathBuf::from(path),
+                start_line: *start_line,
+                end_line: *end_line,
+            },
+            Self::ListDir(ListDirParams { path, depth }) => FileToolInvocation::ListDir {
+                path: path_or_cwd(path),
+                depth: list_dir_depth(*depth),
+            },
+            Self::Grep(GrepParams {
+                pattern,
+                path,
+                globs,
+                ..
+            }) => FileToolInvocation::Grep {
+                pattern: pattern.clone(),
+                path: path_or_cwd(path),
+                globs: globs.clone(),
+            },
+        }
+    }
+
+    pub(crate) fn run(
+        &self,
+        cwd: &Path,
+        sandbox_policy: &SandboxPolicy,
+    ) -> Result<FileToolOutput, String> {
+        match self {
+            Self::ReadFile(params) => read_file(cwd, sandbox_policy, params),
+            Self::ListDir(params) => list_dir(cwd, sandbox_policy, params),
+            Self::Grep(params) => grep(cwd, sandbox_policy, params),
+        }
+    }
+}
+
+/// Runs one of the built-in file tools and reports it to the `Session` via a
+/// `FileToolCall` event.
+pub(crate) async fn handle_file_tool_call(
+    sess: &Session,
+    sub_id: &str,
+    call_id: String,
+    tool_name: &str,
+    arguments: String,
+) -> ResponseInputItem {
+    let request = match FileToolRequest::parse(tool_name, &arguments) {
+        Ok(request) => request,
+        Err(e) => {
+            return ResponseInputItem::FunctionCallOutput {
+                call_id,
+                output: FunctionCallOutputPayload {
+                    content: format!("failed to parse function arguments: {e}"),
+                    success: Some(false),
+                },
+            };
+        }
+    };
+
+    let invocation = request.invocation();
+    let cwd = sess.cwd().to_path_buf();
+    let sandbox_policy = sess.sandbox_policy();
+    let result = tokio::task::spawn_blocking(move || request.run(&cwd, &sandbox_policy))
+        .await
+        .unwrap_or_else(|e| Err(format!("{tool_name} failed: {e}")));
+
+    let (content, summary, success) = match result {
+        Ok(FileToolOutput { content, summary }) => (content, summary, true),
+        Err(e) => (e.clone(), e, false),
+    };
+
+    sess.send_event(Event {
+        id: sub_id.to_string(),
+        msg: EventMsg::FileToolCall(FileToolCallEvent {
+            call_id: call_id.clone(),
+            invocation,
+            summary,
+            success,
+        }),
+    })
+    .await;
+
+    ResponseInputItem::FunctionCallOutput {
+        call_id,
+        output: FunctionCallOutputPayload {
+            content,
+            success: Some(success),
+        },
+    }
+}
+
+fn read_file(
+    cwd: &Path,
+    sandbox_policy: &SandboxPolicy,
+    params: &ReadFileParams,
+) -> Result<FileToolOutput, String> {
+    let path = resolve_readable_path(cwd, sandbox_policy, Some(&params.path))?;
+    if path.is_dir() {
+        return Err(format!(
+            "{} is a directory; use {LIST_DIR_TOOL_NAME} instead",
+            params.path
+        ));
+    }
+    let read_error = |e: std::io::Error| format!("failed to read {}: {e}", params.path);
+    let file = File::open(&path).map_err(read_error)?;
+    let file_len = file.metadata().map_err(read_error)?.len();
+    let mut reader = BufReader::with_capacity(BINARY_SNIFF_LEN, file);
+    if is_binary(reader.fill_buf().map_err(read_error)?) {
+        return Err(format!("{} appears to be a binary file", params.path));
+    }
+
+    let start = params.start_line.unwrap_or(1).max(1);
+    if let Some(end_line) = params.end_line.filter(|end_line| *end_line < start) {
+        return Err(format!("end_line {end_line} is before start_line {start}"));
+    }
+    let last = params
+        .end_line
+        .unwrap_or(usize::MAX)
+        .min(start + READ_FILE_MAX_LINES - 1);
+
+    let mut content = String::new();
+    let mut line = Vec::new();
+    let mut line_count = 0;
+    let mut more_requested = false;
+    let mut reached_eof = false;
+    loop {
+        if !next_line(&mut reader, &mut line, MAX_LINE_BYTES).map_err(read_error)? {
+            reached_eof = true;
+            break;
+        }
+        line_count += 1;
+        if line_count > last {
+            more_requested = params.end_line.is_none_or(|end_line| end_line > last);
+            // Counting the remaining lines is only worth it for files of a
+            // reasonable size.
+            if file_len > MAX_FILE_BYTES {
+                break;
+            }
+        } else if line_count >= start {
+            content.push_str(&format!(
+                "{line_count:>6}\t{}\n",
+                truncate_line(&String::from_utf8_lossy(&line))
+            ));
+        }
+    }
+
+    if line_count == 0 {
+        return Ok(FileToolOutput {
+            content: String::new(),
+            summary: "empty file".to_string(),
+        });
+    }
+    if start > line_count {
+        return Err(format!(
+            "start_line {start} is past the end of {} ({line_count} lines)",
+            params.path
+        ));
+    }
+    let end = last.min(line_count);
+    if more_requested {
+        content.push_str(&format!(
+            "[output limited to {READ_FILE_MAX_LINES} lines; call {READ_FILE_TOOL_NAME} again with start_line = {} to continue]\n",
+            end + 1
+        ));
+    }
+
+    let summary = if reached_eof {
+        format!("lines {start}-{end} of {line_count}")
+    } else {
+        format!("lines {start}-{end}")
+    };
+    Ok(FileToolOutput { content, summary })
+}
+
+fn list_dir(
+    cwd: &Path,
+    sandbox_policy: &SandboxPolicy,
+    params: &ListDirParams,
+) -> Result<FileToolOutput, String> {
+    let root = resolve_readable_path(cwd, sandbox_policy, params.path.as_deref())?;
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", root.display()));
+    }
+
+    let mut entries = Vec::new();
+    let mut truncated = false;
+    for entry in walk_builder(&root)
+        .max_depth(Some(list_dir_depth(params.depth)))
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build()
+    {
+        let Ok(entry) = entry else { continue };
+        if entry.depth() == 0 {
+            continue;
+        }
+        if entries.len() == LIST_DIR_MAX_ENTRIES {
+            truncated = true;
+            break;
+        }
+        let relative = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+        let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+        entries.push(format!(
+            "{}{}",
+            relative.display(),
+            if is_dir { "/" } else { "" }
+        ));
+    }
+
+    let mut summary = format!("{} entries", entries.len());
+    let mut content = entries.join("\n");
+    if truncated {
+        summary.push_str(" (truncated)");
+        content.push_str(&format!(
+            "\n[output limited to {LIST_DIR_MAX_ENTRIES} entries; list a subdirectory or reduce depth]"
+        ));
+    }
+    Ok(FileToolOutput { content, summary })
+}
+
+fn grep(
+    cwd: &Path,
+    sandbox_policy: &SandboxPolicy,
+    params: &GrepParams,
+) -> Result<FileToolOutput, String> {
+    let regex = RegexBuilder::new(&params.pattern)
+        .case_insensitive(params.case_insensitive)
+        .build()
+        .map_err(|e| format!("invalid regex: {e}"))?;
+    let root = resolve_readable_path(cwd, sandbox_policy, params.path.as_deref())?;
+
+    let mut builder = walk_builder(&root);
+    builder.sort_by_file_name(|a, b| a.cmp(b));
+    if !params.globs.is_empty() {
+        let mut overrides = OverrideBuilder::new(&root);
+        for glob in &params.globs {
+            overrides
+                .add(glob)
+                .map_err(|e| format!("invalid glob {glob:?}: {e}"))?;
+        }
+        builder.overrides(overrides.build().map_err(|e| e.to_string())?);
+    }
+
+    let display_base = cwd.canonicalize().unwrap_or_else(|_| cwd.to_path_buf());
+    let mut matches = Vec::new();
+    let mut files_with_matches = 0;
+    let mut skipped_large_files = 0;
+    let mut truncated = false;
+    let mut line = Vec::new();
+    'files: for entry in builder.build() {
+        let Ok(entry) = entry else { continue };
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        if entry
+            .metadata()
+            .is_ok_and(|metadata| metadata.len() > MAX_FILE_BYTES)
+        {
+            skipped_large_files += 1;
+            continue;
+        }
+        let Ok(file) = File::open(entry.path()) else {
+            continue;
+        };
+        let mut reader = BufReader::with_capacity(BINARY_SNIFF_LEN, file);
+        if reader.fill_buf().map_or(true, is_binary) {
+            continue;
+        }
+
+        let display_path = entry
+            .path()
+            .strip_prefix(&display_base)
+            .unwrap_or(entry.path())
+            .display()
+            .to_string();
+        let mut file_matched = false;
+        let mut line_number = 0;
+        // The file is at most `MAX_FILE_BYTES`, so its lines need no cap.
+        while let Ok(true) = next_line(&mut reader, &mut line, usize::MAX) {
+            line_number += 1;
+            let text = String::from_utf8_lossy(&line);
+            if !regex.is_match(&text) {
+                continue;
+            }
+            if matches.len() == GREP_MAX_MATCHES {
+                truncated = true;
+                break 'files;
+            }
+            if !file_matched {
+                file_matched = true;
+                files_with_matches += 1;
+            }
+            matches.push(format!(
+                "{display_path}:{line_number}:{}",
+                truncate_line(&text)
+            ));
+        }
+    }
+
+    let skipped_note = (skipped_large_files > 0).then(|| {
+        format!(
+            "[skipped {skipped_large_files} files larger than {} MiB; use the shell to search them]",
+            MAX_FILE_BYTES / (1024 * 1024)
+        )
+    });
+    if matches.is_empty() {
+        let mut content = "No matches found.".to_string();
+        if let Some(note) = skipped_note {
+            content.push_str(&format!("\n{note}"));
+        }
+        return Ok(FileToolOutput {
+            content,
+            summary: "no matches".to_string(),
+        });
+    }
+
+    let mut summary = format!("{} matches in {files_with_matches} files", matches.len());
+    let mut content = matches.join("\n");
+    if truncated {
+        summary.push_str(" (truncated)");
+        content.push_str(&format!(
+            "\n[output limited to {GREP_MAX_MATCHES} matches; narrow the pattern, path, or globs]"
+        ));
+    }
+    if let Some(note) = skipped_note {
+        content.push_str(&format!("\n{note}"));
+    }
+    Ok(FileToolOutput { content, summary })
+}
+
+/// Resolves `path` against `cwd` and verifies that the result lies within a
+/// directory the sandbox policy allows reading.
+fn resolve_readable_path(
+    cwd: &Path,
+    sandbox_policy: &SandboxPolicy,
+    path: Option<&str>,
+) -> Result<PathBuf, String> {
+    let joined = path.map_or_else(|| cwd.to_path_buf(), |p| cwd.join(p));
+    let canonical = joined
+        .canonicalize()
+        .map_err(|e| format!("{}: {e}", joined.display()))?;
+    if sandbox_policy.has_full_disk_read_access() {
+        return Ok(canonical);
+    }
+
+    let mut readable_roots = sandbox_policy.get_writable_roots_with_cwd(cwd);
+    readable_roots.push(cwd.to_path_buf());
+    let is_readable = readable_roots
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .any(|root| canonical.starts_with(root));
+    if is_readable {
+        Ok(canonical)
+    } else {
+        Err(format!(
+            "{} is outside the directories the sandbox allows reading",
+            joined.display()
+        ))
+    }
+}
+
+/// Walker that honors `.gitignore`/`.ignore` files (even outside of a git
+/// repository) and includes dotfiles other than the `.git` directory itself.
+fn walk_builder(root: &Path) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .hidden(false)
+        .require_git(false)
+        .filter_entry(|entry| entry.file_name() != ".git");
+    builder
+}
+
+fn list_dir_depth(depth: Option<usize>) -> usize {
+    depth
+        .unwrap_or(LIST_DIR_DEFAULT_DEPTH)
+        .clamp(1, LIST_DIR_MAX_DEPTH)
+}
+
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(BINARY_SNIFF_LEN).any(|b| *b == 0)
+}
+
+/// Reads the next line from `reader` into `buf` without its line ending,
+/// keeping at most `max_len` bytes of it. Returns `false` at end of file.
+fn next_line(reader: &mut impl BufRead, buf: &mut Vec<u8>, max_len: usize) -> io::Result<bool> {
+    buf.clear();
+    let mut read_any = false;
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            break;
+        }
+        read_any = true;
+        let newline = available.iter().position(|b| *b == b'\n');
+        let part = &available[..newline.unwrap_or(available.len())];
+        let room = max_len.saturating_sub(buf.len());
+        buf.extend_from_slice(&part[..part.len().min(room)]);
+        let used = newline.map_or(available.len(), |idx| idx + 1);
+        reader.consume(used);
+        if newline.is_some() {
+            break;
+        }
+    }
+    if buf.last() == Some(&b'\r') {
+        buf.pop();
+    }
+    Ok(read_any)
+}
+
+fn truncate_line(line: &str) -> String {
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((idx, _)) => format!("{}…", &line[..idx]),
+        None => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn workspace() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.txt"), "one\ntwo\nthree\n").unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "fn main() {}\n// TODO\n").unwrap();
+        fs::write(dir.path().join("src/notes.md"), "TODO: docs\n").unwrap();
+        fs::create_dir(dir.path().join("target")).unwrap();
+        fs::write(dir.path().join("target/out.rs"), "// TODO\n").unwrap();
+        fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+        dir
+    }
+
+    fn read_only() -> SandboxPolicy {
+        SandboxPolicy::from(vec![])
+    }
+
+    #[test]
+    fn read_file_honors_line_range() {
+        let dir = workspace();
+        let output = read_file(
+            dir.path(),
+            &read_only(),
+            &ReadFileParams {
+                path: "a.txt".to_string(),
+                start_line: Some(2),
+                end_line: Some(3),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            output,
+            FileToolOutput {
+                content: "     2\ttwo\n     3\tthree\n".to_string(),
+                summary: "lines 2-3 of 3".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn next_line_caps_long_lines_and_strips_crlf() {
+        let long = "x".repeat(100);
+        let input = format!("{long}\r\nshort\r\nlast");
+        let mut reader = BufReader::with_capacity(16, input.as_bytes());
+        let mut line = Vec::new();
+
+        assert!(next_line(&mut reader, &mut line, 10).unwrap());
+        assert_eq!(line, b"xxxxxxxxxx");
+        assert!(next_line(&mut reader, &mut line, 10).unwrap());
+        assert_eq!(line, b"short");
+        assert!(next_line(&mut reader, &mut line, 10).unwrap());
+        assert_eq!(line, b"last");
+        assert!(!next_line(&mut reader, &mut line, 10).unwrap());
+    }
+
+    #[test]
+    fn list_dir_skips_gitignored_entries() {
+        let dir = workspace();
+        let output = list_dir(
+            dir.path(),
+            &read_only(),
+            &ListDirParams {
+                path: None,
+                depth: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            output.content,
+            ".gitignore\na.txt\nsrc/\nsrc/lib.rs\nsrc/notes.md"
+        );
+        assert_eq!(output.summary, "5 entries");
+    }
+
+    #[test]
+    fn grep_applies_globs() {
+        let dir = workspace();
+        let output = grep(
+            dir.path(),
+            &read_only(),
+            &GrepParams {
+                pattern: "todo".to_string(),
+                path: None,
+                globs: vec!["*.rs".to_string()],
+                case_insensitive: true,
+            },
+        )
+        .unwrap();
+        assert_eq!(output.content, "src/lib.rs:2:// TODO");
+        assert_eq!(output.summary, "1 matches in 1 files");
+    }
+
+    #[test]
+    fn paths_outside_readable_roots_are_rejected() {
+        let dir = workspace();
+        let outside = TempDir::new().unwrap();
+        fs::write(outside.path().join("secret.txt"), "secret\n").unwrap();
+
+        let params = ReadFileParams {
+            path: outside.path().join("secret.txt").display().to_string(),
+            start_line: None,
+            end_line: None,
+        };
+        assert!(read_file(dir.path(), &read_only(), &params).is_err());
+        assert!(read_file(dir.path(), &SandboxPolicy::new_read_only_policy(), &params).is_ok());
+    }
+}
@@ -14,6 +14,12 @@ env_flags! {
     pub OPENAI_REQUEST_MAX_RETRIES: u64 = 4;
     pub OPENAI_STREAM_MAX_RETRIES: u64 = 10;
 
+    /// Upper bound on how long a request waits before being retried, however
+    /// long the server asks us to wait.
+    pub OPENAI_MAX_RETRY_DELAY_MS: Duration = Duration::from_millis(60_000), |value| {
+        value.parse().map(Duration::from_millis)
+    };
+
     // We generally don't want to disconnect; this updates the timeout to be five minutes
     // which matches the upstream typescript codex impl.
     pub OPENAI_STREAM_IDLE_TIMEOUT_MS: Duration = Duration::from_millis(300_000), |value| {
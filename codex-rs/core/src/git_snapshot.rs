@@ -0,0 +1,431 @@
+//! Snapshots of the working tree that make it possible to undo an agent turn.
+//!
+//! A snapshot is an ordinary git commit whose tree mirrors the working tree
+//! (tracked and untracked files, minus anything that is ignored). It is built
+//! with a throwaway index file so neither the user's index nor any of their
+//! branches are touched, and it is kept reachable through a hidden ref under
+//! `refs/codex/snapshots/` so `git gc` does not collect it. Refs that have
+//! not been updated for [`STALE_SNAPSHOT_AGE`] are deleted the next time a
+//! session takes its first snapshot in the repository.
+
+use std::ffi::OsStr;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::bail;
+use tokio::process::Command;
+use uuid::Uuid;
+
+const SNAPSHOT_REF_PREFIX: &str = "refs/codex/snapshots";
+
+/// Snapshot refs of other sessions that have not moved for this long are
+/// assumed to be abandoned.
+const STALE_SNAPSHOT_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// A point-in-time copy of the working tree stored in the repository's object
+/// database.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct GitSnapshot {
+    repo_root: PathBuf,
+    commit: String,
+}
+
+impl GitSnapshot {
+    /// Records the current state of the working tree containing `cwd`.
+    /// Returns `Ok(None)` if `cwd` is not inside a git repository.
+    ///
+    /// `ref_name` identifies the hidden ref that keeps the snapshot alive;
+    /// consecutive snapshots for the same ref are chained via `previous`.
+    pub(crate) async fn capture(
+        cwd: &Path,
+        ref_name: &str,
+        previous: Option<&GitSnapshot>,
+    ) -> Result<Option<Self>> {
+        let Some(repo_root) = repo_root(cwd).await else {
+            return Ok(None);
+        };
+
+        if previous.is_none() {
+            if let Err(e) = prune_stale_refs(&repo_root, STALE_SNAPSHOT_AGE, ref_name).await {
+                tracing::warn!("failed to prune stale snapshot refs: {e:#}");
+            }
+        }
+
+        let index = TempIndex::new(&repo_root).await?;
+        let tree = index.write_working_tree().await?;
+
+        let mut args = vec!["commit-tree", tree.as_str(), "-m", "codex snapshot"];
+        if let Some(previous) = previous {
+            args.extend(["-p", previous.commit.as_str()]);
+        }
+        let commit = git(&repo_root, None, args).await?;
+
+        let full_ref = format!("{SNAPSHOT_REF_PREFIX}/{ref_name}");
+        git(&repo_root, None, ["update-ref", &full_ref, &commit]).await?;
+
+        Ok(Some(Self { repo_root, commit }))
+    }
+
+    /// Returns a unified diff from this snapshot to the current working tree.
+    /// Paths are relative to the repository root and use `a/`/`b/` prefixes.
+    pub(crate) async fn diff_to_working_tree(&self) -> Result<String> {
+        let index = TempIndex::new(&self.repo_root).await?;
+        let current_tree = index.write_working_tree().await?;
+        let snapshot_tree = format!("{}^{{tree}}", self.commit);
+        let mut diff = git(
+            &self.repo_root,
+            None,
+            [
+                "diff",
+                "--no-color",
+                "--no-ext-diff",
+                "--src-prefix=a/",
+                "--dst-prefix=b/",
+                snapshot_tree.as_str(),
+                current_tree.as_str(),
+            ],
+        )
+        .await?;
+        if !diff.is_empty() {
+            diff.push('\n');
+        }
+        Ok(diff)
+    }
+
+    /// Restores the working tree to the state captured by this snapshot and
+    /// returns the paths (relative to the repository root) that changed.
+    ///
+    /// Files that were created after the snapshot are deleted; files that were
+    /// modified or deleted are written back. Ignored files are left alone.
+    pub(crate) async fn restore(&self) -> Result<Vec<PathBuf>> {
+        let index = TempIndex::new(&self.repo_root).await?;
+        let current_tree = index.write_working_tree().await?;
+        let snapshot_tree = format!("{}^{{tree}}", self.commit);
+
+        let changed = git(
+            &self.repo_root,
+            None,
+            [
+                "diff-tree",
+                "-r",
+                "-z",
+                "--name-only",
+                "--no-renames",
+                snapshot_tree.as_str(),
+                current_tree.as_str(),
+            ],
+        )
+        .await?;
+        let added = git(
+            &self.repo_root,
+            None,
+            [
+                "diff-tree",
+                "-r",
+                "-z",
+                "--name-only",
+                "--no-renames",
+                "--diff-filter=A",
+                snapshot_tree.as_str(),
+                current_tree.as_str(),
+            ],
+        )
+        .await?;
+
+        for path in split_nul(&added) {
+            let path = self.repo_root.join(path);
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => remove_empty_parents(&self.repo_root, &path).await,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("failed to remove {}", path.display()));
+                }
+            }
+        }
+
+        // Only write back files that differ from the snapshot so untouched
+        // files keep their mtimes and do not wake up build tools or watchers.
+        let added: Vec<&str> = split_nul(&added).collect();
+        let to_checkout: Vec<&str> = split_nul(&changed)
+            .filter(|path| !added.contains(path))
+            .collect();
+        if !to_checkout.is_empty() {
+            index.git(["read-tree", snapshot_tree.as_str()]).await?;
+            let mut args = vec!["checkout-index", "-f", "--"];
+            args.extend(to_checkout);
+            index.git(args).await?;
+        }
+
+        Ok(split_nul(&changed).map(PathBuf::from).collect())
+    }
+}
+
+/// Deletes snapshot refs, other than `keep`, whose latest snapshot is older
+/// than `max_age`.
+async fn prune_stale_refs(repo_root: &Path, max_age: Duration, keep: &str) -> Result<()> {
+    let refs = git(
+        repo_root,
+        None,
+        [
+            "for-each-ref",
+            "--format=%(refname) %(committerdate:unix)",
+            SNAPSHOT_REF_PREFIX,
+        ],
+    )
+    .await?;
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let keep = format!("{SNAPSHOT_REF_PREFIX}/{keep}");
+    for line in refs.lines() {
+        let Some((refname, timestamp)) = line.rsplit_once(' ') else {
+            continue;
+        };
+        let Ok(timestamp) = timestamp.parse::<u64>() else {
+            continue;
+        };
+        if refname != keep && timestamp.saturating_add(max_age.as_secs()) <= now {
+            git(repo_root, None, ["update-ref", "-d", refname]).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the top-level directory of the repository containing `cwd`.
+async fn repo_root(cwd: &Path) -> Option<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .current_dir(cwd)
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let root = String::from_utf8(output.stdout).ok()?;
+    Some(PathBuf::from(root.trim_end_matches('\n')))
+}
+
+/// An index file that lives outside the repository for the duration of a
+/// single snapshot operation. It starts as a copy of the real index so that
+/// `git add` can reuse cached stat information instead of rehashing every file.
+struct TempIndex {
+    repo_root: PathBuf,
+    path: PathBuf,
+}
+
+impl TempIndex {
+    async fn new(repo_root: &Path) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("codex-snapshot-index-{}", Uuid::new_v4()));
+        let real_index = git(repo_root, None, ["rev-parse", "--git-path", "index"]).await?;
+        let real_index = repo_root.join(real_index);
+        if real_index.exists() {
+            tokio::fs::copy(&real_index, &path)
+                .await
+                .with_context(|| format!("failed to copy {}", real_index.display()))?;
+        }
+        Ok(Self {
+            repo_root: repo_root.to_path_buf(),
+            path,
+        })
+    }
+
+    /// Stages the entire working tree into this index and returns the id of
+    /// the resulting tree object.
+    async fn write_working_tree(&self) -> Result<String> {
+        self.git(["add", "--all", "--", "."]).await?;
+        self.git(["write-tree"]).await
+    }
+
+    async fn git<I, S>(&self, args: I) -> Result<String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        git(&self.repo_root, Some(&self.path), args).await
+    }
+}
+
+impl Drop for TempIndex {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+async fn git<I, S>(repo_root: &Path, index_file: Option<&Path>, args: I) -> Result<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut command = Command::new("git");
+    command
+        .args(args)
+        .current_dir(repo_root)
+        // Snapshots must work even if the user has not configured an identity.
+        .env("GIT_AUTHOR_NAME", "Codex")
+        .env("GIT_AUTHOR_EMAIL", "codex@localhost")
+        .env("GIT_COMMITTER_NAME", "Codex")
+        .env("GIT_COMMITTER_EMAIL", "codex@localhost");
+    if let Some(index_file) = index_file {
+        command.env("GIT_INDEX_FILE", index_file);
+    }
+
+    let output = command.output().await.context("failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches('\n')
+        .to_string())
+}
+
+fn split_nul(output: &str) -> impl Iterator<Item = &str> {
+    output.split('\0').filter(|s| !s.is_empty())
+}
+
+/// Removes directories left empty by deleting `path`, stopping at `root`.
+async fn remove_empty_parents(root: &Path, path: &Path) {
+    let mut dir = path.parent();
+    while let Some(d) = dir {
+        if d == root || tokio::fs::remove_dir(d).await.is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use tempfile::TempDir;
+
+    async fn init_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        git(dir.path(), None, ["init", "-q"]).await.unwrap();
+        fs::write(dir.path().join("tracked.txt"), "original\n").unwrap();
+        fs::write(dir.path().join(".gitignore"), "ignored.txt\n").unwrap();
+        git(dir.path(), None, ["add", "."]).await.unwrap();
+        git(dir.path(), None, ["commit", "-q", "-m", "init"])
+            .await
+            .unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn restore_reverts_changes_without_touching_index_or_ignored_files() {
+        let dir = init_repo().await;
+        let root = dir.path();
+        fs::write(root.join("untracked.txt"), "keep me\n").unwrap();
+        fs::write(root.join("untouched.txt"), "same\n").unwrap();
+        let untouched_mtime = fs::metadata(root.join("untouched.txt"))
+            .unwrap()
+            .modified()
+            .unwrap();
+
+        let snapshot = GitSnapshot::capture(root, "test", None)
+            .await
+            .unwrap()
+            .unwrap();
+
+        fs::write(root.join("tracked.txt"), "modified\n").unwrap();
+        fs::remove_file(root.join("untracked.txt")).unwrap();
+        fs::create_dir(root.join("new")).unwrap();
+        fs::write(root.join("new/file.txt"), "new\n").unwrap();
+        fs::write(root.join("ignored.txt"), "ignored\n").unwrap();
+
+        let diff = snapshot.diff_to_working_tree().await.unwrap();
+        assert!(diff.contains("diff --git a/tracked.txt b/tracked.txt\n"));
+        assert!(diff.contains("-original\n+modified\n"));
+        assert!(diff.contains("+++ b/new/file.txt\n"));
+        assert!(!diff.contains("ignored.txt"));
+
+        let mut changed = snapshot.restore().await.unwrap();
+        changed.sort();
+        assert_eq!(
+            changed,
+            vec![
+                PathBuf::from("new/file.txt"),
+                PathBuf::from("tracked.txt"),
+                PathBuf::from("untracked.txt"),
+            ]
+        );
+
+        assert_eq!(
+            fs::read_to_string(root.join("tracked.txt")).unwrap(),
+            "original\n"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("untracked.txt")).unwrap(),
+            "keep me\n"
+        );
+        assert!(!root.join("new").exists());
+        assert!(root.join("ignored.txt").exists());
+        // Files that did not change are not rewritten.
+        assert_eq!(
+            fs::metadata(root.join("untouched.txt"))
+                .unwrap()
+                .modified()
+                .unwrap(),
+            untouched_mtime
+        );
+
+        // The user's index and branch are unchanged.
+        let status = git(root, None, ["status", "--porcelain"]).await.unwrap();
+        assert_eq!(status, "?? untouched.txt\n?? untracked.txt");
+        let log = git(root, None, ["log", "--format=%s"]).await.unwrap();
+        assert_eq!(log, "init");
+    }
+
+    #[tokio::test]
+    async fn prunes_stale_refs_of_other_sessions() {
+        let dir = init_repo().await;
+        let root = dir.path();
+        GitSnapshot::capture(root, "old", None).await.unwrap();
+        GitSnapshot::capture(root, "current", None).await.unwrap();
+
+        // Fresh refs survive the regular age threshold.
+        prune_stale_refs(root, STALE_SNAPSHOT_AGE, "current")
+            .await
+            .unwrap();
+        let refs = git(root, None, ["for-each-ref", "--format=%(refname)"])
+            .await
+            .unwrap();
+        assert!(refs.contains("refs/codex/snapshots/old"));
+
+        prune_stale_refs(root, Duration::ZERO, "current")
+            .await
+            .unwrap();
+        let refs = git(
+            root,
+            None,
+            ["for-each-ref", "--format=%(refname)", SNAPSHOT_REF_PREFIX],
+        )
+        .await
+        .unwrap();
+        assert_eq!(refs, "refs/codex/snapshots/current");
+    }
+
+    #[tokio::test]
+    async fn capture_outside_repo_is_noop() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(
+            GitSnapshot::capture(dir.path(), "test", None)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+}
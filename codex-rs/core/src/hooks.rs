@@ -0,0 +1,371 @@
+//! Lifecycle hooks: user scripts that run before and after the agent acts.
+//!
+//! Each hook receives a [`HookEvent`] serialized as JSON on stdin, e.g.
+//!
+//! ````text
+//! {"event":"pre-exec","call-id":"call_1","command":["git","push"],"cwd":"/repo"}
+//! ````
+//!
+//! See [`crate::config_types::Hooks`] for how the exit status and output of a
+//! hook are interpreted.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use uuid::Uuid;
+
+use crate::config_types::HookConfig;
+use crate::protocol::FileChange;
+
+const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub(crate) enum HookEvent {
+    #[serde(rename_all = "kebab-case")]
+    SessionStart {
+        session_id: Uuid,
+        cwd: PathBuf,
+        model: String,
+    },
+
+    #[serde(rename_all = "kebab-case")]
+    PreExec {
+        call_id: String,
+        command: Vec<String>,
+        cwd: PathBuf,
+    },
+
+    #[serde(rename_all = "kebab-case")]
+    PostExec {
+        call_id: String,
+        command: Vec<String>,
+        cwd: PathBuf,
+        exit_code: i32,
+        stdout: String,
+        stderr: String,
+    },
+
+    #[serde(rename_all = "kebab-case")]
+    PrePatch {
+        call_id: String,
+        #[serde(serialize_with = "serialize_changes")]
+        changes: HashMap<PathBuf, FileChange>,
+    },
+
+    #[serde(rename_all = "kebab-case")]
+    PostPatch {
+        call_id: String,
+        #[serde(serialize_with = "serialize_changes")]
+        changes: HashMap<PathBuf, FileChange>,
+        success: bool,
+    },
+
+    #[serde(rename_all = "kebab-case")]
+    TurnComplete {
+        turn_id: String,
+        input_messages: Vec<String>,
+        last_assistant_message: Option<String>,
+    },
+}
+
+/// A [`FileChange`] in the kebab-case used by the rest of the payload.
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum HookFileChange<'a> {
+    Add {
+        content: &'a str,
+    },
+    Delete,
+    #[serde(rename_all = "kebab-case")]
+    Update {
+        unified_diff: &'a str,
+        move_path: Option<&'a Path>,
+    },
+}
+
+fn serialize_changes<S: serde::Serializer>(
+    changes: &HashMap<PathBuf, FileChange>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(changes.iter().map(|(path, change)| {
+        let change = match change {
+            FileChange::Add { content } => HookFileChange::Add { content },
+            FileChange::Delete => HookFileChange::Delete,
+            FileChange::Update {
+                unified_diff,
+                move_path,
+            } => HookFileChange::Update {
+                unified_diff,
+                move_path: move_path.as_deref(),
+            },
+        };
+        (path, change)
+    }))
+}
+
+/// What a single hook run produced.
+struct HookOutput {
+    success: bool,
+    /// Combined stdout and stderr, trimmed.
+    text: String,
+}
+
+/// Runs `hooks` in order until one of them fails. Returns the veto reason of
+/// the failing hook, if any. A hook that cannot be run also vetoes, so that a
+/// broken policy script does not silently allow everything.
+pub(crate) async fn run_pre_hooks(
+    hooks: &[HookConfig],
+    event: &HookEvent,
+    cwd: &Path,
+) -> Result<(), String> {
+    for hook in hooks {
+        let reason = match run_hook(hook, event, cwd).await {
+            Ok(HookOutput { success: true, .. }) => continue,
+            Ok(HookOutput { text, .. }) if text.is_empty() => "no reason given".to_string(),
+            Ok(HookOutput { text, .. }) => text,
+            Err(e) => e,
+        };
+        return Err(format!("blocked by hook `{}`: {reason}", hook_name(hook)));
+    }
+    Ok(())
+}
+
+/// Runs every hook in `hooks` and returns their output, labelled per hook, for
+/// the model. Returns `None` if none of them printed anything.
+pub(crate) async fn run_post_hooks(
+    hooks: &[HookConfig],
+    event: &HookEvent,
+    cwd: &Path,
+) -> Option<String> {
+    let mut sections = Vec::new();
+    for hook in hooks {
+        let name = hook_name(hook);
+        match run_hook(hook, event, cwd).await {
+            Ok(HookOutput { success, text }) => {
+                if text.is_empty() && success {
+                    continue;
+                }
+                let status = if success { "" } else { " (failed)" };
+                sections.push(format!("[hook `{name}`{status}]\n{text}"));
+            }
+            Err(e) => sections.push(format!("[hook `{name}` could not run]\n{e}")),
+        }
+    }
+    if sections.is_empty() {
+        None
+    } else {
+        Some(sections.join("\n"))
+    }
+}
+
+/// Runs `hooks` in the background, logging failures.
+pub(crate) fn spawn_hooks(hooks: &[HookConfig], event: HookEvent, cwd: PathBuf) {
+    if hooks.is_empty() {
+        return;
+    }
+    let hooks = hooks.to_vec();
+    tokio::spawn(async move {
+        for hook in &hooks {
+            match run_hook(hook, &event, &cwd).await {
+                Ok(HookOutput { success: true, .. }) => {}
+                Ok(HookOutput { text, .. }) => {
+                    tracing::warn!("hook `{}` failed: {text}", hook_name(hook));
+                }
+                Err(e) => tracing::warn!("{e}"),
+            }
+        }
+    });
+}
+
+async fn run_hook(hook: &HookConfig, event: &HookEvent, cwd: &Path) -> Result<HookOutput, String> {
+    let name = hook_name(hook);
+    let Some((program, args)) = hook.command.split_first() else {
+        return Err("hook has an empty command".to_string());
+    };
+    let payload =
+        serde_json::to_vec(event).map_err(|e| format!("failed to serialise hook payload: {e}"))?;
+
+    let mut child = Command::new(program)
+        .args(args)
+        .current_dir(cwd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("failed to start hook `{name}`: {e}"))?;
+
+    // Feed stdin while collecting output, both under the timeout: a hook that
+    // never reads its input must not block us once the pipe buffer is full.
+    let stdin = child.stdin.take();
+    let write_payload = async move {
+        if let Some(mut stdin) = stdin {
+            // A hook is free to ignore its input, so a broken pipe is fine.
+            let _ = stdin.write_all(&payload).await;
+        }
+        // `stdin` is dropped here, closing the pipe so the hook sees EOF.
+    };
+    let run = async move {
+        let ((), output) = tokio::join!(write_payload, child.wait_with_output());
+        output
+    };
+
+    let timeout = hook
+        .timeout_secs
+        .map_or(DEFAULT_HOOK_TIMEOUT, Duration::from_secs);
+    // On timeout `run` is dropped along with the child, which kills it
+    // (`kill_on_drop`).
+    let output = match tokio::time::timeout(timeout, run).await {
+        Ok(output) => output.map_err(|e| format!("hook `{name}` failed: {e}"))?,
+        Err(_) => return Err(format!("hook `{name}` timed out after {timeout:?}")),
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let text = [stdout.trim(), stderr.trim()]
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(HookOutput {
+        success: output.status.success(),
+        text,
+    })
+}
+
+fn hook_name(hook: &HookConfig) -> String {
+    hook.command.join(" ")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn hook(script: &str) -> HookConfig {
+        HookConfig {
+            command: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+            timeout_secs: None,
+        }
+    }
+
+    fn pre_exec(command: &[&str]) -> HookEvent {
+        HookEvent::PreExec {
+            call_id: "call_1".to_string(),
+            command: command.iter().map(|s| s.to_string()).collect(),
+            cwd: PathBuf::from("/tmp"),
+        }
+    }
+
+    #[test]
+    fn payload_is_kebab_case() {
+        assert_eq!(
+            serde_json::to_string(&pre_exec(&["git", "push"])).unwrap(),
+            r#"{"event":"pre-exec","call-id":"call_1","command":["git","push"],"cwd":"/tmp"}"#
+        );
+    }
+
+    #[test]
+    fn patch_changes_are_kebab_case_too() {
+        let event = HookEvent::PostPatch {
+            call_id: "call_1".to_string(),
+            changes: HashMap::from([(
+                PathBuf::from("/tmp/a.rs"),
+                FileChange::Update {
+                    unified_diff: "@@ -1 +1 @@\n-a\n+b\n".to_string(),
+                    move_path: Some(PathBuf::from("/tmp/b.rs")),
+                },
+            )]),
+            success: true,
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "event": "post-patch",
+                "call-id": "call_1",
+                "changes": {
+                    "/tmp/a.rs": {
+                        "update": {
+                            "unified-diff": "@@ -1 +1 @@\n-a\n+b\n",
+                            "move-path": "/tmp/b.rs",
+                        },
+                    },
+                },
+                "success": true,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn pre_hook_vetoes_with_its_output() {
+        let no_push = hook(
+            r#"grep -q '"git","push"' && { echo "pushing is not allowed"; exit 1; } || exit 0"#,
+        );
+        let cwd = std::env::temp_dir();
+        assert_eq!(
+            run_pre_hooks(&[no_push.clone()], &pre_exec(&["git", "status"]), &cwd).await,
+            Ok(())
+        );
+        let err = run_pre_hooks(&[no_push], &pre_exec(&["git", "push"]), &cwd)
+            .await
+            .unwrap_err();
+        assert!(err.ends_with(": pushing is not allowed"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn post_hooks_collect_output() {
+        let cwd = std::env::temp_dir();
+        let hooks = [
+            hook("true"),
+            hook("echo formatted"),
+            hook("echo bad >&2; exit 3"),
+        ];
+        assert_eq!(
+            run_post_hooks(&hooks, &pre_exec(&["ls"]), &cwd).await,
+            Some(
+                "[hook `sh -c echo formatted`]\nformatted\n[hook `sh -c echo bad >&2; exit 3` (failed)]\nbad"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            run_post_hooks(&hooks[..1], &pre_exec(&["ls"]), &cwd).await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn hook_ignoring_a_large_payload_times_out() {
+        let sleeper = HookConfig {
+            timeout_secs: Some(1),
+            ..hook("sleep 60")
+        };
+        // Far larger than a pipe buffer, so writing it blocks until the hook
+        // reads, which it never does.
+        let event = HookEvent::PostExec {
+            call_id: "call_1".to_string(),
+            command: vec!["cat".to_string()],
+            cwd: PathBuf::from("/tmp"),
+            exit_code: 0,
+            stdout: "x".repeat(1 << 20),
+            stderr: String::new(),
+        };
+        let cwd = std::env::temp_dir();
+        let result = tokio::time::timeout(
+            Duration::from_secs(10),
+            run_pre_hooks(&[sleeper], &event, &cwd),
+        )
+        .await
+        .unwrap();
+        let err = result.unwrap_err();
+        assert!(err.contains("timed out after 1s"), "{err}");
+    }
+}
@@ -9,14 +9,18 @@ pub fn is_known_safe_command(command: &[String]) -> bool {
 
     // TODO(mbolin): Also support safe commands that are piped together such
     // as `cat foo | wc -l`.
-    matches!(
-        command,
-        [bash, flag, script]
-            if bash == "bash"
-            && flag == "-lc"
-            && try_parse_bash(script).and_then(|tree|
-                try_parse_single_word_only_command(&tree, script)).is_some_and(|parsed_bash_command| is_safe_to_call_with_exec(&parsed_bash_command))
-    )
+    parse_bash_lc_plain_command(command)
+        .is_some_and(|parsed_bash_command| is_safe_to_call_with_exec(&parsed_bash_command))
+}
+
+/// If `command` is `bash -lc <script>` and the script is a single command made
+/// of plain words, returns those words.
+pub(crate) fn parse_bash_lc_plain_command(command: &[String]) -> Option<Vec<String>> {
+    match command {
+        [bash, flag, script] if bash == "bash" && flag == "-lc" => try_parse_bash(script)
+            .and_then(|tree| try_parse_single_word_only_command(&tree, script)),
+        _ => None,
+    }
 }
 
 fn is_safe_to_call_with_exec(command: &[String]) -> bool {
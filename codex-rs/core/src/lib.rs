@@ -5,6 +5,7 @@
 // the TUI or the tracing stack).
 #![deny(clippy::print_stdout, clippy::print_stderr)]
 
+pub mod approval_rules;
 mod chat_completions;
 mod client;
 mod client_common;
@@ -18,11 +19,16 @@ mod conversation_history;
 pub mod error;
 pub mod exec;
 pub mod exec_env;
+mod file_tools;
 mod flags;
+mod git_snapshot;
+mod hooks;
 mod is_safe_command;
 mod mcp_connection_manager;
 mod mcp_tool_call;
 mod message_history;
+mod messages_api;
+pub mod model_family;
 mod model_provider_info;
 pub use model_provider_info::ModelProviderInfo;
 pub use model_provider_info::WireApi;
@@ -32,9 +38,10 @@ pub mod openai_api_key;
 mod openai_tools;
 mod project_doc;
 pub mod protocol;
-mod rollout;
+pub mod rollout;
+mod run_budget;
 mod safety;
+mod turn_diff;
 mod user_notification;
 pub mod util;
-
-pub use client_common::model_supports_reasoning_summaries;
+mod webhook_notifier;
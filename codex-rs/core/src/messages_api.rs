@@ -0,0 +1,592 @@
+//! Client for the Anthropic Messages API (`/v1/messages`).
+//!
+//! The Messages API differs from Chat Completions in a few ways that matter
+//! here: the system prompt is a top-level field, tool calls and their results
+//! are content blocks inside `assistant` and `user` messages respectively, and
+//! consecutive messages must alternate between those two roles.
+
+use std::path::Path;
+use std::time::Duration;
+
+use bytes::Bytes;
+use eventsource_stream::Eventsource;
+use futures::Stream;
+use futures::StreamExt;
+use futures::TryStreamExt;
+use serde_json::Value;
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+use tracing::debug;
+use tracing::trace;
+use tracing::warn;
+
+use crate::ModelProviderInfo;
+use crate::client::read_sse_fixture;
+use crate::client::send_with_retries;
+use crate::client_common::Prompt;
+use crate::client_common::ResponseEvent;
+use crate::client_common::ResponseStream;
+use crate::client_common::RetryNotifier;
+use crate::client_common::TokenUsage;
+use crate::error::CodexErr;
+use crate::error::Result;
+use crate::flags::CODEX_RS_SSE_FIXTURE;
+use crate::model_family::ModelFamily;
+use crate::models::ContentItem;
+use crate::models::LocalShellAction;
+use crate::models::ResponseItem;
+use crate::openai_tools::create_tools_json_for_messages_api;
+
+/// Version of the Messages API the request and event shapes below follow.
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Sent in place of a tool's output when it produced none.
+const EMPTY_TOOL_RESULT: &str = "(no output)";
+
+/// The Messages API requires an explicit cap on the length of the reply; this
+/// one is used when the model's family does not say.
+const DEFAULT_MAX_OUTPUT_TOKENS: u64 = 8192;
+
+/// Implementation for the Anthropic Messages API.
+pub(crate) async fn stream_messages(
+    prompt: &Prompt,
+    model: &str,
+    model_family: &ModelFamily,
+    client: &reqwest::Client,
+    provider: &ModelProviderInfo,
+    on_retry: &RetryNotifier,
+) -> Result<ResponseStream> {
+    if let Some(path) = &*CODEX_RS_SSE_FIXTURE {
+        // short circuit for tests
+        warn!(path, "Streaming from fixture");
+        let (tx_event, rx_event) = mpsc::channel::<Result<ResponseEvent>>(16);
+        tokio::spawn(process_messages_sse(
+            read_sse_fixture(Path::new(path))?,
+            tx_event,
+            provider.stream_idle_timeout(),
+        ));
+        return Ok(ResponseStream { rx_event });
+    }
+
+    let tools_json = create_tools_json_for_messages_api(prompt, model_family)?;
+    let mut system = prompt.get_full_instructions(model_family).into_owned();
+    if let Some(schema) = &prompt.output_schema {
+        // The Messages API has no structured output parameter, so the schema
+        // is only described to the model; the caller validates the reply.
+        system.push_str(&format!(
+            "\n\nYour final message must be a single JSON value, with no surrounding text or code fences, that conforms to this JSON Schema:\n{schema}"
+        ));
+    }
+    let payload = json!({
+        "model": model,
+        "system": system,
+        "messages": messages_from_input(&prompt.input),
+        "tools": tools_json,
+        "max_tokens": model_family.max_output_tokens.unwrap_or(DEFAULT_MAX_OUTPUT_TOKENS),
+        "stream": true,
+    });
+
+    debug!(
+        "POST to {}/messages: {}",
+        provider.base_url,
+        serde_json::to_string_pretty(&payload).unwrap_or_default()
+    );
+
+    let resp = send_with_retries(provider, on_retry, || {
+        Ok(provider
+            .create_request_builder(client, "messages")?
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .json(&payload))
+    })
+    .await?;
+
+    let (tx_event, rx_event) = mpsc::channel::<Result<ResponseEvent>>(16);
+    let stream = resp.bytes_stream().map_err(CodexErr::Reqwest);
+    tokio::spawn(process_messages_sse(
+        stream,
+        tx_event,
+        provider.stream_idle_timeout(),
+    ));
+    Ok(ResponseStream { rx_event })
+}
+
+/// Translates the conversation history into Messages API `messages`. Items
+/// are converted to content blocks and consecutive blocks with the same role
+/// are merged into one message, as the API requires the roles to alternate.
+fn messages_from_input(input: &[ResponseItem]) -> Vec<Value> {
+    let mut messages: Vec<(&'static str, Vec<Value>)> = Vec::new();
+
+    for item in input {
+        let (role, blocks) = match item {
+            ResponseItem::Message { role, content } => {
+                // The system prompt is sent separately, so any other role is
+                // input from the user's side of the conversation.
+                let role = if role == "assistant" {
+                    "assistant"
+                } else {
+                    "user"
+                };
+                let blocks = content.iter().filter_map(content_block).collect();
+                (role, blocks)
+            }
+            ResponseItem::FunctionCall {
+                name,
+                arguments,
+                call_id,
+            } => {
+                let input = serde_json::from_str::<Value>(arguments)
+                    .ok()
+                    .filter(Value::is_object)
+                    .unwrap_or_else(|| json!({}));
+                (
+                    "assistant",
+                    vec![json!({
+                        "type": "tool_use",
+                        "id": call_id,
+                        "name": name,
+                        "input": input,
+                    })],
+                )
+            }
+            ResponseItem::LocalShellCall {
+                id,
+                call_id,
+                action: LocalShellAction::Exec(action),
+                ..
+            } => {
+                // Only produced by the Responses API; replay it as a call to
+                // the equivalent `shell` function.
+                let Some(call_id) = call_id.as_ref().or(id.as_ref()) else {
+                    continue;
+                };
+                (
+                    "assistant",
+                    vec![json!({
+                        "type": "tool_use",
+                        "id": call_id,
+                        "name": "shell",
+                        "input": {
+                            "command": action.command,
+                            "workdir": action.working_directory,
+                            "timeout": action.timeout_ms,
+                        },
+                    })],
+                )
+            }
+            ResponseItem::FunctionCallOutput { call_id, output } => {
+                // The API rejects empty text here too, and a command that
+                // printed nothing is common.
+                let content = if output.content.is_empty() {
+                    EMPTY_TOOL_RESULT
+                } else {
+                    output.content.as_str()
+                };
+                (
+                    "user",
+                    vec![json!({
+                        "type": "tool_result",
+                        "tool_use_id": call_id,
+                        "content": content,
+                        "is_error": output.success == Some(false),
+                    })],
+                )
+            }
+            ResponseItem::Reasoning { .. } | ResponseItem::Other => {
+                // Omit these items from the conversation history.
+                continue;
+            }
+        };
+        if blocks.is_empty() {
+            continue;
+        }
+
+        match messages.last_mut() {
+            Some((last_role, last_blocks)) if *last_role == role => last_blocks.extend(blocks),
+            _ => messages.push((role, blocks)),
+        }
+    }
+
+    messages
+        .into_iter()
+        .map(|(role, content)| json!({"role": role, "content": content}))
+        .collect()
+}
+
+fn content_block(item: &ContentItem) -> Option<Value> {
+    match item {
+        // The API rejects empty text blocks.
+        ContentItem::InputText { text } | ContentItem::OutputText { text } if text.is_empty() => {
+            None
+        }
+        ContentItem::InputText { text } | ContentItem::OutputText { text } => {
+            Some(json!({"type": "text", "text": text}))
+        }
+        ContentItem::InputImage { image_url } => {
+            let source = match image_url
+                .strip_prefix("data:")
+                .and_then(|rest| rest.split_once(";base64,"))
+            {
+                Some((media_type, data)) => json!({
+                    "type": "base64",
+                    "media_type": media_type,
+                    "data": data,
+                }),
+                None => json!({"type": "url", "url": image_url}),
+            };
+            Some(json!({"type": "image", "source": source}))
+        }
+    }
+}
+
+/// A content block of the reply that is still being streamed.
+enum PartialBlock {
+    Text(String),
+    ToolUse {
+        id: String,
+        name: String,
+        input_json: String,
+    },
+    /// Blocks Codex has no use for, such as `thinking`.
+    Ignored,
+}
+
+/// Maps the Messages streaming events onto [`ResponseEvent`]s. Each content
+/// block is forwarded as a single [`ResponseItem`] once it is complete, so no
+/// aggregation is needed downstream.
+async fn process_messages_sse<S>(
+    stream: S,
+    tx_event: mpsc::Sender<Result<ResponseEvent>>,
+    idle_timeout: Duration,
+) where
+    S: Stream<Item = Result<Bytes>> + Unpin,
+{
+    let mut stream = stream.eventsource();
+    let mut message_id = String::new();
+    let mut token_usage = TokenUsage::default();
+    let mut blocks: Vec<PartialBlock> = Vec::new();
+
+    loop {
+        let sse = match timeout(idle_timeout, stream.next()).await {
+            Ok(Some(Ok(ev))) => ev,
+            Ok(Some(Err(e))) => {
+                let _ = tx_event.send(Err(CodexErr::Stream(e.to_string()))).await;
+                return;
+            }
+            Ok(None) => {
+                let _ = tx_event
+                    .send(Err(CodexErr::Stream(
+                        "stream closed before message_stop".into(),
+                    )))
+                    .await;
+                return;
+            }
+            Err(_) => {
+                let _ = tx_event
+                    .send(Err(CodexErr::Stream("idle timeout waiting for SSE".into())))
+                    .await;
+                return;
+            }
+        };
+
+        // The event name is repeated in the `type` field of the data, which
+        // is what we dispatch on.
+        let event: Value = match serde_json::from_str(&sse.data) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        trace!("messages received SSE event: {event:?}");
+
+        let index = event
+            .get("index")
+            .and_then(Value::as_u64)
+            .map(|i| i as usize);
+        match event
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+        {
+            "message_start" => {
+                if let Some(id) = event.pointer("/message/id").and_then(Value::as_str) {
+                    message_id = id.to_string();
+                }
+                if let Some(input_tokens) = event
+                    .pointer("/message/usage/input_tokens")
+                    .and_then(Value::as_u64)
+                {
+                    token_usage.input_tokens = input_tokens;
+                }
+            }
+            "content_block_start" => {
+                let Some(index) = index else { continue };
+                let block = event.get("content_block");
+                let str_field = |name: &str| {
+                    block
+                        .and_then(|b| b.get(name))
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                let partial = match block.and_then(|b| b.get("type")).and_then(Value::as_str) {
+                    Some("text") => PartialBlock::Text(str_field("text")),
+                    Some("tool_use") => PartialBlock::ToolUse {
+                        id: str_field("id"),
+                        name: str_field("name"),
+                        input_json: String::new(),
+                    },
+                    _ => PartialBlock::Ignored,
+                };
+                if blocks.len() <= index {
+                    blocks.resize_with(index + 1, || PartialBlock::Ignored);
+                }
+                blocks[index] = partial;
+            }
+            "content_block_delta" => {
+                let Some(block) = index.and_then(|i| blocks.get_mut(i)) else {
+                    continue;
+                };
+                let delta = event.get("delta");
+                let delta_field = |name: &str| {
+                    delta
+                        .and_then(|d| d.get(name))
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                };
+                match block {
+                    PartialBlock::Text(text) => text.push_str(delta_field("text")),
+                    PartialBlock::ToolUse { input_json, .. } => {
+                        input_json.push_str(delta_field("partial_json"))
+                    }
+                    PartialBlock::Ignored => {}
+                }
+            }
+            "content_block_stop" => {
+                let Some(block) = index.and_then(|i| blocks.get_mut(i)) else {
+                    continue;
+                };
+                let item = match std::mem::replace(block, PartialBlock::Ignored) {
+                    PartialBlock::Text(text) if !text.is_empty() => ResponseItem::Message {
+                        role: "assistant".to_string(),
+                        content: vec![ContentItem::OutputText { text }],
+                    },
+                    PartialBlock::ToolUse {
+                        id,
+                        name,
+                        input_json,
+                    } => ResponseItem::FunctionCall {
+                        name,
+                        // A tool called without arguments streams no JSON.
+                        arguments: if input_json.is_empty() {
+                            "{}".to_string()
+                        } else {
+                            input_json
+                        },
+                        call_id: id,
+                    },
+                    PartialBlock::Text(_) | PartialBlock::Ignored => continue,
+                };
+                if tx_event
+                    .send(Ok(ResponseEvent::OutputItemDone(item)))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            "message_stop" => {
+                let _ = tx_event
+                    .send(Ok(ResponseEvent::Completed {
+                        response_id: message_id,
+                        token_usage: Some(token_usage),
+                    }))
+                    .await;
+                return;
+            }
+            "error" => {
+                let message = event
+                    .pointer("/error/message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown error");
+                let _ = tx_event
+                    .send(Err(CodexErr::Stream(format!(
+                        "Messages API error: {message}"
+                    ))))
+                    .await;
+                return;
+            }
+            "message_delta" => {
+                // Carries the cumulative output token count.
+                if let Some(output_tokens) = event
+                    .pointer("/usage/output_tokens")
+                    .and_then(Value::as_u64)
+                {
+                    token_usage.output_tokens = output_tokens;
+                }
+            }
+            "ping" => {
+                // Handled separately to skip the logging message in the
+                // `other` case.
+            }
+            other => debug!(other, "sse event"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use crate::models::FunctionCallOutputPayload;
+    use pretty_assertions::assert_eq;
+
+    fn text_message(role: &str, text: &str) -> ResponseItem {
+        ResponseItem::Message {
+            role: role.to_string(),
+            content: vec![ContentItem::InputText {
+                text: text.to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn tool_calls_and_results_become_content_blocks() {
+        let input = vec![
+            text_message("user", "list files"),
+            text_message("assistant", "Sure."),
+            ResponseItem::FunctionCall {
+                name: "shell".to_string(),
+                arguments: r#"{"command":["ls"]}"#.to_string(),
+                call_id: "toolu_1".to_string(),
+            },
+            ResponseItem::FunctionCallOutput {
+                call_id: "toolu_1".to_string(),
+                output: FunctionCallOutputPayload {
+                    content: "a.txt".to_string(),
+                    success: Some(true),
+                },
+            },
+            ResponseItem::Message {
+                role: "user".to_string(),
+                content: vec![ContentItem::InputImage {
+                    image_url: "data:image/png;base64,AAAA".to_string(),
+                }],
+            },
+        ];
+
+        assert_eq!(
+            messages_from_input(&input),
+            vec![
+                json!({"role": "user", "content": [{"type": "text", "text": "list files"}]}),
+                json!({"role": "assistant", "content": [
+                    {"type": "text", "text": "Sure."},
+                    {"type": "tool_use", "id": "toolu_1", "name": "shell", "input": {"command": ["ls"]}},
+                ]}),
+                json!({"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "a.txt", "is_error": false},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"}},
+                ]}),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_tool_output_is_replaced() {
+        let input = vec![ResponseItem::FunctionCallOutput {
+            call_id: "toolu_1".to_string(),
+            output: FunctionCallOutputPayload {
+                content: String::new(),
+                success: Some(true),
+            },
+        }];
+
+        assert_eq!(
+            messages_from_input(&input),
+            vec![json!({"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "toolu_1", "content": "(no output)", "is_error": false},
+            ]})]
+        );
+    }
+
+    async fn collect_events(sse: &str) -> Vec<Result<ResponseEvent>> {
+        let stream = futures::stream::iter(vec![Ok(Bytes::from(sse.to_string()))]);
+        let (tx, mut rx) = mpsc::channel(16);
+        tokio::spawn(process_messages_sse(stream, tx, Duration::from_secs(5)));
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn parses_text_and_tool_use_blocks() {
+        let sse = [
+            r#"{"type":"message_start","message":{"id":"msg_1","role":"assistant","content":[],"usage":{"input_tokens":120,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Listing"}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" files."}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_1","name":"shell","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"command\":"}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"[\"ls\"]}"}}"#,
+            r#"{"type":"content_block_stop","index":1}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":35}}"#,
+            r#"{"type":"message_stop"}"#,
+        ]
+        .iter()
+        .map(|data| format!("data: {data}\n\n"))
+        .collect::<String>();
+
+        let events = collect_events(&sse).await;
+        assert_eq!(events.len(), 3);
+        match &events[0] {
+            Ok(ResponseEvent::OutputItemDone(ResponseItem::Message { role, content })) => {
+                assert_eq!(role, "assistant");
+                assert!(
+                    matches!(&content[..], [ContentItem::OutputText { text }] if text == "Listing files.")
+                );
+            }
+            other => panic!("unexpected event: {other:?}"),
+        }
+        match &events[1] {
+            Ok(ResponseEvent::OutputItemDone(ResponseItem::FunctionCall {
+                name,
+                arguments,
+                call_id,
+            })) => {
+                assert_eq!(name, "shell");
+                assert_eq!(arguments, r#"{"command":["ls"]}"#);
+                assert_eq!(call_id, "toolu_1");
+            }
+            other => panic!("unexpected event: {other:?}"),
+        }
+        match &events[2] {
+            Ok(ResponseEvent::Completed {
+                response_id,
+                token_usage,
+            }) => {
+                assert_eq!(response_id, "msg_1");
+                assert_eq!(
+                    *token_usage,
+                    Some(TokenUsage {
+                        input_tokens: 120,
+                        output_tokens: 35,
+                    })
+                );
+            }
+            other => panic!("unexpected event: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn error_event_fails_the_stream() {
+        let sse = "event: error\ndata: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n";
+        let events = collect_events(sse).await;
+        assert_eq!(events.len(), 1);
+        assert!(
+            matches!(&events[0], Err(CodexErr::Stream(msg)) if msg == "Messages API error: Overloaded")
+        );
+    }
+}
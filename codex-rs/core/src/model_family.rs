@@ -0,0 +1,240 @@
+//! What Codex knows about the models it talks to.
+//!
+//! Request parameters and tools that only some models accept are decided by
+//! looking the model up here rather than by ad-hoc checks on its name. Models
+//! are grouped into families by name prefix; the longest matching prefix
+//! wins. Users can add or adjust families via `model_families` in
+//! `config.toml`.
+
+use std::collections::HashMap;
+
+use crate::config_types::ModelFamilyOverride;
+use crate::config_types::ShellToolType;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelFamily {
+    /// The name prefix that identified this family, or `None` when nothing
+    /// is known about the model.
+    pub family: Option<String>,
+
+    /// Number of tokens the model can attend to, if known.
+    pub context_window: Option<u64>,
+
+    /// Maximum number of tokens the model may produce in one reply, if known.
+    pub max_output_tokens: Option<u64>,
+
+    /// Whether the model accepts the `reasoning` request parameter.
+    pub supports_reasoning: bool,
+
+    /// Which tool the model uses to run shell commands.
+    pub shell_tool: ShellToolType,
+
+    /// Whether images can be included in the model's input.
+    pub supports_image_input: bool,
+
+    /// Whether the model needs `apply_patch` explained in its instructions
+    /// when no dedicated `apply_patch` tool is offered.
+    pub needs_apply_patch_instructions: bool,
+}
+
+impl Default for ModelFamily {
+    /// Conservative defaults for a model we know nothing about: no optional
+    /// request parameters and only plain function tools. Images are still
+    /// sent, since silently dropping them is worse than a provider error.
+    fn default() -> Self {
+        Self {
+            family: None,
+            context_window: None,
+            max_output_tokens: None,
+            supports_reasoning: false,
+            shell_tool: ShellToolType::Function,
+            supports_image_input: true,
+            needs_apply_patch_instructions: false,
+        }
+    }
+}
+
+impl ModelFamily {
+    fn apply(&mut self, family_override: &ModelFamilyOverride) {
+        let ModelFamilyOverride {
+            context_window,
+            max_output_tokens,
+            supports_reasoning,
+            shell_tool,
+            supports_image_input,
+        } = family_override;
+        if context_window.is_some() {
+            self.context_window = *context_window;
+        }
+        if max_output_tokens.is_some() {
+            self.max_output_tokens = *max_output_tokens;
+        }
+        if let Some(supports_reasoning) = supports_reasoning {
+            self.supports_reasoning = *supports_reasoning;
+        }
+        if let Some(shell_tool) = shell_tool {
+            self.shell_tool = *shell_tool;
+        }
+        if let Some(supports_image_input) = supports_image_input {
+            self.supports_image_input = *supports_image_input;
+        }
+    }
+}
+
+/// Families Codex knows about out of the box.
+fn built_in_model_family(prefix: &str) -> Option<ModelFamily> {
+    let family = Some(prefix.to_string());
+    let reasoning = |context_window, max_output_tokens| ModelFamily {
+        family: family.clone(),
+        context_window: Some(context_window),
+        max_output_tokens: Some(max_output_tokens),
+        supports_reasoning: true,
+        ..Default::default()
+    };
+    let model_family = match prefix {
+        "o1" | "o3" | "o4-mini" => reasoning(200_000, 100_000),
+        "o3-mini" | "o1-mini" => ModelFamily {
+            supports_image_input: false,
+            ..reasoning(200_000, 100_000)
+        },
+        "codex-" => ModelFamily {
+            shell_tool: ShellToolType::LocalShell,
+            ..reasoning(200_000, 100_000)
+        },
+        "gpt-4.1" => ModelFamily {
+            family,
+            context_window: Some(1_047_576),
+            max_output_tokens: Some(32_768),
+            needs_apply_patch_instructions: true,
+            ..Default::default()
+        },
+        "gpt-4o" => ModelFamily {
+            family,
+            context_window: Some(128_000),
+            max_output_tokens: Some(16_384),
+            ..Default::default()
+        },
+        "claude-" => ModelFamily {
+            family,
+            context_window: Some(200_000),
+            max_output_tokens: Some(8_192),
+            ..Default::default()
+        },
+        "claude-opus-4" => ModelFamily {
+            family,
+            context_window: Some(200_000),
+            max_output_tokens: Some(32_000),
+            ..Default::default()
+        },
+        "claude-sonnet-4" => ModelFamily {
+            family,
+            context_window: Some(200_000),
+            max_output_tokens: Some(64_000),
+            ..Default::default()
+        },
+        _ => return None,
+    };
+    Some(model_family)
+}
+
+const BUILT_IN_PREFIXES: &[&str] = &[
+    "o1",
+    "o1-mini",
+    "o3",
+    "o3-mini",
+    "o4-mini",
+    "codex-",
+    "gpt-4.1",
+    "gpt-4o",
+    "claude-",
+    "claude-opus-4",
+    "claude-sonnet-4",
+];
+
+/// Returns the longest key in `prefixes` that `model` starts with.
+fn longest_prefix<'a>(model: &str, prefixes: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    prefixes
+        .filter(|prefix| model.starts_with(prefix))
+        .max_by_key(|prefix| prefix.len())
+}
+
+/// Describes `model`: the built-in family with the longest matching prefix
+/// (or [`ModelFamily::default`]), adjusted by the user's override with the
+/// longest matching prefix, if any.
+pub fn find_family_for_model(
+    model: &str,
+    overrides: &HashMap<String, ModelFamilyOverride>,
+) -> ModelFamily {
+    let mut model_family = longest_prefix(model, BUILT_IN_PREFIXES.iter().copied())
+        .and_then(built_in_model_family)
+        .unwrap_or_default();
+    if let Some(prefix) = longest_prefix(model, overrides.keys().map(String::as_str)) {
+        model_family.apply(&overrides[prefix]);
+        model_family.family = Some(prefix.to_string());
+    }
+    model_family
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn longest_built_in_prefix_wins() {
+        let o3_mini = find_family_for_model("o3-mini-2025-01-31", &HashMap::new());
+        assert_eq!(o3_mini.family.as_deref(), Some("o3-mini"));
+        assert!(o3_mini.supports_reasoning);
+        assert!(!o3_mini.supports_image_input);
+
+        let codex = find_family_for_model("codex-mini-latest", &HashMap::new());
+        assert_eq!(codex.shell_tool, ShellToolType::LocalShell);
+    }
+
+    #[test]
+    fn unknown_models_get_no_optional_params() {
+        let family = find_family_for_model("llama3.2", &HashMap::new());
+        assert_eq!(family, ModelFamily::default());
+        assert!(family.supports_image_input);
+        // Previously anything starting with "o" was sent reasoning params.
+        assert!(!find_family_for_model("olmo-2", &HashMap::new()).supports_reasoning);
+    }
+
+    #[test]
+    fn overrides_adjust_the_built_in_family() {
+        let overrides = HashMap::from([
+            (
+                "o3".to_string(),
+                ModelFamilyOverride {
+                    context_window: Some(1_000),
+                    ..Default::default()
+                },
+            ),
+            (
+                "qwen".to_string(),
+                ModelFamilyOverride {
+                    supports_reasoning: Some(true),
+                    supports_image_input: Some(false),
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        let o3 = find_family_for_model("o3", &overrides);
+        assert_eq!(o3.context_window, Some(1_000));
+        assert_eq!(o3.max_output_tokens, Some(100_000));
+        assert!(o3.supports_reasoning);
+
+        let qwen = find_family_for_model("qwen3:32b", &overrides);
+        assert_eq!(
+            qwen,
+            ModelFamily {
+                family: Some("qwen".to_string()),
+                supports_reasoning: true,
+                supports_image_input: false,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -9,16 +9,20 @@ use serde::Deserialize;
 use serde::Serialize;
 use std::collections::HashMap;
 use std::env::VarError;
+use std::time::Duration;
 
 use crate::error::EnvVarError;
+use crate::flags::OPENAI_REQUEST_MAX_RETRIES;
+use crate::flags::OPENAI_STREAM_IDLE_TIMEOUT_MS;
+use crate::flags::OPENAI_STREAM_MAX_RETRIES;
 use crate::openai_api_key::get_openai_api_key;
 
 /// Wire protocol that the provider speaks. Most third-party services only
 /// implement the classic OpenAI Chat Completions JSON schema, whereas OpenAI
 /// itself (and a handful of others) additionally expose the more modern
-/// *Responses* API. The two protocols use different request/response shapes
-/// and *cannot* be auto-detected at runtime, therefore each provider entry
-/// must declare which one it expects.
+/// *Responses* API, and Anthropic has its own *Messages* API. The protocols
+/// use different request/response shapes and *cannot* be auto-detected at
+/// runtime, therefore each provider entry must declare which one it expects.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum WireApi {
@@ -27,6 +31,8 @@ pub enum WireApi {
     Responses,
     /// Regular Chat Completions compatible with `/v1/chat/completions`.
     Chat,
+    /// The Anthropic Messages API at `/v1/messages`.
+    Messages,
 }
 
 /// Serializable representation of a provider definition.
@@ -45,6 +51,35 @@ pub struct ModelProviderInfo {
 
     /// Which wire protocol this provider expects.
     pub wire_api: WireApi,
+
+    /// Header that carries the API key as-is, e.g. `api-key` for Azure. When
+    /// unset, the key is sent as `Authorization: Bearer <key>` (or as
+    /// `x-api-key` for the Messages API).
+    pub api_key_header: Option<String>,
+
+    /// Query parameters appended to every request URL, e.g. Azure's
+    /// `api-version`.
+    pub query_params: Option<HashMap<String, String>>,
+
+    /// Additional HTTP headers sent with every request.
+    pub http_headers: Option<HashMap<String, String>>,
+
+    /// HTTP headers whose values are read from environment variables, as a map
+    /// from header name to variable name. Headers whose variable is unset or
+    /// empty are omitted.
+    pub env_http_headers: Option<HashMap<String, String>>,
+
+    /// How many times a failed request is retried. Defaults to
+    /// `OPENAI_REQUEST_MAX_RETRIES`.
+    pub request_max_retries: Option<u64>,
+
+    /// How many times a turn is retried after the response stream drops.
+    /// Defaults to `OPENAI_STREAM_MAX_RETRIES`.
+    pub stream_max_retries: Option<u64>,
+
+    /// How long the response stream may stay silent before it is considered
+    /// disconnected. Defaults to `OPENAI_STREAM_IDLE_TIMEOUT_MS`.
+    pub stream_idle_timeout_ms: Option<u64>,
 }
 
 impl ModelProviderInfo {
@@ -77,6 +112,70 @@ impl ModelProviderInfo {
             None => Ok(None),
         }
     }
+
+    pub(crate) fn request_max_retries(&self) -> u64 {
+        self.request_max_retries
+            .unwrap_or(*OPENAI_REQUEST_MAX_RETRIES)
+    }
+
+    pub(crate) fn stream_max_retries(&self) -> u64 {
+        self.stream_max_retries
+            .unwrap_or(*OPENAI_STREAM_MAX_RETRIES)
+    }
+
+    pub(crate) fn stream_idle_timeout(&self) -> Duration {
+        self.stream_idle_timeout_ms
+            .map_or(*OPENAI_STREAM_IDLE_TIMEOUT_MS, Duration::from_millis)
+    }
+
+    /// Builds a POST request to `path` (relative to `base_url`) carrying the
+    /// provider's query parameters, credentials and headers.
+    pub(crate) fn create_request_builder(
+        &self,
+        client: &reqwest::Client,
+        path: &str,
+    ) -> crate::error::Result<reqwest::RequestBuilder> {
+        let api_key = self.api_key()?;
+        Ok(self.request_builder_with_key(client, path, api_key))
+    }
+
+    /// [`Self::create_request_builder`] with the API key already resolved.
+    fn request_builder_with_key(
+        &self,
+        client: &reqwest::Client,
+        path: &str,
+        api_key: Option<String>,
+    ) -> reqwest::RequestBuilder {
+        let url = format!("{}/{path}", self.base_url.trim_end_matches('/'));
+        let mut builder = client.post(url);
+        if let Some(query_params) = &self.query_params {
+            builder = builder.query(query_params);
+        }
+
+        if let Some(api_key) = api_key {
+            builder = match (&self.api_key_header, self.wire_api) {
+                (Some(header), _) => builder.header(header, api_key),
+                (None, WireApi::Messages) => builder.header("x-api-key", api_key),
+                (None, WireApi::Responses | WireApi::Chat) => builder.bearer_auth(api_key),
+            };
+        }
+
+        if let Some(headers) = &self.http_headers {
+            for (name, value) in headers {
+                builder = builder.header(name, value);
+            }
+        }
+        if let Some(env_headers) = &self.env_http_headers {
+            for (name, env_var) in env_headers {
+                if let Ok(value) = std::env::var(env_var) {
+                    if !value.trim().is_empty() {
+                        builder = builder.header(name, value);
+                    }
+                }
+            }
+        }
+        builder
+    }
 }
 
 /// Built-in default provider list.
@@ -92,6 +191,30 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: Some("OPENAI_API_KEY".into()),
                 env_key_instructions: Some("Create an API key (https://platform.openai.com) and export it as an environment variable.".into()),
                 wire_api: WireApi::Responses,
+                api_key_header: None,
+                query_params: None,
+                http_headers: None,
+                env_http_headers: None,
+                request_max_retries: None,
+                stream_max_retries: None,
+                stream_idle_timeout_ms: None,
+            },
+        ),
+        (
+            "anthropic",
+            P {
+                name: "Anthropic".into(),
+                base_url: "https://api.anthropic.com/v1".into(),
+                env_key: Some("ANTHROPIC_API_KEY".into()),
+                env_key_instructions: Some("Create an API key (https://console.anthropic.com) and export it as an environment variable.".into()),
+                wire_api: WireApi::Messages,
+                api_key_header: None,
+                query_params: None,
+                http_headers: None,
+                env_http_headers: None,
+                request_max_retries: None,
+                stream_max_retries: None,
+                stream_idle_timeout_ms: None,
             },
         ),
         (
@@ -102,6 +225,13 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: Some("OPENROUTER_API_KEY".into()),
                 env_key_instructions: None,
                 wire_api: WireApi::Chat,
+                api_key_header: None,
+                query_params: None,
+                http_headers: None,
+                env_http_headers: None,
+                request_max_retries: None,
+                stream_max_retries: None,
+                stream_idle_timeout_ms: None,
             },
         ),
         (
@@ -112,6 +242,13 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: Some("GEMINI_API_KEY".into()),
                 env_key_instructions: None,
                 wire_api: WireApi::Chat,
+                api_key_header: None,
+                query_params: None,
+                http_headers: None,
+                env_http_headers: None,
+                request_max_retries: None,
+                stream_max_retries: None,
+                stream_idle_timeout_ms: None,
             },
         ),
         (
@@ -122,6 +259,13 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: None,
                 env_key_instructions: None,
                 wire_api: WireApi::Chat,
+                api_key_header: None,
+                query_params: None,
+                http_headers: None,
+                env_http_headers: None,
+                request_max_retries: None,
+                stream_max_retries: None,
+                stream_idle_timeout_ms: None,
             },
         ),
         (
@@ -133,6 +277,13 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: Some("LMSTUDIO_API_KEY".into()),
                 env_key_instructions: None,
                 wire_api: WireApi::Responses,
+                api_key_header: None,
+                query_params: None,
+                http_headers: None,
+                env_http_headers: None,
+                request_max_retries: None,
+                stream_max_retries: None,
+                stream_idle_timeout_ms: None,
             },
         ),
         (
@@ -143,6 +294,13 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: Some("MISTRAL_API_KEY".into()),
                 env_key_instructions: None,
                 wire_api: WireApi::Chat,
+                api_key_header: None,
+                query_params: None,
+                http_headers: None,
+                env_http_headers: None,
+                request_max_retries: None,
+                stream_max_retries: None,
+                stream_idle_timeout_ms: None,
             },
         ),
         (
@@ -153,6 +311,13 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: Some("DEEPSEEK_API_KEY".into()),
                 env_key_instructions: None,
                 wire_api: WireApi::Chat,
+                api_key_header: None,
+                query_params: None,
+                http_headers: None,
+                env_http_headers: None,
+                request_max_retries: None,
+                stream_max_retries: None,
+                stream_idle_timeout_ms: None,
             },
         ),
         (
@@ -163,6 +328,13 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: Some("XAI_API_KEY".into()),
                 env_key_instructions: None,
                 wire_api: WireApi::Chat,
+                api_key_header: None,
+                query_params: None,
+                http_headers: None,
+                env_http_headers: None,
+                request_max_retries: None,
+                stream_max_retries: None,
+                stream_idle_timeout_ms: None,
             },
         ),
         (
@@ -173,6 +345,13 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 env_key: Some("GROQ_API_KEY".into()),
                 env_key_instructions: None,
                 wire_api: WireApi::Chat,
+                api_key_header: None,
+                query_params: None,
+                http_headers: None,
+                env_http_headers: None,
+                request_max_retries: None,
+                stream_max_retries: None,
+                stream_idle_timeout_ms: None,
             },
         ),
     ]
@@ -180,3 +359,47 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
     .map(|(k, v)| (k.to_string(), v))
     .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn azure_style_provider_request() {
+        let provider: ModelProviderInfo = toml::from_str(
+            r#"
+name = "Azure"
+base_url = "https://example.openai.azure.com/openai/"
+env_key = "AZURE_OPENAI_API_KEY"
+wire_api = "responses"
+api_key_header = "api-key"
+query_params = { api-version = "2025-04-01-preview" }
+http_headers = { "X-Routing-Tag" = "eu" }
+env_http_headers = { "X-Org-Id" = "CODEX_TEST_UNSET_ORG_ID_VAR" }
+"#,
+        )
+        .unwrap();
+
+        // Pass the key in rather than reading it from the environment.
+        let request = provider
+            .request_builder_with_key(
+                &reqwest::Client::new(),
+                "responses",
+                Some("azure-test-key".to_string()),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.url().as_str(),
+            "https://example.openai.azure.com/openai/responses?api-version=2025-04-01-preview"
+        );
+        let headers = request.headers();
+        assert_eq!(headers.get("api-key").unwrap(), "azure-test-key");
+        assert_eq!(headers.get("x-routing-tag").unwrap(), "eu");
+        assert!(headers.get("x-org-id").is_none());
+        assert!(headers.get(reqwest::header::AUTHORIZATION).is_none());
+    }
+}
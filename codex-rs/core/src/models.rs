@@ -177,10 +177,18 @@ pub struct ShellToolCallParams {
     pub timeout_ms: Option<u64>,
 }
 
+/// If the `name` of a `ResponseItem::FunctionCall` is `apply_patch`, the
+/// `arguments` field should deserialize to this struct.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ApplyPatchToolCallParams {
+    /// The entire contents of the patch, from `*** Begin Patch` through
+    /// `*** End Patch`.
+    pub input: String,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct FunctionCallOutputPayload {
     pub content: String,
-    #[expect(dead_code)]
     pub success: Option<bool>,
 }
 
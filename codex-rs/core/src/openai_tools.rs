@@ -4,6 +4,11 @@ use std::collections::BTreeMap;
 use std::sync::LazyLock;
 
 use crate::client_common::Prompt;
+use crate::config_types::ShellToolType;
+use crate::file_tools::GREP_TOOL_NAME;
+use crate::file_tools::LIST_DIR_TOOL_NAME;
+use crate::file_tools::READ_FILE_TOOL_NAME;
+use crate::model_family::ModelFamily;
 
 #[derive(Debug, Clone, Serialize)]
 pub(crate) struct ResponsesApiTool {
@@ -28,6 +33,7 @@ pub(crate) enum OpenAiTool {
 #[derive(Debug, Clone, Serialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub(crate) enum JsonSchema {
+    Boolean,
     String,
     Number,
     Array {
@@ -65,6 +71,101 @@ static DEFAULT_TOOLS: LazyLock<Vec<OpenAiTool>> = LazyLock::new(|| {
     })]
 });
 
+/// Built-in read-only file tools that run in-process. These are offered in
+/// addition to the shell tool when `include_file_tools` is enabled.
+static FILE_TOOLS: LazyLock<Vec<OpenAiTool>> = LazyLock::new(|| {
+    let read_file_properties = BTreeMap::from([
+        ("path".to_string(), JsonSchema::String),
+        ("start_line".to_string(), JsonSchema::Number),
+        ("end_line".to_string(), JsonSchema::Number),
+    ]);
+    let list_dir_properties = BTreeMap::from([
+        ("path".to_string(), JsonSchema::String),
+        ("depth".to_string(), JsonSchema::Number),
+    ]);
+    let grep_properties = BTreeMap::from([
+        ("pattern".to_string(), JsonSchema::String),
+        ("path".to_string(), JsonSchema::String),
+        (
+            "globs".to_string(),
+            JsonSchema::Array {
+                items: Box::new(JsonSchema::String),
+            },
+        ),
+        ("case_insensitive".to_string(), JsonSchema::Boolean),
+    ]);
+
+    vec![
+        OpenAiTool::Function(ResponsesApiTool {
+            name: READ_FILE_TOOL_NAME,
+            description: "Reads a text file and returns its lines prefixed with line numbers. `path` is relative to the working directory. `start_line` and `end_line` are 1-based and inclusive; at most 2000 lines are returned per call.",
+            strict: false,
+            parameters: JsonSchema::Object {
+                properties: read_file_properties,
+                required: &["path"],
+                additional_properties: false,
+            },
+        }),
+        OpenAiTool::Function(ResponsesApiTool {
+            name: LIST_DIR_TOOL_NAME,
+            description: "Lists the contents of a directory (defaults to the working directory) up to `depth` levels deep (default 2). Entries matched by .gitignore are skipped and directories end with `/`.",
+            strict: false,
+            parameters: JsonSchema::Object {
+                properties: list_dir_properties,
+                required: &[],
+                additional_properties: false,
+            },
+        }),
+        OpenAiTool::Function(ResponsesApiTool {
+            name: GREP_TOOL_NAME,
+            description: "Searches files under `path` (defaults to the working directory) for lines matching the regular expression `pattern`, skipping binary and .gitignore'd files. `globs` (e.g. [\"*.rs\", \"!tests/**\"]) restricts which files are searched. Returns `path:line:text` for each match.",
+            strict: false,
+            parameters: JsonSchema::Object {
+                properties: grep_properties,
+                required: &["pattern"],
+                additional_properties: false,
+            },
+        }),
+    ]
+});
+
+/// Description of the `apply_patch` function tool. This carries the patch
+/// grammar so that models do not need [`APPLY_PATCH_TOOL_INSTRUCTIONS`] in
+/// their system prompt to use the tool.
+///
+/// [`APPLY_PATCH_TOOL_INSTRUCTIONS`]: codex_apply_patch::APPLY_PATCH_TOOL_INSTRUCTIONS
+const APPLY_PATCH_TOOL_DESCRIPTION: &str = r#"Use this tool to edit files. Pass the entire patch as `input`. The patch format is:
+
+*** Begin Patch
+[ one or more file operations ]
+*** End Patch
+
+Each file operation starts with one of these headers:
+
+*** Add File: <path> - create a new file. Every following line is a `+` line with the initial contents.
+*** Delete File: <path> - remove an existing file. Nothing follows.
+*** Update File: <path> - patch an existing file in place, optionally followed by `*** Move to: <new path>` to rename it.
+
+An update is made of one or more hunks, each introduced by `@@` (optionally followed by a line such as a class or function definition that narrows down where the hunk applies). Within a hunk each line starts with `+` for inserted text, `-` for removed text, or a space for context. Show 3 lines of context above and below each change. `*** End of File` may end a hunk that must match the end of the file.
+
+Paths must be relative, never absolute. If the patch cannot be applied, the error describes the problem and, when possible, the line of the patch that caused it."#;
+
+static APPLY_PATCH_TOOL: LazyLock<OpenAiTool> = LazyLock::new(|| {
+    let mut properties = BTreeMap::new();
+    properties.insert("input".to_string(), JsonSchema::String);
+
+    OpenAiTool::Function(ResponsesApiTool {
+        name: "apply_patch",
+        description: APPLY_PATCH_TOOL_DESCRIPTION,
+        strict: false,
+        parameters: JsonSchema::Object {
+            properties,
+            required: &["input"],
+            additional_properties: false,
+        },
+    })
+});
+
 static DEFAULT_CODEX_MODEL_TOOLS: LazyLock<Vec<OpenAiTool>> =
     LazyLock::new(|| vec![OpenAiTool::LocalShell {}]);
 
@@ -73,18 +174,26 @@ static DEFAULT_CODEX_MODEL_TOOLS: LazyLock<Vec<OpenAiTool>> =
 /// https://platform.openai.com/docs/guides/function-calling?api-mode=responses
 pub(crate) fn create_tools_json_for_responses_api(
     prompt: &Prompt,
-    model: &str,
+    model_family: &ModelFamily,
 ) -> crate::error::Result<Vec<serde_json::Value>> {
     // Assemble tool list: built-in tools + any extra tools from the prompt.
-    let default_tools = if model.starts_with("codex") {
-        &DEFAULT_CODEX_MODEL_TOOLS
+    let default_tools = match model_family.shell_tool {
+        ShellToolType::LocalShell => &DEFAULT_CODEX_MODEL_TOOLS,
+        ShellToolType::Function => &DEFAULT_TOOLS,
+    };
+    let file_tools: &[OpenAiTool] = if prompt.include_file_tools {
+        &FILE_TOOLS
     } else {
-        &DEFAULT_TOOLS
+        &[]
     };
-    let mut tools_json = Vec::with_capacity(default_tools.len() + prompt.extra_tools.len());
-    for t in default_tools.iter() {
+    let mut tools_json =
+        Vec::with_capacity(default_tools.len() + file_tools.len() + prompt.extra_tools.len() + 1);
+    for t in default_tools.iter().chain(file_tools) {
         tools_json.push(serde_json::to_value(t)?);
     }
+    if prompt.include_apply_patch_tool {
+        tools_json.push(serde_json::to_value(&*APPLY_PATCH_TOOL)?);
+    }
     tools_json.extend(
         prompt
             .extra_tools
@@ -101,11 +210,11 @@ pub(crate) fn create_tools_json_for_responses_api(
 /// https://platform.openai.com/docs/guides/function-calling?api-mode=chat
 pub(crate) fn create_tools_json_for_chat_completions_api(
     prompt: &Prompt,
-    model: &str,
+    model_family: &ModelFamily,
 ) -> crate::error::Result<Vec<serde_json::Value>> {
     // We start with the JSON for the Responses API and than rewrite it to match
     // the chat completions tool call format.
-    let responses_api_tools_json = create_tools_json_for_responses_api(prompt, model)?;
+    let responses_api_tools_json = create_tools_json_for_responses_api(prompt, model_family)?;
     let tools_json = responses_api_tools_json
         .into_iter()
         .filter_map(|mut tool| {
@@ -128,6 +237,28 @@ pub(crate) fn create_tools_json_for_chat_completions_api(
     Ok(tools_json)
 }
 
+/// Returns JSON values that are compatible with tool use in the Anthropic
+/// Messages API: https://docs.anthropic.com/en/docs/build-with-claude/tool-use
+pub(crate) fn create_tools_json_for_messages_api(
+    prompt: &Prompt,
+    model_family: &ModelFamily,
+) -> crate::error::Result<Vec<serde_json::Value>> {
+    // As for chat completions, only function tools can be expressed.
+    let responses_api_tools_json = create_tools_json_for_responses_api(prompt, model_family)?;
+    let tools_json = responses_api_tools_json
+        .into_iter()
+        .filter(|tool| tool.get("type") == Some(&serde_json::Value::String("function".to_string())))
+        .map(|tool| {
+            json!({
+                "name": tool.get("name"),
+                "description": tool.get("description"),
+                "input_schema": tool.get("parameters"),
+            })
+        })
+        .collect::<Vec<serde_json::Value>>();
+    Ok(tools_json)
+}
+
 fn mcp_tool_to_openai_tool(
     fully_qualified_name: String,
     tool: mcp_types::Tool,
@@ -155,3 +286,33 @@ fn mcp_tool_to_openai_tool(
         "type": "function",
     })
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn tool_names(prompt: &Prompt) -> Vec<String> {
+        create_tools_json_for_responses_api(prompt, &ModelFamily::default())
+            .unwrap()
+            .iter()
+            .filter_map(|tool| tool.get("name").and_then(|name| name.as_str()))
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn file_tools_are_opt_in() {
+        let names = tool_names(&Prompt::default());
+        assert!(!names.iter().any(|name| name == READ_FILE_TOOL_NAME));
+
+        let names = tool_names(&Prompt {
+            include_file_tools: true,
+            ..Default::default()
+        });
+        for name in [READ_FILE_TOOL_NAME, LIST_DIR_TOOL_NAME, GREP_TOOL_NAME] {
+            assert!(names.iter().any(|n| n == name), "{name} missing: {names:?}");
+        }
+    }
+}
@@ -14,6 +14,7 @@ use uuid::Uuid;
 
 use crate::config_types::ReasoningEffort as ReasoningEffortConfig;
 use crate::config_types::ReasoningSummary as ReasoningSummaryConfig;
+use crate::config_types::SandboxMode;
 use crate::message_history::HistoryEntry;
 use crate::model_provider_info::ModelProviderInfo;
 
@@ -94,6 +95,11 @@ pub enum Op {
         id: String,
         /// The user's decision in response to the request.
         decision: ReviewDecision,
+        /// Parts of the patch the user rejected. Only consulted when
+        /// `decision` approves the patch, in which case everything else is
+        /// applied and the model is told what was left out.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        rejected_changes: Vec<RejectedFileChange>,
     },
 
     /// Append an entry to the persistent cross-session message history.
@@ -107,11 +113,62 @@ pub enum Op {
 
     /// Request a single history entry identified by `log_id` + `offset`.
     GetHistoryEntryRequest { offset: usize, log_id: u64 },
+
+    /// Restore the working tree to the snapshot taken before the most recent
+    /// task started. Only available when the session's `cwd` is inside a git
+    /// repository. The outcome is reported as a `BackgroundEvent` (or an
+    /// `Error`), and the model is told about the revert with its next input.
+    Undo,
+
+    /// Request the diff of everything that changed since the session started.
+    /// Reply is delivered via `EventMsg::SessionDiff`.
+    GetSessionDiff,
+
+    /// Request the approval rules persisted for the session's project.
+    /// Reply is delivered via `EventMsg::ApprovalRules`.
+    ListApprovalRules,
+
+    /// Remove a persisted approval rule from the session's project. Takes
+    /// effect immediately for commands; a revoked writable root stays
+    /// writable until the session ends. Reply is delivered via
+    /// `EventMsg::ApprovalRules` with the remaining rules.
+    RevokeApprovalRule { rule: ApprovalRule },
+
+    /// Change the model and policies while keeping the conversation. Fields
+    /// left as `None` keep their current value. The model (and its provider
+    /// and reasoning settings) is used from the next turn; the approval and
+    /// sandbox policies apply immediately, including to the remaining tool
+    /// calls of a turn in progress. The outcome is reported as a
+    /// `BackgroundEvent`.
+    OverrideTurnContext {
+        /// Provider serving `model`. Switching providers makes the session
+        /// send the full conversation with every request from then on.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        provider: Option<ModelProviderInfo>,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        model: Option<String>,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        model_reasoning_effort: Option<ReasoningEffortConfig>,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        model_reasoning_summary: Option<ReasoningSummaryConfig>,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        approval_policy: Option<AskForApproval>,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sandbox_policy: Option<SandboxPolicy>,
+    },
 }
 
 /// Determines how liberally commands are auto‑approved by the system.
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, strum_macros::Display,
+)]
 #[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
 pub enum AskForApproval {
     /// Under this policy, only “known safe” commands—as determined by
     /// `is_safe_command()`—that **only read files** are auto‑approved.
@@ -167,15 +224,63 @@ impl SandboxPolicy {
     }
 
     pub fn new_full_auto_policy() -> Self {
+        Self::new_workspace_write_policy(&[], false)
+    }
+
+    /// The policy for [`SandboxMode::WorkspaceWrite`].
+    pub fn new_workspace_write_policy(writable_roots: &[PathBuf], network_access: bool) -> Self {
+        let mut permissions = vec![
+            SandboxPermission::DiskFullReadAccess,
+            SandboxPermission::DiskWritePlatformUserTempFolder,
+            SandboxPermission::DiskWriteCwd,
+        ];
+        permissions.extend(writable_roots.iter().map(|folder| {
+            SandboxPermission::DiskWriteFolder {
+                folder: folder.clone(),
+            }
+        }));
+        if network_access {
+            permissions.push(SandboxPermission::NetworkFullAccess);
+        }
+        Self { permissions }
+    }
+
+    /// The policy for [`SandboxMode::DangerFullAccess`].
+    pub fn new_danger_full_access_policy() -> Self {
         Self {
             permissions: vec![
                 SandboxPermission::DiskFullReadAccess,
-                SandboxPermission::DiskWritePlatformUserTempFolder,
-                SandboxPermission::DiskWriteCwd,
+                SandboxPermission::DiskFullWriteAccess,
+                SandboxPermission::NetworkFullAccess,
             ],
         }
     }
 
+    /// The sandbox mode this policy was built from, or `None` for a custom
+    /// list of permissions.
+    pub fn sandbox_mode(&self) -> Option<SandboxMode> {
+        use SandboxPermission::*;
+        if self.is_unrestricted() {
+            return Some(SandboxMode::DangerFullAccess);
+        }
+        if self.permissions == [DiskFullReadAccess] {
+            return Some(SandboxMode::ReadOnly);
+        }
+        let is_workspace_write = self.has_full_disk_read_access()
+            && self.permissions.contains(&DiskWriteCwd)
+            && self.permissions.iter().all(|perm| {
+                matches!(
+                    perm,
+                    DiskFullReadAccess
+                        | DiskWritePlatformUserTempFolder
+                        | DiskWriteCwd
+                        | DiskWriteFolder { .. }
+                        | NetworkFullAccess
+                )
+            });
+        is_workspace_write.then_some(SandboxMode::WorkspaceWrite)
+    }
+
     pub fn has_full_disk_read_access(&self) -> bool {
         self.permissions
             .iter()
@@ -253,6 +358,37 @@ impl SandboxPolicy {
     }
 }
 
+/// Shows the sandbox mode, with any extra writable roots and network access
+/// for `workspace-write`, or the raw permissions for a custom policy.
+impl std::fmt::Display for SandboxPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.sandbox_mode() {
+            Some(SandboxMode::WorkspaceWrite) => {
+                let mut extras: Vec<String> = self
+                    .permissions
+                    .iter()
+                    .filter_map(|perm| match perm {
+                        SandboxPermission::DiskWriteFolder { folder } => {
+                            Some(folder.display().to_string())
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                if self.has_full_network_access() {
+                    extras.push("network".to_string());
+                }
+                write!(f, "{}", SandboxMode::WorkspaceWrite)?;
+                if !extras.is_empty() {
+                    write!(f, " ({})", extras.join(", "))?;
+                }
+                Ok(())
+            }
+            Some(mode) => write!(f, "{mode}"),
+            None => write!(f, "custom {:?}", self.permissions),
+        }
+    }
+}
+
 /// Permissions that should be granted to the sandbox in which the agent
 /// operates.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -329,6 +465,10 @@ pub enum EventMsg {
     /// Agent has completed all actions
     TaskComplete(TaskCompleteEvent),
 
+    /// The task was stopped because it reached a limit in the `budget`
+    /// config. No `TaskComplete` follows.
+    BudgetExceeded(BudgetExceededEvent),
+
     /// Agent text output message
     AgentMessage(AgentMessageEvent),
 
@@ -342,6 +482,11 @@ pub enum EventMsg {
 
     McpToolCallEnd(McpToolCallEndEvent),
 
+    /// One of the built-in read-only file tools (`read_file`, `list_dir`,
+    /// `grep`) finished. These run in-process and complete quickly, so there
+    /// is no corresponding begin event.
+    FileToolCall(FileToolCallEvent),
+
     /// Notification that the server is about to execute a command.
     ExecCommandBegin(ExecCommandBeginEvent),
 
@@ -353,6 +498,10 @@ pub enum EventMsg {
 
     BackgroundEvent(BackgroundEventEvent),
 
+    /// The session switched to a fallback model because the current one kept
+    /// failing. The new model answers from now on.
+    ModelFallback(ModelFallbackEvent),
+
     /// Notification that the agent is about to apply a code patch. Mirrors
     /// `ExecCommandBegin` so front‑ends can show progress indicators.
     PatchApplyBegin(PatchApplyBeginEvent),
@@ -360,6 +509,18 @@ pub enum EventMsg {
     /// Notification that a patch application has finished.
     PatchApplyEnd(PatchApplyEndEvent),
 
+    /// Combined diff of every file that changed during a task, including
+    /// changes made by shell commands when the session is inside a git
+    /// repository. Sent just before `TaskComplete`, and only if something
+    /// changed.
+    TurnDiff(TurnDiffEvent),
+
+    /// Response to `Op::GetSessionDiff`.
+    SessionDiff(SessionDiffEvent),
+
+    /// Response to `Op::ListApprovalRules` and `Op::RevokeApprovalRule`.
+    ApprovalRules(ApprovalRulesEvent),
+
     /// Response to GetHistoryEntryRequest.
     GetHistoryEntryResponse(GetHistoryEntryResponseEvent),
 }
@@ -376,6 +537,23 @@ pub struct TaskCompleteEvent {
     pub last_agent_message: Option<String>,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BudgetExceededEvent {
+    pub limit: BudgetLimit,
+    /// Human-readable description, e.g. "stopped after 20 turns".
+    pub message: String,
+}
+
+/// The `budget` setting that stopped a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetLimit {
+    MaxTurns,
+    MaxToolCalls,
+    MaxDurationSecs,
+    MaxTokens,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AgentMessageEvent {
     pub message: String,
@@ -415,6 +593,75 @@ impl McpToolCallEndEvent {
     }
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FileToolCallEvent {
+    pub call_id: String,
+    /// The tool that was invoked and its arguments.
+    pub invocation: FileToolInvocation,
+    /// One-line description of the result (e.g., `lines 1-40 of 120`) or the
+    /// error message if the call failed.
+    pub summary: String,
+    pub success: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "tool", rename_all = "snake_case")]
+pub enum FileToolInvocation {
+    ReadFile {
+        path: PathBuf,
+        start_line: Option<usize>,
+        end_line: Option<usize>,
+    },
+    ListDir {
+        path: PathBuf,
+        depth: usize,
+    },
+    Grep {
+        pattern: String,
+        path: PathBuf,
+        globs: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for FileToolInvocation {
+    /// Compact, human-readable rendering used by front-ends, e.g.
+    /// `read_file src/lib.rs:10-20` or `grep "TODO" in src (*.rs)`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileToolInvocation::ReadFile {
+                path,
+                start_line,
+                end_line,
+            } => {
+                write!(f, "read_file {}", path.display())?;
+                match (start_line, end_line) {
+                    (None, None) => Ok(()),
+                    (start, end) => write!(
+                        f,
+                        ":{}-{}",
+                        start.unwrap_or(1),
+                        end.map(|e| e.to_string()).unwrap_or_default()
+                    ),
+                }
+            }
+            FileToolInvocation::ListDir { path, depth } => {
+                write!(f, "list_dir {} (depth {depth})", path.display())
+            }
+            FileToolInvocation::Grep {
+                pattern,
+                path,
+                globs,
+            } => {
+                write!(f, "grep {pattern:?} in {}", path.display())?;
+                if !globs.is_empty() {
+                    write!(f, " ({})", globs.join(", "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ExecCommandBeginEvent {
     /// Identifier so this can be paired with the ExecCommandEnd event.
@@ -464,6 +711,16 @@ pub struct BackgroundEventEvent {
     pub message: String,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ModelFallbackEvent {
+    /// The model now answering.
+    pub model: String,
+    /// Key of its provider in `model_providers`.
+    pub provider: String,
+    /// Why the previous model was abandoned.
+    pub reason: String,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PatchApplyBeginEvent {
     /// Identifier so this can be paired with the PatchApplyEnd event.
@@ -486,6 +743,26 @@ pub struct PatchApplyEndEvent {
     pub success: bool,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TurnDiffEvent {
+    pub unified_diff: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SessionDiffEvent {
+    /// Diff of the working tree against its state when the session started.
+    /// Empty if nothing changed.
+    pub unified_diff: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApprovalRulesEvent {
+    /// Directory the rules are scoped to: the root of the git repository
+    /// containing the session's `cwd`, or `cwd` itself.
+    pub project: PathBuf,
+    pub rules: Vec<ApprovalRule>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct GetHistoryEntryResponseEvent {
     pub offset: usize,
@@ -511,7 +788,7 @@ pub struct SessionConfiguredEvent {
 }
 
 /// User's decision in response to an ExecApprovalRequest.
-#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum ReviewDecision {
     /// User has approved this command and the agent should execute it.
@@ -522,16 +799,56 @@ pub enum ReviewDecision {
     /// remainder of the session.
     ApprovedForSession,
 
+    /// User has approved a modified version of the command. `command`
+    /// replaces the proposed argv; it is still subject to the approval and
+    /// sandbox policies, and the agent is told about the change. Treated as
+    /// a rejection in response to a patch approval request, since there is
+    /// no command to replace.
+    ApprovedModifiedCommand { command: Vec<String> },
+
+    /// User has approved this request and wants `rule` saved for the
+    /// current project so that matching requests are approved automatically,
+    /// in this session and in future ones.
+    ApprovedAndRemember { rule: ApprovalRule },
+
     /// User has denied this command and the agent should not execute it, but
     /// it should continue the session and try something else.
     #[default]
     Denied,
 
+    /// Like `Denied`, but the user explained what they objected to. The
+    /// feedback is passed on to the agent verbatim.
+    DeniedWithFeedback { feedback: String },
+
     /// User has denied this command and the agent should not do anything until
     /// the user's next command.
     Abort,
 }
 
+/// An approval that persists across sessions for a single project. See
+/// [`crate::approval_rules`].
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ApprovalRule {
+    /// Commands whose argv starts with `prefix` run without asking. A
+    /// `bash -lc` script counts if it is a single plain command.
+    CommandPrefix { prefix: Vec<String> },
+
+    /// Patches may write anywhere under `path` without asking.
+    WritableRoot { path: PathBuf },
+}
+
+impl std::fmt::Display for ApprovalRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApprovalRule::CommandPrefix { prefix } => {
+                write!(f, "run `{} *`", prefix.join(" "))
+            }
+            ApprovalRule::WritableRoot { path } => write!(f, "write under {}", path.display()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum FileChange {
@@ -545,6 +862,38 @@ pub enum FileChange {
     },
 }
 
+impl FileChange {
+    /// Splits an update's `unified_diff` into its `@@` hunks. These are the
+    /// units that `RejectedFileChange::hunks` refers to. Adds and deletes have
+    /// no hunks and can only be rejected as a whole.
+    pub fn hunks(&self) -> Vec<&str> {
+        let FileChange::Update { unified_diff, .. } = self else {
+            return Vec::new();
+        };
+        let mut starts: Vec<usize> = unified_diff
+            .match_indices("@@ ")
+            .map(|(idx, _)| idx)
+            .filter(|&idx| idx == 0 || unified_diff.as_bytes()[idx - 1] == b'\n')
+            .collect();
+        starts.push(unified_diff.len());
+        starts
+            .windows(2)
+            .map(|w| &unified_diff[w[0]..w[1]])
+            .collect()
+    }
+}
+
+/// A file change (or some of its hunks) that the user declined while approving
+/// the rest of a patch.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RejectedFileChange {
+    pub path: PathBuf,
+    /// Zero-based indices into `FileChange::hunks()`. If `None`, the change
+    /// to this file is rejected entirely.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hunks: Option<Vec<usize>>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Chunk {
     /// 1-based line index of the first line in the original file
@@ -578,4 +927,73 @@ mod tests {
             r#"{"id":"1234","msg":{"type":"session_configured","session_id":"67e55044-10b1-426f-9247-bb680e5fe0c8","model":"codex-mini-latest","history_log_id":0,"history_entry_count":0}}"#
         );
     }
+
+    #[test]
+    fn sandbox_policy_reports_its_mode() {
+        assert_eq!(
+            SandboxPolicy::new_read_only_policy().to_string(),
+            "read-only"
+        );
+        assert_eq!(
+            SandboxPolicy::new_full_auto_policy().to_string(),
+            "workspace-write"
+        );
+        assert_eq!(
+            SandboxPolicy::new_workspace_write_policy(&[PathBuf::from("/opt/cache")], true)
+                .to_string(),
+            "workspace-write (/opt/cache, network)"
+        );
+        assert_eq!(
+            SandboxPolicy::new_danger_full_access_policy().sandbox_mode(),
+            Some(SandboxMode::DangerFullAccess)
+        );
+        let custom = SandboxPolicy::from(vec![SandboxPermission::NetworkFullAccess]);
+        assert_eq!(custom.sandbox_mode(), None);
+        assert_eq!(custom.to_string(), "custom [NetworkFullAccess]");
+    }
+
+    #[test]
+    fn approval_policy_displays_its_config_name() {
+        for policy in [
+            AskForApproval::UnlessAllowListed,
+            AskForApproval::AutoEdit,
+            AskForApproval::OnFailure,
+            AskForApproval::Never,
+        ] {
+            assert_eq!(
+                format!("\"{policy}\""),
+                serde_json::to_string(&policy).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn file_change_hunks() {
+        let change = FileChange::Update {
+            unified_diff: "@@ -1 +1 @@\n-a\n+b\n@@ -9,2 +9 @@\n x\n-@@ y\n".to_string(),
+            move_path: None,
+        };
+        assert_eq!(
+            change.hunks(),
+            vec!["@@ -1 +1 @@\n-a\n+b\n", "@@ -9,2 +9 @@\n x\n-@@ y\n"]
+        );
+        assert!(FileChange::Delete.hunks().is_empty());
+    }
+
+    #[test]
+    fn deserialize_denied_with_feedback() {
+        let op: Op = serde_json::from_str(
+            r#"{"type":"exec_approval","id":"1","decision":{"denied_with_feedback":{"feedback":"use cargo check"}}}"#,
+        )
+        .unwrap();
+        let Op::ExecApproval { decision, .. } = op else {
+            panic!("unexpected op: {op:?}");
+        };
+        assert_eq!(
+            decision,
+            ReviewDecision::DeniedWithFeedback {
+                feedback: "use cargo check".to_string()
+            }
+        );
+    }
 }
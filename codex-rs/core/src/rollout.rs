@@ -2,43 +2,108 @@
 //! [`ResponseItem`] objects exchanged during a session – to disk so that
 //! sessions can be replayed or inspected later (mirrors the behaviour of the
 //! upstream TypeScript implementation).
+//!
+//! The read side ([`list_sessions`], [`read_transcript`], [`RetentionPolicy`])
+//! backs the `codex sessions` subcommands.
 
 use std::fs::File;
 use std::fs::{self};
+use std::io::BufRead;
+use std::io::BufReader;
 use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
 
+use serde::Deserialize;
 use serde::Serialize;
 use time::OffsetDateTime;
 use time::format_description::FormatItem;
 use time::macros::format_description;
 use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::mpsc::{self};
 use uuid::Uuid;
 
 use crate::config::Config;
+use crate::models::ContentItem;
+use crate::models::LocalShellAction;
 use crate::models::ResponseItem;
 
 /// Folder inside `~/.codex` that holds saved rollouts.
 const SESSIONS_SUBDIR: &str = "sessions";
 
-#[derive(Serialize)]
+/// Upper bound on each `git` invocation made while starting a session so a
+/// wedged repository cannot hold back the rest of the rollout for long.
+const GIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sessions written to more recently than this are never pruned, since they
+/// may still be running.
+const RECENTLY_ACTIVE: Duration = Duration::from_secs(60 * 60);
+
+/// First line of every rollout file. Fields other than `id` and `timestamp`
+/// were added over time, so they are optional when reading.
+#[derive(Serialize, Deserialize)]
 struct SessionMeta {
     id: String,
     timestamp: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     instructions: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cwd: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    git: Option<GitInfo>,
+}
+
+/// State of the git repository the session was started in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GitInfo {
+    /// `None` when `HEAD` is detached.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// `None` when the repository has no commits yet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+impl GitInfo {
+    /// Returns `None` if `cwd` is not inside a git repository.
+    async fn collect(cwd: &Path) -> Option<Self> {
+        git_output(cwd, &["rev-parse", "--is-inside-work-tree"]).await?;
+        Some(Self {
+            branch: git_output(cwd, &["symbolic-ref", "--short", "-q", "HEAD"]).await,
+            commit: git_output(cwd, &["rev-parse", "--verify", "-q", "HEAD"]).await,
+        })
+    }
+}
+
+/// Runs `git` in `cwd` and returns its trimmed stdout, or `None` if it failed
+/// or printed nothing.
+async fn git_output(cwd: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(cwd)
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(GIT_TIMEOUT, output).await.ok()?.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let stdout = stdout.trim();
+    (!stdout.is_empty()).then(|| stdout.to_string())
 }
 
 /// Records all [`ResponseItem`]s for a session and flushes them to disk after
 /// every update.
 ///
-/// Rollouts are recorded as JSONL and can be inspected with tools such as:
-///
-/// ```ignore
-/// $ jq -C . ~/.codex/sessions/rollout-2025-05-07T17-24-21-5973b6c0-94b8-487b-a530-2aeb6098ae0e.jsonl
-/// $ fx ~/.codex/sessions/rollout-2025-05-07T17-24-21-5973b6c0-94b8-487b-a530-2aeb6098ae0e.jsonl
-/// ```
+/// Rollouts are recorded as JSONL under `~/.codex/sessions` and can be browsed
+/// with `codex sessions list` / `codex sessions show <ID>`.
 #[derive(Clone)]
 pub(crate) struct RolloutRecorder {
     tx: Sender<String>,
@@ -52,6 +117,8 @@ impl RolloutRecorder {
         config: &Config,
         uuid: Uuid,
         instructions: Option<String>,
+        cwd: &Path,
+        model: &str,
     ) -> std::io::Result<Self> {
         let LogFileInfo {
             file,
@@ -67,11 +134,15 @@ impl RolloutRecorder {
             .format(timestamp_format)
             .map_err(|e| IoError::other(format!("failed to format timestamp: {e}")))?;
 
-        let meta = SessionMeta {
+        let mut meta = SessionMeta {
             timestamp,
             id: session_id.to_string(),
             instructions,
+            cwd: Some(cwd.to_path_buf()),
+            model: Some(model.to_string()),
+            git: None,
         };
+        let cwd = cwd.to_path_buf();
 
         // A reasonably-sized bounded channel. If the buffer fills up the send
         // future will yield, which is fine – we only need to ensure we do not
@@ -84,27 +155,31 @@ impl RolloutRecorder {
         tokio::task::spawn(async move {
             let mut file = tokio::fs::File::from_std(file);
 
-            while let Some(line) = rx.recv().await {
-                // Write line + newline, then flush to disk.
-                if let Err(e) = file.write_all(line.as_bytes()).await {
-                    tracing::warn!("rollout writer: failed to write line: {e}");
-                    break;
+            // Collecting the git state can take a while, so it happens here
+            // rather than before the session starts. Items recorded in the
+            // meantime wait in the channel, which keeps SessionMeta first.
+            meta.git = GitInfo::collect(&cwd).await;
+            let meta = match serde_json::to_string(&meta) {
+                Ok(meta) => meta,
+                Err(e) => {
+                    tracing::warn!("rollout writer: failed to serialize session meta: {e}");
+                    return;
                 }
-                if let Err(e) = file.write_all(b"\n").await {
-                    tracing::warn!("rollout writer: failed to write newline: {e}");
-                    break;
-                }
-                if let Err(e) = file.flush().await {
-                    tracing::warn!("rollout writer: failed to flush: {e}");
+            };
+            if let Err(e) = write_line(&mut file, &meta).await {
+                tracing::warn!("rollout writer: {e}");
+                return;
+            }
+
+            while let Some(line) = rx.recv().await {
+                if let Err(e) = write_line(&mut file, &line).await {
+                    tracing::warn!("rollout writer: {e}");
                     break;
                 }
             }
         });
 
-        let recorder = Self { tx };
-        // Ensure SessionMeta is the first item in the file.
-        recorder.record_item(&meta).await?;
-        Ok(recorder)
+        Ok(Self { tx })
     }
 
     /// Append `items` to the rollout file.
@@ -141,6 +216,19 @@ impl RolloutRecorder {
     }
 }
 
+/// Writes `line` and a newline, then flushes to disk.
+async fn write_line(file: &mut tokio::fs::File, line: &str) -> std::io::Result<()> {
+    file.write_all(line.as_bytes())
+        .await
+        .map_err(|e| IoError::new(e.kind(), format!("failed to write line: {e}")))?;
+    file.write_all(b"\n")
+        .await
+        .map_err(|e| IoError::new(e.kind(), format!("failed to write newline: {e}")))?;
+    file.flush()
+        .await
+        .map_err(|e| IoError::new(e.kind(), format!("failed to flush: {e}")))
+}
+
 struct LogFileInfo {
     /// Opened file handle to the rollout file.
     file: File,
@@ -183,3 +271,437 @@ fn create_log_file(config: &Config, session_id: Uuid) -> std::io::Result<LogFile
         timestamp,
     })
 }
+
+/// A saved session, as summarised by `codex sessions list`.
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    /// Path of the rollout file.
+    pub path: PathBuf,
+    pub id: String,
+    /// Local time at which the session started, e.g.
+    /// `2025-05-07T17:24:21.123Z`.
+    pub timestamp: String,
+    /// Last time the rollout file was written to.
+    pub modified: SystemTime,
+    pub cwd: Option<PathBuf>,
+    pub model: Option<String>,
+    pub git: Option<GitInfo>,
+    /// Text of the first user message, if the session got that far.
+    pub first_prompt: Option<String>,
+}
+
+/// One step of a saved conversation, simplified for display.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TranscriptEntry {
+    User(String),
+    Assistant(String),
+    /// A tool invocation. For shell calls `arguments` is the command line,
+    /// otherwise it is the raw JSON arguments.
+    ToolCall {
+        name: String,
+        arguments: String,
+    },
+    ToolOutput(String),
+}
+
+/// The subset of [`ResponseItem`] shapes that appear in a rollout file. The
+/// recorder writes function call outputs as bare strings, which
+/// `FunctionCallOutputPayload` cannot read back, hence a dedicated type.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum RolloutLine {
+    Message {
+        role: String,
+        content: Vec<ContentItem>,
+    },
+    LocalShellCall {
+        action: LocalShellAction,
+    },
+    FunctionCall {
+        name: String,
+        arguments: String,
+    },
+    FunctionCallOutput {
+        output: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+impl RolloutLine {
+    fn into_entry(self) -> Option<TranscriptEntry> {
+        match self {
+            RolloutLine::Message { role, content } => {
+                let text = content
+                    .into_iter()
+                    .filter_map(|item| match item {
+                        ContentItem::InputText { text } | ContentItem::OutputText { text } => {
+                            Some(text)
+                        }
+                        ContentItem::InputImage { .. } => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                match role.as_str() {
+                    "user" => Some(TranscriptEntry::User(text)),
+                    "assistant" => Some(TranscriptEntry::Assistant(text)),
+                    _ => None,
+                }
+            }
+            RolloutLine::LocalShellCall {
+                action: LocalShellAction::Exec(exec),
+            } => Some(TranscriptEntry::ToolCall {
+                name: "shell".to_string(),
+                arguments: exec.command.join(" "),
+            }),
+            RolloutLine::FunctionCall { name, arguments } => {
+                Some(TranscriptEntry::ToolCall { name, arguments })
+            }
+            RolloutLine::FunctionCallOutput { output } => Some(TranscriptEntry::ToolOutput(output)),
+            RolloutLine::Other => None,
+        }
+    }
+}
+
+/// Returns every session saved under `codex_home`, most recently written to
+/// first. Files that cannot be parsed are skipped with a warning.
+pub fn list_sessions(codex_home: &Path) -> std::io::Result<Vec<SessionInfo>> {
+    let entries = match fs::read_dir(codex_home.join(SESSIONS_SUBDIR)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut sessions = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let is_rollout = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("rollout-") && name.ends_with(".jsonl"));
+        if !is_rollout {
+            continue;
+        }
+        match read_session_info(&path) {
+            Ok(info) => sessions.push(info),
+            Err(e) => tracing::warn!("skipping rollout {}: {e}", path.display()),
+        }
+    }
+    // The recorded start time is local time without an offset, so it cannot
+    // order sessions reliably; the file's modification time can.
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.modified));
+    Ok(sessions)
+}
+
+/// Looks up a session by its id or a unique prefix of it.
+pub fn find_session(codex_home: &Path, id: &str) -> std::io::Result<SessionInfo> {
+    let mut matches = list_sessions(codex_home)?
+        .into_iter()
+        .filter(|session| session.id.starts_with(id))
+        .collect::<Vec<_>>();
+    match matches.len() {
+        0 => Err(IoError::new(
+            ErrorKind::NotFound,
+            format!("no session matches `{id}`"),
+        )),
+        1 => Ok(matches.remove(0)),
+        n => Err(IoError::new(
+            ErrorKind::InvalidInput,
+            format!("`{id}` matches {n} sessions; use a longer prefix"),
+        )),
+    }
+}
+
+/// Reads the conversation recorded in the rollout at `path`.
+pub fn read_transcript(path: &Path) -> std::io::Result<Vec<TranscriptEntry>> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    // Skip the SessionMeta header.
+    lines.next().transpose()?;
+
+    let mut entries = Vec::new();
+    for line in lines {
+        if let Some(entry) = parse_line(&line?) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+fn read_session_info(path: &Path) -> std::io::Result<SessionInfo> {
+    let file = File::open(path)?;
+    let modified = file.metadata()?.modified()?;
+    let mut lines = BufReader::new(file).lines();
+
+    let header = lines
+        .next()
+        .transpose()?
+        .ok_or_else(|| IoError::new(ErrorKind::InvalidData, "empty rollout file"))?;
+    let meta: SessionMeta = serde_json::from_str(&header)
+        .map_err(|e| IoError::new(ErrorKind::InvalidData, format!("bad session header: {e}")))?;
+
+    let mut first_prompt = None;
+    for line in lines {
+        if let Some(TranscriptEntry::User(text)) = parse_line(&line?) {
+            first_prompt = Some(text);
+            break;
+        }
+    }
+
+    Ok(SessionInfo {
+        path: path.to_path_buf(),
+        id: meta.id,
+        timestamp: meta.timestamp,
+        modified,
+        cwd: meta.cwd,
+        model: meta.model,
+        git: meta.git,
+        first_prompt,
+    })
+}
+
+fn parse_line(line: &str) -> Option<TranscriptEntry> {
+    match serde_json::from_str::<RolloutLine>(line) {
+        Ok(item) => item.into_entry(),
+        Err(e) => {
+            tracing::warn!("skipping unparseable rollout line: {e}");
+            None
+        }
+    }
+}
+
+/// Decides which sessions `codex sessions prune` deletes. A session is
+/// expired if it falls outside *either* limit, unless it was written to in
+/// the last hour and so may still be running.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetentionPolicy {
+    /// Keep at most this many of the most recently written sessions.
+    pub keep_last: Option<usize>,
+    /// Expire sessions whose rollout has not been written to for this long.
+    pub max_age: Option<Duration>,
+}
+
+impl RetentionPolicy {
+    /// Returns the members of `sessions` (newest first, as returned by
+    /// [`list_sessions`]) that the policy expires as of `now`.
+    pub fn expired<'a>(
+        &self,
+        sessions: &'a [SessionInfo],
+        now: SystemTime,
+    ) -> Vec<&'a SessionInfo> {
+        sessions
+            .iter()
+            .enumerate()
+            .filter(|(idx, session)| {
+                let recently_active = !now
+                    .duration_since(session.modified)
+                    .is_ok_and(|age| age >= RECENTLY_ACTIVE);
+                if recently_active {
+                    return false;
+                }
+                let over_count = self.keep_last.is_some_and(|keep| *idx >= keep);
+                let too_old = self.max_age.is_some_and(|max_age| {
+                    now.duration_since(session.modified)
+                        .is_ok_and(|age| age > max_age)
+                });
+                over_count || too_old
+            })
+            .map(|(_, session)| session)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    /// Backdates `path` so that sessions are listed in a known order.
+    fn set_age(path: &Path, age: Duration) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - age)
+            .unwrap();
+    }
+
+    fn write_rollout(codex_home: &Path, id: &str, timestamp: &str, lines: &[&str]) -> PathBuf {
+        let dir = codex_home.join(SESSIONS_SUBDIR);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!(
+            "rollout-{}-{id}.jsonl",
+            timestamp.replace(':', "-")
+        ));
+        let header = format!(
+            r#"{{"id":"{id}","timestamp":"{timestamp}","cwd":"/repo","model":"o3","git":{{"branch":"main","commit":"abc123"}}}}"#
+        );
+        let mut contents = header;
+        for line in lines {
+            contents.push('\n');
+            contents.push_str(line);
+        }
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn user_message(text: &str) -> String {
+        format!(
+            r#"{{"type":"message","role":"user","content":[{{"type":"input_text","text":"{text}"}}]}}"#
+        )
+    }
+
+    #[test]
+    fn lists_sessions_newest_first_with_metadata() {
+        let home = TempDir::new().unwrap();
+        let hour = Duration::from_secs(60 * 60);
+        let aaaa = write_rollout(home.path(), "aaaa", "2025-05-01T10:00:00.000Z", &[]);
+        set_age(&aaaa, hour * 2);
+        write_rollout(
+            home.path(),
+            "bbbb",
+            // An earlier start time than `aaaa`, e.g. from another time zone;
+            // the order follows the last write.
+            "2025-04-30T10:00:00.000Z",
+            &[&user_message("fix the build"), &user_message("thanks")],
+        );
+        // Rollouts written before cwd/model/git were recorded still list.
+        let cccc = home
+            .path()
+            .join(SESSIONS_SUBDIR)
+            .join("rollout-old-cccc.jsonl");
+        fs::write(
+            &cccc,
+            r#"{"id":"cccc","timestamp":"2025-04-01T10:00:00.000Z"}"#,
+        )
+        .unwrap();
+        set_age(&cccc, hour * 3);
+
+        let sessions = list_sessions(home.path()).unwrap();
+        let ids = sessions.iter().map(|s| s.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["bbbb", "aaaa", "cccc"]);
+
+        let newest = &sessions[0];
+        assert_eq!(newest.first_prompt.as_deref(), Some("fix the build"));
+        assert_eq!(newest.model.as_deref(), Some("o3"));
+        assert_eq!(newest.cwd.as_deref(), Some(Path::new("/repo")));
+        assert_eq!(
+            newest.git,
+            Some(GitInfo {
+                branch: Some("main".to_string()),
+                commit: Some("abc123".to_string()),
+            })
+        );
+        assert_eq!(sessions[1].first_prompt, None);
+        assert_eq!(sessions[2].model, None);
+    }
+
+    #[test]
+    fn finds_session_by_unique_prefix() {
+        let home = TempDir::new().unwrap();
+        write_rollout(home.path(), "abc-1", "2025-05-01T10:00:00.000Z", &[]);
+        write_rollout(home.path(), "abd-2", "2025-05-02T10:00:00.000Z", &[]);
+
+        assert_eq!(find_session(home.path(), "abc").unwrap().id, "abc-1");
+        assert_eq!(
+            find_session(home.path(), "ab").unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            find_session(home.path(), "zz").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn reads_transcript() {
+        let home = TempDir::new().unwrap();
+        let path = write_rollout(
+            home.path(),
+            "aaaa",
+            "2025-05-01T10:00:00.000Z",
+            &[
+                &user_message("list files"),
+                r#"{"type":"local_shell_call","id":null,"call_id":"c1","status":"completed","action":{"type":"exec","command":["ls","-a"],"timeout_ms":null,"working_directory":null,"env":null,"user":null}}"#,
+                r#"{"type":"function_call","name":"shell","arguments":"{\"command\":[\"ls\"]}","call_id":"c2"}"#,
+                r#"{"type":"function_call_output","call_id":"c2","output":"README.md"}"#,
+                r#"{"type":"message","role":"assistant","content":[{"type":"output_text","text":"Done."}]}"#,
+            ],
+        );
+
+        assert_eq!(
+            read_transcript(&path).unwrap(),
+            vec![
+                TranscriptEntry::User("list files".to_string()),
+                TranscriptEntry::ToolCall {
+                    name: "shell".to_string(),
+                    arguments: "ls -a".to_string(),
+                },
+                TranscriptEntry::ToolCall {
+                    name: "shell".to_string(),
+                    arguments: r#"{"command":["ls"]}"#.to_string(),
+                },
+                TranscriptEntry::ToolOutput("README.md".to_string()),
+                TranscriptEntry::Assistant("Done.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn retention_expires_by_count_or_age() {
+        let now = SystemTime::now();
+        let day = Duration::from_secs(24 * 60 * 60);
+        let session = |id: &str, age_days: u32| SessionInfo {
+            path: PathBuf::from(id),
+            id: id.to_string(),
+            timestamp: String::new(),
+            modified: now - day * age_days,
+            cwd: None,
+            model: None,
+            git: None,
+            first_prompt: None,
+        };
+        let sessions = vec![session("a", 0), session("b", 10), session("c", 40)];
+        let expired_ids = |policy: RetentionPolicy| {
+            policy
+                .expired(&sessions, now)
+                .into_iter()
+                .map(|s| s.id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        // `a` was just written to, so it may still be running.
+        assert_eq!(
+            expired_ids(RetentionPolicy {
+                keep_last: Some(0),
+                max_age: Some(Duration::ZERO),
+            }),
+            vec!["b", "c"]
+        );
+
+        assert_eq!(
+            expired_ids(RetentionPolicy {
+                keep_last: Some(1),
+                max_age: None,
+            }),
+            vec!["b", "c"]
+        );
+        assert_eq!(
+            expired_ids(RetentionPolicy {
+                keep_last: None,
+                max_age: Some(day * 30),
+            }),
+            vec!["c"]
+        );
+        assert_eq!(
+            expired_ids(RetentionPolicy {
+                keep_last: Some(2),
+                max_age: Some(day * 5),
+            }),
+            vec!["b", "c"]
+        );
+        assert!(expired_ids(RetentionPolicy::default()).is_empty());
+    }
+}
@@ -0,0 +1,193 @@
+//! Enforcement of the `budget` config within a task.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::config_types::RunBudget;
+use crate::protocol::BudgetExceededEvent;
+use crate::protocol::BudgetLimit;
+
+/// What a task has used so far, compared against its [`RunBudget`].
+pub(crate) struct BudgetTracker {
+    budget: RunBudget,
+    started_at: Instant,
+    turns: u32,
+    tool_calls: u32,
+    tokens: u64,
+}
+
+impl BudgetTracker {
+    pub(crate) fn new(budget: RunBudget) -> Self {
+        Self {
+            budget,
+            started_at: Instant::now(),
+            turns: 0,
+            tool_calls: 0,
+            tokens: 0,
+        }
+    }
+
+    /// When the task must stop, if it has a wall-clock limit.
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.budget
+            .max_duration_secs
+            .map(|secs| self.started_at + Duration::from_secs(secs))
+    }
+
+    /// Records a completed turn that made `tool_calls` tool calls and used
+    /// `tokens` tokens.
+    pub(crate) fn record_turn(&mut self, tool_calls: usize, tokens: u64) {
+        self.turns += 1;
+        self.tool_calls = self
+            .tool_calls
+            .saturating_add(u32::try_from(tool_calls).unwrap_or(u32::MAX));
+        self.tokens += tokens;
+    }
+
+    /// Checked before each turn: returns the first limit that has been
+    /// reached, if any.
+    pub(crate) fn exceeded(&self) -> Option<BudgetExceededEvent> {
+        let RunBudget {
+            max_turns,
+            max_tool_calls,
+            max_duration_secs,
+            max_tokens,
+        } = self.budget;
+        if max_turns.is_some_and(|max| self.turns >= max) {
+            return Some(BudgetExceededEvent {
+                limit: BudgetLimit::MaxTurns,
+                message: format!("stopped after {} turns", self.turns),
+            });
+        }
+        if max_tool_calls.is_some_and(|max| self.tool_calls >= max) {
+            return Some(BudgetExceededEvent {
+                limit: BudgetLimit::MaxToolCalls,
+                message: format!("stopped after {} tool calls", self.tool_calls),
+            });
+        }
+        if max_tokens.is_some_and(|max| self.tokens >= max) {
+            return Some(BudgetExceededEvent {
+                limit: BudgetLimit::MaxTokens,
+                message: format!("stopped after using {} tokens", self.tokens),
+            });
+        }
+        if max_duration_secs.is_some_and(|secs| self.out_of_time(secs)) {
+            return Some(self.duration_exceeded());
+        }
+        None
+    }
+
+    /// Checked before each tool call, `dispatched` being the calls already
+    /// made in the current turn: returns the limit that rules the call out,
+    /// if any.
+    pub(crate) fn refuses_tool_call(&self, dispatched: usize) -> Option<BudgetExceededEvent> {
+        let dispatched = u32::try_from(dispatched).unwrap_or(u32::MAX);
+        let max_tool_calls = self
+            .budget
+            .max_tool_calls
+            .filter(|max| self.tool_calls.saturating_add(dispatched) >= *max);
+        if let Some(max) = max_tool_calls {
+            return Some(BudgetExceededEvent {
+                limit: BudgetLimit::MaxToolCalls,
+                message: format!("reached the limit of {max} tool calls"),
+            });
+        }
+        if self
+            .budget
+            .max_duration_secs
+            .is_some_and(|secs| self.out_of_time(secs))
+        {
+            return Some(self.duration_exceeded());
+        }
+        None
+    }
+
+    fn out_of_time(&self, max_duration_secs: u64) -> bool {
+        self.started_at.elapsed() >= Duration::from_secs(max_duration_secs)
+    }
+
+    /// The event sent when the task runs past [`Self::deadline`].
+    pub(crate) fn duration_exceeded(&self) -> BudgetExceededEvent {
+        BudgetExceededEvent {
+            limit: BudgetLimit::MaxDurationSecs,
+            message: format!(
+                "stopped after {}s",
+                self.budget.max_duration_secs.unwrap_or_default()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn limit(tracker: &BudgetTracker) -> Option<BudgetLimit> {
+        tracker.exceeded().map(|event| event.limit)
+    }
+
+    #[test]
+    fn unlimited_by_default() {
+        let mut tracker = BudgetTracker::new(RunBudget::default());
+        tracker.record_turn(1_000, 1_000_000);
+        assert_eq!(limit(&tracker), None);
+        assert_eq!(tracker.deadline(), None);
+    }
+
+    #[test]
+    fn stops_once_a_limit_is_reached() {
+        let mut tracker = BudgetTracker::new(RunBudget {
+            max_turns: Some(3),
+            max_tool_calls: Some(4),
+            max_tokens: Some(10_000),
+            ..Default::default()
+        });
+        tracker.record_turn(2, 3_000);
+        assert_eq!(limit(&tracker), None);
+        tracker.record_turn(2, 3_000);
+        assert_eq!(limit(&tracker), Some(BudgetLimit::MaxToolCalls));
+
+        let mut tracker = BudgetTracker::new(RunBudget {
+            max_turns: Some(2),
+            max_tokens: Some(5_000),
+            ..Default::default()
+        });
+        tracker.record_turn(0, 6_000);
+        assert_eq!(limit(&tracker), Some(BudgetLimit::MaxTokens));
+        tracker.record_turn(0, 0);
+        assert_eq!(limit(&tracker), Some(BudgetLimit::MaxTurns));
+        assert_eq!(
+            tracker.exceeded().map(|event| event.message),
+            Some("stopped after 2 turns".to_string())
+        );
+    }
+
+    #[test]
+    fn refuses_tool_calls_past_the_limit() {
+        let mut tracker = BudgetTracker::new(RunBudget {
+            max_tool_calls: Some(3),
+            ..Default::default()
+        });
+        tracker.record_turn(1, 0);
+        assert_eq!(tracker.refuses_tool_call(1).map(|event| event.limit), None);
+        assert_eq!(
+            tracker.refuses_tool_call(2).map(|event| event.limit),
+            Some(BudgetLimit::MaxToolCalls)
+        );
+    }
+
+    #[test]
+    fn zero_duration_is_exceeded_immediately() {
+        let tracker = BudgetTracker::new(RunBudget {
+            max_duration_secs: Some(0),
+            ..Default::default()
+        });
+        assert!(tracker.deadline().is_some());
+        assert_eq!(limit(&tracker), Some(BudgetLimit::MaxDurationSecs));
+        assert_eq!(
+            tracker.refuses_tool_call(0).map(|event| event.limit),
+            Some(BudgetLimit::MaxDurationSecs)
+        );
+    }
+}
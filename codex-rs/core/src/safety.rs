@@ -6,8 +6,10 @@ use std::path::PathBuf;
 use codex_apply_patch::ApplyPatchAction;
 use codex_apply_patch::ApplyPatchFileChange;
 
+use crate::approval_rules::rules_allow_command;
 use crate::exec::SandboxType;
 use crate::is_safe_command::is_known_safe_command;
+use crate::protocol::ApprovalRule;
 use crate::protocol::AskForApproval;
 use crate::protocol::SandboxPolicy;
 
@@ -68,12 +70,13 @@ pub fn assess_command_safety(
     approval_policy: AskForApproval,
     sandbox_policy: &SandboxPolicy,
     approved: &HashSet<Vec<String>>,
+    rules: &[ApprovalRule],
 ) -> SafetyCheck {
     let approve_without_sandbox = || SafetyCheck::AutoApprove {
         sandbox_type: SandboxType::None,
     };
 
-    // Previously approved or allow-listed commands
+    // Previously approved or allow-listed
     // All approval modes allow these commands to continue without sandboxing
     if is_known_safe_command(command) || approved.contains(command) {
         // TODO(ragona): I think we should consider running even these inside the sandbox, but it's
@@ -81,6 +84,17 @@ pub fn assess_command_safety(
         return approve_without_sandbox();
     }
 
+    // A persisted rule outlives the approval that created it, so it only
+    // waives the question, not the sandbox.
+    if rules_allow_command(rules, command) {
+        if sandbox_policy.is_unrestricted() {
+            return approve_without_sandbox();
+        }
+        return SafetyCheck::AutoApprove {
+            sandbox_type: get_platform_sandbox().unwrap_or(SandboxType::None),
+        };
+    }
+
     // Command was not known-safe or allow-listed
     if sandbox_policy.is_unrestricted() {
         approve_without_sandbox()
@@ -192,6 +206,28 @@ mod tests {
     #![allow(clippy::unwrap_used)]
     use super::*;
 
+    #[test]
+    fn rule_approved_commands_stay_sandboxed() {
+        let command = vec!["cargo".to_string(), "test".to_string()];
+        let rules = [ApprovalRule::CommandPrefix {
+            prefix: command.clone(),
+        }];
+        let check = assess_command_safety(
+            &command,
+            AskForApproval::UnlessAllowListed,
+            &SandboxPolicy::new_read_only_policy(),
+            &HashSet::new(),
+            &rules,
+        );
+        let SafetyCheck::AutoApprove { sandbox_type } = check else {
+            panic!("expected the rule to approve the command, got {check:?}");
+        };
+        assert_eq!(
+            sandbox_type,
+            get_platform_sandbox().unwrap_or(SandboxType::None)
+        );
+    }
+
     #[test]
     fn test_writable_roots_constraint() {
         let cwd = std::env::current_dir().unwrap();
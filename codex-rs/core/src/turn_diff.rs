@@ -0,0 +1,108 @@
+//! Baseline tracking used to report what changed during a task when the
+//! session is not inside a git repository. (Inside a repository, the
+//! working-tree snapshots from [`crate::git_snapshot`] are diffed instead, which
+//! also captures edits made by shell commands.)
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use similar::TextDiff;
+
+/// The contents of each file as it was before Codex first modified it.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct DiffBaseline {
+    /// `None` means the file did not exist (or was not valid UTF-8).
+    files: BTreeMap<PathBuf, Option<String>>,
+}
+
+impl DiffBaseline {
+    /// Remembers the current contents of `path` unless a baseline for it was
+    /// already recorded. Must be called before the file is modified.
+    pub(crate) fn record(&mut self, path: &Path) {
+        self.files
+            .entry(path.to_path_buf())
+            .or_insert_with(|| std::fs::read_to_string(path).ok());
+    }
+
+    /// Returns a unified diff between the recorded baselines and the current
+    /// contents of the same files. Paths in the headers are relative to `cwd`.
+    pub(crate) fn unified_diff(&self, cwd: &Path) -> String {
+        let mut out = String::new();
+        for (path, original) in &self.files {
+            let current = std::fs::read_to_string(path).ok();
+            if *original == current {
+                continue;
+            }
+            let display = path.strip_prefix(cwd).unwrap_or(path).display().to_string();
+            let old_header = match original {
+                Some(_) => format!("a/{display}"),
+                None => "/dev/null".to_string(),
+            };
+            let new_header = match current {
+                Some(_) => format!("b/{display}"),
+                None => "/dev/null".to_string(),
+            };
+            let old = original.as_deref().unwrap_or_default();
+            let new = current.as_deref().unwrap_or_default();
+            out.push_str(&format!("diff --git a/{display} b/{display}\n"));
+            out.push_str(
+                &TextDiff::from_lines(old, new)
+                    .unified_diff()
+                    .context_radius(3)
+                    .header(&old_header, &new_header)
+                    .to_string(),
+            );
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn diff_covers_added_modified_and_deleted_files() {
+        let dir = TempDir::new().unwrap();
+        let cwd = dir.path();
+        fs::write(cwd.join("modified.txt"), "a\nb\n").unwrap();
+        fs::write(cwd.join("deleted.txt"), "gone\n").unwrap();
+
+        let mut baseline = DiffBaseline::default();
+        for name in ["added.txt", "deleted.txt", "modified.txt"] {
+            baseline.record(&cwd.join(name));
+        }
+        fs::write(cwd.join("added.txt"), "new\n").unwrap();
+        fs::remove_file(cwd.join("deleted.txt")).unwrap();
+        fs::write(cwd.join("modified.txt"), "a\nc\n").unwrap();
+        // A second record() must not overwrite the original baseline.
+        baseline.record(&cwd.join("modified.txt"));
+
+        assert_eq!(
+            baseline.unified_diff(cwd),
+            "diff --git a/added.txt b/added.txt\n\
+             --- /dev/null\n\
+             +++ b/added.txt\n\
+             @@ -0,0 +1 @@\n\
+             +new\n\
+             diff --git a/deleted.txt b/deleted.txt\n\
+             --- a/deleted.txt\n\
+             +++ /dev/null\n\
+             @@ -1 +0,0 @@\n\
+             -gone\n\
+             diff --git a/modified.txt b/modified.txt\n\
+             --- a/modified.txt\n\
+             +++ b/modified.txt\n\
+             @@ -1,2 +1,2 @@\n \
+             a\n\
+             -b\n\
+             +c\n"
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use serde::Serialize;
 
 /// User can configure a program that will receive notifications. Each
@@ -16,6 +18,36 @@ pub(crate) enum UserNotification {
         /// The last message sent by the assistant in the turn.
         last_assistant_message: Option<String>,
     },
+
+    /// The agent is waiting for the user to approve a command.
+    #[serde(rename_all = "kebab-case")]
+    ExecApprovalRequested {
+        turn_id: String,
+        command: Vec<String>,
+        cwd: PathBuf,
+        reason: Option<String>,
+    },
+
+    /// The agent is waiting for the user to approve a patch.
+    #[serde(rename_all = "kebab-case")]
+    PatchApprovalRequested {
+        turn_id: String,
+
+        /// Files the patch adds, deletes or modifies, sorted.
+        paths: Vec<PathBuf>,
+        reason: Option<String>,
+
+        /// Directory the patch would be granted write access to, if any.
+        grant_root: Option<PathBuf>,
+    },
+
+    /// The turn failed and the agent stopped.
+    #[serde(rename_all = "kebab-case")]
+    Error { turn_id: String, message: String },
+
+    /// The turn was interrupted, e.g. by the user, before it completed.
+    #[serde(rename_all = "kebab-case")]
+    TaskAborted { turn_id: String, reason: String },
 }
 
 #[cfg(test)]
@@ -38,4 +70,19 @@ mod tests {
             r#"{"type":"agent-turn-complete","turn-id":"12345","input-messages":["Rename `foo` to `bar` and update the callsites."],"last-assistant-message":"Rename complete and verified `cargo build` succeeds."}"#
         );
     }
+
+    #[test]
+    fn test_approval_notification() {
+        let notification = UserNotification::PatchApprovalRequested {
+            turn_id: "7".to_string(),
+            paths: vec![PathBuf::from("/repo/a.rs")],
+            reason: None,
+            grant_root: Some(PathBuf::from("/repo")),
+        };
+        let serialized = serde_json::to_string(&notification).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"type":"patch-approval-requested","turn-id":"7","paths":["/repo/a.rs"],"reason":null,"grant-root":"/repo"}"#
+        );
+    }
 }
@@ -0,0 +1,257 @@
+//! Delivers [`UserNotification`]s to an HTTP endpoint.
+//!
+//! Notifications are queued and POSTed by a background task so that a slow or
+//! unreachable endpoint never holds up the agent. When the queue is full, new
+//! notifications are dropped. Short-lived front-ends such as `codex exec` call
+//! [`WebhookNotifier::flush`] before exiting so the queue is not lost with the
+//! process.
+
+use std::time::Duration;
+
+use hmac::Hmac;
+use hmac::Mac;
+use sha2::Sha256;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::oneshot;
+
+use crate::config_types::WebhookNotifierConfig;
+use crate::user_notification::UserNotification;
+
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const DEFAULT_QUEUE_SIZE: usize = 100;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Header carrying the HMAC-SHA256 of the request body, hex encoded.
+const SIGNATURE_HEADER: &str = "X-Codex-Signature";
+
+/// An entry in the delivery queue.
+enum Queued {
+    Notification(String),
+    /// Answered once every notification queued before it has been handled.
+    Flush(oneshot::Sender<()>),
+}
+
+pub(crate) struct WebhookNotifier {
+    tx: mpsc::Sender<Queued>,
+}
+
+impl WebhookNotifier {
+    /// Starts the delivery task. It exits once the notifier is dropped and the
+    /// queue has been drained.
+    pub(crate) fn spawn(config: WebhookNotifierConfig) -> Self {
+        let (tx, mut rx) =
+            mpsc::channel::<Queued>(config.queue_size.unwrap_or(DEFAULT_QUEUE_SIZE).max(1));
+        tokio::spawn(async move {
+            let client = reqwest::Client::new();
+            while let Some(queued) = rx.recv().await {
+                match queued {
+                    Queued::Notification(body) => {
+                        if let Err(e) = deliver(&client, &config, body).await {
+                            tracing::warn!("failed to deliver notification to {}: {e}", config.url);
+                        }
+                    }
+                    Queued::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+        Self { tx }
+    }
+
+    /// Waits until every notification queued so far has been delivered (or
+    /// has failed), for at most `max_wait`. Returns whether the queue drained
+    /// in time.
+    pub(crate) async fn flush(&self, max_wait: Duration) -> bool {
+        let (done_tx, done_rx) = oneshot::channel();
+        tokio::time::timeout(max_wait, async {
+            self.tx.send(Queued::Flush(done_tx)).await.is_ok() && done_rx.await.is_ok()
+        })
+        .await
+        .unwrap_or(false)
+    }
+
+    pub(crate) fn notify(&self, notification: &UserNotification) {
+        let Ok(body) = serde_json::to_string(notification) else {
+            tracing::error!("failed to serialise notification payload");
+            return;
+        };
+        match self.tx.try_send(Queued::Notification(body)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                tracing::warn!("notification webhook queue is full; dropping notification");
+            }
+            Err(TrySendError::Closed(_)) => {
+                tracing::warn!("notification webhook task has stopped");
+            }
+        }
+    }
+}
+
+async fn deliver(
+    client: &reqwest::Client,
+    config: &WebhookNotifierConfig,
+    body: String,
+) -> Result<(), String> {
+    let signature = config.secret.as_deref().map(|secret| sign(secret, &body));
+    let max_retries = config.max_retries.unwrap_or(DEFAULT_MAX_RETRIES);
+    let mut delay = INITIAL_RETRY_DELAY;
+    let mut attempt = 0;
+    loop {
+        let mut request = client
+            .post(&config.url)
+            .timeout(REQUEST_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        for (name, value) in &config.headers {
+            request = request.header(name, value);
+        }
+        if let Some(signature) = &signature {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                if !(status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS) {
+                    return Err(format!("endpoint responded with {status}"));
+                }
+                format!("endpoint responded with {status}")
+            }
+            Err(e) => e.to_string(),
+        };
+
+        if attempt >= max_retries {
+            return Err(error);
+        }
+        attempt += 1;
+        tracing::debug!("retrying notification ({attempt}/{max_retries}) in {delay:?}: {error}");
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+}
+
+/// `sha256=<hex>` HMAC of `body`, in the format popularised by GitHub webhooks.
+fn sign(secret: &str, body: &str) -> String {
+    #[expect(clippy::expect_used)]
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use std::collections::HashMap;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers::header;
+    use wiremock::matchers::method;
+
+    #[test]
+    fn signature_matches_reference_vector() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_and_sends_headers() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(header("x-team", "infra"))
+            .and(header("content-type", "application/json"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let config = WebhookNotifierConfig {
+            url: server.uri(),
+            headers: HashMap::from([("X-Team".to_string(), "infra".to_string())]),
+            secret: Some("s3cret".to_string()),
+            max_retries: Some(1),
+            queue_size: None,
+        };
+        let body = r#"{"type":"error","turn-id":"1","message":"boom"}"#.to_string();
+        deliver(&reqwest::Client::new(), &config, body.clone())
+            .await
+            .unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[1].headers.get(SIGNATURE_HEADER).unwrap(),
+            &sign("s3cret", &body)
+        );
+    }
+
+    #[tokio::test]
+    async fn flush_waits_for_queued_deliveries() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(204).set_delay(Duration::from_millis(200)))
+            .mount(&server)
+            .await;
+        let notifier = WebhookNotifier::spawn(WebhookNotifierConfig {
+            url: server.uri(),
+            headers: HashMap::new(),
+            secret: None,
+            max_retries: Some(0),
+            queue_size: None,
+        });
+        for turn_id in ["1", "2"] {
+            notifier.notify(&UserNotification::Error {
+                turn_id: turn_id.to_string(),
+                message: "boom".to_string(),
+            });
+        }
+
+        assert!(notifier.flush(Duration::from_secs(5)).await);
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+        // An empty queue flushes immediately, and a short wait gives up.
+        assert!(notifier.flush(Duration::from_millis(100)).await);
+        notifier.notify(&UserNotification::Error {
+            turn_id: "3".to_string(),
+            message: "boom".to_string(),
+        });
+        assert!(!notifier.flush(Duration::from_millis(10)).await);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let config = WebhookNotifierConfig {
+            url: server.uri(),
+            headers: HashMap::new(),
+            secret: None,
+            max_retries: Some(3),
+            queue_size: None,
+        };
+        let err = deliver(&reqwest::Client::new(), &config, "{}".to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(err, "endpoint responded with 400 Bad Request");
+    }
+}
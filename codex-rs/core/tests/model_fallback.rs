@@ -0,0 +1,241 @@
+//! Verifies that a turn moves on to the next fallback model, with the full
+//! conversation, once the configured provider keeps failing.
+
+use std::time::Duration;
+
+use codex_core::Codex;
+use codex_core::ModelProviderInfo;
+use codex_core::config_types::ModelFallback;
+use codex_core::exec::CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR;
+use codex_core::protocol::EventMsg;
+use codex_core::protocol::InputItem;
+use codex_core::protocol::Op;
+mod test_support;
+use tempfile::TempDir;
+use test_support::load_default_config_for_test;
+use tokio::time::timeout;
+use wiremock::Mock;
+use wiremock::MockServer;
+use wiremock::ResponseTemplate;
+use wiremock::matchers::method;
+use wiremock::matchers::path;
+
+fn provider(name: &str, base_url: String, wire_api: codex_core::WireApi) -> ModelProviderInfo {
+    ModelProviderInfo {
+        name: name.into(),
+        base_url,
+        // Environment variable that should exist in the test environment.
+        env_key: Some("PATH".into()),
+        env_key_instructions: None,
+        wire_api,
+        api_key_header: None,
+        query_params: None,
+        http_headers: None,
+        env_http_headers: None,
+        request_max_retries: Some(0),
+        stream_max_retries: Some(0),
+        stream_idle_timeout_ms: Some(2_000),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn falls_back_to_the_next_model() {
+    #![allow(clippy::unwrap_used)]
+
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let chat_sse = "data: {\"choices\":[{\"delta\":{\"content\":\"Hi from the backup.\"}}]}\n\n\
+data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"stop\"}]}\n\n";
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(chat_sse, "text/event-stream"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let base_url = format!("{}/v1", server.uri());
+    let ctrl_c = std::sync::Arc::new(tokio::sync::Notify::new());
+    let codex_home = TempDir::new().unwrap();
+    let mut config = load_default_config_for_test(&codex_home);
+    config.model_provider = provider("primary", base_url.clone(), codex_core::WireApi::Responses);
+    config.model_providers.insert(
+        "backup".to_string(),
+        provider("backup", base_url, codex_core::WireApi::Chat),
+    );
+    config.model_fallbacks = vec![ModelFallback {
+        model: "backup-model".to_string(),
+        model_provider: "backup".to_string(),
+    }];
+    let (codex, _init_id) = Codex::spawn(config, ctrl_c).await.unwrap();
+
+    codex
+        .submit(Op::UserInput {
+            items: vec![InputItem::Text {
+                text: "hello".into(),
+            }],
+        })
+        .await
+        .unwrap();
+
+    let mut fallback = None;
+    let mut agent_message = None;
+    loop {
+        let ev = timeout(Duration::from_secs(10), codex.next_event())
+            .await
+            .unwrap()
+            .unwrap();
+        match ev.msg {
+            EventMsg::ModelFallback(event) => fallback = Some(event),
+            EventMsg::AgentMessage(msg) => agent_message = Some(msg.message),
+            EventMsg::TaskComplete(_) => break,
+            EventMsg::Error(err) => panic!("turn failed: {}", err.message),
+            _ => {}
+        }
+    }
+    let fallback = fallback.unwrap();
+    assert_eq!(fallback.model, "backup-model");
+    assert_eq!(fallback.provider, "backup");
+    assert_eq!(agent_message.as_deref(), Some("Hi from the backup."));
+
+    // The backup model was sent the conversation so far.
+    let requests = server.received_requests().await.unwrap();
+    let chat_request: serde_json::Value = requests
+        .iter()
+        .find(|request| request.url.path() == "/v1/chat/completions")
+        .unwrap()
+        .body_json()
+        .unwrap();
+    assert_eq!(chat_request["model"], "backup-model");
+    assert_eq!(
+        chat_request["messages"][1],
+        serde_json::json!({"role": "user", "content": "hello"})
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn reconfiguring_keeps_the_fallback() {
+    #![allow(clippy::unwrap_used)]
+
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/primary/v1/responses"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let backup_sse = "event: response.output_item.done\n\
+data: {\"type\":\"response.output_item.done\",\"item\":{\"type\":\"message\",\"role\":\"assistant\",\"content\":[{\"type\":\"output_text\",\"text\":\"Hi from the backup.\"}]}}\n\n\
+event: response.completed\n\
+data: {\"type\":\"response.completed\",\"response\":{\"id\":\"resp_backup\",\"output\":[]}}\n\n";
+    Mock::given(method("POST"))
+        .and(path("/backup/v1/responses"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(backup_sse, "text/event-stream"),
+        )
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let ctrl_c = std::sync::Arc::new(tokio::sync::Notify::new());
+    let codex_home = TempDir::new().unwrap();
+    let mut config = load_default_config_for_test(&codex_home);
+    config.model_provider = provider(
+        "primary",
+        format!("{}/primary/v1", server.uri()),
+        codex_core::WireApi::Responses,
+    );
+    config.model_providers.insert(
+        "backup".to_string(),
+        provider(
+            "backup",
+            format!("{}/backup/v1", server.uri()),
+            codex_core::WireApi::Responses,
+        ),
+    );
+    config.model_fallbacks = vec![ModelFallback {
+        model: "backup-model".to_string(),
+        model_provider: "backup".to_string(),
+    }];
+    let reconfigure = Op::ConfigureSession {
+        provider: config.model_provider.clone(),
+        model: config.model.clone(),
+        model_reasoning_effort: config.model_reasoning_effort,
+        model_reasoning_summary: config.model_reasoning_summary,
+        instructions: None,
+        approval_policy: config.approval_policy,
+        sandbox_policy: config.sandbox_policy.clone(),
+        disable_response_storage: config.disable_response_storage,
+        notify: None,
+        cwd: config.cwd.clone(),
+    };
+    let (codex, _init_id) = Codex::spawn(config, ctrl_c).await.unwrap();
+
+    for (text, reconfigure) in [("hello", Some(reconfigure)), ("again", None)] {
+        codex
+            .submit(Op::UserInput {
+                items: vec![InputItem::Text { text: text.into() }],
+            })
+            .await
+            .unwrap();
+        loop {
+            let ev = timeout(Duration::from_secs(10), codex.next_event())
+                .await
+                .unwrap()
+                .unwrap();
+            match ev.msg {
+                EventMsg::TaskComplete(_) => break,
+                EventMsg::Error(err) => panic!("turn failed: {}", err.message),
+                _ => {}
+            }
+        }
+        let Some(reconfigure) = reconfigure else {
+            continue;
+        };
+        codex.submit(reconfigure).await.unwrap();
+        loop {
+            let ev = timeout(Duration::from_secs(10), codex.next_event())
+                .await
+                .unwrap()
+                .unwrap();
+            if let EventMsg::SessionConfigured(configured) = ev.msg {
+                assert_eq!(configured.model, "backup-model");
+                break;
+            }
+        }
+    }
+
+    // The second turn went straight to the backup model, which has no
+    // server-side state for the conversation and so got all of it.
+    let requests = server.received_requests().await.unwrap();
+    let second: serde_json::Value = requests.last().unwrap().body_json().unwrap();
+    assert_eq!(second["model"], "backup-model");
+    assert_eq!(second.get("previous_response_id"), None);
+    let input = second["input"].to_string();
+    assert!(input.contains("hello"), "{input}");
+    assert!(input.contains("again"), "{input}");
+}
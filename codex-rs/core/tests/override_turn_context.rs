@@ -0,0 +1,148 @@
+//! Switching provider mid-session keeps the conversation.
+
+use std::time::Duration;
+
+use codex_core::Codex;
+use codex_core::ModelProviderInfo;
+use codex_core::WireApi;
+use codex_core::exec::CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR;
+use codex_core::protocol::EventMsg;
+use codex_core::protocol::InputItem;
+use codex_core::protocol::Op;
+mod test_support;
+use tempfile::TempDir;
+use test_support::load_default_config_for_test;
+use tokio::time::timeout;
+use wiremock::Mock;
+use wiremock::MockServer;
+use wiremock::ResponseTemplate;
+use wiremock::matchers::method;
+use wiremock::matchers::path;
+
+fn provider(name: &str, base_url: String, wire_api: WireApi) -> ModelProviderInfo {
+    ModelProviderInfo {
+        name: name.into(),
+        base_url,
+        // Environment variable that should exist in the test environment.
+        env_key: Some("PATH".into()),
+        env_key_instructions: None,
+        wire_api,
+        api_key_header: None,
+        query_params: None,
+        http_headers: None,
+        env_http_headers: None,
+        request_max_retries: Some(0),
+        stream_max_retries: Some(0),
+        stream_idle_timeout_ms: Some(2_000),
+    }
+}
+
+async fn submit_and_wait(codex: &Codex, text: &str) {
+    #![allow(clippy::unwrap_used)]
+    codex
+        .submit(Op::UserInput {
+            items: vec![InputItem::Text { text: text.into() }],
+        })
+        .await
+        .unwrap();
+    loop {
+        let ev = timeout(Duration::from_secs(10), codex.next_event())
+            .await
+            .unwrap()
+            .unwrap();
+        match ev.msg {
+            EventMsg::TaskComplete(_) => break,
+            EventMsg::Error(err) => panic!("turn failed: {}", err.message),
+            _ => {}
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn switching_provider_sends_the_whole_conversation() {
+    #![allow(clippy::unwrap_used)]
+
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let server = MockServer::start().await;
+    let responses_sse = "event: response.completed\n\
+data: {\"type\":\"response.completed\",\"response\":{\"id\":\"resp1\",\"output\":[]}}\n\n";
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(responses_sse, "text/event-stream"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    let chat_sse = "data: {\"choices\":[{\"delta\":{\"content\":\"Hi.\"}}]}\n\n\
+data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"stop\"}]}\n\n";
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(chat_sse, "text/event-stream"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let base_url = format!("{}/v1", server.uri());
+    let ctrl_c = std::sync::Arc::new(tokio::sync::Notify::new());
+    let codex_home = TempDir::new().unwrap();
+    let mut config = load_default_config_for_test(&codex_home);
+    config.model_provider = provider("primary", base_url.clone(), WireApi::Responses);
+    let (codex, _init_id) = Codex::spawn(config, ctrl_c).await.unwrap();
+
+    submit_and_wait(&codex, "first").await;
+
+    codex
+        .submit(Op::OverrideTurnContext {
+            provider: Some(provider("other", base_url, WireApi::Chat)),
+            model: Some("other-model".to_string()),
+            model_reasoning_effort: None,
+            model_reasoning_summary: None,
+            approval_policy: None,
+            sandbox_policy: None,
+        })
+        .await
+        .unwrap();
+    let ev = timeout(Duration::from_secs(10), codex.next_event())
+        .await
+        .unwrap()
+        .unwrap();
+    let EventMsg::BackgroundEvent(event) = ev.msg else {
+        panic!("expected a BackgroundEvent, got {:?}", ev.msg);
+    };
+    assert_eq!(
+        event.message,
+        "From the next turn: model other-model (other)"
+    );
+
+    submit_and_wait(&codex, "second").await;
+
+    let requests = server.received_requests().await.unwrap();
+    let chat_request: serde_json::Value = requests
+        .iter()
+        .find(|request| request.url.path() == "/v1/chat/completions")
+        .unwrap()
+        .body_json()
+        .unwrap();
+    assert_eq!(chat_request["model"], "other-model");
+    let user_messages: Vec<&serde_json::Value> = chat_request["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|message| message["role"] == "user")
+        .map(|message| &message["content"])
+        .collect();
+    assert_eq!(user_messages, ["first", "second"]);
+}
@@ -0,0 +1,290 @@
+//! Patch approvals end to end: patches sent through the native `apply_patch`
+//! tool are approved like any other, and answering a patch approval request
+//! with an edited command is treated as a rejection: there is no command to
+//! edit, so nothing is applied.
+
+use std::time::Duration;
+
+use codex_core::Codex;
+use codex_core::ModelProviderInfo;
+use codex_core::WireApi;
+use codex_core::exec::CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR;
+use codex_core::protocol::AskForApproval;
+use codex_core::protocol::EventMsg;
+use codex_core::protocol::InputItem;
+use codex_core::protocol::Op;
+use codex_core::protocol::ReviewDecision;
+mod test_support;
+use serde_json::json;
+use tempfile::TempDir;
+use test_support::load_default_config_for_test;
+use tokio::time::timeout;
+use wiremock::Mock;
+use wiremock::MockServer;
+use wiremock::ResponseTemplate;
+use wiremock::matchers::method;
+use wiremock::matchers::path;
+
+fn sse(events: &[serde_json::Value]) -> String {
+    events
+        .iter()
+        .map(|event| {
+            format!(
+                "event: {}\ndata: {event}\n\n",
+                event["type"].as_str().unwrap_or("")
+            )
+        })
+        .collect()
+}
+
+/// Serves `first` for the first model request and `second` for every later
+/// one.
+async fn mount_two_turns(server: &MockServer, first: String, second: String) {
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(first, "text/event-stream"),
+        )
+        .up_to_n_times(1)
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(second, "text/event-stream"),
+        )
+        .mount(server)
+        .await;
+}
+
+fn mock_provider(server: &MockServer) -> ModelProviderInfo {
+    ModelProviderInfo {
+        name: "openai".into(),
+        base_url: format!("{}/v1", server.uri()),
+        // Environment variable that should exist in the test environment.
+        env_key: Some("PATH".into()),
+        env_key_instructions: None,
+        wire_api: WireApi::Responses,
+        api_key_header: None,
+        query_params: None,
+        http_headers: None,
+        env_http_headers: None,
+        request_max_retries: Some(0),
+        stream_max_retries: Some(0),
+        stream_idle_timeout_ms: Some(2_000),
+    }
+}
+
+fn assistant_reply(text: &str) -> String {
+    sse(&[
+        json!({
+            "type": "response.output_item.done",
+            "item": {
+                "type": "message",
+                "role": "assistant",
+                "content": [{ "type": "output_text", "text": text }],
+            },
+        }),
+        json!({ "type": "response.completed", "response": { "id": "resp2", "output": [] } }),
+    ])
+}
+
+/// The `function_call_output` sent back to the model in the last request.
+async fn last_tool_output(server: &MockServer) -> serde_json::Value {
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = requests.last().unwrap().body_json().unwrap();
+    body["input"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|item| item["type"] == "function_call_output")
+        .unwrap()
+        .clone()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn apply_patch_tool_call_is_approved_and_applied() {
+    #![allow(clippy::unwrap_used)]
+
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let workdir = TempDir::new().unwrap();
+    let patch = "*** Begin Patch\n*** Add File: created.txt\n+hello\n*** End Patch";
+    let arguments = json!({ "input": patch }).to_string();
+
+    let server = MockServer::start().await;
+    let first = sse(&[
+        json!({
+            "type": "response.output_item.done",
+            "item": {
+                "type": "function_call",
+                "name": "apply_patch",
+                "arguments": arguments,
+                "call_id": "call1",
+            },
+        }),
+        json!({ "type": "response.completed", "response": { "id": "resp1", "output": [] } }),
+    ]);
+    mount_two_turns(&server, first, assistant_reply("done")).await;
+
+    let codex_home = TempDir::new().unwrap();
+    let mut config = load_default_config_for_test(&codex_home);
+    config.cwd = workdir.path().to_path_buf();
+    config.approval_policy = AskForApproval::UnlessAllowListed;
+    config.include_apply_patch_tool = true;
+    config.model_provider = mock_provider(&server);
+    let ctrl_c = std::sync::Arc::new(tokio::sync::Notify::new());
+    let (codex, _init_id) = Codex::spawn(config, ctrl_c).await.unwrap();
+
+    codex
+        .submit(Op::UserInput {
+            items: vec![InputItem::Text {
+                text: "create a file".into(),
+            }],
+        })
+        .await
+        .unwrap();
+    let mut approval_requested = false;
+    let mut applied = None;
+    loop {
+        let ev = timeout(Duration::from_secs(10), codex.next_event())
+            .await
+            .unwrap()
+            .unwrap();
+        match ev.msg {
+            EventMsg::ApplyPatchApprovalRequest(request) => {
+                approval_requested = true;
+                assert_eq!(
+                    request.changes.keys().collect::<Vec<_>>(),
+                    vec![&workdir.path().join("created.txt")]
+                );
+                codex
+                    .submit(Op::PatchApproval {
+                        id: ev.id,
+                        decision: ReviewDecision::Approved,
+                        rejected_changes: Vec::new(),
+                    })
+                    .await
+                    .unwrap();
+            }
+            EventMsg::PatchApplyEnd(end) => applied = Some(end.success),
+            EventMsg::TaskComplete(_) => break,
+            EventMsg::Error(err) => panic!("turn failed: {}", err.message),
+            _ => {}
+        }
+    }
+
+    assert!(approval_requested, "the patch should need approval");
+    assert_eq!(applied, Some(true));
+    assert_eq!(
+        std::fs::read_to_string(workdir.path().join("created.txt")).unwrap(),
+        "hello\n"
+    );
+    let requests = server.received_requests().await.unwrap();
+    let first_request: serde_json::Value = requests[0].body_json().unwrap();
+    assert!(
+        first_request["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|tool| tool["name"] == "apply_patch"),
+        "the apply_patch tool should be offered"
+    );
+    let output = last_tool_output(&server).await;
+    assert_eq!(output["call_id"], "call1");
+    assert!(
+        output["output"].as_str().unwrap().contains("created.txt"),
+        "unexpected tool output: {output}"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn modified_command_does_not_approve_a_patch() {
+    #![allow(clippy::unwrap_used)]
+
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let workdir = TempDir::new().unwrap();
+    let patch = "*** Begin Patch\n*** Add File: created.txt\n+hello\n*** End Patch";
+    let arguments = json!({ "command": ["apply_patch", patch] }).to_string();
+
+    let server = MockServer::start().await;
+    let first = sse(&[
+        json!({
+            "type": "response.output_item.done",
+            "item": {
+                "type": "function_call",
+                "name": "shell",
+                "arguments": arguments,
+                "call_id": "call1",
+            },
+        }),
+        json!({ "type": "response.completed", "response": { "id": "resp1", "output": [] } }),
+    ]);
+    mount_two_turns(&server, first, assistant_reply("ok")).await;
+
+    let codex_home = TempDir::new().unwrap();
+    let mut config = load_default_config_for_test(&codex_home);
+    config.cwd = workdir.path().to_path_buf();
+    config.approval_policy = AskForApproval::UnlessAllowListed;
+    config.model_provider = mock_provider(&server);
+    let ctrl_c = std::sync::Arc::new(tokio::sync::Notify::new());
+    let (codex, _init_id) = Codex::spawn(config, ctrl_c).await.unwrap();
+
+    codex
+        .submit(Op::UserInput {
+            items: vec![InputItem::Text {
+                text: "create a file".into(),
+            }],
+        })
+        .await
+        .unwrap();
+    loop {
+        let ev = timeout(Duration::from_secs(10), codex.next_event())
+            .await
+            .unwrap()
+            .unwrap();
+        match ev.msg {
+            EventMsg::ApplyPatchApprovalRequest(_) => {
+                codex
+                    .submit(Op::PatchApproval {
+                        id: ev.id,
+                        decision: ReviewDecision::ApprovedModifiedCommand {
+                            command: vec!["true".to_string()],
+                        },
+                        rejected_changes: Vec::new(),
+                    })
+                    .await
+                    .unwrap();
+            }
+            EventMsg::PatchApplyBegin(_) => panic!("patch must not be applied"),
+            EventMsg::TaskComplete(_) => break,
+            EventMsg::Error(err) => panic!("turn failed: {}", err.message),
+            _ => {}
+        }
+    }
+
+    assert!(!workdir.path().join("created.txt").exists());
+    let output = last_tool_output(&server).await;
+    assert!(
+        output["output"]
+            .as_str()
+            .unwrap()
+            .starts_with("patch rejected: the approval named a modified command"),
+        "unexpected tool output: {output}"
+    );
+}
@@ -0,0 +1,224 @@
+//! A task that reaches a limit in `budget` is stopped.
+
+use std::time::Duration;
+
+use codex_core::Codex;
+use codex_core::ModelProviderInfo;
+use codex_core::WireApi;
+use codex_core::config_types::RunBudget;
+use codex_core::exec::CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR;
+use codex_core::protocol::AskForApproval;
+use codex_core::protocol::BudgetLimit;
+use codex_core::protocol::EventMsg;
+use codex_core::protocol::InputItem;
+use codex_core::protocol::Op;
+use codex_core::protocol::ReviewDecision;
+mod test_support;
+use serde_json::json;
+use tempfile::TempDir;
+use test_support::load_default_config_for_test;
+use tokio::time::timeout;
+use wiremock::Mock;
+use wiremock::MockServer;
+use wiremock::ResponseTemplate;
+use wiremock::matchers::method;
+use wiremock::matchers::path;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn stops_a_task_that_reaches_max_turns() {
+    #![allow(clippy::unwrap_used)]
+
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    // Every response asks for another tool call, so the task never ends on
+    // its own.
+    let server = MockServer::start().await;
+    let sse = "event: response.output_item.done\n\
+data: {\"type\":\"response.output_item.done\",\"item\":{\"type\":\"function_call\",\"name\":\"no_such_tool\",\"arguments\":\"{}\",\"call_id\":\"call1\"}}\n\n\
+event: response.completed\n\
+data: {\"type\":\"response.completed\",\"response\":{\"id\":\"resp1\",\"output\":[],\"usage\":{\"input_tokens\":10,\"output_tokens\":5,\"total_tokens\":15}}}\n\n";
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(sse, "text/event-stream"),
+        )
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let codex_home = TempDir::new().unwrap();
+    let mut config = load_default_config_for_test(&codex_home);
+    config.model_provider = ModelProviderInfo {
+        name: "openai".into(),
+        base_url: format!("{}/v1", server.uri()),
+        // Environment variable that should exist in the test environment.
+        env_key: Some("PATH".into()),
+        env_key_instructions: None,
+        wire_api: WireApi::Responses,
+        api_key_header: None,
+        query_params: None,
+        http_headers: None,
+        env_http_headers: None,
+        request_max_retries: Some(0),
+        stream_max_retries: Some(0),
+        stream_idle_timeout_ms: Some(2_000),
+    };
+    config.budget = RunBudget {
+        max_turns: Some(2),
+        ..Default::default()
+    };
+    let ctrl_c = std::sync::Arc::new(tokio::sync::Notify::new());
+    let (codex, _init_id) = Codex::spawn(config, ctrl_c).await.unwrap();
+
+    let task_id = codex
+        .submit(Op::UserInput {
+            items: vec![InputItem::Text {
+                text: "hello".into(),
+            }],
+        })
+        .await
+        .unwrap();
+    let exceeded = loop {
+        let ev = timeout(Duration::from_secs(10), codex.next_event())
+            .await
+            .unwrap()
+            .unwrap();
+        match ev.msg {
+            EventMsg::BudgetExceeded(exceeded) => {
+                assert_eq!(ev.id, task_id);
+                break exceeded;
+            }
+            EventMsg::TaskComplete(_) => panic!("task should not complete"),
+            EventMsg::Error(err) => panic!("turn failed: {}", err.message),
+            _ => {}
+        }
+    };
+    assert_eq!(exceeded.limit, BudgetLimit::MaxTurns);
+    assert_eq!(exceeded.message, "stopped after 2 turns");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn tool_calls_past_max_tool_calls_are_not_run() {
+    #![allow(clippy::unwrap_used)]
+
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    // One response asks for three patches; only the first two may be
+    // applied, each of which first asks for approval.
+    let mut sse = String::new();
+    for call_id in ["call1", "call2", "call3"] {
+        let event = json!({
+            "type": "response.output_item.done",
+            "item": {
+                "type": "function_call",
+                "name": "shell",
+                "arguments": json!({
+                    "command": [
+                        "apply_patch",
+                        format!("*** Begin Patch\n*** Add File: {call_id}.txt\n+x\n*** End Patch"),
+                    ],
+                })
+                .to_string(),
+                "call_id": call_id,
+            },
+        });
+        sse.push_str(&format!(
+            "event: response.output_item.done\ndata: {event}\n\n"
+        ));
+    }
+    let completed =
+        json!({ "type": "response.completed", "response": { "id": "resp1", "output": [] } });
+    sse.push_str(&format!("event: response.completed\ndata: {completed}\n\n"));
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(sse, "text/event-stream"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let codex_home = TempDir::new().unwrap();
+    let workdir = TempDir::new().unwrap();
+    let mut config = load_default_config_for_test(&codex_home);
+    config.cwd = workdir.path().to_path_buf();
+    config.approval_policy = AskForApproval::UnlessAllowListed;
+    config.model_provider = ModelProviderInfo {
+        name: "openai".into(),
+        base_url: format!("{}/v1", server.uri()),
+        // Environment variable that should exist in the test environment.
+        env_key: Some("PATH".into()),
+        env_key_instructions: None,
+        wire_api: WireApi::Responses,
+        api_key_header: None,
+        query_params: None,
+        http_headers: None,
+        env_http_headers: None,
+        request_max_retries: Some(0),
+        stream_max_retries: Some(0),
+        stream_idle_timeout_ms: Some(2_000),
+    };
+    config.budget = RunBudget {
+        max_tool_calls: Some(2),
+        ..Default::default()
+    };
+    let ctrl_c = std::sync::Arc::new(tokio::sync::Notify::new());
+    let (codex, _init_id) = Codex::spawn(config, ctrl_c).await.unwrap();
+
+    codex
+        .submit(Op::UserInput {
+            items: vec![InputItem::Text {
+                text: "hello".into(),
+            }],
+        })
+        .await
+        .unwrap();
+    let mut approval_requests = Vec::new();
+    let exceeded = loop {
+        let ev = timeout(Duration::from_secs(10), codex.next_event())
+            .await
+            .unwrap()
+            .unwrap();
+        match ev.msg {
+            EventMsg::ApplyPatchApprovalRequest(request) => {
+                approval_requests.extend(request.changes.into_keys());
+                codex
+                    .submit(Op::PatchApproval {
+                        id: ev.id,
+                        decision: ReviewDecision::Denied,
+                        rejected_changes: Vec::new(),
+                    })
+                    .await
+                    .unwrap();
+            }
+            EventMsg::BudgetExceeded(exceeded) => break exceeded,
+            EventMsg::TaskComplete(_) => panic!("task should not complete"),
+            EventMsg::Error(err) => panic!("turn failed: {}", err.message),
+            _ => {}
+        }
+    };
+    assert_eq!(
+        approval_requests,
+        vec![
+            workdir.path().join("call1.txt"),
+            workdir.path().join("call2.txt")
+        ]
+    );
+    assert_eq!(exceeded.limit, BudgetLimit::MaxToolCalls);
+}
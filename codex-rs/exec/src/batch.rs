@@ -0,0 +1,467 @@
+//! `codex exec --batch`: run many prompts, each in its own session.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::Context;
+use codex_core::Codex;
+use codex_core::codex_wrapper;
+use codex_core::config::Config;
+use codex_core::config::ConfigOverrides;
+use codex_core::config_types::SandboxMode;
+use codex_core::protocol::BudgetExceededEvent;
+use codex_core::protocol::ErrorEvent;
+use codex_core::protocol::EventMsg;
+use codex_core::protocol::InputItem;
+use codex_core::protocol::Op;
+use codex_core::protocol::TaskCompleteEvent;
+use codex_core::util::is_inside_git_repo;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::sync::Notify;
+use tokio::sync::Semaphore;
+use tokio::sync::watch;
+use tokio::task;
+use tokio::task::JoinSet;
+use toml::Value as TomlValue;
+
+use crate::NOTIFICATION_FLUSH_TIMEOUT;
+use crate::run_outcome::RunOutcome;
+
+/// Exit code of a batch in which at least one task did not complete.
+pub(crate) const BATCH_INCOMPLETE_EXIT_CODE: i32 = 5;
+
+/// One line of the `--batch` file. Unset fields fall back to the options
+/// given on the command line.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BatchTask {
+    /// Identifies the task in its result record. Defaults to the task's line
+    /// number in the file.
+    id: Option<String>,
+    prompt: String,
+    cwd: Option<PathBuf>,
+    model: Option<String>,
+    provider: Option<String>,
+    profile: Option<String>,
+    sandbox: Option<SandboxMode>,
+    /// Same as `-c key=value`, e.g. `{"budget.max_turns": 10}`. Applied after
+    /// the `-c` overrides from the command line.
+    #[serde(default)]
+    config: BTreeMap<String, TomlValue>,
+}
+
+/// Printed to stdout as each task finishes.
+#[derive(Debug, Serialize)]
+struct TaskResult {
+    r#type: &'static str,
+    id: String,
+    status: RunOutcome,
+    exit_code: i32,
+    /// `None` if the task failed before its session started.
+    session_id: Option<String>,
+    cwd: Option<PathBuf>,
+    last_agent_message: Option<String>,
+    /// Why the task did not complete.
+    error: Option<String>,
+    duration_ms: u128,
+}
+
+impl TaskResult {
+    /// A result for a task that has not run (yet), reported as an error.
+    fn new(id: String) -> Self {
+        Self {
+            r#type: "task_result",
+            id,
+            status: RunOutcome::Error,
+            exit_code: RunOutcome::Error.exit_code(),
+            session_id: None,
+            cwd: None,
+            last_agent_message: None,
+            error: None,
+            duration_ms: 0,
+        }
+    }
+}
+
+/// Printed to stdout after the last task.
+#[derive(Debug, Serialize)]
+struct BatchSummary {
+    r#type: &'static str,
+    total: usize,
+    completed: usize,
+    exit_code: i32,
+}
+
+/// Options shared by every task in the batch.
+pub(crate) struct BatchDefaults {
+    pub(crate) overrides: ConfigOverrides,
+    pub(crate) cli_kv_overrides: Vec<(String, TomlValue)>,
+    pub(crate) skip_git_repo_check: bool,
+}
+
+/// Parses the batch file: one JSON object per line, blank lines ignored.
+/// Returns each task with its id.
+pub(crate) fn load_batch_file(path: &Path) -> anyhow::Result<Vec<(String, BatchTask)>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    parse_batch(&contents).with_context(|| format!("invalid batch file {}", path.display()))
+}
+
+fn parse_batch(contents: &str) -> anyhow::Result<Vec<(String, BatchTask)>> {
+    let mut tasks = Vec::new();
+    for (idx, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_number = idx + 1;
+        let task: BatchTask =
+            serde_json::from_str(line).with_context(|| format!("line {line_number}"))?;
+        let id = task.id.clone().unwrap_or_else(|| line_number.to_string());
+        tasks.push((id, task));
+    }
+    if tasks.is_empty() {
+        anyhow::bail!("no tasks");
+    }
+    Ok(tasks)
+}
+
+impl BatchTask {
+    /// The command-line options with this task's settings applied on top.
+    fn apply_to(
+        &mut self,
+        defaults: &BatchDefaults,
+    ) -> (ConfigOverrides, Vec<(String, TomlValue)>) {
+        let mut overrides = defaults.overrides.clone();
+        if let Some(cwd) = self.cwd.take() {
+            overrides.cwd = Some(cwd.canonicalize().unwrap_or(cwd));
+        }
+        if let Some(model) = self.model.take() {
+            overrides.model = Some(model);
+        }
+        if let Some(provider) = self.provider.take() {
+            overrides.model_provider = Some(provider);
+        }
+        if let Some(profile) = self.profile.take() {
+            overrides.config_profile = Some(profile);
+        }
+        if let Some(sandbox) = self.sandbox {
+            // The task's sandbox wins over --full-auto/--sandbox-permission.
+            overrides.sandbox_policy = None;
+            overrides.sandbox_mode = Some(sandbox);
+        }
+        let mut kv_overrides = defaults.cli_kv_overrides.clone();
+        kv_overrides.extend(std::mem::take(&mut self.config));
+        (overrides, kv_overrides)
+    }
+}
+
+/// Runs `tasks`, at most `concurrency` at a time, printing a [`TaskResult`]
+/// per task and then a [`BatchSummary`]. Returns the exit code for the
+/// whole batch. Ctrl-C interrupts the running tasks and skips the queued ones.
+pub(crate) async fn run_batch(
+    tasks: Vec<(String, BatchTask)>,
+    concurrency: usize,
+    defaults: BatchDefaults,
+) -> i32 {
+    let (interrupt_tx, interrupt_rx) = watch::channel(false);
+    let sigint_listener = tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            interrupt_tx.send_replace(true);
+        }
+    });
+    let exit_code = run_tasks(tasks, concurrency, defaults, interrupt_rx).await;
+    sigint_listener.abort();
+    exit_code
+}
+
+async fn run_tasks(
+    tasks: Vec<(String, BatchTask)>,
+    concurrency: usize,
+    defaults: BatchDefaults,
+    interrupt: watch::Receiver<bool>,
+) -> i32 {
+    let defaults = Arc::new(defaults);
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut join_set = JoinSet::new();
+    let mut task_ids = HashMap::new();
+    for (id, task) in tasks {
+        let defaults = Arc::clone(&defaults);
+        let semaphore = Arc::clone(&semaphore);
+        let interrupt = interrupt.clone();
+        let handle = join_set.spawn({
+            let id = id.clone();
+            async move {
+                let _permit = semaphore.acquire_owned().await;
+                run_batch_task(id, task, &defaults, interrupt).await
+            }
+        });
+        task_ids.insert(handle.id(), id);
+    }
+    report_results(join_set, task_ids).await
+}
+
+/// Prints a [`TaskResult`] as each task in `join_set` finishes, then the
+/// [`BatchSummary`], and returns the exit code for the whole batch.
+/// `task_ids` maps each spawned task to its batch task id so that a task
+/// that panicked still gets a result record.
+#[allow(clippy::print_stdout)]
+async fn report_results(
+    mut join_set: JoinSet<TaskResult>,
+    mut task_ids: HashMap<task::Id, String>,
+) -> i32 {
+    let total = task_ids.len();
+    let mut completed = 0;
+    let mut interrupted = false;
+    while let Some(joined) = join_set.join_next_with_id().await {
+        let result = match joined {
+            Ok((_, result)) => result,
+            Err(e) => {
+                let id = task_ids.remove(&e.id()).unwrap_or_default();
+                eprintln!("Batch task {id} panicked: {e}");
+                TaskResult {
+                    error: Some(format!("the task panicked: {e}")),
+                    ..TaskResult::new(id)
+                }
+            }
+        };
+        match result.status {
+            RunOutcome::Completed => completed += 1,
+            RunOutcome::Interrupted => interrupted = true,
+            RunOutcome::Error | RunOutcome::BudgetExceeded | RunOutcome::SchemaViolation => {}
+        }
+        match serde_json::to_string(&result) {
+            Ok(line) => println!("{line}"),
+            Err(e) => eprintln!("Failed to serialize task result: {e}"),
+        }
+    }
+
+    let exit_code = if interrupted {
+        RunOutcome::Interrupted.exit_code()
+    } else if completed < total {
+        BATCH_INCOMPLETE_EXIT_CODE
+    } else {
+        RunOutcome::Completed.exit_code()
+    };
+    let summary = BatchSummary {
+        r#type: "batch_summary",
+        total,
+        completed,
+        exit_code,
+    };
+    match serde_json::to_string(&summary) {
+        Ok(line) => println!("{line}"),
+        Err(e) => eprintln!("Failed to serialize batch summary: {e}"),
+    }
+    exit_code
+}
+
+async fn run_batch_task(
+    id: String,
+    mut task: BatchTask,
+    defaults: &BatchDefaults,
+    mut interrupt: watch::Receiver<bool>,
+) -> TaskResult {
+    let start_time = Instant::now();
+    let mut result = TaskResult::new(id);
+    if *interrupt.borrow() {
+        result.status = RunOutcome::Interrupted;
+        result.error = Some("not started: the batch was interrupted".to_string());
+    } else if let Err(e) = drive_batch_task(&mut task, defaults, &mut interrupt, &mut result).await
+    {
+        result.status = RunOutcome::Error;
+        result.error = Some(format!("{e:#}"));
+    }
+    result.exit_code = result.status.exit_code();
+    result.duration_ms = start_time.elapsed().as_millis();
+    result
+}
+
+/// Runs one task in a fresh session, filling in `result` as it goes.
+async fn drive_batch_task(
+    task: &mut BatchTask,
+    defaults: &BatchDefaults,
+    interrupt: &mut watch::Receiver<bool>,
+    result: &mut TaskResult,
+) -> anyhow::Result<()> {
+    let (overrides, kv_overrides) = task.apply_to(defaults);
+    let config = Config::load_with_cli_overrides(kv_overrides, overrides)?;
+    result.cwd = Some(config.cwd.clone());
+    if !defaults.skip_git_repo_check && !is_inside_git_repo(&config) {
+        anyhow::bail!("Not inside a Git repo and --skip-git-repo-check was not specified.");
+    }
+
+    // Ctrl-C is handled once for the whole batch, through `interrupt`.
+    let (codex, event) =
+        codex_wrapper::init_codex_with_ctrl_c(config, Arc::new(Notify::new())).await?;
+    if let EventMsg::SessionConfigured(session_configured) = event.msg {
+        result.session_id = Some(session_configured.session_id.to_string());
+    }
+    let prompt = std::mem::take(&mut task.prompt);
+    let task_id = codex
+        .submit(Op::UserInput {
+            items: vec![InputItem::Text { text: prompt }],
+        })
+        .await?;
+
+    let finished = wait_for_task(&codex, interrupt, &task_id, result).await;
+    codex.flush_notifications(NOTIFICATION_FLUSH_TIMEOUT).await;
+    finished
+}
+
+/// Follows the task's events until it ends, recording how it went.
+async fn wait_for_task(
+    codex: &Codex,
+    interrupt: &mut watch::Receiver<bool>,
+    task_id: &str,
+    result: &mut TaskResult,
+) -> anyhow::Result<()> {
+    loop {
+        let event = tokio::select! {
+            _ = interrupted(interrupt) => {
+                codex.submit(Op::Interrupt).await.ok();
+                result.status = RunOutcome::Interrupted;
+                return Ok(());
+            }
+            event = codex.next_event() => event?,
+        };
+        match &event.msg {
+            EventMsg::TaskComplete(TaskCompleteEvent { last_agent_message }) => {
+                result.last_agent_message = last_agent_message.clone();
+            }
+            EventMsg::Error(ErrorEvent { message })
+            | EventMsg::BudgetExceeded(BudgetExceededEvent { message, .. }) => {
+                result.error = Some(message.clone());
+            }
+            _ => {}
+        }
+        if let Some(outcome) = RunOutcome::from_event(&event, task_id) {
+            result.status = outcome;
+            return Ok(());
+        }
+    }
+}
+
+/// Resolves once the batch is interrupted, and never if it cannot be.
+async fn interrupted(interrupt: &mut watch::Receiver<bool>) {
+    if interrupt
+        .wait_for(|interrupted| *interrupted)
+        .await
+        .is_err()
+    {
+        std::future::pending::<()>().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parses_tasks_and_defaults_ids_to_line_numbers() {
+        let contents = r#"{"prompt": "fix the build", "cwd": "/repos/a"}
+
+{"id": "b", "prompt": "bump deps", "sandbox": "workspace-write", "config": {"budget.max_turns": 10}}
+"#;
+        let tasks = parse_batch(contents).unwrap();
+        let ids: Vec<&str> = tasks.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["1", "b"]);
+        assert_eq!(tasks[1].1.sandbox, Some(SandboxMode::WorkspaceWrite));
+        assert_eq!(
+            tasks[1].1.config.get("budget.max_turns"),
+            Some(&TomlValue::Integer(10))
+        );
+
+        let err = parse_batch("{\"prompt\": \"x\"}\n{\"promt\": \"y\"}\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2");
+        assert!(parse_batch("\n").is_err());
+    }
+
+    #[test]
+    fn task_settings_override_the_command_line() {
+        let defaults = BatchDefaults {
+            overrides: ConfigOverrides {
+                model: Some("o3".to_string()),
+                sandbox_policy: Some(codex_core::protocol::SandboxPolicy::new_full_auto_policy()),
+                ..Default::default()
+            },
+            cli_kv_overrides: vec![("budget.max_turns".to_string(), TomlValue::Integer(5))],
+            skip_git_repo_check: false,
+        };
+        let (_, mut task) = parse_batch(
+            r#"{"prompt": "x", "model": "o4-mini", "sandbox": "read-only", "config": {"budget.max_turns": 10}}"#,
+        )
+        .unwrap()
+        .remove(0);
+
+        let (overrides, kv_overrides) = task.apply_to(&defaults);
+        assert_eq!(overrides.model.as_deref(), Some("o4-mini"));
+        assert_eq!(overrides.sandbox_policy, None);
+        assert_eq!(overrides.sandbox_mode, Some(SandboxMode::ReadOnly));
+        // Later overrides win when applied in order.
+        assert_eq!(
+            kv_overrides,
+            vec![
+                ("budget.max_turns".to_string(), TomlValue::Integer(5)),
+                ("budget.max_turns".to_string(), TomlValue::Integer(10)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn queued_tasks_are_skipped_once_interrupted() {
+        fn defaults() -> BatchDefaults {
+            BatchDefaults {
+                overrides: ConfigOverrides::default(),
+                cli_kv_overrides: Vec::new(),
+                skip_git_repo_check: false,
+            }
+        }
+        // Outside a Git repo, a task that started would fail instead.
+        let tasks = || {
+            parse_batch(
+                &r#"{"prompt": "x", "cwd": "/"}
+"#
+                .repeat(3),
+            )
+            .unwrap()
+        };
+        let (_interrupt_tx, interrupt) = watch::channel(true);
+
+        let (id, task) = tasks().remove(0);
+        let result = run_batch_task(id, task, &defaults(), interrupt.clone()).await;
+        assert_eq!(result.status, RunOutcome::Interrupted);
+        assert_eq!(result.session_id, None);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("not started: the batch was interrupted")
+        );
+
+        let exit_code = run_tasks(tasks(), 1, defaults(), interrupt).await;
+        assert_eq!(exit_code, RunOutcome::Interrupted.exit_code());
+    }
+
+    #[tokio::test]
+    async fn panicked_tasks_count_as_incomplete() {
+        let mut join_set = JoinSet::new();
+        let mut task_ids = HashMap::new();
+        let completed = join_set.spawn(async {
+            TaskResult {
+                status: RunOutcome::Completed,
+                ..TaskResult::new("ok".to_string())
+            }
+        });
+        task_ids.insert(completed.id(), "ok".to_string());
+        let panicked = join_set.spawn(async { panic!("boom") });
+        task_ids.insert(panicked.id(), "boom".to_string());
+
+        let exit_code = report_results(join_set, task_ids).await;
+        assert_eq!(exit_code, BATCH_INCOMPLETE_EXIT_CODE);
+    }
+}
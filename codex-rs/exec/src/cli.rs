@@ -1,11 +1,21 @@
 use clap::Parser;
 use clap::ValueEnum;
 use codex_common::CliConfigOverrides;
+use codex_common::SandboxModeCliArg;
 use codex_common::SandboxPermissionOption;
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
-#[command(version)]
+#[command(
+    version,
+    after_help = "Exit codes:
+  0    the agent completed the task
+  1    the task failed with an error (or codex exec could not start)
+  3    the final message did not match --output-schema
+  4    the task reached a limit from the `budget` config
+  5    --batch: at least one task did not complete
+  130  the run was interrupted with Ctrl-C"
+)]
 pub struct Cli {
     /// Optional image(s) to attach to the initial prompt.
     #[arg(long = "image", short = 'i', value_name = "FILE", value_delimiter = ',', num_args = 1..)]
@@ -27,6 +37,15 @@ pub struct Cli {
     #[arg(long = "full-auto", default_value_t = false)]
     pub full_auto: bool,
 
+    /// Select the sandbox for commands run by the model. Cannot be combined
+    /// with --full-auto or --sandbox-permission.
+    #[arg(
+        long = "sandbox",
+        value_name = "MODE",
+        conflicts_with_all = ["full_auto", "permissions"]
+    )]
+    pub sandbox_mode: Option<SandboxModeCliArg>,
+
     #[clap(flatten)]
     pub sandbox: SandboxPermissionOption,
 
@@ -49,10 +68,45 @@ pub struct Cli {
     #[arg(long = "color", value_enum, default_value_t = Color::Auto)]
     pub color: Color,
 
+    /// Print events to stdout as JSON Lines, one event per line, followed by a
+    /// summary of the run. Diagnostics are written to stderr. Every line has a
+    /// `version` field that is bumped whenever existing fields change; new
+    /// event types and fields may be added without a bump.
+    #[arg(long = "json", default_value_t = false)]
+    pub json: bool,
+
     /// Specifies file where the last message from the agent should be written.
     #[arg(long = "output-last-message")]
     pub last_message_file: Option<PathBuf>,
 
+    /// Path to a JSON Schema that the agent's final message must conform to.
+    /// The validated JSON is written to --output-last-message if given, and
+    /// to stdout otherwise (with --json, as the `output` field of the
+    /// summary line).
+    #[arg(long = "output-schema", value_name = "FILE")]
+    pub output_schema: Option<PathBuf>,
+
+    /// Run every task in a JSONL file instead of a single prompt. Each line is
+    /// an object with a "prompt" and optionally "id", "cwd", "model",
+    /// "provider", "profile", "sandbox" and "config" (a map of `-c`
+    /// overrides). Each task gets its own session; a JSON result record is
+    /// printed as each one finishes, followed by a summary.
+    #[arg(
+        long = "batch",
+        value_name = "FILE",
+        conflicts_with_all = ["prompt", "images", "json", "last_message_file", "output_schema"]
+    )]
+    pub batch: Option<PathBuf>,
+
+    /// Maximum number of --batch tasks to run at the same time.
+    #[arg(
+        long = "concurrency",
+        value_name = "N",
+        default_value_t = 4,
+        requires = "batch"
+    )]
+    pub concurrency: usize,
+
     /// Initial instructions for the agent. If not provided as an argument (or
     /// if `-` is used), instructions are read from stdin.
     #[arg(value_name = "PROMPT")]
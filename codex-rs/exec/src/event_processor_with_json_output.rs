@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use codex_core::config::Config;
+use codex_core::protocol::ErrorEvent;
+use codex_core::protocol::Event;
+use codex_core::protocol::EventMsg;
+use codex_core::protocol::ExecCommandBeginEvent;
+use codex_core::protocol::ExecCommandEndEvent;
+use codex_core::protocol::PatchApplyBeginEvent;
+use codex_core::protocol::PatchApplyEndEvent;
+use codex_core::protocol::TaskCompleteEvent;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::event_processor::EventProcessor;
+use crate::exec_events::ExecEventLine;
+use crate::exec_events::JSON_OUTPUT_VERSION;
+use crate::run_outcome::RunOutcome;
+
+/// Prints every event as one line of JSON in the schema defined by
+/// [`crate::exec_events`], followed by a final `{"version": 1, "type":
+/// "summary", ...}` line describing the whole run. With `--output-schema`, the validated JSON is the
+/// summary's `output` field, so stdout stays valid JSON Lines.
+#[derive(Default)]
+pub(crate) struct EventProcessorWithJsonOutput {
+    summary: RunSummary,
+    /// Index into `summary.commands` by call id, with the start time.
+    running_commands: HashMap<String, (usize, Instant)>,
+    /// Index into `summary.patches` by call id.
+    pending_patches: HashMap<String, usize>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct RunSummary {
+    last_agent_message: Option<String>,
+    commands: Vec<CommandSummary>,
+    patches: Vec<PatchSummary>,
+    errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CommandSummary {
+    call_id: String,
+    command: Vec<String>,
+    cwd: PathBuf,
+    /// `None` if the command never finished.
+    exit_code: Option<i32>,
+    duration_ms: Option<u128>,
+}
+
+#[derive(Debug, Serialize)]
+struct PatchSummary {
+    call_id: String,
+    auto_approved: bool,
+    files: Vec<PathBuf>,
+    /// `None` if the patch was never applied.
+    success: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct SummaryRecord<'a> {
+    version: u32,
+    r#type: &'static str,
+    status: RunOutcome,
+    exit_code: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<&'a Value>,
+    #[serde(flatten)]
+    summary: &'a RunSummary,
+}
+
+impl EventProcessorWithJsonOutput {
+    fn record(&mut self, msg: &EventMsg) {
+        match msg {
+            EventMsg::Error(ErrorEvent { message }) => {
+                self.summary.errors.push(message.clone());
+            }
+            EventMsg::ExecCommandBegin(ExecCommandBeginEvent {
+                call_id,
+                command,
+                cwd,
+            }) => {
+                self.running_commands.insert(
+                    call_id.clone(),
+                    (self.summary.commands.len(), Instant::now()),
+                );
+                self.summary.commands.push(CommandSummary {
+                    call_id: call_id.clone(),
+                    command: command.clone(),
+                    cwd: cwd.clone(),
+                    exit_code: None,
+                    duration_ms: None,
+                });
+            }
+            EventMsg::ExecCommandEnd(ExecCommandEndEvent {
+                call_id, exit_code, ..
+            }) => {
+                if let Some((idx, start_time)) = self.running_commands.remove(call_id) {
+                    let command = &mut self.summary.commands[idx];
+                    command.exit_code = Some(*exit_code);
+                    command.duration_ms = Some(start_time.elapsed().as_millis());
+                }
+            }
+            EventMsg::PatchApplyBegin(PatchApplyBeginEvent {
+                call_id,
+                auto_approved,
+                changes,
+            }) => {
+                let mut files: Vec<PathBuf> = changes.keys().cloned().collect();
+                files.sort();
+                self.pending_patches
+                    .insert(call_id.clone(), self.summary.patches.len());
+                self.summary.patches.push(PatchSummary {
+                    call_id: call_id.clone(),
+                    auto_approved: *auto_approved,
+                    files,
+                    success: None,
+                });
+            }
+            EventMsg::PatchApplyEnd(PatchApplyEndEvent {
+                call_id, success, ..
+            }) => {
+                if let Some(idx) = self.pending_patches.remove(call_id) {
+                    self.summary.patches[idx].success = Some(*success);
+                }
+            }
+            EventMsg::TaskComplete(TaskCompleteEvent { last_agent_message }) => {
+                self.summary.last_agent_message = last_agent_message.clone();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl EventProcessor for EventProcessorWithJsonOutput {
+    fn print_config_summary(&mut self, _config: &Config, _prompt: &str) {
+        // The `session_configured` line carries what a consumer needs.
+    }
+
+    #[allow(clippy::print_stdout)]
+    fn process_event(&mut self, event: Event) {
+        self.record(&event.msg);
+        let Some(line) = ExecEventLine::from_event(&event) else {
+            return;
+        };
+        match serde_json::to_string(&line) {
+            Ok(line) => println!("{line}"),
+            Err(e) => eprintln!("Failed to serialize event: {e}"),
+        }
+    }
+
+    #[allow(clippy::print_stdout)]
+    fn print_final_summary(&mut self, outcome: RunOutcome, structured_output: Option<&Value>) {
+        let record = SummaryRecord {
+            version: JSON_OUTPUT_VERSION,
+            r#type: "summary",
+            status: outcome,
+            exit_code: outcome.exit_code(),
+            output: structured_output,
+            summary: &self.summary,
+        };
+        match serde_json::to_string(&record) {
+            Ok(line) => println!("{line}"),
+            Err(e) => eprintln!("Failed to serialize summary: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use codex_core::protocol::ApprovalRulesEvent;
+    use codex_core::protocol::FileChange;
+    use codex_core::protocol::SessionConfiguredEvent;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn summarizes_commands_patches_and_errors() {
+        let mut processor = EventProcessorWithJsonOutput::default();
+        let events = [
+            EventMsg::ExecCommandBegin(ExecCommandBeginEvent {
+                call_id: "c1".to_string(),
+                command: vec!["cargo".to_string(), "test".to_string()],
+                cwd: PathBuf::from("/repo"),
+            }),
+            EventMsg::ExecCommandEnd(ExecCommandEndEvent {
+                call_id: "c1".to_string(),
+                stdout: String::new(),
+                stderr: String::new(),
+                exit_code: 101,
+            }),
+            EventMsg::PatchApplyBegin(PatchApplyBeginEvent {
+                call_id: "p1".to_string(),
+                auto_approved: true,
+                changes: HashMap::from([(PathBuf::from("/repo/a.rs"), FileChange::Delete)]),
+            }),
+            EventMsg::PatchApplyEnd(PatchApplyEndEvent {
+                call_id: "p1".to_string(),
+                stdout: String::new(),
+                stderr: String::new(),
+                success: true,
+            }),
+            EventMsg::Error(ErrorEvent {
+                message: "stream disconnected".to_string(),
+            }),
+            EventMsg::TaskComplete(TaskCompleteEvent {
+                last_agent_message: Some("done".to_string()),
+            }),
+        ];
+        for msg in events {
+            processor.record(&msg);
+        }
+
+        let mut record = serde_json::to_value(SummaryRecord {
+            version: JSON_OUTPUT_VERSION,
+            r#type: "summary",
+            status: RunOutcome::Error,
+            exit_code: RunOutcome::Error.exit_code(),
+            output: None,
+            summary: &processor.summary,
+        })
+        .unwrap();
+        assert!(record["commands"][0]["duration_ms"].is_u64());
+        record["commands"][0]["duration_ms"] = serde_json::Value::Null;
+        assert_eq!(
+            record,
+            serde_json::json!({
+                "version": 1,
+                "type": "summary",
+                "status": "error",
+                "exit_code": 1,
+                "last_agent_message": "done",
+                "commands": [{
+                    "call_id": "c1",
+                    "command": ["cargo", "test"],
+                    "cwd": "/repo",
+                    "exit_code": 101,
+                    "duration_ms": null,
+                }],
+                "patches": [{
+                    "call_id": "p1",
+                    "auto_approved": true,
+                    "files": ["/repo/a.rs"],
+                    "success": true,
+                }],
+                "errors": ["stream disconnected"],
+            })
+        );
+    }
+
+    #[test]
+    fn summary_carries_the_structured_output() {
+        let summary = RunSummary::default();
+        let output = serde_json::json!({"files_changed": 2});
+        let record = serde_json::to_value(SummaryRecord {
+            version: JSON_OUTPUT_VERSION,
+            r#type: "summary",
+            status: RunOutcome::Completed,
+            exit_code: 0,
+            output: Some(&output),
+            summary: &summary,
+        })
+        .unwrap();
+        assert_eq!(record["output"], output);
+
+        let record = serde_json::to_value(SummaryRecord {
+            version: JSON_OUTPUT_VERSION,
+            r#type: "summary",
+            status: RunOutcome::SchemaViolation,
+            exit_code: RunOutcome::SchemaViolation.exit_code(),
+            output: None,
+            summary: &summary,
+        })
+        .unwrap();
+        assert_eq!(record["status"], "schema_violation");
+        assert_eq!(record["exit_code"], 3);
+        assert!(record.get("output").is_none());
+    }
+
+    #[test]
+    fn events_use_the_exec_schema() {
+        let line = |msg| {
+            let event = Event {
+                id: "1".to_string(),
+                msg,
+            };
+            serde_json::to_value(ExecEventLine::from_event(&event)).unwrap()
+        };
+
+        let session_id = uuid::Uuid::nil();
+        assert_eq!(
+            line(EventMsg::SessionConfigured(SessionConfiguredEvent {
+                session_id,
+                model: "o3".to_string(),
+                history_log_id: 7,
+                history_entry_count: 3,
+            })),
+            serde_json::json!({
+                "version": 1,
+                "id": "1",
+                "type": "session_configured",
+                "session_id": session_id.to_string(),
+                "model": "o3",
+            })
+        );
+        assert_eq!(
+            line(EventMsg::ExecCommandEnd(ExecCommandEndEvent {
+                call_id: "c1".to_string(),
+                stdout: "ok".to_string(),
+                stderr: String::new(),
+                exit_code: 0,
+            })),
+            serde_json::json!({
+                "version": 1,
+                "id": "1",
+                "type": "command_end",
+                "call_id": "c1",
+                "exit_code": 0,
+                "stdout": "ok",
+                "stderr": "",
+            })
+        );
+        assert_eq!(
+            line(EventMsg::TaskComplete(TaskCompleteEvent {
+                last_agent_message: None,
+            })),
+            serde_json::json!({
+                "version": 1,
+                "id": "1",
+                "type": "task_complete",
+                "last_agent_message": null,
+            })
+        );
+        assert_eq!(
+            line(EventMsg::ApprovalRules(ApprovalRulesEvent {
+                project: PathBuf::from("/repo"),
+                rules: Vec::new(),
+            })),
+            serde_json::Value::Null
+        );
+    }
+}
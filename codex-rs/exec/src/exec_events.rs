@@ -0,0 +1,275 @@
+//! The event lines printed by `codex exec --json`.
+//!
+//! These types are the public contract of the JSON output and are kept apart
+//! from the internal protocol so that it can change freely. Field and type
+//! names here only change together with [`JSON_OUTPUT_VERSION`]; adding a new
+//! event type or a new field does not bump it, so consumers should ignore
+//! what they do not recognize.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use codex_core::protocol::AgentMessageEvent;
+use codex_core::protocol::AgentReasoningEvent;
+use codex_core::protocol::BackgroundEventEvent;
+use codex_core::protocol::BudgetExceededEvent;
+use codex_core::protocol::BudgetLimit;
+use codex_core::protocol::ErrorEvent;
+use codex_core::protocol::Event;
+use codex_core::protocol::EventMsg;
+use codex_core::protocol::ExecCommandBeginEvent;
+use codex_core::protocol::ExecCommandEndEvent;
+use codex_core::protocol::FileToolCallEvent;
+use codex_core::protocol::FileToolInvocation;
+use codex_core::protocol::McpToolCallBeginEvent;
+use codex_core::protocol::McpToolCallEndEvent;
+use codex_core::protocol::ModelFallbackEvent;
+use codex_core::protocol::PatchApplyBeginEvent;
+use codex_core::protocol::PatchApplyEndEvent;
+use codex_core::protocol::SessionConfiguredEvent;
+use codex_core::protocol::TaskCompleteEvent;
+use codex_core::protocol::TurnDiffEvent;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Version of the `--json` output, included in every line.
+pub(crate) const JSON_OUTPUT_VERSION: u32 = 1;
+
+/// One line of `--json` output for an event: `{"version": 1, "id": ...,
+/// "type": ..., ...}`. `id` is the submission the event belongs to.
+#[derive(Debug, Serialize)]
+pub(crate) struct ExecEventLine<'a> {
+    pub version: u32,
+    pub id: &'a str,
+    #[serde(flatten)]
+    pub event: ExecEvent<'a>,
+}
+
+impl<'a> ExecEventLine<'a> {
+    /// Returns `None` for events that have no meaning outside an interactive
+    /// session, such as approval requests and history lookups.
+    pub fn from_event(event: &'a Event) -> Option<Self> {
+        Some(Self {
+            version: JSON_OUTPUT_VERSION,
+            id: &event.id,
+            event: ExecEvent::from_msg(&event.msg)?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum ExecEvent<'a> {
+    SessionConfigured {
+        session_id: String,
+        model: &'a str,
+    },
+    TaskStarted,
+    AgentMessage {
+        message: &'a str,
+    },
+    AgentReasoning {
+        text: &'a str,
+    },
+    CommandBegin {
+        call_id: &'a str,
+        command: &'a [String],
+        cwd: &'a Path,
+    },
+    CommandEnd {
+        call_id: &'a str,
+        exit_code: i32,
+        stdout: &'a str,
+        stderr: &'a str,
+    },
+    PatchApplyBegin {
+        call_id: &'a str,
+        auto_approved: bool,
+        /// Every path the patch touches, sorted.
+        files: Vec<&'a Path>,
+    },
+    PatchApplyEnd {
+        call_id: &'a str,
+        success: bool,
+        stdout: &'a str,
+        stderr: &'a str,
+    },
+    McpToolCallBegin {
+        call_id: &'a str,
+        server: &'a str,
+        tool: &'a str,
+        arguments: Option<&'a Value>,
+    },
+    McpToolCallEnd {
+        call_id: &'a str,
+        success: bool,
+    },
+    FileToolCall {
+        call_id: &'a str,
+        /// `read_file`, `list_dir` or `grep`.
+        tool: &'static str,
+        path: &'a Path,
+        summary: &'a str,
+        success: bool,
+    },
+    TurnDiff {
+        unified_diff: &'a str,
+    },
+    ModelFallback {
+        model: &'a str,
+        provider: &'a str,
+        reason: &'a str,
+    },
+    BudgetExceeded {
+        /// `max_turns`, `max_tool_calls`, `max_duration_secs` or `max_tokens`.
+        limit: &'static str,
+        message: &'a str,
+    },
+    Background {
+        message: &'a str,
+    },
+    Error {
+        message: &'a str,
+    },
+    TaskComplete {
+        last_agent_message: Option<&'a str>,
+    },
+}
+
+impl<'a> ExecEvent<'a> {
+    fn from_msg(msg: &'a EventMsg) -> Option<Self> {
+        let event = match msg {
+            EventMsg::SessionConfigured(SessionConfiguredEvent {
+                session_id, model, ..
+            }) => ExecEvent::SessionConfigured {
+                session_id: session_id.to_string(),
+                model,
+            },
+            EventMsg::TaskStarted => ExecEvent::TaskStarted,
+            EventMsg::AgentMessage(AgentMessageEvent { message }) => {
+                ExecEvent::AgentMessage { message }
+            }
+            EventMsg::AgentReasoning(AgentReasoningEvent { text }) => {
+                ExecEvent::AgentReasoning { text }
+            }
+            EventMsg::ExecCommandBegin(ExecCommandBeginEvent {
+                call_id,
+                command,
+                cwd,
+            }) => ExecEvent::CommandBegin {
+                call_id,
+                command,
+                cwd,
+            },
+            EventMsg::ExecCommandEnd(ExecCommandEndEvent {
+                call_id,
+                stdout,
+                stderr,
+                exit_code,
+            }) => ExecEvent::CommandEnd {
+                call_id,
+                exit_code: *exit_code,
+                stdout,
+                stderr,
+            },
+            EventMsg::PatchApplyBegin(PatchApplyBeginEvent {
+                call_id,
+                auto_approved,
+                changes,
+            }) => {
+                let mut files: Vec<&Path> = changes.keys().map(PathBuf::as_path).collect();
+                files.sort();
+                ExecEvent::PatchApplyBegin {
+                    call_id,
+                    auto_approved: *auto_approved,
+                    files,
+                }
+            }
+            EventMsg::PatchApplyEnd(PatchApplyEndEvent {
+                call_id,
+                stdout,
+                stderr,
+                success,
+            }) => ExecEvent::PatchApplyEnd {
+                call_id,
+                success: *success,
+                stdout,
+                stderr,
+            },
+            EventMsg::McpToolCallBegin(McpToolCallBeginEvent {
+                call_id,
+                server,
+                tool,
+                arguments,
+            }) => ExecEvent::McpToolCallBegin {
+                call_id,
+                server,
+                tool,
+                arguments: arguments.as_ref(),
+            },
+            EventMsg::McpToolCallEnd(McpToolCallEndEvent { call_id, result }) => {
+                ExecEvent::McpToolCallEnd {
+                    call_id,
+                    success: result
+                        .as_ref()
+                        .is_ok_and(|result| result.is_error != Some(true)),
+                }
+            }
+            EventMsg::FileToolCall(FileToolCallEvent {
+                call_id,
+                invocation,
+                summary,
+                success,
+            }) => {
+                let (tool, path) = match invocation {
+                    FileToolInvocation::ReadFile { path, .. } => ("read_file", path),
+                    FileToolInvocation::ListDir { path, .. } => ("list_dir", path),
+                    FileToolInvocation::Grep { path, .. } => ("grep", path),
+                };
+                ExecEvent::FileToolCall {
+                    call_id,
+                    tool,
+                    path,
+                    summary,
+                    success: *success,
+                }
+            }
+            EventMsg::TurnDiff(TurnDiffEvent { unified_diff }) => {
+                ExecEvent::TurnDiff { unified_diff }
+            }
+            EventMsg::ModelFallback(ModelFallbackEvent {
+                model,
+                provider,
+                reason,
+            }) => ExecEvent::ModelFallback {
+                model,
+                provider,
+                reason,
+            },
+            EventMsg::BudgetExceeded(BudgetExceededEvent { limit, message }) => {
+                let limit = match limit {
+                    BudgetLimit::MaxTurns => "max_turns",
+                    BudgetLimit::MaxToolCalls => "max_tool_calls",
+                    BudgetLimit::MaxDurationSecs => "max_duration_secs",
+                    BudgetLimit::MaxTokens => "max_tokens",
+                };
+                ExecEvent::BudgetExceeded { limit, message }
+            }
+            EventMsg::BackgroundEvent(BackgroundEventEvent { message }) => {
+                ExecEvent::Background { message }
+            }
+            EventMsg::Error(ErrorEvent { message }) => ExecEvent::Error { message },
+            EventMsg::TaskComplete(TaskCompleteEvent { last_agent_message }) => {
+                ExecEvent::TaskComplete {
+                    last_agent_message: last_agent_message.as_deref(),
+                }
+            }
+            EventMsg::ExecApprovalRequest(_)
+            | EventMsg::ApplyPatchApprovalRequest(_)
+            | EventMsg::SessionDiff(_)
+            | EventMsg::ApprovalRules(_)
+            | EventMsg::GetHistoryEntryResponse(_) => return None,
+        };
+        Some(event)
+    }
+}
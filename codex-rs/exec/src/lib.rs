@@ -1,12 +1,20 @@
+mod batch;
 mod cli;
 mod event_processor;
+mod event_processor_with_human_output;
+mod event_processor_with_json_output;
+mod exec_events;
+mod output_schema;
+mod run_outcome;
 
 use std::io::IsTerminal;
 use std::io::Read;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
+use batch::BatchDefaults;
 pub use cli::Cli;
 use codex_core::codex_wrapper;
 use codex_core::config::Config;
@@ -20,11 +28,18 @@ use codex_core::protocol::SandboxPolicy;
 use codex_core::protocol::TaskCompleteEvent;
 use codex_core::util::is_inside_git_repo;
 use event_processor::EventProcessor;
+use event_processor_with_human_output::EventProcessorWithHumanOutput;
+use event_processor_with_json_output::EventProcessorWithJsonOutput;
+use output_schema::OutputSchema;
+use run_outcome::RunOutcome;
 use tracing::debug;
 use tracing::error;
 use tracing::info;
 use tracing_subscriber::EnvFilter;
 
+/// How long to wait for queued webhook notifications before exiting.
+const NOTIFICATION_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub async fn run_main(cli: Cli, codex_linux_sandbox_exe: Option<PathBuf>) -> anyhow::Result<()> {
     let Cli {
         images,
@@ -32,51 +47,21 @@ pub async fn run_main(cli: Cli, codex_linux_sandbox_exe: Option<PathBuf>) -> any
         provider,
         config_profile,
         full_auto,
+        sandbox_mode,
         sandbox,
         cwd,
         skip_git_repo_check,
         stream,
         color,
+        json,
         last_message_file,
+        output_schema: output_schema_path,
+        batch,
+        concurrency,
         prompt,
         config_overrides,
     } = cli;
 
-    // Determine the prompt based on CLI arg and/or stdin.
-    let prompt = match prompt {
-        Some(p) if p != "-" => p,
-        // Either `-` was passed or no positional arg.
-        maybe_dash => {
-            // When no arg (None) **and** stdin is a TTY, bail out early – unless the
-            // user explicitly forced reading via `-`.
-            let force_stdin = matches!(maybe_dash.as_deref(), Some("-"));
-
-            if std::io::stdin().is_terminal() && !force_stdin {
-                eprintln!(
-                    "No prompt provided. Either specify one as an argument or pipe the prompt into stdin."
-                );
-                std::process::exit(1);
-            }
-
-            // Ensure the user knows we are waiting on stdin, as they may
-            // have gotten into this state by mistake. If so, and they are not
-            // writing to stdin, Codex will hang indefinitely, so this should
-            // help them debug in that case.
-            if !force_stdin {
-                eprintln!("Reading prompt from stdin...");
-            }
-            let mut buffer = String::new();
-            if let Err(e) = std::io::stdin().read_to_string(&mut buffer) {
-                eprintln!("Failed to read prompt from stdin: {e}");
-                std::process::exit(1);
-            } else if buffer.trim().is_empty() {
-                eprintln!("No prompt provided via stdin.");
-                std::process::exit(1);
-            }
-            buffer
-        }
-    };
-
     let (stdout_with_ansi, stderr_with_ansi) = match color {
         cli::Color::Always => (true, true),
         cli::Color::Never => (false, false),
@@ -86,12 +71,35 @@ pub async fn run_main(cli: Cli, codex_linux_sandbox_exe: Option<PathBuf>) -> any
         ),
     };
 
+    // TODO(mbolin): Take a more thoughtful approach to logging.
+    let default_level = "error";
+    let _ = tracing_subscriber::fmt()
+        // Fallback to the `default_level` log filter if the environment
+        // variable is not set _or_ contains an invalid value
+        .with_env_filter(
+            EnvFilter::try_from_default_env()
+                .or_else(|_| EnvFilter::try_new(default_level))
+                .unwrap_or_else(|_| EnvFilter::new(default_level)),
+        )
+        .with_ansi(stderr_with_ansi)
+        .with_writer(std::io::stderr)
+        .try_init();
+
     let sandbox_policy = if full_auto {
         Some(SandboxPolicy::new_full_auto_policy())
     } else {
         sandbox.permissions.clone().map(Into::into)
     };
 
+    let output_schema = match output_schema_path.as_deref().map(OutputSchema::load) {
+        Some(Ok(output_schema)) => Some(output_schema),
+        Some(Err(e)) => {
+            eprintln!("Error loading --output-schema: {e:#}");
+            std::process::exit(1);
+        }
+        None => None,
+    };
+
     // Load configuration and determine approval policy
     let overrides = ConfigOverrides {
         model,
@@ -100,10 +108,12 @@ pub async fn run_main(cli: Cli, codex_linux_sandbox_exe: Option<PathBuf>) -> any
         // the user for approval.
         approval_policy: Some(AskForApproval::Never),
         sandbox_policy,
+        sandbox_mode: sandbox_mode.map(Into::into),
         cwd: cwd.map(|p| p.canonicalize().unwrap_or(p)),
         model_provider: provider,
         codex_linux_sandbox_exe,
         stream: Some(stream),
+        output_schema: output_schema.as_ref().map(|s| s.schema().clone()),
     };
     // Parse `-c` overrides.
     let cli_kv_overrides = match config_overrides.parse_overrides() {
@@ -114,9 +124,33 @@ pub async fn run_main(cli: Cli, codex_linux_sandbox_exe: Option<PathBuf>) -> any
         }
     };
 
+    if let Some(batch_file) = batch {
+        let tasks = match batch::load_batch_file(&batch_file) {
+            Ok(tasks) => tasks,
+            Err(e) => {
+                eprintln!("Error loading --batch: {e:#}");
+                std::process::exit(1);
+            }
+        };
+        let defaults = BatchDefaults {
+            overrides,
+            cli_kv_overrides,
+            skip_git_repo_check,
+        };
+        let exit_code = batch::run_batch(tasks, concurrency, defaults).await;
+        std::process::exit(exit_code);
+    }
+
+    let prompt = read_prompt(prompt);
     let config = Config::load_with_cli_overrides(cli_kv_overrides, overrides)?;
-    let mut event_processor =
-        EventProcessor::create_with_ansi(stdout_with_ansi, !config.hide_agent_reasoning);
+    let mut event_processor: Box<dyn EventProcessor> = if json {
+        Box::new(EventProcessorWithJsonOutput::default())
+    } else {
+        Box::new(EventProcessorWithHumanOutput::create_with_ansi(
+            stdout_with_ansi,
+            !config.hide_agent_reasoning,
+        ))
+    };
     // Print the effective configuration and prompt so users can see what Codex
     // is using.
     event_processor.print_config_summary(&config, &prompt);
@@ -126,23 +160,10 @@ pub async fn run_main(cli: Cli, codex_linux_sandbox_exe: Option<PathBuf>) -> any
         std::process::exit(1);
     }
 
-    // TODO(mbolin): Take a more thoughtful approach to logging.
-    let default_level = "error";
-    let _ = tracing_subscriber::fmt()
-        // Fallback to the `default_level` log filter if the environment
-        // variable is not set _or_ contains an invalid value
-        .with_env_filter(
-            EnvFilter::try_from_default_env()
-                .or_else(|_| EnvFilter::try_new(default_level))
-                .unwrap_or_else(|_| EnvFilter::new(default_level)),
-        )
-        .with_ansi(stderr_with_ansi)
-        .with_writer(std::io::stderr)
-        .try_init();
-
     let (codex_wrapper, event, ctrl_c) = codex_wrapper::init_codex(config).await?;
     let codex = Arc::new(codex_wrapper);
     info!("Codex initialized with event: {event:?}");
+    event_processor.process_event(event);
 
     let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Event>();
     {
@@ -208,24 +229,117 @@ pub async fn run_main(cli: Cli, codex_linux_sandbox_exe: Option<PathBuf>) -> any
     let initial_prompt_task_id = codex.submit(Op::UserInput { items }).await?;
     info!("Sent prompt with event ID: {initial_prompt_task_id}");
 
-    // Run the loop until the task is complete.
+    // Run the loop until the task ends. If the channel closes first, the
+    // run was interrupted with Ctrl-C.
+    let mut outcome = RunOutcome::Interrupted;
+    let mut last_assistant_message = None;
     while let Some(event) = rx.recv().await {
-        let (is_last_event, last_assistant_message) = match &event.msg {
-            EventMsg::TaskComplete(TaskCompleteEvent { last_agent_message }) => {
-                (true, last_agent_message.clone())
-            }
-            _ => (false, None),
-        };
+        if let EventMsg::TaskComplete(TaskCompleteEvent { last_agent_message }) = &event.msg {
+            last_assistant_message = last_agent_message.clone();
+        }
+        let task_outcome = RunOutcome::from_event(&event, &initial_prompt_task_id);
         event_processor.process_event(event);
-        if is_last_event {
-            handle_last_message(last_assistant_message, last_message_file.as_deref())?;
+        if let Some(task_outcome) = task_outcome {
+            outcome = task_outcome;
             break;
         }
     }
 
+    // Validate before the summary so it reports the final outcome.
+    let structured_output = match &output_schema {
+        Some(output_schema) if outcome == RunOutcome::Completed => {
+            match validate_structured_output(output_schema, last_assistant_message.as_deref()) {
+                Some(value) => Some(value),
+                None => {
+                    outcome = RunOutcome::SchemaViolation;
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+    let stdout_output = match (&structured_output, &last_message_file) {
+        (Some(value), None) => Some(value),
+        _ => None,
+    };
+    event_processor.print_final_summary(outcome, stdout_output);
+    // Every exit below would drop notifications still queued for the webhook.
+    codex.flush_notifications(NOTIFICATION_FLUSH_TIMEOUT).await;
+
+    if outcome != RunOutcome::Completed {
+        std::process::exit(outcome.exit_code());
+    }
+    match (structured_output, last_message_file.as_deref()) {
+        (Some(value), Some(path)) => std::fs::write(path, serde_json::to_string_pretty(&value)?)?,
+        // Already printed with the summary.
+        (Some(_), None) => {}
+        (None, last_message_file) => {
+            handle_last_message(last_assistant_message, last_message_file)?
+        }
+    }
+
     Ok(())
 }
 
+/// Determines the prompt based on the CLI arg and/or stdin.
+fn read_prompt(prompt: Option<String>) -> String {
+    match prompt {
+        Some(p) if p != "-" => p,
+        // Either `-` was passed or no positional arg.
+        maybe_dash => {
+            // When no arg (None) **and** stdin is a TTY, bail out early – unless the
+            // user explicitly forced reading via `-`.
+            let force_stdin = matches!(maybe_dash.as_deref(), Some("-"));
+
+            if std::io::stdin().is_terminal() && !force_stdin {
+                eprintln!(
+                    "No prompt provided. Either specify one as an argument or pipe the prompt into stdin."
+                );
+                std::process::exit(1);
+            }
+
+            // Ensure the user knows we are waiting on stdin, as they may
+            // have gotten into this state by mistake. If so, and they are not
+            // writing to stdin, Codex will hang indefinitely, so this should
+            // help them debug in that case.
+            if !force_stdin {
+                eprintln!("Reading prompt from stdin...");
+            }
+            let mut buffer = String::new();
+            if let Err(e) = std::io::stdin().read_to_string(&mut buffer) {
+                eprintln!("Failed to read prompt from stdin: {e}");
+                std::process::exit(1);
+            } else if buffer.trim().is_empty() {
+                eprintln!("No prompt provided via stdin.");
+                std::process::exit(1);
+            }
+            buffer
+        }
+    }
+}
+
+/// Validates the final message against `--output-schema`, returning the
+/// parsed JSON. Problems are reported on stderr.
+fn validate_structured_output(
+    output_schema: &OutputSchema,
+    last_agent_message: Option<&str>,
+) -> Option<serde_json::Value> {
+    let validated = match last_agent_message {
+        Some(message) => output_schema.validate(message),
+        None => Err(vec!["the agent did not send a final message".to_string()]),
+    };
+    match validated {
+        Ok(value) => Some(value),
+        Err(errors) => {
+            eprintln!("Final message does not match --output-schema:");
+            for error in errors {
+                eprintln!("  {error}");
+            }
+            None
+        }
+    }
+}
+
 fn handle_last_message(
     last_agent_message: Option<String>,
     last_message_file: Option<&Path>,
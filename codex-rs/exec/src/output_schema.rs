@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use anyhow::Context;
+use jsonschema::JSONSchema;
+use serde_json::Value;
+
+/// The JSON Schema passed to `--output-schema`.
+pub(crate) struct OutputSchema {
+    schema: Value,
+    validator: JSONSchema,
+}
+
+impl OutputSchema {
+    pub(crate) fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let schema: Value = serde_json::from_str(&contents)
+            .with_context(|| format!("{} is not valid JSON", path.display()))?;
+        let validator = JSONSchema::compile(&schema)
+            .map_err(|e| anyhow::anyhow!("{} is not a valid JSON Schema: {e}", path.display()))?;
+        Ok(Self { schema, validator })
+    }
+
+    pub(crate) fn schema(&self) -> &Value {
+        &self.schema
+    }
+
+    /// Parses the agent's final message and checks it against the schema.
+    /// On failure, returns one description per problem found.
+    pub(crate) fn validate(&self, message: &str) -> Result<Value, Vec<String>> {
+        let value: Value = serde_json::from_str(strip_code_fence(message))
+            .map_err(|e| vec![format!("final message is not valid JSON: {e}")])?;
+        if let Err(errors) = self.validator.validate(&value) {
+            return Err(errors
+                .map(|e| {
+                    let path = e.instance_path.to_string();
+                    if path.is_empty() {
+                        e.to_string()
+                    } else {
+                        format!("{path}: {e}")
+                    }
+                })
+                .collect());
+        }
+        Ok(value)
+    }
+}
+
+/// Models without structured output support sometimes wrap their JSON in a
+/// Markdown code fence despite being told not to.
+fn strip_code_fence(message: &str) -> &str {
+    let message = message.trim();
+    match message
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+    {
+        // Drop the info string (e.g. `json`) on the opening line.
+        Some(fenced) => fenced.split_once('\n').map_or(fenced, |(_, body)| body),
+        None => message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn schema() -> OutputSchema {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "summary": { "type": "string" },
+                "files_changed": { "type": "integer" },
+            },
+            "required": ["summary", "files_changed"],
+            "additionalProperties": false,
+        });
+        OutputSchema {
+            validator: JSONSchema::compile(&schema).unwrap(),
+            schema,
+        }
+    }
+
+    #[test]
+    fn accepts_conforming_output() {
+        let expected = json!({ "summary": "done", "files_changed": 2 });
+        assert_eq!(
+            schema().validate(r#"{"summary": "done", "files_changed": 2}"#),
+            Ok(expected.clone())
+        );
+        assert_eq!(
+            schema().validate("```json\n{\"summary\": \"done\", \"files_changed\": 2}\n```"),
+            Ok(expected)
+        );
+    }
+
+    #[test]
+    fn reports_each_violation() {
+        let errors = schema()
+            .validate(r#"{"summary": 1, "files_changed": 2}"#)
+            .unwrap_err();
+        assert_eq!(errors, vec![r#"/summary: 1 is not of type "string""#]);
+
+        let errors = schema().validate("All done!").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("final message is not valid JSON"));
+    }
+}
@@ -0,0 +1,46 @@
+use codex_core::protocol::Event;
+use codex_core::protocol::EventMsg;
+use serde::Serialize;
+use strum_macros::Display;
+
+/// How a `codex exec` run ended. Each outcome has its own exit code; see the
+/// `after_help` text of [`crate::Cli`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum RunOutcome {
+    /// The agent finished the task.
+    Completed,
+    /// The task failed, e.g. because the model provider kept failing.
+    Error,
+    /// The task reached a limit from the `budget` config.
+    BudgetExceeded,
+    /// The agent finished, but its final message did not match
+    /// `--output-schema`.
+    SchemaViolation,
+    /// The run was interrupted with Ctrl-C.
+    Interrupted,
+}
+
+impl RunOutcome {
+    /// How the task `task_id` ended, if `event` ends it.
+    pub(crate) fn from_event(event: &Event, task_id: &str) -> Option<Self> {
+        match &event.msg {
+            EventMsg::TaskComplete(_) => Some(RunOutcome::Completed),
+            EventMsg::Error(_) if event.id == task_id => Some(RunOutcome::Error),
+            EventMsg::BudgetExceeded(_) => Some(RunOutcome::BudgetExceeded),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn exit_code(self) -> i32 {
+        match self {
+            RunOutcome::Completed => 0,
+            RunOutcome::Error => 1,
+            RunOutcome::SchemaViolation => 3,
+            RunOutcome::BudgetExceeded => 4,
+            // 128 + SIGINT, as a shell reports it.
+            RunOutcome::Interrupted => 130,
+        }
+    }
+}
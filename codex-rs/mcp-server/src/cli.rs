@@ -0,0 +1,23 @@
+use std::net::SocketAddr;
+
+use clap::Parser;
+
+#[derive(Parser, Debug, Default)]
+#[command(version)]
+pub struct Cli {
+    /// Serve MCP over streamable HTTP on the given address (e.g.
+    /// `127.0.0.1:8765`) instead of reading JSON-RPC from stdin.
+    #[arg(long = "listen", value_name = "ADDR")]
+    pub listen: Option<SocketAddr>,
+
+    /// Bearer token that HTTP clients must present in the `Authorization`
+    /// header. Only meaningful together with `--listen`.
+    #[arg(
+        long = "auth-token",
+        value_name = "TOKEN",
+        env = "CODEX_MCP_AUTH_TOKEN",
+        hide_env_values = true,
+        requires = "listen"
+    )]
+    pub auth_token: Option<String>,
+}
@@ -0,0 +1,212 @@
+//! Asks the MCP client to approve a command or patch on behalf of a running
+//! `codex` tool call.
+//!
+//! When Codex needs approval, the server sends a `codex/approval` request
+//! whose params carry the `tools/call` id and the approval request event, and
+//! waits for the client's [`CodexToolCallApprovalResponse`]. Clients that do
+//! not implement the method answer with an error, in which case the tool call
+//! ends with an `exec-approval-required` or `patch-approval-required` status
+//! as before.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+
+use codex_core::protocol::Event;
+use codex_core::protocol::ReviewDecision;
+use mcp_types::JSONRPC_VERSION;
+use mcp_types::JSONRPCMessage;
+use mcp_types::JSONRPCRequest;
+use mcp_types::RequestId;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+
+pub(crate) const APPROVAL_METHOD: &str = "codex/approval";
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct ApprovalRequestParams<'a> {
+    tool_call_id: &'a RequestId,
+    event: &'a Event,
+}
+
+/// The client's answer to a `codex/approval` request, for both commands and
+/// patches.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct CodexToolCallApprovalResponse {
+    pub decision: CodexToolCallReviewDecision,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum CodexToolCallReviewDecision {
+    Approved,
+    ApprovedForSession,
+    Denied,
+    /// Denied, with an explanation that is passed on to the model.
+    DeniedWithFeedback {
+        feedback: String,
+    },
+    Abort,
+}
+
+impl From<CodexToolCallReviewDecision> for ReviewDecision {
+    fn from(value: CodexToolCallReviewDecision) -> Self {
+        match value {
+            CodexToolCallReviewDecision::Approved => ReviewDecision::Approved,
+            CodexToolCallReviewDecision::ApprovedForSession => ReviewDecision::ApprovedForSession,
+            CodexToolCallReviewDecision::Denied => ReviewDecision::Denied,
+            CodexToolCallReviewDecision::DeniedWithFeedback { feedback } => {
+                ReviewDecision::DeniedWithFeedback { feedback }
+            }
+            CodexToolCallReviewDecision::Abort => ReviewDecision::Abort,
+        }
+    }
+}
+
+/// Approval requests sent to the client that have not been answered yet.
+#[derive(Default)]
+pub(crate) struct PendingApprovals {
+    next_id: AtomicI64,
+    pending: Mutex<HashMap<i64, oneshot::Sender<Option<Value>>>>,
+}
+
+impl PendingApprovals {
+    /// Asks the client to approve `event` for the tool call `tool_call_id`.
+    /// Returns `None` if the client answered with an error or with something
+    /// that is not a [`CodexToolCallApprovalResponse`].
+    pub(crate) async fn ask(
+        &self,
+        outgoing: &Sender<JSONRPCMessage>,
+        tool_call_id: &RequestId,
+        event: &Event,
+    ) -> Option<ReviewDecision> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id, tx);
+
+        let params = serde_json::to_value(ApprovalRequestParams {
+            tool_call_id,
+            event,
+        })
+        .ok()?;
+        let request = JSONRPCMessage::Request(JSONRPCRequest {
+            id: RequestId::Integer(id),
+            jsonrpc: JSONRPC_VERSION.into(),
+            method: APPROVAL_METHOD.to_string(),
+            params: Some(params),
+        });
+        if outgoing.send(request).await.is_err() {
+            return None;
+        }
+
+        let result = rx.await.ok()??;
+        match serde_json::from_value::<CodexToolCallApprovalResponse>(result) {
+            Ok(response) => Some(response.decision.into()),
+            Err(e) => {
+                tracing::warn!("Invalid {APPROVAL_METHOD} response: {e}");
+                None
+            }
+        }
+    }
+
+    /// Hands the client's answer (`None` for an error) to the waiting
+    /// [`PendingApprovals::ask`]. Returns `false` if `id` is not one of ours.
+    pub(crate) fn resolve(&self, id: &RequestId, result: Option<Value>) -> bool {
+        let RequestId::Integer(id) = id else {
+            return false;
+        };
+        let tx = self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(id);
+        let Some(tx) = tx else {
+            return false;
+        };
+        tx.send(result).ok();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use codex_core::protocol::EventMsg;
+    use codex_core::protocol::ExecApprovalRequestEvent;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn decisions_map_to_review_decisions() {
+        let response: CodexToolCallApprovalResponse = serde_json::from_value(json!({
+            "decision": {"denied-with-feedback": {"feedback": "use cargo nextest"}},
+        }))
+        .unwrap();
+        assert_eq!(
+            ReviewDecision::from(response.decision),
+            ReviewDecision::DeniedWithFeedback {
+                feedback: "use cargo nextest".to_string(),
+            }
+        );
+
+        let response: CodexToolCallApprovalResponse =
+            serde_json::from_value(json!({"decision": "approved-for-session"})).unwrap();
+        assert_eq!(
+            ReviewDecision::from(response.decision),
+            ReviewDecision::ApprovedForSession
+        );
+    }
+
+    #[tokio::test]
+    async fn ask_waits_for_the_matching_response() {
+        let approvals = Arc::new(PendingApprovals::default());
+        let (outgoing, mut requests) = mpsc::channel(1);
+        let event = Event {
+            id: "1".to_string(),
+            msg: EventMsg::ExecApprovalRequest(ExecApprovalRequestEvent {
+                command: vec!["rm".to_string(), "-rf".to_string(), "target".to_string()],
+                cwd: PathBuf::from("/repo"),
+                reason: None,
+            }),
+        };
+
+        let asking = {
+            let approvals = approvals.clone();
+            tokio::spawn(async move {
+                approvals
+                    .ask(&outgoing, &RequestId::Integer(7), &event)
+                    .await
+            })
+        };
+        let JSONRPCMessage::Request(request) = requests.recv().await.unwrap() else {
+            panic!("expected a request");
+        };
+        assert_eq!(request.method, APPROVAL_METHOD);
+        assert_eq!(request.params.unwrap()["tool-call-id"], json!(7));
+
+        assert!(!approvals.resolve(&RequestId::String("other".to_string()), None));
+        assert!(approvals.resolve(
+            &request.id,
+            Some(json!({"decision": {"denied-with-feedback": {"feedback": "not now"}}})),
+        ));
+        assert_eq!(
+            asking.await.unwrap(),
+            Some(ReviewDecision::DeniedWithFeedback {
+                feedback: "not now".to_string(),
+            })
+        );
+    }
+}
@@ -1,5 +1,6 @@
 //! Configuration object accepted by the `codex` MCP tool-call.
 
+use codex_core::config_types::SandboxMode;
 use codex_core::protocol::AskForApproval;
 use codex_core::protocol::SandboxPolicy;
 use mcp_types::Tool;
@@ -10,6 +11,7 @@ use serde::Deserialize;
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+use crate::codex_tool_result::create_output_schema_for_codex_tool_call_result;
 use crate::json_to_toml::json_to_toml;
 
 /// Client-supplied configuration for a `codex` tool-call.
@@ -37,8 +39,13 @@ pub(crate) struct CodexToolCallParam {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub approval_policy: Option<CodexToolCallApprovalPolicy>,
 
+    /// Sandbox mode: `read-only`, `workspace-write` or `danger-full-access`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<CodexToolCallSandboxMode>,
+
     /// Sandbox permissions using the same string values accepted by the CLI
-    /// (e.g. "disk-write-cwd", "network-full-access").
+    /// (e.g. "disk-write-cwd", "network-full-access"). Takes precedence over
+    /// `sandbox`.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub sandbox_permissions: Option<Vec<CodexToolCallSandboxPermission>>,
 
@@ -73,6 +80,24 @@ impl From<CodexToolCallApprovalPolicy> for AskForApproval {
     }
 }
 
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum CodexToolCallSandboxMode {
+    ReadOnly,
+    WorkspaceWrite,
+    DangerFullAccess,
+}
+
+impl From<CodexToolCallSandboxMode> for SandboxMode {
+    fn from(value: CodexToolCallSandboxMode) -> Self {
+        match value {
+            CodexToolCallSandboxMode::ReadOnly => SandboxMode::ReadOnly,
+            CodexToolCallSandboxMode::WorkspaceWrite => SandboxMode::WorkspaceWrite,
+            CodexToolCallSandboxMode::DangerFullAccess => SandboxMode::DangerFullAccess,
+        }
+    }
+}
+
 // TODO: Support additional writable folders via a separate property on
 // CodexToolCallParam.
 
@@ -132,6 +157,7 @@ pub(crate) fn create_tool_for_codex_tool_call_param() -> Tool {
     Tool {
         name: "codex".to_string(),
         input_schema: tool_input_schema,
+        output_schema: Some(create_output_schema_for_codex_tool_call_result()),
         description: Some(
             "Run a Codex session. Accepts configuration parameters matching the Codex Config struct."
                 .to_string(),
@@ -153,6 +179,7 @@ impl CodexToolCallParam {
             profile,
             cwd,
             approval_policy,
+            sandbox,
             sandbox_permissions,
             config: cli_overrides,
         } = self;
@@ -167,9 +194,11 @@ impl CodexToolCallParam {
             cwd: cwd.map(PathBuf::from),
             approval_policy: approval_policy.map(Into::into),
             sandbox_policy,
+            sandbox_mode: sandbox.map(Into::into),
             model_provider: None,
             codex_linux_sandbox_exe,
             stream: None,
+            output_schema: None,
         };
 
         let cli_overrides = cli_overrides
@@ -242,8 +271,17 @@ mod tests {
                 "description": "The *initial user prompt* to start the Codex conversation.",
                 "type": "string"
               },
+              "sandbox": {
+                "description": "Sandbox mode: `read-only`, `workspace-write` or `danger-full-access`.",
+                "enum": [
+                  "read-only",
+                  "workspace-write",
+                  "danger-full-access"
+                ],
+                "type": "string"
+              },
               "sandbox-permissions": {
-                "description": "Sandbox permissions using the same string values accepted by the CLI (e.g. \"disk-write-cwd\", \"network-full-access\").",
+                "description": "Sandbox permissions using the same string values accepted by the CLI (e.g. \"disk-write-cwd\", \"network-full-access\"). Takes precedence over `sandbox`.",
                 "items": {
                   "enum": [
                     "disk-full-read-access",
@@ -261,6 +299,98 @@ mod tests {
             "required": [
               "prompt"
             ]
+          },
+          "outputSchema": {
+            "properties": {
+              "commands": {
+                "description": "Commands executed during the session, in order.",
+                "items": {
+                  "properties": {
+                    "command": {
+                      "description": "The command that was executed.",
+                      "items": {
+                        "type": "string"
+                      },
+                      "type": "array"
+                    },
+                    "cwd": {
+                      "description": "Working directory the command ran in.",
+                      "type": "string"
+                    },
+                    "exit-code": {
+                      "description": "Exit code of the command; absent if it never finished.",
+                      "format": "int32",
+                      "type": "integer"
+                    }
+                  },
+                  "required": [
+                    "command",
+                    "cwd"
+                  ],
+                  "type": "object"
+                },
+                "type": "array"
+              },
+              "error": {
+                "description": "Error message when the session failed.",
+                "type": "string"
+              },
+              "file-changes": {
+                "description": "Files changed by patches that were applied successfully.",
+                "items": {
+                  "properties": {
+                    "kind": {
+                      "description": "Kind of change applied to the file.",
+                      "enum": [
+                        "add",
+                        "delete",
+                        "update"
+                      ],
+                      "type": "string"
+                    },
+                    "move-path": {
+                      "description": "New path of the file if it was moved.",
+                      "type": "string"
+                    },
+                    "path": {
+                      "description": "Path of the file, relative to the session's working directory when possible.",
+                      "type": "string"
+                    }
+                  },
+                  "required": [
+                    "kind",
+                    "path"
+                  ],
+                  "type": "object"
+                },
+                "type": "array"
+              },
+              "last-agent-message": {
+                "description": "The final message from the agent, if any.",
+                "type": "string"
+              },
+              "status": {
+                "description": "How the Codex session ended.",
+                "enum": [
+                  "completed",
+                  "failed",
+                  "exec-approval-required",
+                  "patch-approval-required"
+                ],
+                "type": "string"
+              },
+              "unified-diff": {
+                "description": "Combined unified diff of all file changes, including those made by shell commands when the working directory is a git repository.",
+                "type": "string"
+              }
+            },
+            "required": [
+              "commands",
+              "file-changes",
+              "status",
+              "unified-diff"
+            ],
+            "type": "object"
           }
         });
         assert_eq!(expected_tool_json, tool_json);
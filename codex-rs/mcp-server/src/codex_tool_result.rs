@@ -0,0 +1,407 @@
+//! Structured result returned by the `codex` MCP tool-call.
+//!
+//! While a Codex session runs, [`CodexToolCallResultBuilder`] observes its
+//! events and records the commands that ran and the patches that were
+//! applied. The final [`CallToolResult`] carries the agent's last message as
+//! text plus the same information as `structuredContent`, whose shape is
+//! advertised as the tool's output schema.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use codex_core::protocol::EventMsg;
+use codex_core::protocol::ExecCommandBeginEvent;
+use codex_core::protocol::ExecCommandEndEvent;
+use codex_core::protocol::FileChange;
+use codex_core::protocol::PatchApplyBeginEvent;
+use codex_core::protocol::PatchApplyEndEvent;
+use codex_core::protocol::TurnDiffEvent;
+use mcp_types::CallToolResult;
+use mcp_types::CallToolResultContent;
+use mcp_types::TextContent;
+use mcp_types::ToolOutputSchema;
+use schemars::JsonSchema;
+use schemars::r#gen::SchemaSettings;
+use serde::Serialize;
+
+/// Structured content of the `codex` tool-call result.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct CodexToolCallResult {
+    /// How the Codex session ended.
+    pub status: CodexToolCallStatus,
+
+    /// The final message from the agent, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_agent_message: Option<String>,
+
+    /// Error message when the session failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Files changed by patches that were applied successfully.
+    pub file_changes: Vec<CodexToolCallFileChange>,
+
+    /// Commands executed during the session, in order.
+    pub commands: Vec<CodexToolCallCommand>,
+
+    /// Combined unified diff of all file changes, including those made by
+    /// shell commands when the working directory is a git repository.
+    pub unified_diff: String,
+}
+
+// As with the input schema, the enums below intentionally carry no
+// docstrings to keep the generated JSON schema compact.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum CodexToolCallStatus {
+    Completed,
+    Failed,
+    ExecApprovalRequired,
+    PatchApprovalRequired,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct CodexToolCallFileChange {
+    /// Path of the file, relative to the session's working directory when
+    /// possible.
+    pub path: String,
+
+    /// Kind of change applied to the file.
+    pub kind: CodexToolCallFileChangeKind,
+
+    /// New path of the file if it was moved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub move_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum CodexToolCallFileChangeKind {
+    Add,
+    Delete,
+    Update,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct CodexToolCallCommand {
+    /// The command that was executed.
+    pub command: Vec<String>,
+
+    /// Working directory the command ran in.
+    pub cwd: String,
+
+    /// Exit code of the command; absent if it never finished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+}
+
+pub(crate) fn create_output_schema_for_codex_tool_call_result() -> ToolOutputSchema {
+    let schema = SchemaSettings::draft2019_09()
+        .with(|s| {
+            s.inline_subschemas = true;
+            s.option_add_null_type = false
+        })
+        .into_generator()
+        .into_root_schema_for::<CodexToolCallResult>();
+
+    #[expect(clippy::expect_used)]
+    let schema_value =
+        serde_json::to_value(&schema).expect("Codex tool output schema should serialise to JSON");
+
+    serde_json::from_value::<ToolOutputSchema>(schema_value).unwrap_or_else(|e| {
+        panic!("failed to create output schema: {e}");
+    })
+}
+
+/// Accumulates a [`CodexToolCallResult`] from the events of a Codex session.
+pub(crate) struct CodexToolCallResultBuilder {
+    cwd: PathBuf,
+    last_agent_message: Option<String>,
+    /// Changes announced by `PatchApplyBegin`, keyed by `call_id`, waiting
+    /// for the matching `PatchApplyEnd`.
+    pending_patches: HashMap<String, HashMap<PathBuf, FileChange>>,
+    /// Index into `commands` for each running command, keyed by `call_id`.
+    running_commands: HashMap<String, usize>,
+    applied_changes: Vec<(PathBuf, FileChange)>,
+    commands: Vec<CodexToolCallCommand>,
+    /// Diff reported by Codex via `TurnDiff`. Preferred over the diff
+    /// reconstructed from `applied_changes` when present.
+    turn_diff: Option<String>,
+}
+
+impl CodexToolCallResultBuilder {
+    pub(crate) fn new(cwd: PathBuf) -> Self {
+        Self {
+            cwd,
+            last_agent_message: None,
+            pending_patches: HashMap::new(),
+            running_commands: HashMap::new(),
+            applied_changes: Vec::new(),
+            commands: Vec::new(),
+            turn_diff: None,
+        }
+    }
+
+    pub(crate) fn observe(&mut self, msg: &EventMsg) {
+        match msg {
+            EventMsg::AgentMessage(ev) => {
+                self.last_agent_message = Some(ev.message.clone());
+            }
+            EventMsg::ExecCommandBegin(ExecCommandBeginEvent {
+                call_id,
+                command,
+                cwd,
+            }) => {
+                self.running_commands
+                    .insert(call_id.clone(), self.commands.len());
+                self.commands.push(CodexToolCallCommand {
+                    command: command.clone(),
+                    cwd: cwd.display().to_string(),
+                    exit_code: None,
+                });
+            }
+            EventMsg::ExecCommandEnd(ExecCommandEndEvent {
+                call_id, exit_code, ..
+            }) => {
+                if let Some(idx) = self.running_commands.remove(call_id) {
+                    self.commands[idx].exit_code = Some(*exit_code);
+                }
+            }
+            EventMsg::PatchApplyBegin(PatchApplyBeginEvent {
+                call_id, changes, ..
+            }) => {
+                self.pending_patches
+                    .insert(call_id.clone(), changes.clone());
+            }
+            EventMsg::PatchApplyEnd(PatchApplyEndEvent {
+                call_id, success, ..
+            }) => {
+                if let Some(changes) = self.pending_patches.remove(call_id) {
+                    if *success {
+                        let mut changes: Vec<_> = changes.into_iter().collect();
+                        changes.sort_by(|(a, _), (b, _)| a.cmp(b));
+                        self.applied_changes.extend(changes);
+                    }
+                }
+            }
+            EventMsg::TurnDiff(TurnDiffEvent { unified_diff }) => {
+                self.turn_diff = Some(unified_diff.clone());
+            }
+            _ => {}
+        }
+    }
+
+    /// Produce the final tool-call result. `error` is set when the session
+    /// failed and doubles as the text content in that case.
+    pub(crate) fn finish(
+        self,
+        status: CodexToolCallStatus,
+        error: Option<String>,
+    ) -> CallToolResult {
+        let file_changes = self
+            .applied_changes
+            .iter()
+            .map(|(path, change)| {
+                let (kind, move_path) = match change {
+                    FileChange::Add { .. } => (CodexToolCallFileChangeKind::Add, None),
+                    FileChange::Delete => (CodexToolCallFileChangeKind::Delete, None),
+                    FileChange::Update { move_path, .. } => (
+                        CodexToolCallFileChangeKind::Update,
+                        move_path.as_deref().map(|p| self.display_path(p)),
+                    ),
+                };
+                CodexToolCallFileChange {
+                    path: self.display_path(path),
+                    kind,
+                    move_path,
+                }
+            })
+            .collect();
+        let unified_diff = match &self.turn_diff {
+            Some(diff) => diff.clone(),
+            None => self
+                .applied_changes
+                .iter()
+                .map(|(path, change)| self.unified_diff_for_change(path, change))
+                .collect::<String>(),
+        };
+
+        let text = match (status, &error) {
+            (CodexToolCallStatus::ExecApprovalRequired, _) => "EXEC_APPROVAL_REQUIRED".to_string(),
+            (CodexToolCallStatus::PatchApprovalRequired, _) => {
+                "PATCH_APPROVAL_REQUIRED".to_string()
+            }
+            (_, Some(error)) => format!("Codex task failed: {error}"),
+            (_, None) => self.last_agent_message.clone().unwrap_or_default(),
+        };
+        let is_error = error.is_some();
+
+        let structured = CodexToolCallResult {
+            status,
+            last_agent_message: self.last_agent_message,
+            error,
+            file_changes,
+            commands: self.commands,
+            unified_diff,
+        };
+
+        CallToolResult {
+            content: vec![CallToolResultContent::TextContent(TextContent {
+                r#type: "text".to_string(),
+                text,
+                annotations: None,
+            })],
+            is_error: is_error.then_some(true),
+            structured_content: serde_json::to_value(structured).ok(),
+        }
+    }
+
+    fn display_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.cwd)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
+
+    fn unified_diff_for_change(&self, path: &Path, change: &FileChange) -> String {
+        let path_str = self.display_path(path);
+        match change {
+            FileChange::Add { content } => {
+                let lines: Vec<&str> = content.lines().collect();
+                let mut diff = format!(
+                    "--- /dev/null\n+++ b/{path_str}\n@@ -0,0 +1,{} @@\n",
+                    lines.len()
+                );
+                for line in lines {
+                    diff.push('+');
+                    diff.push_str(line);
+                    diff.push('\n');
+                }
+                diff
+            }
+            FileChange::Delete => format!("--- a/{path_str}\n+++ /dev/null\n"),
+            FileChange::Update {
+                unified_diff,
+                move_path,
+            } => {
+                let new_path = move_path
+                    .as_deref()
+                    .map_or_else(|| path_str.clone(), |p| self.display_path(p));
+                format!("--- a/{path_str}\n+++ b/{new_path}\n{unified_diff}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use codex_core::protocol::AgentMessageEvent;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[test]
+    fn collects_commands_and_applied_patches() {
+        let cwd = PathBuf::from("/repo");
+        let mut builder = CodexToolCallResultBuilder::new(cwd.clone());
+
+        builder.observe(&EventMsg::ExecCommandBegin(ExecCommandBeginEvent {
+            call_id: "c1".to_string(),
+            command: vec!["ls".to_string()],
+            cwd: cwd.clone(),
+        }));
+        builder.observe(&EventMsg::ExecCommandEnd(ExecCommandEndEvent {
+            call_id: "c1".to_string(),
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: 0,
+        }));
+        builder.observe(&EventMsg::PatchApplyBegin(PatchApplyBeginEvent {
+            call_id: "p1".to_string(),
+            auto_approved: true,
+            changes: HashMap::from([
+                (
+                    cwd.join("a.txt"),
+                    FileChange::Add {
+                        content: "hello\n".to_string(),
+                    },
+                ),
+                (
+                    cwd.join("b.txt"),
+                    FileChange::Update {
+                        unified_diff: "@@ -1 +1 @@\n-old\n+new\n".to_string(),
+                        move_path: None,
+                    },
+                ),
+            ]),
+        }));
+        builder.observe(&EventMsg::PatchApplyEnd(PatchApplyEndEvent {
+            call_id: "p1".to_string(),
+            stdout: String::new(),
+            stderr: String::new(),
+            success: true,
+        }));
+        // A failed patch is not reported as applied.
+        builder.observe(&EventMsg::PatchApplyBegin(PatchApplyBeginEvent {
+            call_id: "p2".to_string(),
+            auto_approved: true,
+            changes: HashMap::from([(cwd.join("c.txt"), FileChange::Delete)]),
+        }));
+        builder.observe(&EventMsg::PatchApplyEnd(PatchApplyEndEvent {
+            call_id: "p2".to_string(),
+            stdout: String::new(),
+            stderr: "boom".to_string(),
+            success: false,
+        }));
+        builder.observe(&EventMsg::AgentMessage(AgentMessageEvent {
+            message: "done".to_string(),
+        }));
+
+        let result = builder.finish(CodexToolCallStatus::Completed, None);
+        assert_eq!(result.is_error, None);
+        assert_eq!(
+            result.structured_content,
+            Some(json!({
+                "status": "completed",
+                "last-agent-message": "done",
+                "file-changes": [
+                    { "path": "a.txt", "kind": "add" },
+                    { "path": "b.txt", "kind": "update" },
+                ],
+                "commands": [
+                    { "command": ["ls"], "cwd": "/repo", "exit-code": 0 },
+                ],
+                "unified-diff": "--- /dev/null\n+++ b/a.txt\n@@ -0,0 +1,1 @@\n+hello\n--- a/b.txt\n+++ b/b.txt\n@@ -1 +1 @@\n-old\n+new\n",
+            }))
+        );
+    }
+
+    #[test]
+    fn failure_is_reported_as_error() {
+        let builder = CodexToolCallResultBuilder::new(PathBuf::from("/repo"));
+        let result = builder.finish(
+            CodexToolCallStatus::Failed,
+            Some("stream error".to_string()),
+        );
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(
+            result.content,
+            vec![CallToolResultContent::TextContent(TextContent {
+                r#type: "text".to_string(),
+                text: "Codex task failed: stream error".to_string(),
+                annotations: None,
+            })]
+        );
+        assert_eq!(
+            result.structured_content.unwrap()["status"],
+            json!("failed")
+        );
+    }
+}
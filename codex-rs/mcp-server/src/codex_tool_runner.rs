@@ -2,14 +2,16 @@
 //! Tokio task. Separated from `message_processor.rs` to keep that file small
 //! and to make future feature-growth easier to manage.
 
+use std::sync::Arc;
+
 use codex_core::codex_wrapper::init_codex;
 use codex_core::config::Config as CodexConfig;
-use codex_core::protocol::AgentMessageEvent;
+use codex_core::protocol::BudgetExceededEvent;
+use codex_core::protocol::ErrorEvent;
 use codex_core::protocol::Event;
 use codex_core::protocol::EventMsg;
 use codex_core::protocol::InputItem;
 use codex_core::protocol::Op;
-use codex_core::protocol::TaskCompleteEvent;
 use mcp_types::CallToolResult;
 use mcp_types::CallToolResultContent;
 use mcp_types::JSONRPC_VERSION;
@@ -19,6 +21,10 @@ use mcp_types::RequestId;
 use mcp_types::TextContent;
 use tokio::sync::mpsc::Sender;
 
+use crate::codex_tool_approval::PendingApprovals;
+use crate::codex_tool_result::CodexToolCallResultBuilder;
+use crate::codex_tool_result::CodexToolCallStatus;
+
 /// Convert a Codex [`Event`] to an MCP notification.
 fn codex_event_to_notification(event: &Event) -> JSONRPCMessage {
     #[expect(clippy::expect_used)]
@@ -29,16 +35,35 @@ fn codex_event_to_notification(event: &Event) -> JSONRPCMessage {
     })
 }
 
+/// Send the `tools/call` response for request `id`.
+async fn send_call_tool_result(
+    outgoing: &Sender<JSONRPCMessage>,
+    id: RequestId,
+    result: CallToolResult,
+) {
+    let _ = outgoing
+        .send(JSONRPCMessage::Response(JSONRPCResponse {
+            jsonrpc: JSONRPC_VERSION.into(),
+            id,
+            result: result.into(),
+        }))
+        .await;
+}
+
 /// Run a complete Codex session and stream events back to the client.
 ///
-/// On completion (success or error) the function sends the appropriate
-/// `tools/call` response so the LLM can continue the conversation.
+/// Approvals are requested from the client through `approvals`. On completion
+/// (success or error), or when the client cannot answer an approval request,
+/// the function sends the appropriate `tools/call` response so the LLM can
+/// continue the conversation.
 pub async fn run_codex_tool_session(
     id: RequestId,
     initial_prompt: String,
     config: CodexConfig,
     outgoing: Sender<JSONRPCMessage>,
+    approvals: Arc<PendingApprovals>,
 ) {
+    let cwd = config.cwd.clone();
     let (codex, first_event, _ctrl_c) = match init_codex(config).await {
         Ok(res) => res,
         Err(e) => {
@@ -49,14 +74,9 @@ pub async fn run_codex_tool_session(
                     annotations: None,
                 })],
                 is_error: Some(true),
+                structured_content: None,
             };
-            let _ = outgoing
-                .send(JSONRPCMessage::Response(JSONRPCResponse {
-                    jsonrpc: JSONRPC_VERSION.into(),
-                    id,
-                    result: result.into(),
-                }))
-                .await;
+            send_call_tool_result(&outgoing, id, result).await;
             return;
         }
     };
@@ -77,7 +97,10 @@ pub async fn run_codex_tool_session(
         tracing::error!("Failed to submit initial prompt: {e}");
     }
 
-    let mut last_agent_message: Option<String> = None;
+    let mut result = CodexToolCallResultBuilder::new(cwd);
+    // Errors reported before the task starts (e.g. MCP servers that failed
+    // to launch) are not fatal; once the task is running, an error ends it.
+    let mut task_started = false;
 
     // Stream events until the task needs to pause for user interaction or
     // completes.
@@ -85,94 +108,87 @@ pub async fn run_codex_tool_session(
         match codex.next_event().await {
             Ok(event) => {
                 let _ = outgoing.send(codex_event_to_notification(&event)).await;
+                result.observe(&event.msg);
 
                 match &event.msg {
-                    EventMsg::AgentMessage(AgentMessageEvent { message }) => {
-                        last_agent_message = Some(message.clone());
-                    }
                     EventMsg::ExecApprovalRequest(_) => {
-                        let result = CallToolResult {
-                            content: vec![CallToolResultContent::TextContent(TextContent {
-                                r#type: "text".to_string(),
-                                text: "EXEC_APPROVAL_REQUIRED".to_string(),
-                                annotations: None,
-                            })],
-                            is_error: None,
+                        let Some(decision) = approvals.ask(&outgoing, &id, &event).await else {
+                            let result =
+                                result.finish(CodexToolCallStatus::ExecApprovalRequired, None);
+                            send_call_tool_result(&outgoing, id.clone(), result).await;
+                            break;
                         };
-                        let _ = outgoing
-                            .send(JSONRPCMessage::Response(JSONRPCResponse {
-                                jsonrpc: JSONRPC_VERSION.into(),
-                                id: id.clone(),
-                                result: result.into(),
-                            }))
-                            .await;
-                        break;
+                        if let Err(e) = codex
+                            .submit(Op::ExecApproval {
+                                id: event.id.clone(),
+                                decision,
+                            })
+                            .await
+                        {
+                            tracing::error!("Failed to submit exec approval: {e}");
+                        }
                     }
                     EventMsg::ApplyPatchApprovalRequest(_) => {
-                        let result = CallToolResult {
-                            content: vec![CallToolResultContent::TextContent(TextContent {
-                                r#type: "text".to_string(),
-                                text: "PATCH_APPROVAL_REQUIRED".to_string(),
-                                annotations: None,
-                            })],
-                            is_error: None,
+                        let Some(decision) = approvals.ask(&outgoing, &id, &event).await else {
+                            let result =
+                                result.finish(CodexToolCallStatus::PatchApprovalRequired, None);
+                            send_call_tool_result(&outgoing, id.clone(), result).await;
+                            break;
                         };
-                        let _ = outgoing
-                            .send(JSONRPCMessage::Response(JSONRPCResponse {
-                                jsonrpc: JSONRPC_VERSION.into(),
-                                id: id.clone(),
-                                result: result.into(),
-                            }))
-                            .await;
+                        if let Err(e) = codex
+                            .submit(Op::PatchApproval {
+                                id: event.id.clone(),
+                                decision,
+                                rejected_changes: Vec::new(),
+                            })
+                            .await
+                        {
+                            tracing::error!("Failed to submit patch approval: {e}");
+                        }
+                    }
+                    EventMsg::TaskStarted => {
+                        task_started = true;
+                    }
+                    EventMsg::TaskComplete(_) => {
+                        let result = result.finish(CodexToolCallStatus::Completed, None);
+                        send_call_tool_result(&outgoing, id.clone(), result).await;
                         break;
                     }
-                    EventMsg::TaskComplete(TaskCompleteEvent {
-                        last_agent_message: _,
-                    }) => {
-                        let result = if let Some(msg) = last_agent_message {
-                            CallToolResult {
-                                content: vec![CallToolResultContent::TextContent(TextContent {
-                                    r#type: "text".to_string(),
-                                    text: msg,
-                                    annotations: None,
-                                })],
-                                is_error: None,
-                            }
-                        } else {
-                            CallToolResult {
-                                content: vec![CallToolResultContent::TextContent(TextContent {
-                                    r#type: "text".to_string(),
-                                    text: String::new(),
-                                    annotations: None,
-                                })],
-                                is_error: None,
-                            }
-                        };
-                        let _ = outgoing
-                            .send(JSONRPCMessage::Response(JSONRPCResponse {
-                                jsonrpc: JSONRPC_VERSION.into(),
-                                id: id.clone(),
-                                result: result.into(),
-                            }))
-                            .await;
+                    EventMsg::Error(ErrorEvent { message }) if task_started => {
+                        let result =
+                            result.finish(CodexToolCallStatus::Failed, Some(message.clone()));
+                        send_call_tool_result(&outgoing, id.clone(), result).await;
+                        break;
+                    }
+                    EventMsg::BudgetExceeded(BudgetExceededEvent { message, .. }) => {
+                        let result = result.finish(
+                            CodexToolCallStatus::Failed,
+                            Some(format!("Budget exceeded: {message}")),
+                        );
+                        send_call_tool_result(&outgoing, id.clone(), result).await;
                         break;
                     }
                     EventMsg::SessionConfigured(_) => {
                         tracing::error!("unexpected SessionConfigured event");
                     }
                     EventMsg::Error(_)
-                    | EventMsg::TaskStarted
+                    | EventMsg::AgentMessage(_)
                     | EventMsg::AgentReasoning(_)
                     | EventMsg::McpToolCallBegin(_)
                     | EventMsg::McpToolCallEnd(_)
+                    | EventMsg::FileToolCall(_)
+                    | EventMsg::TurnDiff(_)
+                    | EventMsg::SessionDiff(_)
+                    | EventMsg::ApprovalRules(_)
                     | EventMsg::ExecCommandBegin(_)
                     | EventMsg::ExecCommandEnd(_)
                     | EventMsg::BackgroundEvent(_)
+                    | EventMsg::ModelFallback(_)
                     | EventMsg::PatchApplyBegin(_)
                     | EventMsg::PatchApplyEnd(_)
                     | EventMsg::GetHistoryEntryResponse(_) => {
                         // For now, we do not do anything extra for these
-                        // events. Note that
+                        // events beyond recording them in `result`. Note that
                         // send(codex_event_to_notification(&event)) above has
                         // already dispatched these events as notifications,
                         // though we may want to do give different treatment to
@@ -188,14 +204,9 @@ pub async fn run_codex_tool_session(
                         annotations: None,
                     })],
                     is_error: Some(true),
+                    structured_content: None,
                 };
-                let _ = outgoing
-                    .send(JSONRPCMessage::Response(JSONRPCResponse {
-                        jsonrpc: JSONRPC_VERSION.into(),
-                        id: id.clone(),
-                        result: result.into(),
-                    }))
-                    .await;
+                send_call_tool_result(&outgoing, id.clone(), result).await;
                 break;
             }
         }
@@ -0,0 +1,785 @@
+//! MCP "streamable HTTP" transport.
+//!
+//! A single endpoint (`/mcp`) accepts JSON-RPC messages via `POST`. An
+//! `initialize` request creates a new session with its own
+//! [`MessageProcessor`]; the session id is returned in the `Mcp-Session-Id`
+//! header and must accompany every later request. Responses to a `POST` are
+//! streamed back as SSE (or a plain JSON body for clients that do not accept
+//! `text/event-stream`). Server notifications go to the session's standalone
+//! SSE stream opened with `GET`, or to the in-flight `POST` streams when no
+//! such stream is open. `DELETE` terminates the session.
+//!
+//! Sessions that see no requests for [`SESSION_IDLE_TIMEOUT`] and have no open
+//! stream are expired. Closing a session, either way, aborts the Codex tool
+//! calls it still has running.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::io::Result as IoResult;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use axum::Json;
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::http::StatusCode;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::response::sse::Event as SseEvent;
+use axum::response::sse::KeepAlive;
+use axum::response::sse::Sse;
+use axum::routing::post;
+use mcp_types::JSONRPCBatchRequestItem;
+use mcp_types::JSONRPCMessage;
+use mcp_types::RequestId;
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+use tracing::debug;
+use tracing::error;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::CHANNEL_CAPACITY;
+use crate::message_processor::MessageProcessor;
+
+/// Path of the single MCP endpoint.
+const MCP_ENDPOINT: &str = "/mcp";
+
+/// Header used to carry the session id assigned on `initialize`.
+const SESSION_ID_HEADER: &str = "mcp-session-id";
+
+/// How long a session may go without requests (and without an open stream)
+/// before it is closed.
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// JSON-RPC error code for unparseable payloads.
+const PARSE_ERROR: i64 = -32700;
+
+/// JSON-RPC error code for structurally invalid requests.
+const INVALID_REQUEST: i64 = -32600;
+
+pub(crate) async fn run_http_server(
+    addr: SocketAddr,
+    auth_token: Option<String>,
+    codex_linux_sandbox_exe: Option<PathBuf>,
+) -> IoResult<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!(
+        "MCP server listening on http://{}{MCP_ENDPOINT}",
+        listener.local_addr()?
+    );
+    if auth_token.is_none() && !addr.ip().is_loopback() {
+        tracing::warn!("serving MCP on a non-loopback address without --auth-token");
+    }
+    serve(
+        listener,
+        auth_token,
+        codex_linux_sandbox_exe,
+        SESSION_IDLE_TIMEOUT,
+    )
+    .await
+}
+
+async fn serve(
+    listener: TcpListener,
+    auth_token: Option<String>,
+    codex_linux_sandbox_exe: Option<PathBuf>,
+    idle_timeout: Duration,
+) -> IoResult<()> {
+    let state = Arc::new(HttpState {
+        sessions: Mutex::new(HashMap::new()),
+        auth_token,
+        codex_linux_sandbox_exe,
+        idle_timeout,
+    });
+    tokio::spawn(expire_idle_sessions(Arc::downgrade(&state)));
+    let router = Router::new()
+        .route(
+            MCP_ENDPOINT,
+            post(handle_post).get(handle_get).delete(handle_delete),
+        )
+        .with_state(state);
+
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await
+}
+
+struct HttpState {
+    sessions: Mutex<HashMap<String, Arc<HttpSession>>>,
+    auth_token: Option<String>,
+    codex_linux_sandbox_exe: Option<PathBuf>,
+    idle_timeout: Duration,
+}
+
+impl HttpState {
+    /// Validate the `Origin` and `Authorization` headers. Returns the response
+    /// to send back when the request must be rejected.
+    #[allow(clippy::result_large_err)]
+    fn authorize(&self, headers: &HeaderMap) -> Result<(), Response> {
+        if !origin_matches_host(headers) {
+            return Err(error_response(StatusCode::FORBIDDEN, "origin not allowed"));
+        }
+
+        let Some(expected) = &self.auth_token else {
+            return Ok(());
+        };
+        let presented = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        match presented {
+            Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+            _ => {
+                let mut response = error_response(StatusCode::UNAUTHORIZED, "unauthorized");
+                response.headers_mut().insert(
+                    header::WWW_AUTHENTICATE,
+                    header::HeaderValue::from_static("Bearer"),
+                );
+                Err(response)
+            }
+        }
+    }
+
+    /// Resolve the session referenced by the `Mcp-Session-Id` header.
+    #[allow(clippy::result_large_err)]
+    fn session(&self, headers: &HeaderMap) -> Result<(String, Arc<HttpSession>), Response> {
+        let Some(session_id) = headers.get(SESSION_ID_HEADER).and_then(|v| v.to_str().ok()) else {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                "missing Mcp-Session-Id header",
+            ));
+        };
+
+        let sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        match sessions.get(session_id) {
+            Some(session) => {
+                session.touch();
+                Ok((session_id.to_string(), Arc::clone(session)))
+            }
+            None => Err(error_response(StatusCode::NOT_FOUND, "unknown session")),
+        }
+    }
+
+    /// Remove the session from the map and stop its processor.
+    fn close_session(&self, session_id: &str) {
+        let removed = self
+            .sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(session_id);
+        if let Some(session) = removed {
+            session.close();
+        }
+    }
+
+    /// Close every session that has been idle for longer than `idle_timeout`.
+    fn expire_idle_sessions(&self) {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.retain(|session_id, session| {
+            if !session.is_idle(self.idle_timeout) {
+                return true;
+            }
+            info!("expired idle MCP HTTP session {session_id}");
+            session.close();
+            false
+        });
+    }
+}
+
+/// Periodically expires idle sessions until the server state is dropped.
+async fn expire_idle_sessions(state: std::sync::Weak<HttpState>) {
+    let Some(period) = state.upgrade().map(|state| state.idle_timeout / 2) else {
+        return;
+    };
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        match state.upgrade() {
+            Some(state) => state.expire_idle_sessions(),
+            None => return,
+        }
+    }
+}
+
+/// One MCP session: a dedicated [`MessageProcessor`] plus the bookkeeping
+/// required to route its outgoing messages to the right HTTP stream.
+struct HttpSession {
+    incoming_tx: mpsc::Sender<JSONRPCMessage>,
+    routes: Arc<Mutex<Routes>>,
+    /// Task that owns the [`MessageProcessor`]; aborting it aborts the Codex
+    /// sessions the processor started.
+    processor: AbortHandle,
+    last_seen: Mutex<Instant>,
+}
+
+#[derive(Default)]
+struct Routes {
+    next_stream_id: u64,
+    /// Streams waiting for the response to a request, keyed by request id.
+    pending: HashMap<String, (u64, mpsc::Sender<JSONRPCMessage>)>,
+    /// Stream opened via `GET` for server-initiated messages.
+    standalone: Option<mpsc::Sender<JSONRPCMessage>>,
+}
+
+impl HttpSession {
+    fn spawn(codex_linux_sandbox_exe: Option<PathBuf>) -> Self {
+        let (incoming_tx, mut incoming_rx) = mpsc::channel::<JSONRPCMessage>(CHANNEL_CAPACITY);
+        let (outgoing_tx, mut outgoing_rx) = mpsc::channel::<JSONRPCMessage>(CHANNEL_CAPACITY);
+        let routes = Arc::new(Mutex::new(Routes::default()));
+
+        let processor = tokio::spawn(async move {
+            let mut processor = MessageProcessor::new(outgoing_tx, codex_linux_sandbox_exe);
+            while let Some(msg) = incoming_rx.recv().await {
+                processor.process_message(msg);
+            }
+            debug!("HTTP session processor exited (session closed)");
+        })
+        .abort_handle();
+
+        tokio::spawn({
+            let routes = Arc::clone(&routes);
+            async move {
+                while let Some(msg) = outgoing_rx.recv().await {
+                    route_outgoing(&routes, msg).await;
+                }
+                debug!("HTTP session router exited (channel closed)");
+            }
+        });
+
+        Self {
+            incoming_tx,
+            routes,
+            processor,
+            last_seen: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn touch(&self) {
+        *self.last_seen.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    /// A session is idle when no request has touched it for `timeout` and no
+    /// client is still listening on one of its streams.
+    fn is_idle(&self, timeout: Duration) -> bool {
+        let routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        let streaming = routes.pending.values().any(|(_, tx)| !tx.is_closed())
+            || routes.standalone.as_ref().is_some_and(|tx| !tx.is_closed());
+        let last_seen = *self.last_seen.lock().unwrap_or_else(|e| e.into_inner());
+        !streaming && last_seen.elapsed() >= timeout
+    }
+
+    fn close(&self) {
+        self.processor.abort();
+    }
+
+    /// Register a response stream for the given request ids.
+    fn register_stream(&self, request_keys: &[String], tx: mpsc::Sender<JSONRPCMessage>) {
+        let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        let stream_id = routes.next_stream_id;
+        routes.next_stream_id += 1;
+        for key in request_keys {
+            routes.pending.insert(key.clone(), (stream_id, tx.clone()));
+        }
+    }
+
+    fn set_standalone_stream(&self, tx: mpsc::Sender<JSONRPCMessage>) {
+        let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        routes.standalone = Some(tx);
+    }
+}
+
+/// Deliver a message produced by a session's [`MessageProcessor`]. Responses
+/// go to the stream that carried the matching request; everything else goes
+/// to the standalone stream, falling back to all in-flight request streams.
+async fn route_outgoing(routes: &Mutex<Routes>, msg: JSONRPCMessage) {
+    let targets: Vec<mpsc::Sender<JSONRPCMessage>> = {
+        let mut routes = routes.lock().unwrap_or_else(|e| e.into_inner());
+        let standalone = routes.standalone.clone().filter(|tx| !tx.is_closed());
+        match response_key(&msg) {
+            Some(key) => match routes.pending.remove(&key) {
+                Some((_, tx)) => vec![tx],
+                None => standalone.into_iter().collect(),
+            },
+            None => match standalone {
+                Some(tx) => vec![tx],
+                None => {
+                    let mut seen = HashSet::new();
+                    routes
+                        .pending
+                        .values()
+                        .filter(|(stream_id, _)| seen.insert(*stream_id))
+                        .map(|(_, tx)| tx.clone())
+                        .collect()
+                }
+            },
+        }
+    };
+
+    if targets.is_empty() {
+        debug!("no open stream for outgoing message; dropping it");
+    }
+    for tx in targets {
+        if tx.send(msg.clone()).await.is_err() {
+            debug!("HTTP stream closed before message could be delivered");
+        }
+    }
+}
+
+async fn handle_post(
+    State(state): State<Arc<HttpState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Err(response) = state.authorize(&headers) {
+        return response;
+    }
+
+    let msg = match serde_json::from_slice::<JSONRPCMessage>(&body) {
+        Ok(msg) => msg,
+        Err(e) => {
+            return json_rpc_error_response(
+                StatusCode::BAD_REQUEST,
+                PARSE_ERROR,
+                format!("failed to parse JSON-RPC message: {e}"),
+            );
+        }
+    };
+
+    let is_initialize = matches!(&msg, JSONRPCMessage::Request(r) if r.method == "initialize");
+    let (session_id, session) = if is_initialize {
+        if headers.contains_key(SESSION_ID_HEADER) {
+            return json_rpc_error_response(
+                StatusCode::BAD_REQUEST,
+                INVALID_REQUEST,
+                "initialize must not carry an Mcp-Session-Id header".to_string(),
+            );
+        }
+        let session_id = Uuid::new_v4().to_string();
+        let session = Arc::new(HttpSession::spawn(state.codex_linux_sandbox_exe.clone()));
+        state
+            .sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(session_id.clone(), Arc::clone(&session));
+        info!("created MCP HTTP session {session_id}");
+        (session_id, session)
+    } else {
+        match state.session(&headers) {
+            Ok(found) => found,
+            Err(response) => return response,
+        }
+    };
+    let session_header = [(SESSION_ID_HEADER, session_id)];
+
+    let request_keys = request_keys(&msg);
+    if request_keys.is_empty() {
+        // Only notifications and/or responses: nothing to stream back.
+        if session.incoming_tx.send(msg).await.is_err() {
+            return error_response(StatusCode::NOT_FOUND, "session closed");
+        }
+        return (StatusCode::ACCEPTED, session_header).into_response();
+    }
+
+    let (stream_tx, mut stream_rx) = mpsc::channel::<JSONRPCMessage>(CHANNEL_CAPACITY);
+    session.register_stream(&request_keys, stream_tx);
+    if session.incoming_tx.send(msg).await.is_err() {
+        return error_response(StatusCode::NOT_FOUND, "session closed");
+    }
+
+    let expected = request_keys.len();
+    if accepts_event_stream(&headers) {
+        let stream =
+            futures::stream::unfold((stream_rx, expected), |(mut rx, remaining)| async move {
+                if remaining == 0 {
+                    return None;
+                }
+                let msg = rx.recv().await?;
+                let remaining = if response_key(&msg).is_some() {
+                    remaining - 1
+                } else {
+                    remaining
+                };
+                Some((Ok::<_, Infallible>(to_sse_event(&msg)), (rx, remaining)))
+            });
+        (
+            session_header,
+            Sse::new(stream).keep_alive(KeepAlive::default()),
+        )
+            .into_response()
+    } else {
+        // The client only accepts a plain JSON body, so wait for every
+        // response and drop any notifications that arrive in between.
+        let mut responses = Vec::with_capacity(expected);
+        while responses.len() < expected {
+            match stream_rx.recv().await {
+                Some(msg) if response_key(&msg).is_some() => responses.push(msg),
+                Some(_) => {}
+                None => break,
+            }
+        }
+        match responses.len() {
+            1 => (session_header, Json(responses.remove(0))).into_response(),
+            _ => (session_header, Json(responses)).into_response(),
+        }
+    }
+}
+
+async fn handle_get(State(state): State<Arc<HttpState>>, headers: HeaderMap) -> Response {
+    if let Err(response) = state.authorize(&headers) {
+        return response;
+    }
+    if !accepts_event_stream(&headers) {
+        return error_response(
+            StatusCode::NOT_ACCEPTABLE,
+            "GET requires Accept: text/event-stream",
+        );
+    }
+    let (_, session) = match state.session(&headers) {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+
+    let (tx, rx) = mpsc::channel::<JSONRPCMessage>(CHANNEL_CAPACITY);
+    session.set_standalone_stream(tx);
+    let stream = futures::stream::unfold(rx, |mut rx| async move {
+        let msg = rx.recv().await?;
+        Some((Ok::<_, Infallible>(to_sse_event(&msg)), rx))
+    });
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+async fn handle_delete(State(state): State<Arc<HttpState>>, headers: HeaderMap) -> Response {
+    if let Err(response) = state.authorize(&headers) {
+        return response;
+    }
+    let (session_id, _) = match state.session(&headers) {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+    state.close_session(&session_id);
+    info!("closed MCP HTTP session {session_id}");
+    StatusCode::OK.into_response()
+}
+
+/// Ids of the requests contained in `msg`, which determine how many
+/// responses the originating `POST` has to wait for.
+fn request_keys(msg: &JSONRPCMessage) -> Vec<String> {
+    match msg {
+        JSONRPCMessage::Request(r) => vec![request_key(&r.id)],
+        JSONRPCMessage::BatchRequest(batch) => batch
+            .iter()
+            .filter_map(|item| match item {
+                JSONRPCBatchRequestItem::JSONRPCRequest(r) => Some(request_key(&r.id)),
+                JSONRPCBatchRequestItem::JSONRPCNotification(_) => None,
+            })
+            .collect(),
+        JSONRPCMessage::Notification(_)
+        | JSONRPCMessage::Response(_)
+        | JSONRPCMessage::Error(_)
+        | JSONRPCMessage::BatchResponse(_) => Vec::new(),
+    }
+}
+
+/// Key of the request answered by `msg`, if `msg` is a response.
+fn response_key(msg: &JSONRPCMessage) -> Option<String> {
+    match msg {
+        JSONRPCMessage::Response(r) => Some(request_key(&r.id)),
+        JSONRPCMessage::Error(e) => Some(request_key(&e.id)),
+        _ => None,
+    }
+}
+
+/// `RequestId` is neither `Eq` nor `Hash`, so map it to a string that keeps
+/// `1` and `"1"` distinct.
+fn request_key(id: &RequestId) -> String {
+    match id {
+        RequestId::String(s) => format!("s:{s}"),
+        RequestId::Integer(i) => format!("i:{i}"),
+    }
+}
+
+fn to_sse_event(msg: &JSONRPCMessage) -> SseEvent {
+    match serde_json::to_string(msg) {
+        Ok(json) => SseEvent::default().event("message").data(json),
+        Err(e) => {
+            error!("Failed to serialize JSONRPCMessage: {e}");
+            SseEvent::default().comment("serialization error")
+        }
+    }
+}
+
+fn accepts_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .any(|v| v.contains("text/event-stream"))
+}
+
+/// Browsers always send `Origin`; reject cross-origin requests so a web page
+/// cannot drive a local server via DNS rebinding.
+fn origin_matches_host(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN).and_then(|v| v.to_str().ok()) else {
+        return true;
+    };
+    let Some(host) = headers.get(header::HOST).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    let authority = origin
+        .split_once("://")
+        .map_or(origin, |(_, rest)| rest)
+        .trim_end_matches('/');
+    authority.eq_ignore_ascii_case(host)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    json_rpc_error_response(status, INVALID_REQUEST, message.to_string())
+}
+
+fn json_rpc_error_response(status: StatusCode, code: i64, message: String) -> Response {
+    let body = json!({
+        "jsonrpc": mcp_types::JSONRPC_VERSION,
+        "id": null,
+        "error": { "code": code, "message": message },
+    });
+    (status, Json(body)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::expect_used)]
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    async fn start_server(auth_token: Option<&str>) -> String {
+        start_server_with_idle_timeout(auth_token, SESSION_IDLE_TIMEOUT).await
+    }
+
+    async fn start_server_with_idle_timeout(
+        auth_token: Option<&str>,
+        idle_timeout: Duration,
+    ) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(
+            listener,
+            auth_token.map(str::to_string),
+            None,
+            idle_timeout,
+        ));
+        format!("http://{addr}{MCP_ENDPOINT}")
+    }
+
+    async fn initialize_session(client: &reqwest::Client, url: &str) -> String {
+        let response = client
+            .post(url)
+            .header(header::ACCEPT, "application/json")
+            .json(&initialize_request())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        response
+            .headers()
+            .get(SESSION_ID_HEADER)
+            .expect("session header")
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    fn initialize_request() -> serde_json::Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": "2025-03-26",
+                "capabilities": {},
+                "clientInfo": { "name": "test", "version": "0.0.0" },
+            },
+        })
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_without_bearer_token() {
+        let url = start_server(Some("secret")).await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(&url)
+            .json(&initialize_request())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let response = client
+            .post(&url)
+            .bearer_auth("wrong")
+            .json(&initialize_request())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn session_lifecycle() {
+        let url = start_server(Some("secret")).await;
+        let client = reqwest::Client::new();
+
+        // initialize with a JSON-only client creates a session.
+        let response = client
+            .post(&url)
+            .bearer_auth("secret")
+            .header(header::ACCEPT, "application/json")
+            .json(&initialize_request())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let session_id = response
+            .headers()
+            .get(SESSION_ID_HEADER)
+            .expect("session header")
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["id"], json!(1));
+        assert_eq!(
+            body["result"]["serverInfo"]["name"],
+            json!("codex-mcp-server")
+        );
+
+        // Requests without a session are rejected.
+        let response = client
+            .post(&url)
+            .bearer_auth("secret")
+            .json(&json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        // Notifications are accepted without a body.
+        let response = client
+            .post(&url)
+            .bearer_auth("secret")
+            .header(SESSION_ID_HEADER, &session_id)
+            .json(&json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+
+        // Requests are answered over SSE when the client accepts it.
+        let response = client
+            .post(&url)
+            .bearer_auth("secret")
+            .header(SESSION_ID_HEADER, &session_id)
+            .header(header::ACCEPT, "application/json, text/event-stream")
+            .json(&json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/list" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/event-stream"
+        );
+        let body = response.text().await.unwrap();
+        let data = body
+            .lines()
+            .find_map(|line| line.strip_prefix("data: "))
+            .expect("SSE data line");
+        let message: serde_json::Value = serde_json::from_str(data).unwrap();
+        assert_eq!(message["id"], json!(3));
+        assert_eq!(message["result"]["tools"][0]["name"], json!("codex"));
+
+        // DELETE terminates the session.
+        let response = client
+            .delete(&url)
+            .bearer_auth("secret")
+            .header(SESSION_ID_HEADER, &session_id)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let response = client
+            .post(&url)
+            .bearer_auth("secret")
+            .header(SESSION_ID_HEADER, &session_id)
+            .json(&json!({ "jsonrpc": "2.0", "id": 4, "method": "ping" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn idle_sessions_expire() {
+        let url = start_server_with_idle_timeout(None, Duration::from_millis(200)).await;
+        let client = reqwest::Client::new();
+        let session_id = initialize_session(&client, &url).await;
+
+        // Activity keeps the session alive.
+        for id in 0..3 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let response = client
+                .post(&url)
+                .header(SESSION_ID_HEADER, &session_id)
+                .header(header::ACCEPT, "application/json")
+                .json(&json!({ "jsonrpc": "2.0", "id": id, "method": "tools/list" }))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::OK);
+        }
+
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        let response = client
+            .post(&url)
+            .header(SESSION_ID_HEADER, &session_id)
+            .json(&json!({ "jsonrpc": "2.0", "id": 9, "method": "tools/list" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn origin_must_match_host() {
+        let mut headers = HeaderMap::new();
+        assert!(origin_matches_host(&headers));
+
+        headers.insert(header::HOST, "127.0.0.1:8765".parse().unwrap());
+        headers.insert(header::ORIGIN, "http://127.0.0.1:8765".parse().unwrap());
+        assert!(origin_matches_host(&headers));
+
+        headers.insert(header::ORIGIN, "http://evil.example".parse().unwrap());
+        assert!(!origin_matches_host(&headers));
+    }
+}
@@ -14,11 +14,17 @@ use tracing::debug;
 use tracing::error;
 use tracing::info;
 
+mod cli;
+mod codex_tool_approval;
 mod codex_tool_config;
+mod codex_tool_result;
 mod codex_tool_runner;
+mod http_transport;
 mod json_to_toml;
 mod message_processor;
 
+pub use cli::Cli;
+
 use crate::message_processor::MessageProcessor;
 
 /// Size of the bounded channels used to communicate between tasks. The value
@@ -26,13 +32,23 @@ use crate::message_processor::MessageProcessor;
 /// plenty for an interactive CLI.
 const CHANNEL_CAPACITY: usize = 128;
 
-pub async fn run_main(codex_linux_sandbox_exe: Option<PathBuf>) -> IoResult<()> {
+pub async fn run_main(cli: Cli, codex_linux_sandbox_exe: Option<PathBuf>) -> IoResult<()> {
     // Install a simple subscriber so `tracing` output is visible.  Users can
     // control the log level with `RUST_LOG`.
     tracing_subscriber::fmt()
         .with_writer(std::io::stderr)
         .init();
 
+    match cli.listen {
+        Some(addr) => {
+            http_transport::run_http_server(addr, cli.auth_token, codex_linux_sandbox_exe).await
+        }
+        None => run_stdio(codex_linux_sandbox_exe).await,
+    }
+}
+
+/// Serve a single MCP client over stdin/stdout.
+async fn run_stdio(codex_linux_sandbox_exe: Option<PathBuf>) -> IoResult<()> {
     // Set up channels.
     let (incoming_tx, mut incoming_rx) = mpsc::channel::<JSONRPCMessage>(CHANNEL_CAPACITY);
     let (outgoing_tx, mut outgoing_rx) = mpsc::channel::<JSONRPCMessage>(CHANNEL_CAPACITY);
@@ -66,14 +82,7 @@ pub async fn run_main(codex_linux_sandbox_exe: Option<PathBuf>) -> IoResult<()>
         let mut processor = MessageProcessor::new(outgoing_tx.clone(), codex_linux_sandbox_exe);
         async move {
             while let Some(msg) = incoming_rx.recv().await {
-                match msg {
-                    JSONRPCMessage::Request(r) => processor.process_request(r),
-                    JSONRPCMessage::Response(r) => processor.process_response(r),
-                    JSONRPCMessage::Notification(n) => processor.process_notification(n),
-                    JSONRPCMessage::BatchRequest(b) => processor.process_batch_request(b),
-                    JSONRPCMessage::Error(e) => processor.process_error(e),
-                    JSONRPCMessage::BatchResponse(b) => processor.process_batch_response(b),
-                }
+                processor.process_message(msg);
             }
 
             info!("processor task exited (channel closed)");
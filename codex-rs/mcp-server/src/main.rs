@@ -1,8 +1,11 @@
+use clap::Parser;
+use codex_mcp_server::Cli;
 use codex_mcp_server::run_main;
 
 fn main() -> anyhow::Result<()> {
     codex_linux_sandbox::run_with_sandbox(|codex_linux_sandbox_exe| async move {
-        run_main(codex_linux_sandbox_exe).await?;
+        let cli = Cli::parse();
+        run_main(cli, codex_linux_sandbox_exe).await?;
         Ok(())
     })
 }
@@ -1,5 +1,7 @@
 use std::path::PathBuf;
+use std::sync::Arc;
 
+use crate::codex_tool_approval::PendingApprovals;
 use crate::codex_tool_config::CodexToolCallParam;
 use crate::codex_tool_config::create_tool_for_codex_tool_call_param;
 
@@ -25,17 +27,23 @@ use mcp_types::ServerNotification;
 use mcp_types::TextContent;
 use serde_json::json;
 use tokio::sync::mpsc;
-use tokio::task;
+use tokio::task::JoinSet;
 
 pub(crate) struct MessageProcessor {
     outgoing: mpsc::Sender<JSONRPCMessage>,
     initialized: bool,
     codex_linux_sandbox_exe: Option<PathBuf>,
+    /// Codex sessions started by `tools/call`. Dropping the processor aborts
+    /// any that are still running.
+    tool_sessions: JoinSet<()>,
+    /// Approval requests those sessions are waiting on the client for.
+    approvals: Arc<PendingApprovals>,
 }
 
 impl MessageProcessor {
     /// Create a new `MessageProcessor`, retaining a handle to the outgoing
-    /// `Sender` so handlers can enqueue messages to be written to stdout.
+    /// `Sender` so handlers can enqueue messages to be written back to the
+    /// client (stdout or an HTTP stream, depending on the transport).
     pub(crate) fn new(
         outgoing: mpsc::Sender<JSONRPCMessage>,
         codex_linux_sandbox_exe: Option<PathBuf>,
@@ -44,6 +52,20 @@ impl MessageProcessor {
             outgoing,
             initialized: false,
             codex_linux_sandbox_exe,
+            tool_sessions: JoinSet::new(),
+            approvals: Arc::new(PendingApprovals::default()),
+        }
+    }
+
+    /// Dispatch any incoming JSON-RPC message to the matching handler.
+    pub(crate) fn process_message(&mut self, msg: JSONRPCMessage) {
+        match msg {
+            JSONRPCMessage::Request(r) => self.process_request(r),
+            JSONRPCMessage::Response(r) => self.process_response(r),
+            JSONRPCMessage::Notification(n) => self.process_notification(n),
+            JSONRPCMessage::BatchRequest(b) => self.process_batch_request(b),
+            JSONRPCMessage::Error(e) => self.process_error(e),
+            JSONRPCMessage::BatchResponse(b) => self.process_batch_response(b),
         }
     }
 
@@ -106,6 +128,10 @@ impl MessageProcessor {
     /// Handle a standalone JSON-RPC response originating from the peer.
     pub(crate) fn process_response(&mut self, response: JSONRPCResponse) {
         tracing::info!("<- response: {:?}", response);
+        let JSONRPCResponse { id, result, .. } = response;
+        if !self.approvals.resolve(&id, Some(result)) {
+            tracing::warn!("Response to unknown request {id:?}");
+        }
     }
 
     /// Handle a fire-and-forget JSON-RPC notification.
@@ -163,6 +189,7 @@ impl MessageProcessor {
     /// Handle an error object received from the peer.
     pub(crate) fn process_error(&mut self, err: JSONRPCError) {
         tracing::error!("<- error: {:?}", err);
+        self.approvals.resolve(&err.id, None);
     }
 
     /// Handle a batch of responses/errors.
@@ -322,7 +349,7 @@ impl MessageProcessor {
     }
 
     fn handle_call_tool(
-        &self,
+        &mut self,
         id: RequestId,
         params: <mcp_types::CallToolRequest as mcp_types::ModelContextProtocolRequest>::Params,
     ) {
@@ -339,6 +366,7 @@ impl MessageProcessor {
                     annotations: None,
                 })],
                 is_error: Some(true),
+                structured_content: None,
             };
             self.send_response::<mcp_types::CallToolRequest>(id, result);
             return;
@@ -358,6 +386,7 @@ impl MessageProcessor {
                                 annotations: None,
                             })],
                             is_error: Some(true),
+                            structured_content: None,
                         };
                         self.send_response::<mcp_types::CallToolRequest>(id, result);
                         return;
@@ -371,6 +400,7 @@ impl MessageProcessor {
                             annotations: None,
                         })],
                         is_error: Some(true),
+                        structured_content: None,
                     };
                     self.send_response::<mcp_types::CallToolRequest>(id, result);
                     return;
@@ -386,6 +416,7 @@ impl MessageProcessor {
                         annotations: None,
                     })],
                     is_error: Some(true),
+                    structured_content: None,
                 };
                 self.send_response::<mcp_types::CallToolRequest>(id, result);
                 return;
@@ -394,13 +425,23 @@ impl MessageProcessor {
 
         // Clone outgoing sender to move into async task.
         let outgoing = self.outgoing.clone();
+        let approvals = self.approvals.clone();
+
+        // Reap sessions that have already finished.
+        while self.tool_sessions.try_join_next().is_some() {}
 
         // Spawn an async task to handle the Codex session so that we do not
         // block the synchronous message-processing loop.
-        task::spawn(async move {
+        self.tool_sessions.spawn(async move {
             // Run the Codex session and stream events back to the client.
-            crate::codex_tool_runner::run_codex_tool_session(id, initial_prompt, config, outgoing)
-                .await;
+            crate::codex_tool_runner::run_codex_tool_session(
+                id,
+                initial_prompt,
+                config,
+                outgoing,
+                approvals,
+            )
+            .await;
         });
     }
 
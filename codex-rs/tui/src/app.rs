@@ -79,6 +79,12 @@ impl<'a> App<'a> {
                         crossterm::event::Event::Resize(_, _) => {
                             app_event_tx.send(AppEvent::Redraw);
                         }
+                        crossterm::event::Event::FocusGained => {
+                            app_event_tx.send(AppEvent::FocusChanged(true));
+                        }
+                        crossterm::event::Event::FocusLost => {
+                            app_event_tx.send(AppEvent::FocusChanged(false));
+                        }
                         crossterm::event::Event::Mouse(MouseEvent {
                             kind: MouseEventKind::ScrollUp,
                             ..
@@ -219,11 +225,35 @@ impl<'a> App<'a> {
                     AppState::Chat { widget } => widget.submit_op(op),
                     AppState::Login { .. } | AppState::GitWarning { .. } => {}
                 },
+                AppEvent::FocusChanged(focused) => match &mut self.app_state {
+                    AppState::Chat { widget } => widget.set_terminal_focused(focused),
+                    AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                },
                 AppEvent::LatestLog(line) => match &mut self.app_state {
                     AppState::Chat { widget } => widget.update_latest_log(line),
                     AppState::Login { .. } | AppState::GitWarning { .. } => {}
                 },
                 AppEvent::DispatchCommand(command) => match command {
+                    SlashCommand::Model => match &mut self.app_state {
+                        AppState::Chat { widget } => widget.open_model_picker(),
+                        AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                    },
+                    SlashCommand::Approvals => match &mut self.app_state {
+                        AppState::Chat { widget } => widget.open_approvals_picker(),
+                        AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                    },
+                    SlashCommand::Diff => match &mut self.app_state {
+                        AppState::Chat { widget } => widget.submit_op(Op::GetSessionDiff),
+                        AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                    },
+                    SlashCommand::Undo => match &mut self.app_state {
+                        AppState::Chat { widget } => widget.submit_op(Op::Undo),
+                        AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                    },
+                    SlashCommand::Rules => match &mut self.app_state {
+                        AppState::Chat { widget } => widget.submit_op(Op::ListApprovalRules),
+                        AppState::Login { .. } | AppState::GitWarning { .. } => {}
+                    },
                     SlashCommand::Clear => match &mut self.app_state {
                         AppState::Chat { widget } => widget.clear_conversation_history(),
                         AppState::Login { .. } | AppState::GitWarning { .. } => {}
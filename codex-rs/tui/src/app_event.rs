@@ -11,6 +11,9 @@ pub(crate) enum AppEvent {
 
     KeyEvent(KeyEvent),
 
+    /// The terminal gained (`true`) or lost (`false`) focus.
+    FocusChanged(bool),
+
     /// Scroll event with a value representing the "scroll delta" as the net
     /// scroll up/down events within a short time window.
     Scroll(i32),
@@ -16,12 +16,15 @@ mod bottom_pane_view;
 mod chat_composer;
 mod chat_composer_history;
 mod command_popup;
+mod selection_view;
 mod status_indicator_view;
 
 pub(crate) use chat_composer::ChatComposer;
 pub(crate) use chat_composer::InputResult;
+pub(crate) use selection_view::SelectionItem;
 
 use approval_modal_view::ApprovalModalView;
+use selection_view::SelectionView;
 use status_indicator_view::StatusIndicatorView;
 
 /// Pane displayed in the lower half of the chat UI.
@@ -149,6 +152,13 @@ impl BottomPane<'_> {
         self.request_redraw()
     }
 
+    /// Show a list of choices in place of the composer, e.g. for `/model`.
+    pub(crate) fn show_selection_view(&mut self, title: String, items: Vec<SelectionItem>) {
+        let view = SelectionView::new(title, items, self.app_event_tx.clone());
+        self.active_view = Some(Box::new(view));
+        self.request_redraw()
+    }
+
     /// Height (terminal rows) required by the current bottom pane.
     pub fn calculate_required_height(&self, area: &Rect) -> u16 {
         if let Some(view) = &self.active_view {
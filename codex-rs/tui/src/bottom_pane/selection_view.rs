@@ -0,0 +1,132 @@
+use codex_core::protocol::Op;
+use crossterm::event::KeyCode;
+use crossterm::event::KeyEvent;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Alignment;
+use ratatui::layout::Rect;
+use ratatui::style::Color;
+use ratatui::style::Style;
+use ratatui::style::Stylize;
+use ratatui::text::Line;
+use ratatui::text::Span;
+use ratatui::widgets::Block;
+use ratatui::widgets::BorderType;
+use ratatui::widgets::Borders;
+use ratatui::widgets::Paragraph;
+use ratatui::widgets::Widget;
+
+use crate::app_event::AppEvent;
+use crate::app_event_sender::AppEventSender;
+
+use super::BottomPane;
+use super::BottomPaneView;
+
+/// Rows taken by the border around the list.
+const BORDER_LINES: u16 = 2;
+
+/// One choice in a [`SelectionView`].
+pub(crate) struct SelectionItem {
+    pub(crate) label: String,
+    pub(crate) description: Option<String>,
+    /// Whether this is the setting currently in effect.
+    pub(crate) is_current: bool,
+    /// Sent to the agent when the item is chosen.
+    pub(crate) op: Op,
+}
+
+/// A list of choices, such as the models offered by `/model`. Choosing one
+/// submits its `Op`; esc closes the list without changing anything.
+pub(crate) struct SelectionView {
+    title: String,
+    items: Vec<SelectionItem>,
+    selected_idx: usize,
+    app_event_tx: AppEventSender,
+    complete: bool,
+}
+
+impl SelectionView {
+    pub(crate) fn new(
+        title: String,
+        items: Vec<SelectionItem>,
+        app_event_tx: AppEventSender,
+    ) -> Self {
+        let selected_idx = items.iter().position(|item| item.is_current).unwrap_or(0);
+        Self {
+            title,
+            items,
+            selected_idx,
+            app_event_tx,
+            complete: false,
+        }
+    }
+}
+
+impl<'a> BottomPaneView<'a> for SelectionView {
+    fn handle_key_event(&mut self, _pane: &mut BottomPane<'a>, key_event: KeyEvent) {
+        let len = self.items.len();
+        match key_event.code {
+            KeyCode::Up if len > 0 => {
+                self.selected_idx = (self.selected_idx + len - 1) % len;
+            }
+            KeyCode::Down if len > 0 => {
+                self.selected_idx = (self.selected_idx + 1) % len;
+            }
+            KeyCode::Enter => {
+                if let Some(item) = self.items.get(self.selected_idx) {
+                    self.app_event_tx.send(AppEvent::CodexOp(item.op.clone()));
+                }
+                self.complete = true;
+            }
+            KeyCode::Esc => {
+                self.complete = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    fn calculate_required_height(&self, _area: &Rect) -> u16 {
+        self.items.len().max(1) as u16 + BORDER_LINES
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer) {
+        let lines: Vec<Line> = if self.items.is_empty() {
+            vec![Line::from("Nothing to choose from".italic())]
+        } else {
+            self.items
+                .iter()
+                .enumerate()
+                .map(|(idx, item)| {
+                    let (prefix, style) = if idx == self.selected_idx {
+                        ("▶", Style::default().fg(Color::LightBlue))
+                    } else {
+                        (" ", Style::default())
+                    };
+                    let mut spans = vec![Span::styled(format!(" {prefix} {}", item.label), style)];
+                    if item.is_current {
+                        spans.push(" (current)".dim());
+                    }
+                    if let Some(description) = &item.description {
+                        spans.push(format!("  {description}").dim());
+                    }
+                    Line::from(spans)
+                })
+                .collect()
+        };
+
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title(self.title.clone())
+                    .title_bottom(
+                        Line::from("enter to select | esc to cancel").alignment(Alignment::Right),
+                    )
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .render(area, buf);
+    }
+}
@@ -3,9 +3,11 @@ use std::sync::Arc;
 
 use codex_core::codex_wrapper::init_codex;
 use codex_core::config::Config;
+use codex_core::model_family::find_family_for_model;
 use codex_core::protocol::AgentMessageEvent;
 use codex_core::protocol::AgentReasoningEvent;
 use codex_core::protocol::ApplyPatchApprovalRequestEvent;
+use codex_core::protocol::BudgetExceededEvent;
 use codex_core::protocol::ErrorEvent;
 use codex_core::protocol::Event;
 use codex_core::protocol::EventMsg;
@@ -15,9 +17,12 @@ use codex_core::protocol::ExecCommandEndEvent;
 use codex_core::protocol::InputItem;
 use codex_core::protocol::McpToolCallBeginEvent;
 use codex_core::protocol::McpToolCallEndEvent;
+use codex_core::protocol::ModelFallbackEvent;
 use codex_core::protocol::Op;
 use codex_core::protocol::PatchApplyBeginEvent;
+use codex_core::protocol::SessionDiffEvent;
 use codex_core::protocol::TaskCompleteEvent;
+use codex_core::protocol::TurnDiffEvent;
 use crossterm::event::KeyEvent;
 use ratatui::buffer::Buffer;
 use ratatui::layout::Constraint;
@@ -35,7 +40,10 @@ use crate::bottom_pane::BottomPane;
 use crate::bottom_pane::BottomPaneParams;
 use crate::bottom_pane::InputResult;
 use crate::conversation_history_widget::ConversationHistoryWidget;
+use crate::exec_command::strip_bash_lc_and_escape;
 use crate::history_cell::PatchEventType;
+use crate::terminal_notification::TerminalNotifier;
+use crate::turn_context_picker;
 use crate::user_approval_widget::ApprovalRequest;
 
 pub(crate) struct ChatWidget<'a> {
@@ -46,6 +54,7 @@ pub(crate) struct ChatWidget<'a> {
     input_focus: InputFocus,
     config: Config,
     initial_user_message: Option<UserMessage>,
+    terminal_notifier: TerminalNotifier,
 }
 
 #[derive(Clone, Copy, Eq, PartialEq)]
@@ -126,6 +135,7 @@ impl ChatWidget<'_> {
                 has_input_focus: true,
             }),
             input_focus: InputFocus::BottomPane,
+            terminal_notifier: TerminalNotifier::new(config.tui.notifications),
             config,
             initial_user_message: create_initial_user_message(
                 initial_prompt.unwrap_or_default(),
@@ -134,6 +144,10 @@ impl ChatWidget<'_> {
         }
     }
 
+    pub(crate) fn set_terminal_focused(&mut self, focused: bool) {
+        self.terminal_notifier.set_focused(focused);
+    }
+
     pub(crate) fn handle_key_event(&mut self, key_event: KeyEvent) {
         // Special-case <Tab>: normally toggles focus between history and bottom panes.
         // However, when the slash-command popup is visible we forward the key
@@ -259,11 +273,20 @@ impl ChatWidget<'_> {
                 self.conversation_history.add_error(message);
                 self.bottom_pane.set_task_running(false);
             }
+            EventMsg::BudgetExceeded(BudgetExceededEvent { message, .. }) => {
+                self.conversation_history
+                    .add_error(format!("Budget exceeded: {message}"));
+                self.bottom_pane.set_task_running(false);
+            }
             EventMsg::ExecApprovalRequest(ExecApprovalRequestEvent {
                 command,
                 cwd,
                 reason,
             }) => {
+                self.terminal_notifier.notify(&format!(
+                    "Codex wants to run `{}`",
+                    strip_bash_lc_and_escape(&command)
+                ));
                 let request = ApprovalRequest::Exec {
                     id,
                     command,
@@ -288,14 +311,19 @@ impl ChatWidget<'_> {
                 // prompt before they have seen *what* is being requested.
                 // ------------------------------------------------------------------
 
+                self.terminal_notifier.notify(&match changes.len() {
+                    1 => "Codex wants to edit 1 file".to_string(),
+                    n => format!("Codex wants to edit {n} files"),
+                });
                 self.conversation_history
-                    .add_patch_event(PatchEventType::ApprovalRequest, changes);
+                    .add_patch_event(PatchEventType::ApprovalRequest, changes.clone());
 
                 self.conversation_history.scroll_to_bottom();
 
                 // Now surface the approval request in the BottomPane as before.
                 let request = ApprovalRequest::ApplyPatch {
                     id,
+                    changes,
                     reason,
                     grant_root,
                 };
@@ -352,6 +380,32 @@ impl ChatWidget<'_> {
                     .record_completed_mcp_tool_call(call_id, success, result);
                 self.request_redraw();
             }
+            EventMsg::FileToolCall(event) => {
+                self.conversation_history.add_file_tool_call(event);
+                self.request_redraw();
+            }
+            EventMsg::TurnDiff(TurnDiffEvent { unified_diff }) => {
+                self.conversation_history.add_turn_diff(&unified_diff);
+                self.request_redraw();
+            }
+            EventMsg::SessionDiff(SessionDiffEvent { unified_diff }) => {
+                self.conversation_history.add_session_diff(&unified_diff);
+                self.request_redraw();
+            }
+            EventMsg::ApprovalRules(event) => {
+                self.conversation_history.add_approval_rules(event);
+                self.request_redraw();
+            }
+            EventMsg::ModelFallback(ModelFallbackEvent {
+                model,
+                provider,
+                reason,
+            }) => {
+                self.conversation_history.add_background_event(format!(
+                    "Switched to {model} ({provider}) after: {reason}"
+                ));
+                self.request_redraw();
+            }
             EventMsg::GetHistoryEntryResponse(event) => {
                 let codex_core::protocol::GetHistoryEntryResponseEvent {
                     offset,
@@ -394,8 +448,60 @@ impl ChatWidget<'_> {
         self.request_redraw();
     }
 
+    pub(crate) fn open_model_picker(&mut self) {
+        let items = turn_context_picker::model_items(&self.config);
+        self.bottom_pane
+            .show_selection_view("Model".to_string(), items);
+    }
+
+    pub(crate) fn open_approvals_picker(&mut self) {
+        let items = turn_context_picker::approval_items(&self.config);
+        self.bottom_pane
+            .show_selection_view("Approvals".to_string(), items);
+    }
+
     /// Forward an `Op` directly to codex.
-    pub(crate) fn submit_op(&self, op: Op) {
+    pub(crate) fn submit_op(&mut self, op: Op) {
+        if let Op::OverrideTurnContext {
+            provider,
+            model,
+            model_reasoning_effort,
+            model_reasoning_summary,
+            approval_policy,
+            sandbox_policy,
+        } = &op
+        {
+            // Keep our copy of the config in step so that pickers and
+            // session info reflect what the agent is now using.
+            if let Some(provider) = provider {
+                if let Some((id, _)) = self
+                    .config
+                    .model_providers
+                    .iter()
+                    .find(|(_, info)| *info == provider)
+                {
+                    self.config.model_provider_id = id.clone();
+                }
+                self.config.model_provider = provider.clone();
+            }
+            if let Some(model) = model {
+                self.config.model_family =
+                    find_family_for_model(model, &self.config.model_families);
+                self.config.model = model.clone();
+            }
+            if let Some(effort) = model_reasoning_effort {
+                self.config.model_reasoning_effort = *effort;
+            }
+            if let Some(summary) = model_reasoning_summary {
+                self.config.model_reasoning_summary = *summary;
+            }
+            if let Some(approval_policy) = approval_policy {
+                self.config.approval_policy = *approval_policy;
+            }
+            if let Some(sandbox_policy) = sandbox_policy {
+                self.config.sandbox_policy = sandbox_policy.clone();
+            }
+        }
         if let Err(e) = self.codex_op_tx.send(op) {
             tracing::error!("failed to submit op: {e}");
         }
@@ -2,6 +2,7 @@ use clap::ArgAction;
 use clap::Parser;
 use codex_common::ApprovalModeCliArg;
 use codex_common::CliConfigOverrides;
+use codex_common::SandboxModeCliArg;
 use codex_common::SandboxPermissionOption;
 use std::path::PathBuf;
 
@@ -35,6 +36,15 @@ pub struct Cli {
     #[arg(long = "full-auto", default_value_t = false)]
     pub full_auto: bool,
 
+    /// Select the sandbox for commands run by the model. Cannot be combined
+    /// with --full-auto or --sandbox-permission.
+    #[arg(
+        long = "sandbox",
+        value_name = "MODE",
+        conflicts_with_all = ["full_auto", "permissions"]
+    )]
+    pub sandbox_mode: Option<SandboxModeCliArg>,
+
     #[clap(flatten)]
     pub sandbox: SandboxPermissionOption,
 
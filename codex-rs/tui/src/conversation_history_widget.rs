@@ -3,7 +3,9 @@ use crate::history_cell::CommandOutput;
 use crate::history_cell::HistoryCell;
 use crate::history_cell::PatchEventType;
 use codex_core::config::Config;
+use codex_core::protocol::ApprovalRulesEvent;
 use codex_core::protocol::FileChange;
+use codex_core::protocol::FileToolCallEvent;
 use codex_core::protocol::SessionConfiguredEvent;
 use crossterm::event::KeyCode;
 use crossterm::event::KeyEvent;
@@ -235,6 +237,22 @@ impl ConversationHistoryWidget {
         ));
     }
 
+    pub fn add_file_tool_call(&mut self, event: FileToolCallEvent) {
+        self.add_to_history(HistoryCell::new_file_tool_call(event));
+    }
+
+    pub fn add_turn_diff(&mut self, unified_diff: &str) {
+        self.add_to_history(HistoryCell::new_turn_diff(unified_diff));
+    }
+
+    pub fn add_session_diff(&mut self, unified_diff: &str) {
+        self.add_to_history(HistoryCell::new_session_diff(unified_diff));
+    }
+
+    pub fn add_approval_rules(&mut self, event: ApprovalRulesEvent) {
+        self.add_to_history(HistoryCell::new_approval_rules(event));
+    }
+
     fn add_to_history(&mut self, cell: HistoryCell) {
         let width = self.cached_width.get();
         let count = if width > 0 { cell.height(width) } else { 0 };
@@ -20,6 +20,23 @@ pub(crate) fn strip_bash_lc_and_escape(command: &[String]) -> String {
     }
 }
 
+/// Turns the user's edit of the string produced by [`strip_bash_lc_and_escape`]
+/// back into an argv. Commands that were run through `bash -lc` stay that way;
+/// anything else is split with shell quoting rules. Returns `None` if the
+/// result would be empty or the quoting is unbalanced.
+pub(crate) fn parse_edited_command(original: &[String], edited: &str) -> Option<Vec<String>> {
+    let edited = edited.trim();
+    if edited.is_empty() {
+        return None;
+    }
+    match original {
+        [first, second, _] if first == "bash" && second == "-lc" => {
+            Some(vec![first.clone(), second.clone(), edited.to_string()])
+        }
+        _ => shlex::split(edited).filter(|argv| !argv.is_empty()),
+    }
+}
+
 /// If `path` is absolute and inside $HOME, return the part *after* the home
 /// directory; otherwise, return the path as-is. Note if `path` is the homedir,
 /// this will return and empty path.
@@ -59,4 +76,25 @@ mod tests {
         let cmdline = strip_bash_lc_and_escape(&args);
         assert_eq!(cmdline, "echo hello");
     }
+
+    #[test]
+    fn test_parse_edited_command() {
+        let bash: Vec<String> = vec!["bash".into(), "-lc".into(), "git commit".into()];
+        assert_eq!(
+            parse_edited_command(&bash, "git commit --no-verify"),
+            Some(vec![
+                "bash".into(),
+                "-lc".into(),
+                "git commit --no-verify".into()
+            ])
+        );
+
+        let argv: Vec<String> = vec!["ls".into(), "src".into()];
+        assert_eq!(
+            parse_edited_command(&argv, "ls 'src/my dir'"),
+            Some(vec!["ls".into(), "src/my dir".into()])
+        );
+        assert_eq!(parse_edited_command(&argv, "ls 'src"), None);
+        assert_eq!(parse_edited_command(&argv, "  "), None);
+    }
 }
@@ -8,8 +8,9 @@ use codex_ansi_escape::ansi_escape_line;
 use codex_common::elapsed::format_duration;
 use codex_core::WireApi;
 use codex_core::config::Config;
-use codex_core::model_supports_reasoning_summaries;
+use codex_core::protocol::ApprovalRulesEvent;
 use codex_core::protocol::FileChange;
+use codex_core::protocol::FileToolCallEvent;
 use codex_core::protocol::SessionConfiguredEvent;
 use image::DynamicImage;
 use image::GenericImageView;
@@ -84,6 +85,17 @@ pub(crate) enum HistoryCell {
     /// Completed MCP tool call where we show the result serialized as JSON.
     CompletedMcpToolCall { view: TextBlock },
 
+    /// Completed call to one of the built-in file tools. These are shown as a
+    /// single line since their output is only meant for the model.
+    CompletedFileToolCall { view: TextBlock },
+
+    /// Diff of the changes made during a task (summary only) or during the
+    /// whole session (shown in full, requested via `/diff`).
+    Diff { view: TextBlock },
+
+    /// Approval rules saved for the current project (requested via `/rules`).
+    ApprovalRules { view: TextBlock },
+
     /// Completed MCP tool call where the result is an image.
     /// Admittedly, [mcp_types::CallToolResult] can have multiple content types,
     /// which could be a mix of text and images, so we need to tighten this up.
@@ -152,10 +164,10 @@ impl HistoryCell {
                 ("model", config.model.clone()),
                 ("provider", config.model_provider_id.clone()),
                 ("approval", format!("{:?}", config.approval_policy)),
-                ("sandbox", format!("{:?}", config.sandbox_policy)),
+                ("sandbox", config.sandbox_policy.to_string()),
             ];
             if config.model_provider.wire_api == WireApi::Responses
-                && model_supports_reasoning_summaries(&config.model)
+                && config.model_family.supports_reasoning
             {
                 entries.push((
                     "reasoning effort",
@@ -449,6 +461,106 @@ impl HistoryCell {
         }
     }
 
+    pub(crate) fn new_file_tool_call(event: FileToolCallEvent) -> Self {
+        let FileToolCallEvent {
+            call_id: _,
+            invocation,
+            summary,
+            success,
+        } = event;
+        let summary = if success {
+            summary.dim()
+        } else {
+            summary.red()
+        };
+        let lines: Vec<Line<'static>> = vec![
+            Line::from(vec![
+                "file ".magenta(),
+                invocation.to_string().into(),
+                " · ".dim(),
+                summary,
+            ]),
+            Line::from(""),
+        ];
+        HistoryCell::CompletedFileToolCall {
+            view: TextBlock::new(lines),
+        }
+    }
+
+    /// One line per changed file with its added/removed line counts.
+    pub(crate) fn new_turn_diff(unified_diff: &str) -> Self {
+        let mut lines: Vec<Line<'static>> = vec![Line::from("turn diff".magenta().bold())];
+        for (path, added, removed) in diff_file_stats(unified_diff) {
+            lines.push(Line::from(vec![
+                path.into(),
+                " ".into(),
+                format!("+{added}").green(),
+                " ".into(),
+                format!("-{removed}").red(),
+            ]));
+        }
+        lines.push(Line::from(
+            "Use /diff to see all changes made in this session.".dim(),
+        ));
+        lines.push(Line::from(""));
+        HistoryCell::Diff {
+            view: TextBlock::new(lines),
+        }
+    }
+
+    pub(crate) fn new_session_diff(unified_diff: &str) -> Self {
+        let mut lines: Vec<Line<'static>> = vec![Line::from("session diff".magenta().bold())];
+        if unified_diff.is_empty() {
+            lines.push(Line::from("No changes.".dim()));
+        }
+        for line in unified_diff.lines() {
+            let line = line.to_string();
+            let styled = if line.starts_with("diff --git ")
+                || line.starts_with("+++ ")
+                || line.starts_with("--- ")
+            {
+                line.bold()
+            } else if line.starts_with('+') {
+                line.green()
+            } else if line.starts_with('-') {
+                line.red()
+            } else if line.starts_with("@@") {
+                line.cyan()
+            } else {
+                line.into()
+            };
+            lines.push(Line::from(styled));
+        }
+        lines.push(Line::from(""));
+        HistoryCell::Diff {
+            view: TextBlock::new(lines),
+        }
+    }
+
+    pub(crate) fn new_approval_rules(event: ApprovalRulesEvent) -> Self {
+        let ApprovalRulesEvent { project, rules } = event;
+        let mut lines: Vec<Line<'static>> = vec![
+            Line::from("approval rules".magenta().bold()),
+            Line::from(format!("for {}", project.display()).dim()),
+        ];
+        if rules.is_empty() {
+            lines.push(Line::from(
+                "None yet. Choose \"always allow\" when approving a request to add one.".dim(),
+            ));
+        } else {
+            for (idx, rule) in rules.iter().enumerate() {
+                lines.push(Line::from(format!("{}. {rule}", idx + 1)));
+            }
+            lines.push(Line::from(
+                "Revoke a rule with `codex rules revoke <number>`.".dim(),
+            ));
+        }
+        lines.push(Line::from(""));
+        HistoryCell::ApprovalRules {
+            view: TextBlock::new(lines),
+        }
+    }
+
     pub(crate) fn new_background_event(message: String) -> Self {
         let mut lines: Vec<Line<'static>> = Vec::new();
         lines.push(Line::from("event".dim()));
@@ -552,6 +664,9 @@ impl CellWidget for HistoryCell {
             | HistoryCell::SessionInfo { view }
             | HistoryCell::CompletedExecCommand { view }
             | HistoryCell::CompletedMcpToolCall { view }
+            | HistoryCell::CompletedFileToolCall { view }
+            | HistoryCell::Diff { view }
+            | HistoryCell::ApprovalRules { view }
             | HistoryCell::PendingPatch { view }
             | HistoryCell::ActiveExecCommand { view, .. }
             | HistoryCell::ActiveMcpToolCall { view, .. } => view.height(width),
@@ -573,6 +688,9 @@ impl CellWidget for HistoryCell {
             | HistoryCell::SessionInfo { view }
             | HistoryCell::CompletedExecCommand { view }
             | HistoryCell::CompletedMcpToolCall { view }
+            | HistoryCell::CompletedFileToolCall { view }
+            | HistoryCell::Diff { view }
+            | HistoryCell::ApprovalRules { view }
             | HistoryCell::PendingPatch { view }
             | HistoryCell::ActiveExecCommand { view, .. }
             | HistoryCell::ActiveMcpToolCall { view, .. } => {
@@ -759,3 +877,67 @@ fn ensure_image_cache(
 
     height_rows
 }
+
+/// Returns `(path, added, removed)` for every file in a git-style unified diff.
+fn diff_file_stats(unified_diff: &str) -> Vec<(String, usize, usize)> {
+    let mut stats: Vec<(String, usize, usize)> = Vec::new();
+    // `---`/`+++` are only file headers before the first `@@` of a file;
+    // inside a hunk they are removed/added lines such as `-- comment`.
+    let mut in_hunk = false;
+    for line in unified_diff.lines() {
+        if let Some(rest) = line.strip_prefix("diff --git a/") {
+            let path = rest
+                .split_once(" b/")
+                .map_or(rest, |(_, new_path)| new_path)
+                .to_string();
+            stats.push((path, 0, 0));
+            in_hunk = false;
+        } else if line.starts_with("@@") {
+            in_hunk = true;
+        } else if !in_hunk {
+            continue;
+        } else if let Some((_, added, removed)) = stats.last_mut() {
+            if line.starts_with('+') {
+                *added += 1;
+            } else if line.starts_with('-') {
+                *removed += 1;
+            }
+        }
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn diff_file_stats_counts_lines_that_look_like_headers() {
+        let diff = [
+            "diff --git a/schema.sql b/schema.sql",
+            "index 1111111..2222222 100644",
+            "--- a/schema.sql",
+            "+++ b/schema.sql",
+            "@@ -1,3 +1,3 @@",
+            "--- drop the old table",
+            "+++ counter",
+            " CREATE TABLE t (id INT);",
+            "-DROP TABLE old;",
+            "diff --git a/new.md b/new.md",
+            "new file mode 100644",
+            "--- /dev/null",
+            "+++ b/new.md",
+            "@@ -0,0 +1,1 @@",
+            "+---",
+        ]
+        .join("\n");
+        assert_eq!(
+            diff_file_stats(&diff),
+            vec![
+                ("schema.sql".to_string(), 1, 2),
+                ("new.md".to_string(), 1, 0),
+            ]
+        );
+    }
+}
@@ -35,12 +35,15 @@ mod log_layer;
 mod login_screen;
 mod markdown;
 mod mouse_capture;
+mod patch_review;
 mod scroll_event_helper;
 mod slash_command;
 mod status_indicator_widget;
+mod terminal_notification;
 mod text_block;
 mod text_formatting;
 mod tui;
+mod turn_context_picker;
 mod user_approval_widget;
 
 pub use cli::Cli;
@@ -62,11 +65,13 @@ pub fn run_main(cli: Cli, codex_linux_sandbox_exe: Option<PathBuf>) -> std::io::
             model: cli.model.clone(),
             approval_policy,
             sandbox_policy,
+            sandbox_mode: cli.sandbox_mode.map(Into::into),
             cwd: cli.cwd.clone().map(|p| p.canonicalize().unwrap_or(p)),
             model_provider: cli.provider.clone(),
             config_profile: cli.config_profile.clone(),
             codex_linux_sandbox_exe,
             stream: Some(cli.stream),
+            output_schema: None,
         };
         // Parse `-c` overrides from the CLI.
         let cli_kv_overrides = match cli.config_overrides.parse_overrides() {
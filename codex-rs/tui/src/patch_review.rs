@@ -0,0 +1,264 @@
+//! Selection state for reviewing a proposed patch file by file and hunk by
+//! hunk inside the approval modal.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use codex_core::protocol::FileChange;
+use codex_core::protocol::RejectedFileChange;
+use ratatui::prelude::*;
+use ratatui::text::Line;
+
+/// Maximum number of file/hunk rows shown at once; the list scrolls to keep
+/// the cursor visible.
+const MAX_VISIBLE_ROWS: usize = 10;
+
+/// Maximum number of lines of the highlighted hunk shown below the list.
+const MAX_PREVIEW_LINES: usize = 8;
+
+struct ReviewFile {
+    path: PathBuf,
+    /// One-letter kind, as in `git status --short`.
+    kind: &'static str,
+    hunks: Vec<String>,
+    /// One flag per hunk, or a single flag for adds and deletes.
+    accepted: Vec<bool>,
+}
+
+#[derive(Clone, Copy)]
+enum Row {
+    File(usize),
+    Hunk(usize, usize),
+}
+
+pub(crate) struct PatchReview {
+    files: Vec<ReviewFile>,
+    rows: Vec<Row>,
+    cursor: usize,
+}
+
+impl PatchReview {
+    pub(crate) fn new(changes: &HashMap<PathBuf, FileChange>) -> Self {
+        let mut files: Vec<ReviewFile> = changes
+            .iter()
+            .map(|(path, change)| {
+                let kind = match change {
+                    FileChange::Add { .. } => "A",
+                    FileChange::Delete => "D",
+                    FileChange::Update {
+                        move_path: Some(_), ..
+                    } => "R",
+                    FileChange::Update { .. } => "M",
+                };
+                let hunks: Vec<String> = change.hunks().into_iter().map(str::to_string).collect();
+                let accepted = vec![true; hunks.len().max(1)];
+                ReviewFile {
+                    path: path.clone(),
+                    kind,
+                    hunks,
+                    accepted,
+                }
+            })
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut rows = Vec::new();
+        for (file_idx, file) in files.iter().enumerate() {
+            rows.push(Row::File(file_idx));
+            rows.extend((0..file.hunks.len()).map(|hunk_idx| Row::Hunk(file_idx, hunk_idx)));
+        }
+
+        Self {
+            files,
+            rows,
+            cursor: 0,
+        }
+    }
+
+    pub(crate) fn move_up(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub(crate) fn move_down(&mut self) {
+        if self.cursor + 1 < self.rows.len() {
+            self.cursor += 1;
+        }
+    }
+
+    /// Toggles the row under the cursor. Toggling a file accepts all of its
+    /// hunks unless they were all accepted already, in which case it rejects
+    /// them.
+    pub(crate) fn toggle(&mut self) {
+        match self.rows.get(self.cursor) {
+            Some(Row::File(file_idx)) => {
+                let accepted = &mut self.files[*file_idx].accepted;
+                let new_value = !accepted.iter().all(|a| *a);
+                accepted.iter_mut().for_each(|a| *a = new_value);
+            }
+            Some(Row::Hunk(file_idx, hunk_idx)) => {
+                let flag = &mut self.files[*file_idx].accepted[*hunk_idx];
+                *flag = !*flag;
+            }
+            None => {}
+        }
+    }
+
+    /// True if the user deselected everything.
+    pub(crate) fn all_rejected(&self) -> bool {
+        self.files
+            .iter()
+            .all(|file| file.accepted.iter().all(|a| !*a))
+    }
+
+    /// The parts of the patch the user deselected.
+    pub(crate) fn rejected_changes(&self) -> Vec<RejectedFileChange> {
+        self.files
+            .iter()
+            .filter_map(|file| {
+                if file.accepted.iter().all(|a| !*a) {
+                    Some(RejectedFileChange {
+                        path: file.path.clone(),
+                        hunks: None,
+                    })
+                } else if file.accepted.iter().all(|a| *a) {
+                    None
+                } else {
+                    let hunks = (0..file.accepted.len())
+                        .filter(|idx| !file.accepted[*idx])
+                        .collect();
+                    Some(RejectedFileChange {
+                        path: file.path.clone(),
+                        hunks: Some(hunks),
+                    })
+                }
+            })
+            .collect()
+    }
+
+    /// Lines for the row list followed by a preview of the highlighted hunk.
+    pub(crate) fn lines(&self) -> Vec<Line<'static>> {
+        let first_row = (self.cursor + 1).saturating_sub(MAX_VISIBLE_ROWS);
+        let mut lines: Vec<Line<'static>> = self
+            .rows
+            .iter()
+            .enumerate()
+            .skip(first_row)
+            .take(MAX_VISIBLE_ROWS)
+            .map(|(row_idx, row)| {
+                let prefix = if row_idx == self.cursor { "▶" } else { " " };
+                let line = match *row {
+                    Row::File(file_idx) => {
+                        let file = &self.files[file_idx];
+                        let checkbox = if file.accepted.iter().all(|a| *a) {
+                            "[x]"
+                        } else if file.accepted.iter().any(|a| *a) {
+                            "[~]"
+                        } else {
+                            "[ ]"
+                        };
+                        format!(
+                            "  {prefix} {checkbox} {} {}",
+                            file.kind,
+                            file.path.display()
+                        )
+                    }
+                    Row::Hunk(file_idx, hunk_idx) => {
+                        let file = &self.files[file_idx];
+                        let checkbox = if file.accepted[hunk_idx] {
+                            "[x]"
+                        } else {
+                            "[ ]"
+                        };
+                        let header = file.hunks[hunk_idx].lines().next().unwrap_or_default();
+                        format!("  {prefix}     {checkbox} {header}")
+                    }
+                };
+                if row_idx == self.cursor {
+                    Line::styled(line, Style::new().fg(Color::Blue))
+                } else {
+                    Line::from(line)
+                }
+            })
+            .collect();
+
+        if let Some(Row::Hunk(file_idx, hunk_idx)) = self.rows.get(self.cursor) {
+            let hunk = &self.files[*file_idx].hunks[*hunk_idx];
+            for line in hunk.lines().skip(1).take(MAX_PREVIEW_LINES) {
+                let text = format!("      {line}");
+                lines.push(if line.starts_with('+') {
+                    text.green().into()
+                } else if line.starts_with('-') {
+                    text.red().into()
+                } else {
+                    text.dim().into()
+                });
+            }
+        }
+
+        lines.push(Line::from(
+            "↑/↓ move · space toggle · enter apply selected · esc back".dim(),
+        ));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn review() -> PatchReview {
+        PatchReview::new(&HashMap::from([
+            (
+                PathBuf::from("a.txt"),
+                FileChange::Update {
+                    unified_diff: "@@ -1 +1 @@\n-a\n+b\n@@ -9 +9 @@\n-c\n+d\n".to_string(),
+                    move_path: None,
+                },
+            ),
+            (PathBuf::from("b.txt"), FileChange::Delete),
+        ]))
+    }
+
+    #[test]
+    fn toggling_hunks_and_files() {
+        let mut review = review();
+        assert_eq!(review.rejected_changes(), Vec::new());
+
+        // Rows: a.txt, hunk 0, hunk 1, b.txt.
+        review.move_down();
+        review.move_down();
+        review.toggle();
+        review.move_down();
+        review.toggle();
+        assert_eq!(
+            review.rejected_changes(),
+            vec![
+                RejectedFileChange {
+                    path: PathBuf::from("a.txt"),
+                    hunks: Some(vec![1]),
+                },
+                RejectedFileChange {
+                    path: PathBuf::from("b.txt"),
+                    hunks: None,
+                },
+            ]
+        );
+        assert!(!review.all_rejected());
+
+        // Toggling a partially accepted file accepts all of it.
+        review.move_up();
+        review.move_up();
+        review.move_up();
+        review.toggle();
+        assert_eq!(
+            review.rejected_changes(),
+            vec![RejectedFileChange {
+                path: PathBuf::from("b.txt"),
+                hunks: None,
+            }]
+        );
+    }
+}
@@ -12,6 +12,11 @@ use strum_macros::IntoStaticStr;
 )]
 #[strum(serialize_all = "kebab-case")]
 pub enum SlashCommand {
+    Model,
+    Approvals,
+    Diff,
+    Undo,
+    Rules,
     Clear,
     ToggleMouseMode,
     Quit,
@@ -21,6 +26,11 @@ impl SlashCommand {
     /// User-visible description shown in the popup.
     pub fn description(self) -> &'static str {
         match self {
+            SlashCommand::Model => "Switch the model or its reasoning effort.",
+            SlashCommand::Approvals => "Choose when Codex asks before running commands.",
+            SlashCommand::Diff => "Show all file changes made in this session.",
+            SlashCommand::Undo => "Revert file changes made during the last task.",
+            SlashCommand::Rules => "List the approval rules saved for this project.",
             SlashCommand::Clear => "Clear the chat history.",
             SlashCommand::ToggleMouseMode => {
                 "Toggle mouse mode (enable for scrolling, disable for text selection)"
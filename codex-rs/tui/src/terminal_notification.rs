@@ -0,0 +1,95 @@
+//! Desktop notifications sent through the terminal when Codex needs the user
+//! while they are looking at another window.
+
+use std::io::Write;
+use std::io::stdout;
+
+use codex_core::config_types::TerminalNotificationMethod;
+
+pub(crate) struct TerminalNotifier {
+    method: TerminalNotificationMethod,
+    /// Set once the terminal reports a focus change. Until then focus is
+    /// unknown (not every terminal or multiplexer reports it) and we notify.
+    focused: Option<bool>,
+    /// Inside tmux, OSC sequences must be wrapped to reach the outer terminal.
+    in_tmux: bool,
+}
+
+impl TerminalNotifier {
+    pub(crate) fn new(method: TerminalNotificationMethod) -> Self {
+        Self {
+            method,
+            focused: None,
+            in_tmux: std::env::var_os("TMUX").is_some(),
+        }
+    }
+
+    pub(crate) fn set_focused(&mut self, focused: bool) {
+        self.focused = Some(focused);
+    }
+
+    /// Notifies the user unless the terminal is known to be focused. Errors are
+    /// ignored: a missed notification must not disturb the UI.
+    pub(crate) fn notify(&self, message: &str) {
+        if self.focused == Some(true) {
+            return;
+        }
+        let Some(sequence) = notification_sequence(self.method, message, self.in_tmux) else {
+            return;
+        };
+        let mut out = stdout();
+        let _ = out.write_all(sequence.as_bytes());
+        let _ = out.flush();
+    }
+}
+
+fn notification_sequence(
+    method: TerminalNotificationMethod,
+    message: &str,
+    in_tmux: bool,
+) -> Option<String> {
+    // Control characters would terminate the sequence early.
+    let message: String = message.chars().filter(|c| !c.is_control()).collect();
+    let osc = match method {
+        TerminalNotificationMethod::Osc9 => format!("\x1b]9;{message}\x07"),
+        TerminalNotificationMethod::Osc777 => format!("\x1b]777;notify;Codex;{message}\x07"),
+        TerminalNotificationMethod::Bell => return Some("\x07".to_string()),
+        TerminalNotificationMethod::None => return None,
+    };
+    if in_tmux {
+        // DCS passthrough; every ESC in the payload is doubled. Requires
+        // `set -g allow-passthrough on` in tmux 3.3 and later.
+        Some(format!(
+            "\x1bPtmux;{}\x1b\\",
+            osc.replace('\x1b', "\x1b\x1b")
+        ))
+    } else {
+        Some(osc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn sequences() {
+        assert_eq!(
+            notification_sequence(TerminalNotificationMethod::Osc9, "run\n`ls`?", false),
+            Some("\x1b]9;run`ls`?\x07".to_string())
+        );
+        assert_eq!(
+            notification_sequence(TerminalNotificationMethod::Osc777, "hi", false),
+            Some("\x1b]777;notify;Codex;hi\x07".to_string())
+        );
+        assert_eq!(
+            notification_sequence(TerminalNotificationMethod::Osc9, "hi", true),
+            Some("\x1bPtmux;\x1b\x1b]9;hi\x07\x1b\\".to_string())
+        );
+        assert_eq!(
+            notification_sequence(TerminalNotificationMethod::None, "hi", false),
+            None
+        );
+    }
+}
@@ -4,8 +4,10 @@ use std::io::stdout;
 
 use codex_core::config::Config;
 use crossterm::event::DisableBracketedPaste;
+use crossterm::event::DisableFocusChange;
 use crossterm::event::DisableMouseCapture;
 use crossterm::event::EnableBracketedPaste;
+use crossterm::event::EnableFocusChange;
 use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
 use ratatui::crossterm::execute;
@@ -23,6 +25,8 @@ pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 pub fn init(config: &Config) -> Result<(Tui, MouseCapture)> {
     execute!(stdout(), EnterAlternateScreen)?;
     execute!(stdout(), EnableBracketedPaste)?;
+    // Focus reports tell us whether an approval prompt needs a notification.
+    execute!(stdout(), EnableFocusChange)?;
     let mouse_capture = MouseCapture::new_with_capture(!config.tui.disable_mouse_capture)?;
 
     enable_raw_mode()?;
@@ -48,6 +52,7 @@ pub fn restore() -> Result<()> {
         // on shutdown, so ignore the error in this case.
     }
     execute!(stdout(), DisableBracketedPaste)?;
+    execute!(stdout(), DisableFocusChange)?;
     execute!(stdout(), LeaveAlternateScreen)?;
     disable_raw_mode()?;
     Ok(())
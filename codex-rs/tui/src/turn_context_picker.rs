@@ -0,0 +1,217 @@
+//! Choices offered by the `/model` and `/approvals` slash commands. Each
+//! choice is submitted as an `Op::OverrideTurnContext`.
+
+use codex_core::WireApi;
+use codex_core::config::Config;
+use codex_core::config_types::ReasoningEffort;
+use codex_core::model_family::find_family_for_model;
+use codex_core::protocol::AskForApproval;
+use codex_core::protocol::Op;
+use codex_core::protocol::SandboxPolicy;
+
+use crate::bottom_pane::SelectionItem;
+
+/// Models suggested in addition to the configured ones when the session is
+/// using the OpenAI provider.
+const OPENAI_MODELS: &[&str] = &["codex-mini-latest", "o3", "o4-mini", "gpt-4.1"];
+
+const REASONING_EFFORTS: &[ReasoningEffort] = &[
+    ReasoningEffort::Low,
+    ReasoningEffort::Medium,
+    ReasoningEffort::High,
+];
+
+/// The current model, the configured fallback models and, for OpenAI, a few
+/// well-known models. Models that accept reasoning parameters are offered
+/// once per reasoning effort.
+pub(crate) fn model_items(config: &Config) -> Vec<SelectionItem> {
+    let mut candidates = vec![(config.model.clone(), config.model_provider_id.clone())];
+    candidates.extend(
+        config
+            .model_fallbacks
+            .iter()
+            .map(|fallback| (fallback.model.clone(), fallback.model_provider.clone())),
+    );
+    if config.model_provider_id == "openai" {
+        candidates.extend(
+            OPENAI_MODELS
+                .iter()
+                .map(|model| (model.to_string(), "openai".to_string())),
+        );
+    }
+
+    let mut items = Vec::new();
+    let mut seen = Vec::new();
+    for (model, provider_id) in candidates {
+        if seen.contains(&(model.clone(), provider_id.clone())) {
+            continue;
+        }
+        seen.push((model.clone(), provider_id.clone()));
+
+        let provider = if provider_id == config.model_provider_id {
+            config.model_provider.clone()
+        } else {
+            match config.model_providers.get(&provider_id) {
+                Some(provider) => provider.clone(),
+                None => continue,
+            }
+        };
+        let is_current_model = model == config.model && provider_id == config.model_provider_id;
+        let family = find_family_for_model(&model, &config.model_families);
+        let label = format!("{model} ({})", provider.name);
+        let op = |model_reasoning_effort| Op::OverrideTurnContext {
+            provider: Some(provider.clone()),
+            model: Some(model.clone()),
+            model_reasoning_effort,
+            model_reasoning_summary: None,
+            approval_policy: None,
+            sandbox_policy: None,
+        };
+
+        if family.supports_reasoning && provider.wire_api == WireApi::Responses {
+            for effort in REASONING_EFFORTS {
+                items.push(SelectionItem {
+                    label: format!("{label}, {effort} effort"),
+                    description: None,
+                    is_current: is_current_model && config.model_reasoning_effort == *effort,
+                    op: op(Some(*effort)),
+                });
+            }
+        } else {
+            items.push(SelectionItem {
+                label,
+                description: None,
+                is_current: is_current_model,
+                op: op(None),
+            });
+        }
+    }
+    items
+}
+
+/// Approval policy presets. The ones that run commands without asking also
+/// switch to the `--full-auto` sandbox they rely on; the others keep whatever
+/// sandbox the session has, e.g. `danger-full-access` or a custom one.
+pub(crate) fn approval_items(config: &Config) -> Vec<SelectionItem> {
+    [
+        (
+            "Suggest",
+            "Ask before running anything that is not known to be safe",
+            AskForApproval::UnlessAllowListed,
+            None,
+        ),
+        (
+            "Auto edit",
+            "Apply patches without asking; ask before running commands",
+            AskForApproval::AutoEdit,
+            None,
+        ),
+        (
+            "Full auto",
+            "Run commands in the sandbox; ask only when they fail there",
+            AskForApproval::OnFailure,
+            Some(SandboxPolicy::new_full_auto_policy()),
+        ),
+        (
+            "Never ask",
+            "Run commands in the sandbox and report failures to the model",
+            AskForApproval::Never,
+            Some(SandboxPolicy::new_full_auto_policy()),
+        ),
+    ]
+    .into_iter()
+    .map(
+        |(label, description, approval_policy, sandbox_policy)| SelectionItem {
+            label: label.to_string(),
+            description: Some(description.to_string()),
+            is_current: config.approval_policy == approval_policy
+                && sandbox_policy
+                    .as_ref()
+                    .is_none_or(|sandbox_policy| *sandbox_policy == config.sandbox_policy),
+            op: Op::OverrideTurnContext {
+                provider: None,
+                model: None,
+                model_reasoning_effort: None,
+                model_reasoning_summary: None,
+                approval_policy: Some(approval_policy),
+                sandbox_policy,
+            },
+        },
+    )
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use codex_core::config::ConfigOverrides;
+    use codex_core::config::ConfigToml;
+    use codex_core::config_types::ModelFallback;
+    use pretty_assertions::assert_eq;
+
+    fn config(model: &str) -> Config {
+        let mut config = Config::load_from_base_config_with_overrides(
+            ConfigToml::default(),
+            ConfigOverrides {
+                model: Some(model.to_string()),
+                model_provider: Some("openai".to_string()),
+                ..Default::default()
+            },
+            std::env::temp_dir(),
+        )
+        .unwrap();
+        config.model_fallbacks = vec![ModelFallback {
+            model: "llama3.2".to_string(),
+            model_provider: "ollama".to_string(),
+        }];
+        config
+    }
+
+    #[test]
+    fn offers_each_reasoning_effort_for_reasoning_models() {
+        let items = model_items(&config("o3"));
+        let labels: Vec<&str> = items.iter().map(|item| item.label.as_str()).collect();
+        assert_eq!(
+            labels[..4],
+            [
+                "o3 (OpenAI), low effort",
+                "o3 (OpenAI), medium effort",
+                "o3 (OpenAI), high effort",
+                "llama3.2 (Ollama)",
+            ]
+        );
+        let current: Vec<&str> = items
+            .iter()
+            .filter(|item| item.is_current)
+            .map(|item| item.label.as_str())
+            .collect();
+        assert_eq!(current, ["o3 (OpenAI), medium effort"]);
+        // o3 is not offered twice.
+        assert_eq!(labels.iter().filter(|l| l.starts_with("o3 ")).count(), 3);
+        assert!(labels.contains(&"gpt-4.1 (OpenAI)"));
+    }
+
+    #[test]
+    fn approval_presets_keep_an_unrelated_sandbox() {
+        let mut config = config("o3");
+        config.approval_policy = AskForApproval::OnFailure;
+        config.sandbox_policy = SandboxPolicy::new_danger_full_access_policy();
+        let items = approval_items(&config);
+
+        // Same approval policy, but a different sandbox than "Full auto" sets.
+        assert!(items.iter().all(|item| !item.is_current));
+        let Op::OverrideTurnContext { sandbox_policy, .. } = &items[0].op else {
+            panic!("unexpected op {:?}", items[0].op);
+        };
+        assert_eq!(*sandbox_policy, None);
+
+        config.approval_policy = AskForApproval::UnlessAllowListed;
+        let current: Vec<String> = approval_items(&config)
+            .into_iter()
+            .filter(|item| item.is_current)
+            .map(|item| item.label)
+            .collect();
+        assert_eq!(current, ["Suggest"]);
+    }
+}
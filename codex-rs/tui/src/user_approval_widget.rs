@@ -6,9 +6,15 @@
 //! UI to Rust using [`ratatui`]. The goal is feature‑parity for the keyboard
 //! driven workflow – a fully‑fledged visual match is not required.
 
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::path::PathBuf;
 
+use codex_core::approval_rules::suggested_command_prefix;
+use codex_core::protocol::ApprovalRule;
+use codex_core::protocol::FileChange;
 use codex_core::protocol::Op;
+use codex_core::protocol::RejectedFileChange;
 use codex_core::protocol::ReviewDecision;
 use crossterm::event::KeyCode;
 use crossterm::event::KeyEvent;
@@ -29,8 +35,10 @@ use tui_input::backend::crossterm::EventHandler;
 
 use crate::app_event::AppEvent;
 use crate::app_event_sender::AppEventSender;
+use crate::exec_command::parse_edited_command;
 use crate::exec_command::relativize_to_home;
 use crate::exec_command::strip_bash_lc_and_escape;
+use crate::patch_review::PatchReview;
 
 /// Request coming from the agent that needs user approval.
 pub(crate) enum ApprovalRequest {
@@ -42,58 +50,78 @@ pub(crate) enum ApprovalRequest {
     },
     ApplyPatch {
         id: String,
+        changes: HashMap<PathBuf, FileChange>,
         reason: Option<String>,
         grant_root: Option<PathBuf>,
     },
 }
 
 /// Options displayed in the *select* mode.
+#[derive(Clone)]
 struct SelectOption {
-    label: &'static str,
+    label: Cow<'static, str>,
     decision: Option<ReviewDecision>,
-    /// `true` when this option switches the widget to *input* mode.
-    enters_input_mode: bool,
+    /// Mode the widget switches to when this option is chosen.
+    enters_mode: Option<Mode>,
 }
 
 // keep in same order as in the TS implementation
 const SELECT_OPTIONS: &[SelectOption] = &[
     SelectOption {
-        label: "Yes (y)",
+        label: Cow::Borrowed("Yes (y)"),
         decision: Some(ReviewDecision::Approved),
 
-        enters_input_mode: false,
+        enters_mode: None,
     },
     SelectOption {
-        label: "Yes, always approve this exact command for this session (a)",
+        label: Cow::Borrowed("Yes, always approve this exact command for this session (a)"),
         decision: Some(ReviewDecision::ApprovedForSession),
 
-        enters_input_mode: false,
+        enters_mode: None,
     },
     SelectOption {
-        label: "Edit or give feedback (e)",
+        label: Cow::Borrowed("No, and tell the model why (e)"),
         decision: None,
 
-        enters_input_mode: true,
+        enters_mode: Some(Mode::Input),
     },
     SelectOption {
-        label: "No, and keep going (n)",
+        label: Cow::Borrowed("No, and keep going (n)"),
         decision: Some(ReviewDecision::Denied),
 
-        enters_input_mode: false,
+        enters_mode: None,
     },
     SelectOption {
-        label: "No, and stop for now (esc)",
+        label: Cow::Borrowed("No, and stop for now (esc)"),
         decision: Some(ReviewDecision::Abort),
 
-        enters_input_mode: false,
+        enters_mode: None,
     },
 ];
 
+/// Only offered for commands, right after "Yes".
+const EDIT_COMMAND_OPTION: SelectOption = SelectOption {
+    label: Cow::Borrowed("Edit the command, then run it (c)"),
+    decision: None,
+    enters_mode: Some(Mode::Edit),
+};
+
+/// Only offered for patches, right after "Yes".
+const REVIEW_PATCH_OPTION: SelectOption = SelectOption {
+    label: Cow::Borrowed("Choose which files and hunks to apply (r)"),
+    decision: None,
+    enters_mode: Some(Mode::Review),
+};
+
 /// Internal mode the widget is in – mirrors the TypeScript component.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Mode {
     Select,
     Input,
+    /// Per-file/per-hunk selection of a patch.
+    Review,
+    /// Inline editing of a proposed command.
+    Edit,
 }
 
 /// A modal prompting the user to approve or deny the pending request.
@@ -102,9 +130,24 @@ pub(crate) struct UserApprovalWidget<'a> {
     app_event_tx: AppEventSender,
     confirmation_prompt: Paragraph<'a>,
 
+    /// Options shown in *select* mode.
+    select_options: Vec<SelectOption>,
+
+    /// Rule saved for the project if the user picks "always allow".
+    remember_rule: Option<ApprovalRule>,
+
     /// Currently selected index in *select* mode.
     selected_option: usize,
 
+    /// Selection state for *review* mode. Only set for patches.
+    patch_review: Option<PatchReview>,
+
+    /// The command being edited in *edit* mode, prefilled with the proposal.
+    command_input: Input,
+
+    /// Set when the edited command could not be parsed.
+    command_parse_error: bool,
+
     /// State for the optional input widget.
     input: Input,
 
@@ -179,11 +222,68 @@ impl UserApprovalWidget<'_> {
             }
         };
 
+        let mut select_options: Vec<SelectOption> = SELECT_OPTIONS.to_vec();
+        let mut command_input = Input::default();
+        let (patch_review, remember_rule, remember_label) = match &approval_request {
+            ApprovalRequest::Exec { command, .. } => {
+                select_options.insert(1, EDIT_COMMAND_OPTION);
+                command_input = Input::new(strip_bash_lc_and_escape(command));
+                let prefix = suggested_command_prefix(command);
+                let label = prefix.as_ref().map(|prefix| {
+                    format!(
+                        "Yes, and always allow `{} …` in this project (p)",
+                        prefix.join(" ")
+                    )
+                });
+                (
+                    None,
+                    prefix.map(|prefix| ApprovalRule::CommandPrefix { prefix }),
+                    label,
+                )
+            }
+            ApprovalRequest::ApplyPatch {
+                changes,
+                grant_root,
+                ..
+            } => {
+                select_options.insert(1, REVIEW_PATCH_OPTION);
+                let label = grant_root.as_ref().map(|root| {
+                    format!(
+                        "Yes, and always allow writes under {} in this project (p)",
+                        root.display()
+                    )
+                });
+                (
+                    Some(PatchReview::new(changes)),
+                    grant_root
+                        .clone()
+                        .map(|path| ApprovalRule::WritableRoot { path }),
+                    label,
+                )
+            }
+        };
+        if let (Some(rule), Some(label)) = (&remember_rule, remember_label) {
+            // Right after "always approve ... for this session".
+            select_options.insert(
+                3,
+                SelectOption {
+                    label: Cow::Owned(label),
+                    decision: Some(ReviewDecision::ApprovedAndRemember { rule: rule.clone() }),
+                    enters_mode: None,
+                },
+            );
+        }
+
         Self {
             approval_request,
             app_event_tx,
             confirmation_prompt,
+            select_options,
+            remember_rule,
             selected_option: 0,
+            patch_review,
+            command_input,
+            command_parse_error: false,
             input,
             mode: Mode::Select,
             done: false,
@@ -196,11 +296,18 @@ impl UserApprovalWidget<'_> {
 
         match self.mode {
             Mode::Select => {
-                let num_option_lines = SELECT_OPTIONS.len() as u16;
+                let num_option_lines = self.select_options.len() as u16;
                 confirmation_prompt_height + num_option_lines + BORDER_LINES
             }
-            Mode::Input => {
-                //   1. "Give the model feedback ..." prompt
+            Mode::Review => {
+                let num_review_lines =
+                    self.patch_review
+                        .as_ref()
+                        .map_or(0, |review| review.lines().len()) as u16;
+                confirmation_prompt_height + num_review_lines + BORDER_LINES
+            }
+            Mode::Input | Mode::Edit => {
+                //   1. "Tell the model why ..." / "Edit the command ..." prompt
                 //   2. A single‑line input field (we allocate exactly one row;
                 //      the `tui-input` widget will scroll horizontally if the
                 //      text exceeds the width).
@@ -226,6 +333,8 @@ impl UserApprovalWidget<'_> {
         match self.mode {
             Mode::Select => self.handle_select_key(key),
             Mode::Input => self.handle_input_key(key),
+            Mode::Review => self.handle_review_key(key),
+            Mode::Edit => self.handle_edit_key(key),
         }
     }
 
@@ -233,13 +342,13 @@ impl UserApprovalWidget<'_> {
         match key_event.code {
             KeyCode::Up => {
                 if self.selected_option == 0 {
-                    self.selected_option = SELECT_OPTIONS.len() - 1;
+                    self.selected_option = self.select_options.len() - 1;
                 } else {
                     self.selected_option -= 1;
                 }
             }
             KeyCode::Down => {
-                self.selected_option = (self.selected_option + 1) % SELECT_OPTIONS.len();
+                self.selected_option = (self.selected_option + 1) % self.select_options.len();
             }
             KeyCode::Char('y') => {
                 self.send_decision(ReviewDecision::Approved);
@@ -247,17 +356,28 @@ impl UserApprovalWidget<'_> {
             KeyCode::Char('a') => {
                 self.send_decision(ReviewDecision::ApprovedForSession);
             }
+            KeyCode::Char('p') => {
+                if let Some(rule) = self.remember_rule.clone() {
+                    self.send_decision(ReviewDecision::ApprovedAndRemember { rule });
+                }
+            }
             KeyCode::Char('n') => {
                 self.send_decision(ReviewDecision::Denied);
             }
             KeyCode::Char('e') => {
                 self.mode = Mode::Input;
             }
+            KeyCode::Char('r') if self.patch_review.is_some() => {
+                self.mode = Mode::Review;
+            }
+            KeyCode::Char('c') if matches!(self.approval_request, ApprovalRequest::Exec { .. }) => {
+                self.mode = Mode::Edit;
+            }
             KeyCode::Enter => {
-                let opt = &SELECT_OPTIONS[self.selected_option];
-                if opt.enters_input_mode {
-                    self.mode = Mode::Input;
-                } else if let Some(decision) = opt.decision {
+                let opt = &self.select_options[self.selected_option];
+                if let Some(mode) = opt.enters_mode {
+                    self.mode = mode;
+                } else if let Some(decision) = opt.decision.clone() {
                     self.send_decision(decision);
                 }
             }
@@ -272,8 +392,12 @@ impl UserApprovalWidget<'_> {
         // Handle special keys first.
         match key_event.code {
             KeyCode::Enter => {
-                let feedback = self.input.value().to_string();
-                self.send_decision_with_feedback(ReviewDecision::Denied, feedback);
+                let feedback = self.input.value().trim().to_string();
+                if feedback.is_empty() {
+                    self.send_decision(ReviewDecision::Denied);
+                } else {
+                    self.send_decision(ReviewDecision::DeniedWithFeedback { feedback });
+                }
             }
             KeyCode::Esc => {
                 // Cancel input – treat as deny without feedback.
@@ -287,11 +411,64 @@ impl UserApprovalWidget<'_> {
         }
     }
 
+    fn handle_edit_key(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Enter => {
+                let ApprovalRequest::Exec { command, .. } = &self.approval_request else {
+                    return;
+                };
+                match parse_edited_command(command, self.command_input.value()) {
+                    Some(edited) if edited == *command => {
+                        self.send_decision(ReviewDecision::Approved);
+                    }
+                    Some(edited) => {
+                        self.send_decision(ReviewDecision::ApprovedModifiedCommand {
+                            command: edited,
+                        });
+                    }
+                    None => self.command_parse_error = true,
+                }
+            }
+            KeyCode::Esc => {
+                self.mode = Mode::Select;
+            }
+            _ => {
+                self.command_parse_error = false;
+                let ct_event = crossterm::event::Event::Key(key_event);
+                self.command_input.handle_event(&ct_event);
+            }
+        }
+    }
+
+    fn handle_review_key(&mut self, key_event: KeyEvent) {
+        let Some(review) = self.patch_review.as_mut() else {
+            self.mode = Mode::Select;
+            return;
+        };
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => review.move_up(),
+            KeyCode::Down | KeyCode::Char('j') => review.move_down(),
+            KeyCode::Char(' ') => review.toggle(),
+            KeyCode::Enter => {
+                if review.all_rejected() {
+                    self.send_decision(ReviewDecision::Denied);
+                } else {
+                    let rejected_changes = review.rejected_changes();
+                    self.send_op(ReviewDecision::Approved, rejected_changes);
+                }
+            }
+            KeyCode::Esc => {
+                self.mode = Mode::Select;
+            }
+            _ => {}
+        }
+    }
+
     fn send_decision(&mut self, decision: ReviewDecision) {
-        self.send_decision_with_feedback(decision, String::new())
+        self.send_op(decision, Vec::new());
     }
 
-    fn send_decision_with_feedback(&mut self, decision: ReviewDecision, _feedback: String) {
+    fn send_op(&mut self, decision: ReviewDecision, rejected_changes: Vec<RejectedFileChange>) {
         let op = match &self.approval_request {
             ApprovalRequest::Exec { id, .. } => Op::ExecApproval {
                 id: id.clone(),
@@ -300,11 +477,10 @@ impl UserApprovalWidget<'_> {
             ApprovalRequest::ApplyPatch { id, .. } => Op::PatchApproval {
                 id: id.clone(),
                 decision,
+                rejected_changes,
             },
         };
 
-        // Ignore feedback for now – the current `Op` variants do not carry it.
-
         // Forward the Op to the agent. The caller (ChatWidget) will trigger a
         // redraw after it processes the resulting state change, so we avoid
         // issuing an extra Redraw here to prevent a transient frame where the
@@ -345,7 +521,8 @@ impl WidgetRef for &UserApprovalWidget<'_> {
         // non-wrapping lines rather than a Paragraph because get_height(Rect)
         // depends on this behavior for its calculation.
         let lines = match self.mode {
-            Mode::Select => SELECT_OPTIONS
+            Mode::Select => self
+                .select_options
                 .iter()
                 .enumerate()
                 .map(|(idx, opt)| {
@@ -358,11 +535,30 @@ impl WidgetRef for &UserApprovalWidget<'_> {
                 })
                 .collect(),
             Mode::Input => {
+                let subject = match self.approval_request {
+                    ApprovalRequest::Exec { .. } => "command",
+                    ApprovalRequest::ApplyPatch { .. } => "patch",
+                };
                 vec![
-                    Line::from("Give the model feedback on this command:"),
+                    Line::from(format!(
+                        "Tell the model why you are rejecting this {subject} (enter to send):"
+                    )),
                     Line::from(self.input.value()),
                 ]
             }
+            Mode::Review => self
+                .patch_review
+                .as_ref()
+                .map(PatchReview::lines)
+                .unwrap_or_default(),
+            Mode::Edit => {
+                let prompt = if self.command_parse_error {
+                    Line::from("Could not parse the command; check its quoting.".red())
+                } else {
+                    Line::from("Edit the command (enter to run, esc to go back):")
+                };
+                vec![prompt, Line::from(self.command_input.value())]
+            }
         };
 
         outer.render(area, buf);